@@ -9,8 +9,11 @@ use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use std::time::SystemTime;
 
+use crate::shell_analyzer::{analyze_command, KnownDirs, ShellFileOpKind};
+
 /// Represents an agent type supported by the collector.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
@@ -36,6 +39,8 @@ pub struct Session {
     pub version: Option<String>,
     /// Git branch at time of session
     pub git_branch: Option<String>,
+    /// First human prompt of the session (truncated)
+    pub first_message: Option<String>,
     /// Session start timestamp (ISO 8601)
     pub started_at: Option<String>,
     /// Last activity timestamp (from file modification)
@@ -69,9 +74,74 @@ fn get_claude_projects_dir() -> Option<PathBuf> {
     dirs::home_dir().map(|h| h.join(".claude").join("projects"))
 }
 
-/// Check if a project directory name is a temp folder (should be skipped).
-fn is_temp_project(name: &str) -> bool {
-    name.contains("private-var-folders")
+/// Include/exclude rules applied to encoded project directory names during discovery.
+///
+/// Patterns are matched against the directory name under `~/.claude/projects`
+/// (e.g. `-home-alice-app`) and may contain `*` wildcards. A directory is kept when
+/// it matches at least one include pattern (or no include patterns are set) and
+/// none of the exclude patterns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiscoveryRules {
+    /// Patterns a directory must match to be discovered (empty = everything)
+    pub include: Vec<String>,
+    /// Patterns that exclude a directory from discovery
+    pub exclude: Vec<String>,
+}
+
+impl Default for DiscoveryRules {
+    /// Accept every project except OS temp folders (macOS `/private/var/folders`, `/tmp`).
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: vec!["*-private-var-folders-*".to_string(), "-tmp-*".to_string()],
+        }
+    }
+}
+
+impl DiscoveryRules {
+    /// Check whether an encoded project directory name passes these rules.
+    pub fn allows(&self, dir_name: &str) -> bool {
+        let included = self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| wildcard_match(pattern, dir_name));
+        included
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| wildcard_match(pattern, dir_name))
+    }
+}
+
+/// Match text against a pattern where `*` matches any run of characters.
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text position it is currently covering
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // Let the last `*` swallow one more character and retry
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 /// Convert SystemTime to ISO 8601 string.
@@ -88,16 +158,22 @@ fn system_time_to_iso(time: SystemTime) -> String {
 }
 
 /// Discover all Claude Code projects and their sessions.
-pub fn discover_projects() -> Vec<Project> {
-    let projects_dir = match get_claude_projects_dir() {
-        Some(p) if p.exists() => p,
-        _ => return Vec::new(),
-    };
+pub fn discover_projects(rules: &DiscoveryRules) -> Vec<Project> {
+    match get_claude_projects_dir() {
+        Some(p) if p.exists() => discover_projects_in(&p, rules),
+        _ => Vec::new(),
+    }
+}
 
+/// Discover projects under a specific projects directory.
+///
+/// Accepts every encoded project directory (`-Users-...`, `-home-...`, `-root-...`, ...)
+/// that passes the include/exclude rules.
+fn discover_projects_in(projects_dir: &Path, rules: &DiscoveryRules) -> Vec<Project> {
     let mut projects: HashMap<String, Project> = HashMap::new();
 
     // Iterate through project directories
-    let entries = match fs::read_dir(&projects_dir) {
+    let entries = match fs::read_dir(projects_dir) {
         Ok(e) => e,
         Err(_) => return Vec::new(),
     };
@@ -113,8 +189,8 @@ pub fn discover_projects() -> Vec<Project> {
             None => continue,
         };
 
-        // Skip temp folders and non-user projects
-        if is_temp_project(&dir_name) || !dir_name.starts_with("-Users-") {
+        // Skip directories filtered out by the discovery rules (temp folders by default)
+        if !rules.allows(&dir_name) {
             continue;
         }
 
@@ -136,11 +212,6 @@ pub fn discover_projects() -> Vec<Project> {
 fn process_project_dir(dir_path: &Path) -> Option<Project> {
     let entries = fs::read_dir(dir_path).ok()?;
 
-    // Derive project path from folder name (e.g., "-Users-reuben-project" -> "/Users/reuben/project")
-    // This is more reliable than reading cwd from session files, which can vary within a project
-    let dir_name = dir_path.file_name()?.to_string_lossy().to_string();
-    let project_path = decode_project_path(&dir_name);
-
     let mut session_files: Vec<PathBuf> = Vec::new();
     let mut subagent_count = 0u32;
     let mut latest_mtime: Option<SystemTime> = None;
@@ -186,6 +257,9 @@ fn process_project_dir(dir_path: &Path) -> Option<Project> {
         return None;
     }
 
+    // Resolve the real project path (the folder name alone is ambiguous, see resolve_project_path)
+    let project_path = resolve_project_path(dir_path, &session_files);
+
     // Extract project name from path
     let project_name = Path::new(&project_path)
         .file_name()
@@ -214,20 +288,230 @@ fn process_project_dir(dir_path: &Path) -> Option<Project> {
 }
 
 /// Convert a project path to its encoded directory name.
-/// e.g., "/Users/ramos/project" -> "-Users-ramos-project"
-fn encode_project_path(project_path: &str) -> String {
-    project_path.replace('/', "-")
+/// Claude Code replaces every non-alphanumeric character with `-`.
+/// e.g., "/Users/ramos/my_project" -> "-Users-ramos-my-project"
+pub fn encode_project_path(project_path: &str) -> String {
+    project_path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Cache of encoded directory name -> resolved project path.
+fn resolved_paths() -> &'static Mutex<HashMap<String, String>> {
+    static RESOLVED_PATHS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+    RESOLVED_PATHS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Resolve the real project path for an encoded project directory.
+///
+/// The encoding is lossy (`/home/me/my-app` and `/home/me/my/app` both become
+/// `-home-me-my-app`), so the path is resolved in order of reliability:
+/// 1. The `cwd` recorded in the session files (or an ancestor of it) that encodes to the folder name
+/// 2. A filesystem walk that picks existing directories matching the encoded segments
+/// 3. Naive decoding (every `-` becomes `/`) as a last resort
+///
+/// Successful resolutions are cached by folder name.
+fn resolve_project_path(dir_path: &Path, session_files: &[PathBuf]) -> String {
+    let dir_name = match dir_path.file_name() {
+        Some(n) => n.to_string_lossy().to_string(),
+        None => return dir_path.to_string_lossy().to_string(),
+    };
+
+    if let Ok(cache) = resolved_paths().lock() {
+        if let Some(path) = cache.get(&dir_name) {
+            return path.clone();
+        }
+    }
+
+    let resolved = resolve_from_session_cwd(&dir_name, session_files)
+        .or_else(|| resolve_from_filesystem(&dir_name));
+
+    match resolved {
+        Some(path) => {
+            if let Ok(mut cache) = resolved_paths().lock() {
+                cache.insert(dir_name, path.clone());
+            }
+            path
+        }
+        // Not cached: the directory may show up later (e.g. remounted volume)
+        None => naive_decode_project_path(&dir_name),
+    }
+}
+
+/// Maximum lines to scan per session file when looking for a `cwd` field.
+const CWD_SCAN_LINES: usize = 50;
+
+/// Find a `cwd` in the session files whose path (or one of its ancestors) encodes to `dir_name`.
+fn resolve_from_session_cwd(dir_name: &str, session_files: &[PathBuf]) -> Option<String> {
+    #[derive(Deserialize)]
+    struct CwdEntry {
+        cwd: Option<String>,
+    }
+
+    for session_file in session_files {
+        let file = match File::open(session_file) {
+            Ok(f) => f,
+            Err(_) => continue,
+        };
+
+        for line in BufReader::new(file).lines().take(CWD_SCAN_LINES) {
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => break,
+            };
+
+            // Quick check before parsing
+            if !line.contains("\"cwd\"") {
+                continue;
+            }
+
+            let cwd = match serde_json::from_str::<CwdEntry>(&line)
+                .ok()
+                .and_then(|e| e.cwd)
+            {
+                Some(c) => c,
+                None => continue,
+            };
+
+            // The session may have started in a subdirectory of the project
+            for ancestor in Path::new(&cwd).ancestors() {
+                let candidate = ancestor.to_string_lossy();
+                if encode_project_path(&candidate) == dir_name {
+                    return Some(candidate.to_string());
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Walk the filesystem from the root, matching directory entries against the encoded name.
+fn resolve_from_filesystem(dir_name: &str) -> Option<String> {
+    // The leading `-` is the root separator
+    let rest = dir_name.strip_prefix('-')?;
+    resolve_path_segments(Path::new("/"), rest).map(|p| p.to_string_lossy().to_string())
+}
+
+/// Recursively match the remaining encoded name against entries of `base`.
+fn resolve_path_segments(base: &Path, rest: &str) -> Option<PathBuf> {
+    if rest.is_empty() {
+        return Some(base.to_path_buf());
+    }
+
+    let entries = fs::read_dir(base).ok()?;
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let encoded = encode_project_path(&name);
+
+        // The entry must cover a whole prefix of the remaining name, followed by a separator
+        let remainder = if rest == encoded {
+            ""
+        } else {
+            match rest
+                .strip_prefix(&encoded)
+                .and_then(|r| r.strip_prefix('-'))
+            {
+                Some(r) => r,
+                None => continue,
+            }
+        };
+
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        if let Some(resolved) = resolve_path_segments(&path, remainder) {
+            return Some(resolved);
+        }
+    }
+
+    None
 }
 
-/// Convert an encoded directory name back to a project path.
+/// Convert an encoded directory name back to a project path by treating every `-` as `/`.
 /// e.g., "-Users-ramos-project" -> "/Users/ramos/project"
-fn decode_project_path(encoded_name: &str) -> String {
+/// Only used when the real path cannot be resolved.
+fn naive_decode_project_path(encoded_name: &str) -> String {
     encoded_name.replace('-', "/")
 }
 
-/// Get sessions for a specific project (lightweight - no file content parsing).
-/// Only returns session ID and last activity time from file metadata.
+/// Get sessions for a specific project.
+/// Metadata comes from the head and tail of each session file (cached by size and mtime).
 pub fn get_sessions_for_project(project_path: &str) -> Vec<Session> {
+    let mut sessions: Vec<Session> = Vec::new();
+
+    for (session_id, path) in get_session_files(project_path) {
+        // Get file modification time for last_activity
+        let last_activity = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .map(system_time_to_iso)
+            .unwrap_or_default();
+
+        let metadata = get_session_metadata(&path);
+
+        sessions.push(Session {
+            id: session_id,
+            slug: metadata.slug,
+            summary: metadata.summary,
+            model: metadata.model,
+            version: metadata.version,
+            git_branch: metadata.git_branch,
+            first_message: metadata.first_message,
+            started_at: metadata.started_at,
+            last_activity,
+            message_count: metadata.message_count,
+        });
+    }
+
+    // Sort by last activity descending
+    sessions.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
+    sessions
+}
+
+/// List the session files of a project as (session_id, path) pairs.
+/// Sub-agent files and files without a UUID name are skipped.
+pub fn get_session_files(project_path: &str) -> Vec<(String, PathBuf)> {
+    get_project_jsonl_files(project_path)
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with("agent-") && is_uuid_format(name))
+        .collect()
+}
+
+/// List the sub-agent files of a project as (agent_id, path) pairs.
+pub fn get_subagent_files(project_path: &str) -> Vec<(String, PathBuf)> {
+    get_project_jsonl_files(project_path)
+        .into_iter()
+        .filter_map(|(name, path)| {
+            let agent_id = name.strip_prefix("agent-")?.to_string();
+            Some((agent_id, path))
+        })
+        .collect()
+}
+
+/// List the sub-agent files of one session of a project as (agent_id, path) pairs.
+pub fn get_session_subagent_files(project_path: &str, session_id: &str) -> Vec<(String, PathBuf)> {
+    get_subagent_files(project_path)
+        .into_iter()
+        .filter(|(_, path)| subagent_parent_session(path).as_deref() == Some(session_id))
+        .collect()
+}
+
+/// Read the parent session ID from the first line of a sub-agent file.
+pub fn subagent_parent_session(path: &Path) -> Option<String> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?)
+        .read_line(&mut line)
+        .ok()?;
+    let json: Value = serde_json::from_str(&line).ok()?;
+    json["sessionId"].as_str().map(String::from)
+}
+
+/// List the JSONL files of a project as (file_stem, path) pairs.
+fn get_project_jsonl_files(project_path: &str) -> Vec<(String, PathBuf)> {
     let projects_dir = match get_claude_projects_dir() {
         Some(p) if p.exists() => p,
         _ => return Vec::new(),
@@ -237,16 +521,12 @@ pub fn get_sessions_for_project(project_path: &str) -> Vec<Session> {
     let encoded_name = encode_project_path(project_path);
     let project_dir = projects_dir.join(&encoded_name);
 
-    if !project_dir.exists() {
-        return Vec::new();
-    }
-
     let entries = match fs::read_dir(&project_dir) {
         Ok(e) => e,
         Err(_) => return Vec::new(),
     };
 
-    let mut sessions: Vec<Session> = Vec::new();
+    let mut files = Vec::new();
 
     for entry in entries.flatten() {
         let path = entry.path();
@@ -260,33 +540,286 @@ pub fn get_sessions_for_project(project_path: &str) -> Vec<Session> {
             None => continue,
         };
 
-        // Skip agent files and non-UUID files
-        if file_name.starts_with("agent-") || !is_uuid_format(&file_name) {
-            continue;
+        files.push((file_name, path));
+    }
+
+    files
+}
+
+// =============================================================================
+// Session Metadata
+// =============================================================================
+
+/// Bytes read from the start of a session file for metadata.
+const METADATA_HEAD_BYTES: u64 = 256 * 1024;
+/// Bytes read from the end of a session file for metadata.
+const METADATA_TAIL_BYTES: u64 = 256 * 1024;
+/// Maximum length of the first human message shown in the session list.
+const FIRST_MESSAGE_MAX_CHARS: usize = 200;
+
+/// Session metadata extracted from a session file.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct SessionMetadata {
+    slug: Option<String>,
+    summary: Option<String>,
+    first_message: Option<String>,
+    model: Option<String>,
+    version: Option<String>,
+    git_branch: Option<String>,
+    started_at: Option<String>,
+    message_count: u32,
+}
+
+/// Cached metadata, valid while the file size and mtime are unchanged.
+struct CachedSessionMetadata {
+    file_size: u64,
+    modified: SystemTime,
+    metadata: SessionMetadata,
+    counter: MessageCounter,
+}
+
+/// Running count of the messages in a session file, advanced as the file grows
+/// so that only appended lines are scanned.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct MessageCounter {
+    /// Offset just past the last complete line counted
+    offset: u64,
+    count: u32,
+    /// Id of the last assistant message; its content blocks are logged on separate lines
+    last_assistant_id: Option<String>,
+}
+
+/// Cache of session file path -> extracted metadata.
+fn session_metadata_cache() -> &'static Mutex<HashMap<PathBuf, CachedSessionMetadata>> {
+    static SESSION_METADATA: OnceLock<Mutex<HashMap<PathBuf, CachedSessionMetadata>>> =
+        OnceLock::new();
+    SESSION_METADATA.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Internal struct for parsing the metadata fields of a JSONL entry.
+#[derive(Deserialize)]
+struct JsonlMetadataEntry {
+    #[serde(rename = "type")]
+    entry_type: Option<String>,
+    slug: Option<String>,
+    summary: Option<String>,
+    version: Option<String>,
+    #[serde(rename = "gitBranch")]
+    git_branch: Option<String>,
+    timestamp: Option<String>,
+    #[serde(rename = "userType")]
+    user_type: Option<String>,
+    #[serde(rename = "isCompactSummary")]
+    is_compact_summary: Option<bool>,
+    #[serde(rename = "isMeta")]
+    is_meta: Option<bool>,
+    message: Option<JsonlMetadataMessage>,
+}
+
+#[derive(Deserialize)]
+struct JsonlMetadataMessage {
+    model: Option<String>,
+    content: Option<Value>,
+}
+
+/// Get metadata for a session file, re-extracting only if the file changed.
+fn get_session_metadata(session_file: &Path) -> SessionMetadata {
+    let (file_size, modified) = match fs::metadata(session_file) {
+        Ok(m) => (m.len(), m.modified().unwrap_or(SystemTime::UNIX_EPOCH)),
+        Err(_) => return SessionMetadata::default(),
+    };
+
+    let mut counter = MessageCounter::default();
+    if let Ok(cache) = session_metadata_cache().lock() {
+        if let Some(cached) = cache.get(session_file) {
+            if cached.file_size == file_size && cached.modified == modified {
+                return cached.metadata.clone();
+            }
+            counter = cached.counter.clone();
         }
+    }
 
-        // Get file modification time for last_activity (no content parsing!)
-        let last_activity = fs::metadata(&path)
-            .and_then(|m| m.modified())
-            .map(system_time_to_iso)
-            .unwrap_or_default();
+    let mut metadata = extract_session_metadata(session_file, file_size);
+    counter.advance(session_file, file_size);
+    metadata.message_count = counter.count;
+
+    if let Ok(mut cache) = session_metadata_cache().lock() {
+        cache.insert(
+            session_file.to_path_buf(),
+            CachedSessionMetadata {
+                file_size,
+                modified,
+                metadata: metadata.clone(),
+                counter,
+            },
+        );
+    }
 
-        sessions.push(Session {
-            id: file_name,
-            slug: None,
-            summary: None,
-            model: None,
-            version: None,
-            git_branch: None,
-            started_at: None,
-            last_activity,
-            message_count: 0,
-        });
+    metadata
+}
+
+/// Extract session metadata by parsing only the head and tail of the file.
+///
+/// The head provides the start timestamp, first human message, slug, version and branch;
+/// the tail provides the latest model and summary. The message count is kept separately
+/// by [`MessageCounter`].
+fn extract_session_metadata(session_file: &Path, file_size: u64) -> SessionMetadata {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut metadata = SessionMetadata::default();
+
+    let mut file = match File::open(session_file) {
+        Ok(f) => f,
+        Err(_) => return metadata,
+    };
+
+    // Head: complete lines within the first METADATA_HEAD_BYTES
+    let mut head = String::new();
+    let mut reader = BufReader::new(&file);
+    let mut consumed: u64 = 0;
+    while consumed < METADATA_HEAD_BYTES {
+        let bytes_read = match reader.read_line(&mut head) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        consumed += bytes_read as u64;
+    }
+    for line in head.lines() {
+        apply_metadata_line(&mut metadata, line);
     }
 
-    // Sort by last activity descending
-    sessions.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
-    sessions
+    // Tail: complete lines within the last METADATA_TAIL_BYTES (skip if the head covered it)
+    if file_size > consumed {
+        let tail_start = file_size.saturating_sub(METADATA_TAIL_BYTES).max(consumed);
+        let mut tail = Vec::new();
+        if file.seek(SeekFrom::Start(tail_start)).is_ok() && file.read_to_end(&mut tail).is_ok() {
+            let tail = String::from_utf8_lossy(&tail);
+            // The first line is partial unless the tail starts right after the head
+            let skip = usize::from(tail_start != consumed);
+            for line in tail.lines().skip(skip) {
+                apply_metadata_line(&mut metadata, line);
+            }
+        }
+    }
+
+    metadata
+}
+
+/// Update metadata from a single JSONL line.
+/// Start/identity fields keep their first value; model and summary keep the latest.
+fn apply_metadata_line(metadata: &mut SessionMetadata, line: &str) {
+    let entry: JsonlMetadataEntry = match serde_json::from_str(line) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+
+    if entry.entry_type.as_deref() == Some("summary") {
+        if let Some(summary) = entry.summary {
+            metadata.summary = Some(summary);
+        }
+        return;
+    }
+
+    if metadata.started_at.is_none() {
+        metadata.started_at = entry.timestamp;
+    }
+    if metadata.slug.is_none() {
+        metadata.slug = entry.slug;
+    }
+    if metadata.version.is_none() {
+        metadata.version = entry.version;
+    }
+    if metadata.git_branch.is_none() {
+        metadata.git_branch = entry.git_branch.filter(|b| !b.is_empty());
+    }
+
+    let message = match entry.message {
+        Some(m) => m,
+        None => return,
+    };
+
+    match entry.entry_type.as_deref() {
+        Some("assistant") => {
+            // Synthetic assistant messages (e.g. API errors) use a "<synthetic>" model
+            if let Some(model) = message.model.filter(|m| !m.starts_with('<')) {
+                metadata.model = Some(model);
+            }
+        }
+        Some("user") if metadata.first_message.is_none() => {
+            let is_human = entry.user_type.as_deref() == Some("external")
+                && entry.is_meta != Some(true)
+                && entry.is_compact_summary != Some(true);
+            if let Some(content) = message.content.filter(|c| !is_tool_result_content(c)) {
+                if is_human {
+                    let text = extract_preview_from_content(&content);
+                    metadata.first_message =
+                        Some(truncate_string(text.trim(), FIRST_MESSAGE_MAX_CHARS));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+impl MessageCounter {
+    /// Count the complete lines appended since the last call. A file that shrank was
+    /// rewritten and is recounted from the start.
+    fn advance(&mut self, session_file: &Path, file_size: u64) {
+        use std::io::{Seek, SeekFrom};
+
+        if file_size < self.offset {
+            *self = Self::default();
+        }
+        let mut file = match File::open(session_file) {
+            Ok(f) => f,
+            Err(_) => return,
+        };
+        if file.seek(SeekFrom::Start(self.offset)).is_err() {
+            return;
+        }
+
+        let mut reader = BufReader::new(file);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            // A partial last line is still being written; count it once complete
+            if line.last() != Some(&b'\n') {
+                break;
+            }
+            self.offset += line.len() as u64;
+            self.count_line(&String::from_utf8_lossy(&line));
+        }
+    }
+
+    /// Count human prompts and assistant responses with substring checks (no JSON parsing).
+    /// Tool results and meta lines are not prompts, and the content blocks of one
+    /// assistant response share its message id.
+    fn count_line(&mut self, line: &str) {
+        if line.contains("\"type\":\"assistant\"") {
+            let id = assistant_message_id(line);
+            if id.is_none() || id != self.last_assistant_id.as_deref() {
+                self.count += 1;
+            }
+            self.last_assistant_id = id.map(str::to_string);
+        } else if line.contains("\"type\":\"user\"")
+            && !line.contains("\"type\":\"tool_result\"")
+            && !line.contains("\"isMeta\":true")
+        {
+            self.count += 1;
+        }
+    }
+}
+
+/// Extract the API message id ("msg_...") of an assistant line.
+fn assistant_message_id(line: &str) -> Option<&str> {
+    const ID_PREFIX: &str = "\"id\":\"msg_";
+    let start = line.find(ID_PREFIX)? + ID_PREFIX.len() - "msg_".len();
+    let len = line[start..].find('"')?;
+    Some(&line[start..start + len])
 }
 
 /// Check if a string looks like a UUID (8-4-4-4-12 format).
@@ -319,6 +852,18 @@ pub enum FileEditType {
     Added,
     Modified,
     Deleted,
+    Renamed,
+}
+
+/// How reliably an edit was detected.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EditConfidence {
+    /// Recorded by a file editing tool (Edit, Write, MultiEdit, NotebookEdit)
+    #[default]
+    High,
+    /// Inferred from a Bash command
+    Low,
 }
 
 /// A file that was edited during a session.
@@ -331,6 +876,12 @@ pub struct FileEdit {
     pub edit_type: FileEditType,
     /// Timestamp of the last edit to this file (ISO 8601)
     pub last_edited_at: Option<String>,
+    /// Original relative path (Renamed only)
+    #[serde(default)]
+    pub renamed_from: Option<String>,
+    /// Low when the latest change was inferred from a shell command
+    #[serde(default)]
+    pub confidence: EditConfidence,
 }
 
 /// A single diff operation on a file.
@@ -345,42 +896,435 @@ pub struct FileDiff {
     pub sequence: u32,
     /// Timestamp of the change (ISO 8601)
     pub timestamp: Option<String>,
+    /// Whether the change replaces the whole file (Write)
+    pub is_write: bool,
+    /// Whether every occurrence of old_string was replaced
+    pub replace_all: bool,
+    /// Notebook cell ID (NotebookEdit only)
+    pub cell_id: Option<String>,
+    /// Notebook edit mode: "replace", "insert" or "delete" (NotebookEdit only)
+    pub edit_mode: Option<String>,
 }
 
-/// All diffs for a specific file.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct FileWithDiffs {
-    /// Absolute file path
-    pub file_path: String,
-    /// Relative path from project root
-    pub relative_path: String,
-    /// Type of edit
-    pub edit_type: FileEditType,
-    /// All diffs in order
-    pub diffs: Vec<FileDiff>,
-}
+/// A single file change extracted from a tool_use input.
+///
+/// Edit and Write produce one change; MultiEdit produces one per entry in `edits`;
+/// NotebookEdit produces one cell-level change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolFileChange {
+    /// Absolute path of the changed file
+    pub file_path: String,
+    /// The text that was replaced (empty for Write and NotebookEdit)
+    pub old_string: String,
+    /// The new text (full content for Write, cell source for NotebookEdit)
+    pub new_string: String,
+    /// Whether the change replaces the whole file (Write)
+    pub is_write: bool,
+    /// Whether every occurrence of old_string is replaced (Edit/MultiEdit `replace_all`)
+    pub replace_all: bool,
+    /// Whether the change shows the file existed before the session touched it
+    pub has_prior_content: bool,
+    /// Notebook cell ID (NotebookEdit only)
+    pub cell_id: Option<String>,
+    /// Notebook edit mode (NotebookEdit only)
+    pub edit_mode: Option<String>,
+}
+
+impl ToolFileChange {
+    /// Convert to a FileDiff with the given sequence number and timestamp.
+    pub fn to_diff(&self, sequence: u32, timestamp: Option<String>) -> FileDiff {
+        FileDiff {
+            old_string: self.old_string.clone(),
+            new_string: self.new_string.clone(),
+            sequence,
+            timestamp,
+            is_write: self.is_write,
+            replace_all: self.replace_all,
+            cell_id: self.cell_id.clone(),
+            edit_mode: self.edit_mode.clone(),
+        }
+    }
+}
+
+/// Extract file changes from a tool_use (Edit, Write, MultiEdit, NotebookEdit).
+/// Returns an empty list for other tools or malformed inputs.
+pub fn extract_tool_file_changes(tool_name: &str, input: &Value) -> Vec<ToolFileChange> {
+    let str_field = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string()
+    };
+    let bool_field =
+        |value: &Value, key: &str| value.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+
+    match tool_name {
+        "Edit" => {
+            let Some(file_path) = input.get("file_path").and_then(|v| v.as_str()) else {
+                return Vec::new();
+            };
+            let old_string = str_field(input, "old_string");
+            vec![ToolFileChange {
+                file_path: file_path.to_string(),
+                has_prior_content: !old_string.is_empty(),
+                old_string,
+                new_string: str_field(input, "new_string"),
+                is_write: false,
+                replace_all: bool_field(input, "replace_all"),
+                cell_id: None,
+                edit_mode: None,
+            }]
+        }
+        "Write" => {
+            let Some(file_path) = input.get("file_path").and_then(|v| v.as_str()) else {
+                return Vec::new();
+            };
+            vec![ToolFileChange {
+                file_path: file_path.to_string(),
+                old_string: String::new(),
+                new_string: str_field(input, "content"),
+                is_write: true,
+                replace_all: false,
+                has_prior_content: false,
+                cell_id: None,
+                edit_mode: None,
+            }]
+        }
+        "MultiEdit" => {
+            let Some(file_path) = input.get("file_path").and_then(|v| v.as_str()) else {
+                return Vec::new();
+            };
+            let Some(edits) = input.get("edits").and_then(|v| v.as_array()) else {
+                return Vec::new();
+            };
+            edits
+                .iter()
+                .enumerate()
+                .map(|(i, edit)| {
+                    let old_string = str_field(edit, "old_string");
+                    ToolFileChange {
+                        file_path: file_path.to_string(),
+                        // Later edits may target text added by earlier ones, so only
+                        // the first edit says anything about the file's prior content
+                        has_prior_content: i == 0 && !old_string.is_empty(),
+                        old_string,
+                        new_string: str_field(edit, "new_string"),
+                        is_write: false,
+                        replace_all: bool_field(edit, "replace_all"),
+                        cell_id: None,
+                        edit_mode: None,
+                    }
+                })
+                .collect()
+        }
+        "NotebookEdit" => {
+            let Some(notebook_path) = input.get("notebook_path").and_then(|v| v.as_str()) else {
+                return Vec::new();
+            };
+            let edit_mode = input
+                .get("edit_mode")
+                .and_then(|v| v.as_str())
+                .unwrap_or("replace")
+                .to_string();
+            vec![ToolFileChange {
+                file_path: notebook_path.to_string(),
+                old_string: String::new(),
+                new_string: str_field(input, "new_source"),
+                is_write: false,
+                replace_all: false,
+                // NotebookEdit only operates on an existing notebook
+                has_prior_content: true,
+                cell_id: input
+                    .get("cell_id")
+                    .and_then(|v| v.as_str())
+                    .map(String::from),
+                edit_mode: Some(edit_mode),
+            }]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// All diffs for a specific file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileWithDiffs {
+    /// Absolute file path
+    pub file_path: String,
+    /// Relative path from project root
+    pub relative_path: String,
+    /// Type of edit
+    pub edit_type: FileEditType,
+    /// All diffs in order
+    pub diffs: Vec<FileDiff>,
+}
+
+/// Internal struct for parsing JSONL entries to extract tool_use.
+#[derive(Deserialize)]
+struct JsonlToolEntry {
+    #[serde(rename = "type")]
+    entry_type: Option<String>,
+    message: Option<JsonlMessage>,
+    timestamp: Option<String>,
+    cwd: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonlMessage {
+    content: Option<Vec<JsonlContent>>,
+}
+
+#[derive(Deserialize)]
+struct JsonlContent {
+    #[serde(rename = "type")]
+    content_type: Option<String>,
+    name: Option<String>,
+    input: Option<Value>,
+}
+
+/// Accumulates file operations while scanning a session and resolves them into FileEdits.
+///
+/// Shared by `get_session_file_edits` and the session index builder/updater so all of
+/// them classify files the same way.
+#[derive(Debug, Default)]
+pub struct FileEditTracker {
+    /// Latest operation per relative path (added vs modified is resolved in `finish`)
+    operations: HashMap<String, FileEditType>,
+    /// Paths with evidence the file existed before the session touched it
+    prior_content: HashSet<String>,
+    /// Latest timestamp per path
+    timestamps: HashMap<String, String>,
+    /// Original path of files moved during the session
+    renamed_from: HashMap<String, String>,
+    /// Paths whose latest operation was inferred from a shell command
+    inferred: HashSet<String>,
+    /// Directories the session created or used, for classifying `mv` destinations
+    known_dirs: KnownDirs,
+}
+
+impl FileEditTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed a tracker with previously resolved edits and the directories known at that
+    /// point, for incremental updates.
+    pub fn from_edits(edits: &[FileEdit], known_dirs: KnownDirs) -> Self {
+        let mut tracker = Self {
+            known_dirs,
+            ..Self::default()
+        };
+        for edit in edits {
+            tracker
+                .operations
+                .insert(edit.path.clone(), edit.edit_type.clone());
+            if edit.edit_type != FileEditType::Added {
+                tracker.prior_content.insert(edit.path.clone());
+            }
+            if let Some(ts) = &edit.last_edited_at {
+                tracker.timestamps.insert(edit.path.clone(), ts.clone());
+            }
+            if let Some(from) = &edit.renamed_from {
+                tracker.renamed_from.insert(edit.path.clone(), from.clone());
+            }
+            if edit.confidence == EditConfidence::Low {
+                tracker.inferred.insert(edit.path.clone());
+            }
+        }
+        tracker
+    }
+
+    /// Record a tool_use. Returns the relative path of each content change, one per
+    /// Edit/Write/MultiEdit/NotebookEdit change (shell operations are not included).
+    ///
+    /// Bash commands are analyzed relative to `cwd` (falling back to the project root);
+    /// files outside the project are ignored.
+    pub fn record_tool_use(
+        &mut self,
+        tool_name: &str,
+        input: &Value,
+        project_path: &str,
+        cwd: Option<&str>,
+        timestamp: Option<&str>,
+    ) -> Vec<String> {
+        let mut changed = Vec::new();
+
+        for change in extract_tool_file_changes(tool_name, input) {
+            let rel_path = make_relative_path(&change.file_path, project_path);
+            self.known_dirs
+                .add_parents_of(&Path::new(project_path).join(&rel_path));
+            self.record_change(&rel_path, &change);
+            self.touch(&rel_path, timestamp);
+            changed.push(rel_path);
+        }
+
+        if tool_name == "Bash" {
+            let command = input.get("command").and_then(|v| v.as_str()).unwrap_or("");
+            for op in analyze_command(command, cwd.unwrap_or(project_path), &mut self.known_dirs) {
+                let Some(rel_path) = project_relative_path(&op.path, project_path) else {
+                    continue;
+                };
+                match op.kind {
+                    ShellFileOpKind::Renamed { from } => {
+                        let Some(rel_from) = project_relative_path(&from, project_path) else {
+                            continue;
+                        };
+                        self.record_rename(&rel_from, &rel_path);
+                    }
+                    ShellFileOpKind::Deleted => {
+                        if !self.operations.contains_key(&rel_path) {
+                            self.prior_content.insert(rel_path.clone());
+                        }
+                        self.operations
+                            .insert(rel_path.clone(), FileEditType::Deleted);
+                        self.renamed_from.remove(&rel_path);
+                    }
+                    ShellFileOpKind::Modified => {
+                        if !self.operations.contains_key(&rel_path) {
+                            self.prior_content.insert(rel_path.clone());
+                        }
+                        if self.operations.get(&rel_path) != Some(&FileEditType::Renamed) {
+                            self.operations
+                                .insert(rel_path.clone(), FileEditType::Modified);
+                        }
+                    }
+                }
+                self.inferred.insert(rel_path.clone());
+                self.touch(&rel_path, timestamp);
+            }
+        }
+
+        changed
+    }
+
+    /// Record a change made by a file editing tool.
+    fn record_change(&mut self, rel_path: &str, change: &ToolFileChange) {
+        self.inferred.remove(rel_path);
+
+        if change.is_write {
+            // Write to a file that wasn't previously edited = added
+            // Write to a file that was edited = modified
+            match self.operations.get(rel_path) {
+                None => {
+                    self.operations
+                        .insert(rel_path.to_string(), FileEditType::Added);
+                }
+                Some(FileEditType::Deleted) => {
+                    // Recreated after a delete
+                    let edit_type = if self.prior_content.contains(rel_path) {
+                        FileEditType::Modified
+                    } else {
+                        FileEditType::Added
+                    };
+                    self.operations.insert(rel_path.to_string(), edit_type);
+                }
+                Some(_) => {}
+            }
+        } else {
+            // Check if this edit has old_string content (indicates existing file)
+            if change.has_prior_content {
+                self.prior_content.insert(rel_path.to_string());
+            }
+
+            // Mark as modified (we'll determine added/modified later)
+            self.operations
+                .insert(rel_path.to_string(), FileEditType::Modified);
+        }
+    }
+
+    /// Move the tracked state of `from` to `to`.
+    fn record_rename(&mut self, from: &str, to: &str) {
+        let was_tracked = self.operations.remove(from).is_some();
+        let had_prior_content = self.prior_content.remove(from) || !was_tracked;
+        let origin = self
+            .renamed_from
+            .remove(from)
+            .unwrap_or_else(|| from.to_string());
+        self.inferred.remove(from);
+        self.timestamps.remove(from);
+
+        if !had_prior_content {
+            // File was created during this session, so the move just relocates a new file
+            self.operations.insert(to.to_string(), FileEditType::Added);
+            self.renamed_from.remove(to);
+        } else if origin == to {
+            self.operations
+                .insert(to.to_string(), FileEditType::Modified);
+            self.prior_content.insert(to.to_string());
+            self.renamed_from.remove(to);
+        } else {
+            self.operations
+                .insert(to.to_string(), FileEditType::Renamed);
+            self.prior_content.insert(to.to_string());
+            self.renamed_from.insert(to.to_string(), origin);
+        }
+    }
+
+    /// Track the latest timestamp for a path.
+    fn touch(&mut self, rel_path: &str, timestamp: Option<&str>) {
+        if let Some(ts) = timestamp {
+            self.timestamps.insert(rel_path.to_string(), ts.to_string());
+        }
+    }
+
+    /// Resolve the final edit list, sorted by path.
+    /// Take the directories seen so far, to persist them alongside the resolved edits.
+    pub fn take_known_dirs(&mut self) -> KnownDirs {
+        std::mem::take(&mut self.known_dirs)
+    }
 
-/// Internal struct for parsing JSONL entries to extract tool_use.
-#[derive(Deserialize)]
-struct JsonlToolEntry {
-    #[serde(rename = "type")]
-    entry_type: Option<String>,
-    message: Option<JsonlMessage>,
-    timestamp: Option<String>,
-}
+    pub fn finish(self) -> Vec<FileEdit> {
+        let FileEditTracker {
+            operations,
+            prior_content,
+            timestamps,
+            mut renamed_from,
+            inferred,
+            ..
+        } = self;
+
+        let mut edits: Vec<FileEdit> = operations
+            .into_iter()
+            .map(|(path, mut edit_type)| {
+                let from = renamed_from.remove(&path);
+                // A renamed file that was edited afterwards is still a rename.
+                // If a file was written but never had prior content, it's "added".
+                if edit_type == FileEditType::Modified {
+                    if from.is_some() {
+                        edit_type = FileEditType::Renamed;
+                    } else if !prior_content.contains(&path) {
+                        edit_type = FileEditType::Added;
+                    }
+                }
+                FileEdit {
+                    last_edited_at: timestamps.get(&path).cloned(),
+                    renamed_from: from.filter(|_| edit_type == FileEditType::Renamed),
+                    confidence: if inferred.contains(&path) {
+                        EditConfidence::Low
+                    } else {
+                        EditConfidence::High
+                    },
+                    path,
+                    edit_type,
+                }
+            })
+            .collect();
 
-#[derive(Deserialize)]
-struct JsonlMessage {
-    content: Option<Vec<JsonlContent>>,
+        // Sort by path for consistent display (frontend can re-sort by timestamp for log view)
+        edits.sort_by(|a, b| a.path.cmp(&b.path));
+        edits
+    }
 }
 
-#[derive(Deserialize)]
-struct JsonlContent {
-    #[serde(rename = "type")]
-    content_type: Option<String>,
-    name: Option<String>,
-    input: Option<Value>,
+/// Relative path of `path` if it lies inside the project (and isn't the project root).
+fn project_relative_path(path: &str, project_path: &str) -> Option<String> {
+    let project = Path::new(project_path);
+    let relative = Path::new(path).strip_prefix(project).ok()?;
+    if relative.as_os_str().is_empty() {
+        return None;
+    }
+    Some(relative.to_string_lossy().to_string())
 }
 
 /// Get the session file path for a project and session ID.
@@ -415,148 +1359,120 @@ pub fn get_subagent_file_path(project_path: &str, agent_id: &str) -> Option<Path
 
 /// Extract all file edits from a session (lightweight - just file list and types).
 pub fn get_session_file_edits(project_path: &str, session_id: &str) -> Vec<FileEdit> {
-    let session_file = match get_session_file_path(project_path, session_id) {
-        Some(p) => p,
-        None => return Vec::new(),
-    };
-
-    let file = match File::open(&session_file) {
-        Ok(f) => f,
-        Err(_) => return Vec::new(),
-    };
-
-    let reader = BufReader::new(file);
-
-    // Track files and whether they existed before (had Edit with old_string)
-    let mut file_operations: HashMap<String, FileEditType> = HashMap::new();
-    let mut files_with_prior_content: HashSet<String> = HashSet::new();
-    let mut file_timestamps: HashMap<String, String> = HashMap::new();
+    match get_session_file_path(project_path, session_id) {
+        Some(session_file) => get_file_edits_from_session_file(&session_file, project_path),
+        None => Vec::new(),
+    }
+}
 
-    for line in reader.lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => continue,
-        };
+/// Extract all file edits from a session JSONL file.
+pub fn get_file_edits_from_session_file(session_file: &Path, project_path: &str) -> Vec<FileEdit> {
+    let mut tracker = FileEditTracker::new();
 
-        // Quick check: skip lines that don't contain tool_use indicators
-        if !line.contains("\"tool_use\"") {
-            continue;
-        }
+    for_each_tool_use(session_file, |entry, tool_name, input| {
+        tracker.record_tool_use(
+            tool_name,
+            input,
+            project_path,
+            entry.cwd.as_deref(),
+            entry.timestamp.as_deref(),
+        );
+    });
 
-        let entry: JsonlToolEntry = match serde_json::from_str(&line) {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
+    tracker.finish()
+}
 
-        // Only process assistant messages
-        if entry.entry_type.as_deref() != Some("assistant") {
-            continue;
+/// Get all diffs for a specific file in a session.
+pub fn get_file_diffs(project_path: &str, session_id: &str, file_path: &str) -> Vec<FileDiff> {
+    match get_session_file_path(project_path, session_id) {
+        Some(session_file) => {
+            get_file_diffs_from_session_file(&session_file, project_path, file_path)
         }
+        None => Vec::new(),
+    }
+}
 
-        let content = match entry.message.and_then(|m| m.content) {
-            Some(c) => c,
-            None => continue,
-        };
+/// Get all diffs for a specific file from a session JSONL file.
+fn get_file_diffs_from_session_file(
+    session_file: &Path,
+    project_path: &str,
+    file_path: &str,
+) -> Vec<FileDiff> {
+    let target_path = make_relative_path(file_path, project_path);
+    let mut diffs: Vec<FileDiff> = Vec::new();
+    let mut sequence: u32 = 0;
 
-        for item in content {
-            if item.content_type.as_deref() != Some("tool_use") {
+    for_each_tool_use(session_file, |entry, tool_name, input| {
+        for change in extract_tool_file_changes(tool_name, input) {
+            if make_relative_path(&change.file_path, project_path) != target_path {
                 continue;
             }
 
-            let tool_name = match &item.name {
-                Some(n) => n.as_str(),
-                None => continue,
-            };
-
-            let input = match &item.input {
-                Some(i) => i,
-                None => continue,
-            };
+            diffs.push(change.to_diff(sequence, entry.timestamp.clone()));
+            sequence += 1;
+        }
+    });
 
-            let timestamp = entry.timestamp.clone();
+    diffs
+}
 
-            match tool_name {
-                "Edit" => {
-                    if let Some(file_path) = input.get("file_path").and_then(|v| v.as_str()) {
-                        let rel_path = make_relative_path(file_path, project_path);
+/// Get the diffs for every file in a session JSONL file in one pass, keyed by relative path.
+/// Sequence numbers are per file, matching `get_file_diffs`.
+pub fn get_all_file_diffs_from_session_file(
+    session_file: &Path,
+    project_path: &str,
+) -> HashMap<String, Vec<FileDiff>> {
+    let mut diffs: HashMap<String, Vec<FileDiff>> = HashMap::new();
+
+    for_each_tool_use(session_file, |entry, tool_name, input| {
+        for change in extract_tool_file_changes(tool_name, input) {
+            let file_diffs = diffs
+                .entry(make_relative_path(&change.file_path, project_path))
+                .or_default();
+            let sequence = file_diffs.len() as u32;
+            file_diffs.push(change.to_diff(sequence, entry.timestamp.clone()));
+        }
+    });
 
-                        // Check if this edit has old_string content (indicates existing file)
-                        if let Some(old_str) = input.get("old_string").and_then(|v| v.as_str()) {
-                            if !old_str.is_empty() {
-                                files_with_prior_content.insert(rel_path.clone());
-                            }
-                        }
+    diffs
+}
 
-                        // Mark as modified (we'll determine added/modified later)
-                        file_operations.insert(rel_path.clone(), FileEditType::Modified);
+/// A Bash tool call made in a session.
+#[derive(Debug, Clone)]
+pub struct SessionBashCommand {
+    /// The command line
+    pub command: String,
+    /// Timestamp of the tool call (ISO 8601)
+    pub timestamp: Option<String>,
+}
 
-                        // Track timestamp (always update to get the latest)
-                        if let Some(ts) = timestamp {
-                            file_timestamps.insert(rel_path, ts);
-                        }
-                    }
-                }
-                "Write" => {
-                    if let Some(file_path) = input.get("file_path").and_then(|v| v.as_str()) {
-                        let rel_path = make_relative_path(file_path, project_path);
-
-                        // Write to a file that wasn't previously edited = added
-                        // Write to a file that was edited = modified
-                        if !file_operations.contains_key(&rel_path) {
-                            file_operations.insert(rel_path.clone(), FileEditType::Added);
-                        }
+/// Get every Bash command run in a session JSONL file, in order.
+pub fn get_bash_commands_from_session_file(session_file: &Path) -> Vec<SessionBashCommand> {
+    let mut commands = Vec::new();
 
-                        // Track timestamp (always update to get the latest)
-                        if let Some(ts) = timestamp {
-                            file_timestamps.insert(rel_path, ts);
-                        }
-                    }
-                }
-                // TODO: Could track file deletions via Bash rm commands
-                _ => {}
-            }
+    for_each_tool_use(session_file, |entry, tool_name, input| {
+        if tool_name != "Bash" {
+            return;
         }
-    }
-
-    // Convert to FileEdit vec, determining final edit type
-    let mut edits: Vec<FileEdit> = file_operations
-        .into_iter()
-        .map(|(path, mut edit_type)| {
-            // If a file was written but never had prior content, it's "added"
-            // If it had prior content (from Edit old_string), it's "modified"
-            if edit_type == FileEditType::Modified && !files_with_prior_content.contains(&path) {
-                edit_type = FileEditType::Added;
-            }
-            let last_edited_at = file_timestamps.get(&path).cloned();
-            FileEdit {
-                path,
-                edit_type,
-                last_edited_at,
-            }
-        })
-        .collect();
+        if let Some(command) = input.get("command").and_then(|v| v.as_str()) {
+            commands.push(SessionBashCommand {
+                command: command.to_string(),
+                timestamp: entry.timestamp.clone(),
+            });
+        }
+    });
 
-    // Sort by path for consistent display (frontend can re-sort by timestamp for log view)
-    edits.sort_by(|a, b| a.path.cmp(&b.path));
-    edits
+    commands
 }
 
-/// Get all diffs for a specific file in a session.
-pub fn get_file_diffs(project_path: &str, session_id: &str, file_path: &str) -> Vec<FileDiff> {
-    let session_file = match get_session_file_path(project_path, session_id) {
-        Some(p) => p,
-        None => return Vec::new(),
-    };
-
-    let file = match File::open(&session_file) {
+/// Call `f` for every tool_use in the assistant messages of a session JSONL file, in order.
+fn for_each_tool_use(session_file: &Path, mut f: impl FnMut(&JsonlToolEntry, &str, &Value)) {
+    let file = match File::open(session_file) {
         Ok(f) => f,
-        Err(_) => return Vec::new(),
+        Err(_) => return,
     };
 
     let reader = BufReader::new(file);
-    let target_path = make_relative_path(file_path, project_path);
-    let mut diffs: Vec<FileDiff> = Vec::new();
-    let mut sequence: u32 = 0;
 
     for line in reader.lines() {
         let line = match line {
@@ -564,21 +1480,22 @@ pub fn get_file_diffs(project_path: &str, session_id: &str, file_path: &str) ->
             Err(_) => continue,
         };
 
-        // Quick check
+        // Quick check: skip lines that don't contain tool_use indicators
         if !line.contains("\"tool_use\"") {
             continue;
         }
 
-        let entry: JsonlToolEntry = match serde_json::from_str(&line) {
+        let mut entry: JsonlToolEntry = match serde_json::from_str(&line) {
             Ok(e) => e,
             Err(_) => continue,
         };
 
+        // Only process assistant messages
         if entry.entry_type.as_deref() != Some("assistant") {
             continue;
         }
 
-        let content = match entry.message.and_then(|m| m.content) {
+        let content = match entry.message.take().and_then(|m| m.content) {
             Some(c) => c,
             None => continue,
         };
@@ -598,63 +1515,13 @@ pub fn get_file_diffs(project_path: &str, session_id: &str, file_path: &str) ->
                 None => continue,
             };
 
-            let entry_path = match input.get("file_path").and_then(|v| v.as_str()) {
-                Some(p) => make_relative_path(p, project_path),
-                None => continue,
-            };
-
-            if entry_path != target_path {
-                continue;
-            }
-
-            let timestamp = entry.timestamp.clone();
-
-            match tool_name {
-                "Edit" => {
-                    let old_string = input
-                        .get("old_string")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    let new_string = input
-                        .get("new_string")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-
-                    diffs.push(FileDiff {
-                        old_string,
-                        new_string,
-                        sequence,
-                        timestamp,
-                    });
-                    sequence += 1;
-                }
-                "Write" => {
-                    let content = input
-                        .get("content")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-
-                    diffs.push(FileDiff {
-                        old_string: String::new(),
-                        new_string: content,
-                        sequence,
-                        timestamp,
-                    });
-                    sequence += 1;
-                }
-                _ => {}
-            }
+            f(&entry, tool_name, input);
         }
     }
-
-    diffs
 }
 
 /// Convert an absolute file path to a relative path from the project root.
-fn make_relative_path(file_path: &str, project_path: &str) -> String {
+pub fn make_relative_path(file_path: &str, project_path: &str) -> String {
     // Ensure project_path ends without slash for consistent stripping
     let project = project_path.trim_end_matches('/');
 
@@ -684,6 +1551,35 @@ pub struct CompactMetadata {
     pub pre_tokens: u64,
 }
 
+/// Tokens billed for one API response (`message.usage` of an assistant line).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    /// Uncached input tokens
+    pub input_tokens: u64,
+    /// Generated tokens
+    pub output_tokens: u64,
+    /// Input tokens read from the prompt cache
+    pub cache_read_tokens: u64,
+    /// Input tokens written to the prompt cache
+    pub cache_creation_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Size of the prompt sent to the model: all input tokens, cached or not.
+    pub fn context_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_read_tokens + self.cache_creation_tokens
+    }
+
+    /// Add another usage to this one.
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+    }
+}
+
 /// Response from get_session_events with pagination info.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -744,6 +1640,13 @@ pub struct SessionEvent {
     pub is_tool_result: bool,
     /// Whether this is a meta/context injection (isMeta: true)
     pub is_meta: bool,
+    /// Model that generated an assistant message
+    pub model: Option<String>,
+    /// API message ID (shared by the lines of one streamed response, which all
+    /// repeat its usage)
+    pub message_id: Option<String>,
+    /// Tokens billed for the response (assistant messages)
+    pub usage: Option<TokenUsage>,
 }
 
 /// Internal struct for parsing JSONL entries for event log.
@@ -794,7 +1697,18 @@ struct JsonlToolUseResult {
 
 #[derive(Deserialize)]
 struct JsonlEventMessage {
+    id: Option<String>,
+    model: Option<String>,
     content: Option<Value>,
+    usage: Option<JsonlUsage>,
+}
+
+#[derive(Deserialize)]
+struct JsonlUsage {
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    cache_read_input_tokens: Option<u64>,
+    cache_creation_input_tokens: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -1020,6 +1934,16 @@ pub fn parse_session_event(line: &str, sequence: u32, byte_offset: u64) -> Optio
     // isMeta indicates context injection
     let is_meta = entry.is_meta.unwrap_or(false);
 
+    let message = entry.message.as_ref();
+    let usage = message
+        .and_then(|m| m.usage.as_ref())
+        .map(|usage| TokenUsage {
+            input_tokens: usage.input_tokens.unwrap_or(0),
+            output_tokens: usage.output_tokens.unwrap_or(0),
+            cache_read_tokens: usage.cache_read_input_tokens.unwrap_or(0),
+            cache_creation_tokens: usage.cache_creation_input_tokens.unwrap_or(0),
+        });
+
     Some(SessionEvent {
         sequence,
         uuid: entry.uuid,
@@ -1042,6 +1966,9 @@ pub fn parse_session_event(line: &str, sequence: u32, byte_offset: u64) -> Optio
         is_compact_summary: entry.is_compact_summary,
         is_tool_result,
         is_meta,
+        model: message.and_then(|m| m.model.clone()),
+        message_id: message.and_then(|m| m.id.clone()),
+        usage,
     })
 }
 
@@ -1531,6 +2458,7 @@ pub fn get_policy_evaluation(project_path: &str, filename: &str) -> Option<Strin
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TempDir;
     use std::time::Instant;
 
     #[test]
@@ -1541,18 +2469,506 @@ mod tests {
         assert!(!is_uuid_format(""));
     }
 
+    /// Create an empty fixture projects directory unique to this test run.
+    fn fixture_projects_dir(name: &str) -> TempDir {
+        TempDir::new(&format!("fixture-{}", name))
+    }
+
+    /// Write a session JSONL file into an encoded project directory.
+    fn write_fixture_session(
+        projects_dir: &Path,
+        encoded_name: &str,
+        file_name: &str,
+        content: &str,
+    ) {
+        let project_dir = projects_dir.join(encoded_name);
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(project_dir.join(file_name), content).unwrap();
+    }
+
+    const FIXTURE_SESSION: &str = "040f5516-2ff1-4738-8190-2b8248f631de.jsonl";
+
+    #[test]
+    fn test_default_rules_skip_temp_projects() {
+        let rules = DiscoveryRules::default();
+        assert!(
+            !rules.allows("-private-var-folders-8s-x9ypf18955j7w6-zgzqtpclr0000gn-T--tmp08X8zw")
+        );
+        assert!(!rules.allows("-tmp-tmp08X8zw"));
+        assert!(rules.allows("-Users-ramos-cupcake-cupcake-rego-cupcake-rewrite"));
+        assert!(rules.allows("-home-alice-app"));
+        assert!(rules.allows("-root-app"));
+    }
+
+    #[test]
+    fn test_rules_include_and_exclude() {
+        let rules = DiscoveryRules {
+            include: vec!["-home-*".to_string(), "-workspace".to_string()],
+            exclude: vec!["*-scratch".to_string()],
+        };
+        assert!(rules.allows("-home-alice-app"));
+        assert!(rules.allows("-workspace"));
+        assert!(!rules.allows("-workspace-app"));
+        assert!(!rules.allows("-home-alice-scratch"));
+        assert!(!rules.allows("-Users-alice-app"));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("-home-*-app", "-home-alice-app"));
+        assert!(wildcard_match("*a*b*", "xxaxxbxx"));
+        assert!(!wildcard_match("-home-*-app", "-home-alice-api"));
+        assert!(!wildcard_match("abc", "abcd"));
+    }
+
+    #[test]
+    fn test_discover_projects_across_layouts() {
+        let projects_dir = fixture_projects_dir("layouts");
+        write_fixture_session(
+            &projects_dir,
+            "-Users-alice-mac-project",
+            FIXTURE_SESSION,
+            "{}\n",
+        );
+        write_fixture_session(
+            &projects_dir,
+            "-home-bob-linux-project",
+            FIXTURE_SESSION,
+            "{}\n",
+        );
+        write_fixture_session(&projects_dir, "-root-container", FIXTURE_SESSION, "{}\n");
+        write_fixture_session(&projects_dir, "-workspace", FIXTURE_SESSION, "{}\n");
+        write_fixture_session(
+            &projects_dir,
+            "-private-var-folders-8s-x9ypf-T-tmp",
+            FIXTURE_SESSION,
+            "{}\n",
+        );
+        // Project directory without any session files is ignored
+        write_fixture_session(&projects_dir, "-home-bob-empty", "notes.txt", "");
+
+        let projects = discover_projects_in(&projects_dir, &DiscoveryRules::default());
+        let mut names: Vec<&str> = projects.iter().map(|p| p.project_name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["container", "project", "project", "workspace"]);
+        assert!(projects.iter().all(|p| p.session_count == 1));
+    }
+
+    #[test]
+    fn test_discover_projects_counts_subagents() {
+        let projects_dir = fixture_projects_dir("subagents");
+        write_fixture_session(&projects_dir, "-home-bob-app", FIXTURE_SESSION, "{}\n");
+        write_fixture_session(
+            &projects_dir,
+            "-home-bob-app",
+            "agent-01cdb344.jsonl",
+            "{}\n",
+        );
+
+        let projects = discover_projects_in(&projects_dir, &DiscoveryRules::default());
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].session_count, 1);
+        assert_eq!(projects[0].subagent_count, 1);
+    }
+
+    #[test]
+    fn test_encode_project_path() {
+        assert_eq!(
+            encode_project_path("/Users/ramos/project"),
+            "-Users-ramos-project"
+        );
+        assert_eq!(
+            encode_project_path("/home/me/my_app.rs"),
+            "-home-me-my-app-rs"
+        );
+        assert_eq!(encode_project_path("/home/me/.config"), "-home-me--config");
+    }
+
+    #[test]
+    fn test_resolve_project_path_from_session_cwd() {
+        let projects_dir = fixture_projects_dir("resolve-cwd");
+        // Path does not need to exist: the recorded cwd is trusted when it encodes to the folder name
+        let real_path = "/home/me/my-app";
+        let encoded = encode_project_path(real_path);
+        // Session started in a subdirectory of the project
+        write_fixture_session(
+            &projects_dir,
+            &encoded,
+            FIXTURE_SESSION,
+            "{\"type\":\"summary\",\"summary\":\"x\"}\n{\"type\":\"user\",\"cwd\":\"/home/me/my-app/src\"}\n",
+        );
+
+        let project_dir = projects_dir.join(&encoded);
+        let session_files = vec![project_dir.join(FIXTURE_SESSION)];
+        assert_eq!(
+            resolve_project_path(&project_dir, &session_files),
+            real_path
+        );
+    }
+
+    #[test]
+    fn test_resolve_project_path_from_filesystem() {
+        let projects_dir = fixture_projects_dir("resolve-fs");
+        let real_dir = projects_dir.join("checkouts").join("my-app.rs");
+        fs::create_dir_all(&real_dir).unwrap();
+        let real_path = real_dir.to_string_lossy().to_string();
+        let encoded = encode_project_path(&real_path);
+        write_fixture_session(&projects_dir, &encoded, FIXTURE_SESSION, "{}\n");
+
+        let project_dir = projects_dir.join(&encoded);
+        let session_files = vec![project_dir.join(FIXTURE_SESSION)];
+        assert_eq!(
+            resolve_project_path(&project_dir, &session_files),
+            real_path
+        );
+    }
+
+    #[test]
+    fn test_resolve_project_path_falls_back_to_naive_decode() {
+        assert_eq!(
+            resolve_project_path(Path::new("/nowhere/-nonexistent-agent-console-dir"), &[]),
+            "/nonexistent/agent/console/dir"
+        );
+    }
+
+    const FIXTURE_METADATA_SESSION: &str = concat!(
+        r#"{"type":"summary","summary":"Old summary","leafUuid":"a"}"#,
+        "\n",
+        r#"{"type":"user","userType":"external","isMeta":true,"timestamp":"2025-01-01T09:59:00Z","message":{"role":"user","content":"Caveat: injected"}}"#,
+        "\n",
+        r#"{"type":"user","userType":"external","slug":"async-knitting-panda","version":"2.0.1","gitBranch":"main","timestamp":"2025-01-01T10:00:00Z","message":{"role":"user","content":"Fix the login bug"}}"#,
+        "\n",
+        r#"{"type":"assistant","timestamp":"2025-01-01T10:00:05Z","message":{"model":"claude-sonnet-4-5","content":[{"type":"text","text":"On it"}]}}"#,
+        "\n",
+        r#"{"type":"user","userType":"external","timestamp":"2025-01-01T10:00:06Z","message":{"role":"user","content":[{"type":"tool_result","content":"ok"}]}}"#,
+        "\n",
+        r#"{"type":"assistant","timestamp":"2025-01-01T10:00:07Z","message":{"model":"claude-opus-4-5","content":[{"type":"text","text":"Done"}]}}"#,
+        "\n",
+        r#"{"type":"summary","summary":"Fixed login bug","leafUuid":"b"}"#,
+        "\n",
+    );
+
+    #[test]
+    fn test_extract_session_metadata() {
+        let projects_dir = fixture_projects_dir("metadata");
+        write_fixture_session(
+            &projects_dir,
+            "-home-me-app",
+            FIXTURE_SESSION,
+            FIXTURE_METADATA_SESSION,
+        );
+        let session_file = projects_dir.join("-home-me-app").join(FIXTURE_SESSION);
+
+        let metadata = get_session_metadata(&session_file);
+        assert_eq!(metadata.slug.as_deref(), Some("async-knitting-panda"));
+        assert_eq!(metadata.summary.as_deref(), Some("Fixed login bug"));
+        assert_eq!(metadata.first_message.as_deref(), Some("Fix the login bug"));
+        assert_eq!(metadata.model.as_deref(), Some("claude-opus-4-5"));
+        assert_eq!(metadata.version.as_deref(), Some("2.0.1"));
+        assert_eq!(metadata.git_branch.as_deref(), Some("main"));
+        assert_eq!(metadata.started_at.as_deref(), Some("2025-01-01T09:59:00Z"));
+        // One human prompt and two responses; the meta line and tool result are not messages
+        assert_eq!(metadata.message_count, 3);
+    }
+
+    #[test]
+    fn test_session_metadata_cache_invalidated_on_growth() {
+        let projects_dir = fixture_projects_dir("metadata-cache");
+        write_fixture_session(
+            &projects_dir,
+            "-home-me-app",
+            FIXTURE_SESSION,
+            FIXTURE_METADATA_SESSION,
+        );
+        let session_file = projects_dir.join("-home-me-app").join(FIXTURE_SESSION);
+        assert_eq!(get_session_metadata(&session_file).message_count, 3);
+
+        let mut content = FIXTURE_METADATA_SESSION.to_string();
+        content.push_str(
+            r#"{"type":"assistant","message":{"model":"claude-haiku-4-5","content":"More"}}"#,
+        );
+        content.push('\n');
+        fs::write(&session_file, content).unwrap();
+
+        let metadata = get_session_metadata(&session_file);
+        assert_eq!(metadata.message_count, 4);
+        assert_eq!(metadata.model.as_deref(), Some("claude-haiku-4-5"));
+    }
+
+    #[test]
+    fn test_message_counter_counts_prompts_and_responses_incrementally() {
+        let projects_dir = fixture_projects_dir("message-counter");
+        let lines = [
+            r#"{"type":"user","userType":"external","message":{"role":"user","content":"Run it"}}"#,
+            // One response streamed as three content-block lines
+            r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"thinking","thinking":"hm"}]}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"text","text":"Running"}]}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_1","content":[{"type":"tool_use","id":"toolu_1"}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","content":"ok"}]}}"#,
+            r#"{"type":"assistant","message":{"id":"msg_2","content":[{"type":"text","text":"Done"}]}}"#,
+        ];
+        let mut content = lines.join("\n").into_bytes();
+        content.push(b'\n');
+        // A non-UTF-8 line must not stop the count
+        content.extend_from_slice(b"{\"type\":\"system\",\"content\":\"\xff\xfe\"}\n");
+        content.extend_from_slice(
+            br#"{"type":"user","userType":"external","message":{"role":"user","content":"Thanks"}}"#,
+        );
+        content.push(b'\n');
+        // Partial line still being written
+        let complete_len = content.len() as u64;
+        content.extend_from_slice(br#"{"type":"assistant","message":{"id":"msg_3""#);
+        write_fixture_session(&projects_dir, "-home-me-app", FIXTURE_SESSION, "");
+        let session_file = projects_dir.join("-home-me-app").join(FIXTURE_SESSION);
+        fs::write(&session_file, &content).unwrap();
+
+        let mut counter = MessageCounter::default();
+        counter.advance(&session_file, content.len() as u64);
+        assert_eq!(counter.count, 4);
+        assert_eq!(counter.offset, complete_len);
+
+        // Only the appended bytes are scanned; the finished line continues msg_3
+        content.extend_from_slice(b",\"content\":[]}}\n");
+        content.extend_from_slice(br#"{"type":"assistant","message":{"id":"msg_3","content":[]}}"#);
+        content.push(b'\n');
+        fs::write(&session_file, &content).unwrap();
+        counter.advance(&session_file, content.len() as u64);
+        assert_eq!(counter.count, 5);
+        assert_eq!(counter.offset, content.len() as u64);
+
+        // A rewritten (shorter) file is recounted
+        fs::write(&session_file, format!("{}\n", lines[0])).unwrap();
+        counter.advance(&session_file, lines[0].len() as u64 + 1);
+        assert_eq!(counter.count, 1);
+    }
+
+    #[test]
+    fn test_extract_session_metadata_reads_tail_of_large_file() {
+        let projects_dir = fixture_projects_dir("metadata-tail");
+        let mut content = FIXTURE_METADATA_SESSION.to_string();
+        // Push the final lines well past the head window
+        let filler = format!(
+            "{{\"type\":\"system\",\"content\":\"{}\"}}\n",
+            "x".repeat(1024)
+        );
+        for _ in 0..(2 * METADATA_HEAD_BYTES as usize / filler.len()) {
+            content.push_str(&filler);
+        }
+        content.push_str(r#"{"type":"summary","summary":"Tail summary","leafUuid":"c"}"#);
+        content.push('\n');
+        write_fixture_session(&projects_dir, "-home-me-app", FIXTURE_SESSION, &content);
+        let session_file = projects_dir.join("-home-me-app").join(FIXTURE_SESSION);
+
+        let metadata = get_session_metadata(&session_file);
+        assert_eq!(metadata.summary.as_deref(), Some("Tail summary"));
+        assert_eq!(metadata.first_message.as_deref(), Some("Fix the login bug"));
+    }
+
+    /// Recorded session with MultiEdit and NotebookEdit tool calls.
+    const MULTI_NOTEBOOK_FIXTURE: &str =
+        include_str!("../fixtures/sessions/multi-notebook-edits.jsonl");
+    const FIXTURE_PROJECT: &str = "/home/dev/notebook-app";
+
+    /// Write a recorded fixture to a temp session file, returned with its directory.
+    fn fixture_session_file(name: &str, content: &str) -> (TempDir, PathBuf) {
+        let projects_dir = fixture_projects_dir(name);
+        write_fixture_session(
+            &projects_dir,
+            "-home-dev-notebook-app",
+            FIXTURE_SESSION,
+            content,
+        );
+        let session_file = projects_dir
+            .join("-home-dev-notebook-app")
+            .join(FIXTURE_SESSION);
+        (projects_dir, session_file)
+    }
+
+    #[test]
+    fn test_extract_multi_edit_changes() {
+        let input = serde_json::json!({
+            "file_path": "/proj/a.rs",
+            "edits": [
+                {"old_string": "", "new_string": "fn a() {}"},
+                {"old_string": "a()", "new_string": "b()", "replace_all": true}
+            ]
+        });
+        let changes = extract_tool_file_changes("MultiEdit", &input);
+        assert_eq!(changes.len(), 2);
+        assert!(!changes[0].has_prior_content);
+        assert!(!changes[1].has_prior_content);
+        assert_eq!(changes[1].old_string, "a()");
+        assert_eq!(changes[1].new_string, "b()");
+        assert!(!changes[0].replace_all);
+        assert!(changes[1].replace_all);
+
+        assert!(extract_tool_file_changes("MultiEdit", &serde_json::json!({})).is_empty());
+        assert!(extract_tool_file_changes("Read", &input).is_empty());
+    }
+
+    #[test]
+    fn test_file_edits_include_multi_and_notebook_edits() {
+        let (_dir, session_file) =
+            fixture_session_file("multi-notebook-edits", MULTI_NOTEBOOK_FIXTURE);
+        let edits = get_file_edits_from_session_file(&session_file, FIXTURE_PROJECT);
+
+        let summary: Vec<(&str, &FileEditType)> = edits
+            .iter()
+            .map(|e| (e.path.as_str(), &e.edit_type))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("analysis.ipynb", &FileEditType::Modified),
+                ("src/config.py", &FileEditType::Modified),
+                ("src/new_module.py", &FileEditType::Added),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_diffs_for_multi_edit() {
+        let (_dir, session_file) = fixture_session_file("multi-edit-diffs", MULTI_NOTEBOOK_FIXTURE);
+        let diffs =
+            get_file_diffs_from_session_file(&session_file, FIXTURE_PROJECT, "src/config.py");
+
+        assert_eq!(diffs.len(), 3);
+        assert_eq!(diffs[0].old_string, "def load_cfg(path):");
+        assert_eq!(diffs[0].new_string, "def load_config(path):");
+        assert_eq!(diffs[2].old_string, "cfg.");
+        let sequences: Vec<u32> = diffs.iter().map(|d| d.sequence).collect();
+        assert_eq!(sequences, vec![0, 1, 2]);
+        assert!(diffs.iter().all(|d| d.cell_id.is_none()));
+        assert_eq!(
+            diffs[0].timestamp.as_deref(),
+            Some("2025-10-01T10:00:05.000Z")
+        );
+    }
+
+    #[test]
+    fn test_file_diffs_for_notebook_edit() {
+        let (_dir, session_file) = fixture_session_file("notebook-diffs", MULTI_NOTEBOOK_FIXTURE);
+        let diffs = get_file_diffs_from_session_file(
+            &session_file,
+            FIXTURE_PROJECT,
+            "/home/dev/notebook-app/analysis.ipynb",
+        );
+
+        let cells: Vec<(Option<&str>, Option<&str>, &str)> = diffs
+            .iter()
+            .map(|d| {
+                (
+                    d.cell_id.as_deref(),
+                    d.edit_mode.as_deref(),
+                    d.new_string.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                (
+                    Some("a1b2c3"),
+                    Some("replace"),
+                    "df = load_config(\"data.csv\")\ndf.describe()"
+                ),
+                (Some("a1b2c3"), Some("insert"), "# Summary"),
+                (Some("d4e5f6"), Some("delete"), ""),
+            ]
+        );
+    }
+
+    /// Recorded session with file deletions and renames done through Bash.
+    const BASH_FILE_OPS_FIXTURE: &str = include_str!("../fixtures/sessions/bash-file-ops.jsonl");
+
+    #[test]
+    fn test_file_edits_from_bash_commands() {
+        let (_dir, session_file) = fixture_session_file("bash-file-ops", BASH_FILE_OPS_FIXTURE);
+        let edits = get_file_edits_from_session_file(&session_file, "/home/dev/web-app");
+
+        let summary: Vec<(&str, &FileEditType, Option<&str>, EditConfidence)> = edits
+            .iter()
+            .map(|e| {
+                (
+                    e.path.as_str(),
+                    &e.edit_type,
+                    e.renamed_from.as_deref(),
+                    e.confidence,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "build.log",
+                    &FileEditType::Modified,
+                    None,
+                    EditConfidence::Low
+                ),
+                ("notes.txt", &FileEditType::Added, None, EditConfidence::Low),
+                (
+                    "scripts/legacy.sh",
+                    &FileEditType::Deleted,
+                    None,
+                    EditConfidence::Low
+                ),
+                (
+                    "src/new_name.ts",
+                    &FileEditType::Renamed,
+                    Some("src/old_name.ts"),
+                    EditConfidence::High
+                ),
+            ]
+        );
+    }
+
     #[test]
-    fn test_is_temp_project() {
-        assert!(is_temp_project(
-            "-private-var-folders-8s-x9ypf18955j7w6-zgzqtpclr0000gn-T--tmp08X8zw"
-        ));
-        assert!(!is_temp_project("-Users-ramos-cupcake-cupcake-rego-cupcake-rewrite"));
+    fn test_tracker_resumes_from_edits() {
+        let mut tracker = FileEditTracker::new();
+        let bash = |command: &str| serde_json::json!({ "command": command });
+        tracker.record_tool_use("Bash", &bash("mv a.rs b.rs"), "/proj", None, Some("t1"));
+        let edits = tracker.finish();
+
+        // A later rename continues the chain from the original path
+        let mut resumed = FileEditTracker::from_edits(&edits, KnownDirs::default());
+        resumed.record_tool_use("Bash", &bash("mv b.rs c.rs"), "/proj", None, Some("t2"));
+        let edits = resumed.finish();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].path, "c.rs");
+        assert_eq!(edits[0].edit_type, FileEditType::Renamed);
+        assert_eq!(edits[0].renamed_from.as_deref(), Some("a.rs"));
+        assert_eq!(edits[0].last_edited_at.as_deref(), Some("t2"));
+
+        // Moving it back is a plain modification
+        let mut resumed = FileEditTracker::from_edits(&edits, KnownDirs::default());
+        resumed.record_tool_use("Bash", &bash("mv c.rs a.rs"), "/proj", None, None);
+        let edits = resumed.finish();
+        assert_eq!(edits[0].path, "a.rs");
+        assert_eq!(edits[0].edit_type, FileEditType::Modified);
+        assert_eq!(edits[0].renamed_from, None);
+
+        // Directories created or used before resuming are still known to be directories
+        let write = serde_json::json!({ "file_path": "/proj/src/lib.rs", "content": "" });
+        let mut tracker = FileEditTracker::new();
+        tracker.record_tool_use("Write", &write, "/proj", None, None);
+        tracker.record_tool_use("Bash", &bash("mkdir out"), "/proj", None, None);
+        let known_dirs = tracker.take_known_dirs();
+        let mut resumed = FileEditTracker::from_edits(&tracker.finish(), known_dirs);
+        resumed.record_tool_use("Bash", &bash("mv main.rs src"), "/proj", None, None);
+        resumed.record_tool_use("Bash", &bash("mv notes.md out"), "/proj", None, None);
+        let edits = resumed.finish();
+        assert_eq!(edits[0].path, "out/notes.md");
+        assert_eq!(edits[0].renamed_from.as_deref(), Some("notes.md"));
+        assert_eq!(edits[2].path, "src/main.rs");
+        assert_eq!(edits[2].renamed_from.as_deref(), Some("main.rs"));
     }
 
     #[test]
     fn bench_discover_projects() {
         let start = Instant::now();
-        let projects = discover_projects();
+        let projects = discover_projects(&DiscoveryRules::default());
         let elapsed = start.elapsed();
         println!(
             "discover_projects: {} projects in {:?}",
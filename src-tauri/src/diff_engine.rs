@@ -0,0 +1,409 @@
+//! Structured diff computation.
+//!
+//! Turns before/after text into hunks of numbered lines with intra-line word
+//! highlights, so the frontend doesn't have to diff large files itself. Line diffs come
+//! from libgit2; word highlights pair each removed line with the added line at the same
+//! position in its change block and diff their tokens. Results can be paged by hunk.
+
+use git2::{DiffLineType, Patch};
+use serde::{Deserialize, Serialize};
+
+/// Default number of unchanged lines shown around each change.
+const DEFAULT_CONTEXT_LINES: u32 = 3;
+/// Lines with more tokens than this get no word highlights (the token diff is quadratic).
+const MAX_WORD_DIFF_TOKENS: usize = 500;
+
+/// Options for computing a structured diff.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StructuredDiffOptions {
+    /// Unchanged lines shown around each change
+    pub context_lines: u32,
+    /// Ignore whitespace when comparing lines
+    pub ignore_whitespace: bool,
+    /// Index of the first hunk to return
+    pub hunk_offset: usize,
+    /// Maximum number of hunks to return (None for all)
+    pub hunk_limit: Option<usize>,
+}
+
+impl Default for StructuredDiffOptions {
+    fn default() -> Self {
+        Self {
+            context_lines: DEFAULT_CONTEXT_LINES,
+            ignore_whitespace: false,
+            hunk_offset: 0,
+            hunk_limit: None,
+        }
+    }
+}
+
+/// Role of a line in a hunk.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// A changed range within a line, in UTF-16 code units (JavaScript string offsets).
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WordSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// One line of a hunk.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    /// Line number in the old content (None for added lines)
+    pub old_line: Option<u32>,
+    /// Line number in the new content (None for removed lines)
+    pub new_line: Option<u32>,
+    /// Line content without its line ending
+    pub content: String,
+    /// Changed words within an added or removed line
+    pub highlights: Vec<WordSpan>,
+}
+
+/// A contiguous group of changes with surrounding context.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    /// First line of the hunk in the old content (1-based)
+    pub old_start: u32,
+    /// Number of old content lines in the hunk
+    pub old_lines: u32,
+    /// First line of the hunk in the new content (1-based)
+    pub new_start: u32,
+    /// Number of new content lines in the hunk
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+/// A page of hunks plus totals for the whole diff.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuredDiff {
+    /// Hunks in the requested page
+    pub hunks: Vec<DiffHunk>,
+    /// Index of the first returned hunk
+    pub hunk_offset: usize,
+    /// Number of hunks in the whole diff
+    pub total_hunks: usize,
+    /// Whether there are more hunks after this page
+    pub has_more: bool,
+    /// Lines added across the whole diff
+    pub additions: usize,
+    /// Lines removed across the whole diff
+    pub deletions: usize,
+}
+
+impl StructuredDiff {
+    /// A diff with no changes.
+    pub fn empty() -> Self {
+        Self {
+            hunks: Vec::new(),
+            hunk_offset: 0,
+            total_hunks: 0,
+            has_more: false,
+            additions: 0,
+            deletions: 0,
+        }
+    }
+}
+
+/// Compute the structured diff between two texts.
+pub fn compute_diff(
+    old: &str,
+    new: &str,
+    options: &StructuredDiffOptions,
+) -> Result<StructuredDiff, String> {
+    let mut diff_options = git2::DiffOptions::new();
+    diff_options
+        .context_lines(options.context_lines)
+        .ignore_whitespace(options.ignore_whitespace);
+
+    let patch = Patch::from_buffers(
+        old.as_bytes(),
+        None,
+        new.as_bytes(),
+        None,
+        Some(&mut diff_options),
+    )
+    .map_err(|e| format!("Failed to compute diff: {}", e))?;
+
+    let (_, additions, deletions) = patch
+        .line_stats()
+        .map_err(|e| format!("Failed to count lines: {}", e))?;
+
+    let total_hunks = patch.num_hunks();
+    let end = match options.hunk_limit {
+        Some(limit) => total_hunks.min(options.hunk_offset.saturating_add(limit)),
+        None => total_hunks,
+    };
+
+    let mut hunks = Vec::new();
+    for hunk_idx in options.hunk_offset..end {
+        let (hunk, line_count) = patch
+            .hunk(hunk_idx)
+            .map_err(|e| format!("Failed to read hunk: {}", e))?;
+
+        let mut lines = Vec::with_capacity(line_count);
+        for line_idx in 0..line_count {
+            let line = patch
+                .line_in_hunk(hunk_idx, line_idx)
+                .map_err(|e| format!("Failed to read diff line: {}", e))?;
+            let kind = match line.origin_value() {
+                DiffLineType::Context => DiffLineKind::Context,
+                DiffLineType::Addition => DiffLineKind::Added,
+                DiffLineType::Deletion => DiffLineKind::Removed,
+                // "\ No newline at end of file" markers
+                _ => continue,
+            };
+            let content = String::from_utf8_lossy(line.content());
+            lines.push(DiffLine {
+                kind,
+                old_line: line.old_lineno(),
+                new_line: line.new_lineno(),
+                content: content.trim_end_matches(['\n', '\r']).to_string(),
+                highlights: Vec::new(),
+            });
+        }
+        highlight_words(&mut lines);
+
+        hunks.push(DiffHunk {
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
+    }
+
+    Ok(StructuredDiff {
+        hunks,
+        hunk_offset: options.hunk_offset,
+        total_hunks,
+        has_more: end < total_hunks,
+        additions,
+        deletions,
+    })
+}
+
+/// Add word highlights to each block of removed lines followed by added lines.
+fn highlight_words(lines: &mut [DiffLine]) {
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].kind != DiffLineKind::Removed {
+            i += 1;
+            continue;
+        }
+        let removed_start = i;
+        while i < lines.len() && lines[i].kind == DiffLineKind::Removed {
+            i += 1;
+        }
+        let added_start = i;
+        while i < lines.len() && lines[i].kind == DiffLineKind::Added {
+            i += 1;
+        }
+
+        let pairs = (added_start - removed_start).min(i - added_start);
+        for offset in 0..pairs {
+            let (old_spans, new_spans) = word_diff(
+                &lines[removed_start + offset].content,
+                &lines[added_start + offset].content,
+            );
+            lines[removed_start + offset].highlights = old_spans;
+            lines[added_start + offset].highlights = new_spans;
+        }
+    }
+}
+
+/// Changed spans of two lines, compared token by token.
+fn word_diff(old: &str, new: &str) -> (Vec<WordSpan>, Vec<WordSpan>) {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+    if old_tokens.len() > MAX_WORD_DIFF_TOKENS || new_tokens.len() > MAX_WORD_DIFF_TOKENS {
+        return (Vec::new(), Vec::new());
+    }
+
+    // Longest common subsequence table over token texts
+    let (n, m) = (old_tokens.len(), new_tokens.len());
+    let mut lcs = vec![vec![0u16; m + 1]; n + 1];
+    for a in (0..n).rev() {
+        for b in (0..m).rev() {
+            lcs[a][b] = if old_tokens[a].text == new_tokens[b].text {
+                lcs[a + 1][b + 1] + 1
+            } else {
+                lcs[a + 1][b].max(lcs[a][b + 1])
+            };
+        }
+    }
+
+    let mut old_spans = Vec::new();
+    let mut new_spans = Vec::new();
+    let (mut a, mut b) = (0, 0);
+    while a < n || b < m {
+        if a < n && b < m && old_tokens[a].text == new_tokens[b].text {
+            a += 1;
+            b += 1;
+        } else if b < m && (a == n || lcs[a][b + 1] >= lcs[a + 1][b]) {
+            push_span(&mut new_spans, new_tokens[b].span);
+            b += 1;
+        } else {
+            push_span(&mut old_spans, old_tokens[a].span);
+            a += 1;
+        }
+    }
+
+    (old_spans, new_spans)
+}
+
+/// Extend the last span when `span` directly follows it.
+fn push_span(spans: &mut Vec<WordSpan>, span: WordSpan) {
+    match spans.last_mut() {
+        Some(last) if last.end == span.start => last.end = span.end,
+        _ => spans.push(span),
+    }
+}
+
+struct Token<'a> {
+    text: &'a str,
+    span: WordSpan,
+}
+
+/// Split a line into words (letters, digits, `_`), whitespace runs and single symbols.
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Symbol,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Symbol
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    let mut utf16_pos = 0;
+    while let Some((start, c)) = chars.next() {
+        let token_class = class(c);
+        let mut end = start + c.len_utf8();
+        let mut utf16_len = c.len_utf16();
+        if token_class != Class::Symbol {
+            while let Some(&(idx, next)) = chars.peek() {
+                if class(next) != token_class {
+                    break;
+                }
+                end = idx + next.len_utf8();
+                utf16_len += next.len_utf16();
+                chars.next();
+            }
+        }
+        tokens.push(Token {
+            text: &line[start..end],
+            span: WordSpan {
+                start: utf16_pos,
+                end: utf16_pos + utf16_len,
+            },
+        });
+        utf16_pos += utf16_len;
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(diff: &StructuredDiff) -> Vec<String> {
+        diff.hunks
+            .iter()
+            .flat_map(|h| &h.lines)
+            .map(|l| {
+                let marker = match l.kind {
+                    DiffLineKind::Context => ' ',
+                    DiffLineKind::Added => '+',
+                    DiffLineKind::Removed => '-',
+                };
+                format!("{}{:?}{:?} {}", marker, l.old_line, l.new_line, l.content)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hunks_and_word_highlights() {
+        let old = "fn main() {\n    let total = add(1, 2);\n    println!(\"{}\", total);\n}\n";
+        let new = "fn main() {\n    let total = sum(1, 2, 3);\n    println!(\"{}\", total);\n}\n";
+        let diff = compute_diff(old, new, &StructuredDiffOptions::default()).unwrap();
+
+        assert_eq!(diff.total_hunks, 1);
+        assert_eq!((diff.additions, diff.deletions), (1, 1));
+        assert_eq!(
+            summary(&diff),
+            vec![
+                " Some(1)Some(1) fn main() {",
+                "-Some(2)None     let total = add(1, 2);",
+                "+NoneSome(2)     let total = sum(1, 2, 3);",
+                " Some(3)Some(3)     println!(\"{}\", total);",
+                " Some(4)Some(4) }",
+            ]
+        );
+
+        let removed = &diff.hunks[0].lines[1];
+        let added = &diff.hunks[0].lines[2];
+        let spans = |line: &DiffLine| -> Vec<String> {
+            let units: Vec<u16> = line.content.encode_utf16().collect();
+            line.highlights
+                .iter()
+                .map(|s| String::from_utf16(&units[s.start..s.end]).unwrap())
+                .collect()
+        };
+        assert_eq!(spans(removed), vec!["add"]);
+        assert_eq!(spans(added), vec!["sum", ", 3"]);
+    }
+
+    #[test]
+    fn test_ignore_whitespace_and_paging() {
+        let old: String = (1..=40).map(|i| format!("line {}\n", i)).collect();
+        let new = old
+            .replace("line 5\n", "line five\n")
+            .replace("line 20\n", "  line 20\n")
+            .replace("line 35\n", "line thirty-five\n");
+
+        let diff = compute_diff(&old, &new, &StructuredDiffOptions::default()).unwrap();
+        assert_eq!(diff.total_hunks, 3);
+
+        let options = StructuredDiffOptions {
+            ignore_whitespace: true,
+            hunk_offset: 1,
+            hunk_limit: Some(1),
+            ..Default::default()
+        };
+        let page = compute_diff(&old, &new, &options).unwrap();
+        assert_eq!(page.total_hunks, 2);
+        assert_eq!(page.hunks.len(), 1);
+        assert!(!page.has_more);
+        assert_eq!(page.hunks[0].new_start, 32);
+        assert!(summary(&page).contains(&"+NoneSome(35) line thirty-five".to_string()));
+    }
+
+    #[test]
+    fn test_tokens_use_utf16_offsets() {
+        let (old, new) = word_diff("naïve 🎉 x", "naïve 🎉 y");
+        assert_eq!(old, vec![WordSpan { start: 9, end: 10 }]);
+        assert_eq!(new, vec![WordSpan { start: 9, end: 10 }]);
+    }
+}
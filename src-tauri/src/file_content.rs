@@ -0,0 +1,292 @@
+//! File content classification.
+//!
+//! Decides whether raw file bytes are text (and in which encoding), binary data or an
+//! image, decodes text to UTF-8 and caps how much of it is sent to the frontend.
+//!
+//! Detection follows git's heuristic: content with a NUL byte in its first 8000 bytes is
+//! binary, unless a UTF-16 byte order mark says otherwise. Recognised image headers are
+//! always binary. Text that isn't valid UTF-8 is decoded as Latin-1, which maps every
+//! byte to a character.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Maximum bytes of text content returned for one side of a diff.
+pub const MAX_TEXT_BYTES: usize = 1024 * 1024;
+/// Bytes inspected for a NUL when classifying content as binary.
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// Encoding text content was decoded from.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TextEncoding {
+    Utf8,
+    /// UTF-8 with a byte order mark
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// Not valid UTF-8; decoded byte for byte as ISO-8859-1
+    Latin1,
+}
+
+/// Format and dimensions of an image.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfo {
+    /// Image format: "png", "jpeg", "gif", "bmp" or "webp"
+    pub format: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// What a piece of content turned out to be.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentInfo {
+    /// Size of the full content in bytes
+    pub size_bytes: u64,
+    /// Encoding of text content (None for binary content)
+    pub encoding: Option<TextEncoding>,
+    /// Whether the decoded text was cut off at the size cap
+    pub truncated: bool,
+    /// Format and dimensions for recognised image formats
+    pub image: Option<ImageInfo>,
+}
+
+impl ContentInfo {
+    /// Whether the content is binary (not text).
+    pub fn is_binary(&self) -> bool {
+        self.encoding.is_none()
+    }
+}
+
+/// Decoded text (empty for binary content) and what the content is.
+#[derive(Debug, Clone)]
+pub struct DecodedContent {
+    pub text: String,
+    pub info: ContentInfo,
+}
+
+/// Classify and decode content, capping text at `MAX_TEXT_BYTES`.
+///
+/// `bytes` may be a prefix of content whose full size is `size_bytes`.
+pub fn decode_content(bytes: &[u8], size_bytes: u64) -> DecodedContent {
+    decode_content_capped(bytes, size_bytes, MAX_TEXT_BYTES)
+}
+
+/// Read up to `max_bytes` of a file, returning the bytes read and the file's full size.
+pub fn read_file_prefix(path: &Path, max_bytes: usize) -> io::Result<(Vec<u8>, u64)> {
+    let file = File::open(path)?;
+    let size_bytes = file.metadata()?.len();
+    let mut bytes = Vec::with_capacity(max_bytes.min(size_bytes as usize));
+    file.take(max_bytes as u64).read_to_end(&mut bytes)?;
+    Ok((bytes, size_bytes))
+}
+
+fn decode_content_capped(bytes: &[u8], size_bytes: u64, max_text_bytes: usize) -> DecodedContent {
+    let truncated = size_bytes > max_text_bytes as u64 || bytes.len() > max_text_bytes;
+    let capped = &bytes[..bytes.len().min(max_text_bytes)];
+
+    let image = image_info(bytes);
+    let decoded = if image.is_some() {
+        None
+    } else if let Some(rest) = capped.strip_prefix(b"\xEF\xBB\xBF") {
+        decode_utf8(rest, truncated).map(|text| (text, TextEncoding::Utf8Bom))
+    } else if let Some(rest) = capped.strip_prefix(b"\xFF\xFE") {
+        Some((
+            decode_utf16(rest, u16::from_le_bytes),
+            TextEncoding::Utf16Le,
+        ))
+    } else if let Some(rest) = capped.strip_prefix(b"\xFE\xFF") {
+        Some((
+            decode_utf16(rest, u16::from_be_bytes),
+            TextEncoding::Utf16Be,
+        ))
+    } else if capped[..capped.len().min(BINARY_SNIFF_BYTES)].contains(&0) {
+        None
+    } else {
+        match decode_utf8(capped, truncated) {
+            Some(text) => Some((text, TextEncoding::Utf8)),
+            None => Some((
+                capped.iter().map(|&b| b as char).collect(),
+                TextEncoding::Latin1,
+            )),
+        }
+    };
+
+    let (text, encoding) = match decoded {
+        Some((text, encoding)) => (text, Some(encoding)),
+        None => (String::new(), None),
+    };
+
+    DecodedContent {
+        text,
+        info: ContentInfo {
+            size_bytes,
+            encoding,
+            truncated: truncated && encoding.is_some(),
+            image,
+        },
+    }
+}
+
+/// Decode UTF-8, dropping a sequence cut off at the end of truncated content.
+fn decode_utf8(bytes: &[u8], truncated: bool) -> Option<String> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text.to_string()),
+        // error_len() is None when the input ends in the middle of a character
+        Err(e) if truncated && e.error_len().is_none() => {
+            Some(String::from_utf8_lossy(&bytes[..e.valid_up_to()]).to_string())
+        }
+        Err(_) => None,
+    }
+}
+
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| to_unit([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Recognise common image formats from their headers.
+fn image_info(bytes: &[u8]) -> Option<ImageInfo> {
+    let u16_be = |at: usize| Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let u16_le = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let u32_be = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let u32_le = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let image = |format: &str, width: u32, height: u32| ImageInfo {
+        format: format.to_string(),
+        width,
+        height,
+    };
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some(image("png", u32_be(16)?, u32_be(20)?));
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some(image("gif", u16_le(6)? as u32, u16_le(8)? as u32));
+    }
+    // "BM" alone is too common at the start of text; also require a known DIB header size
+    let bmp_header = u32_le(14).is_some_and(|size| matches!(size, 12 | 40 | 52 | 56 | 108 | 124));
+    if bytes.starts_with(b"BM") && bmp_header {
+        let height = u32_le(22)? as i32;
+        return Some(image("bmp", u32_le(18)?, height.unsigned_abs()));
+    }
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        let (width, height) = match bytes.get(12..16)? {
+            b"VP8X" => {
+                let width = u32_le(24)? & 0xFF_FFFF;
+                let height = u32_le(27)? & 0xFF_FFFF;
+                (width + 1, height + 1)
+            }
+            b"VP8L" => {
+                let bits = u32_le(21)?;
+                ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1)
+            }
+            b"VP8 " => ((u16_le(26)? & 0x3FFF) as u32, (u16_le(28)? & 0x3FFF) as u32),
+            _ => return None,
+        };
+        return Some(image("webp", width, height));
+    }
+    if bytes.starts_with(b"\xFF\xD8") {
+        // Walk the JPEG segments to the start-of-frame marker holding the dimensions
+        let mut at = 2;
+        while at + 4 <= bytes.len() {
+            if bytes[at] != 0xFF {
+                return None;
+            }
+            let marker = bytes[at + 1];
+            let length = u16_be(at + 2)? as usize;
+            let is_frame = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+            if is_frame {
+                return Some(image(
+                    "jpeg",
+                    u16_be(at + 7)? as u32,
+                    u16_be(at + 5)? as u32,
+                ));
+            }
+            at += 2 + length;
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_encodings() {
+        let utf8 = decode_content("héllo\n".as_bytes(), 7);
+        assert_eq!(utf8.text, "héllo\n");
+        assert_eq!(utf8.info.encoding, Some(TextEncoding::Utf8));
+
+        let bom = decode_content(b"\xEF\xBB\xBFx = 1\n", 9);
+        assert_eq!(bom.text, "x = 1\n");
+        assert_eq!(bom.info.encoding, Some(TextEncoding::Utf8Bom));
+
+        let utf16 = decode_content(b"\xFF\xFEh\x00i\x00", 6);
+        assert_eq!(utf16.text, "hi");
+        assert_eq!(utf16.info.encoding, Some(TextEncoding::Utf16Le));
+
+        let latin1 = decode_content(b"caf\xE9\n", 5);
+        assert_eq!(latin1.text, "café\n");
+        assert_eq!(latin1.info.encoding, Some(TextEncoding::Latin1));
+    }
+
+    #[test]
+    fn test_truncation_keeps_whole_characters() {
+        // "ééé" is 6 bytes; a 5 byte cap splits the last character
+        let decoded = decode_content_capped("ééé".as_bytes(), 6, 5);
+        assert_eq!(decoded.text, "éé");
+        assert!(decoded.info.truncated);
+        assert_eq!(decoded.info.size_bytes, 6);
+        assert_eq!(decoded.info.encoding, Some(TextEncoding::Utf8));
+    }
+
+    #[test]
+    fn test_binary_and_images() {
+        let binary = decode_content(b"\x7FELF\x02\x01\x01\x00\x00", 9);
+        assert!(binary.info.is_binary());
+        assert!(binary.text.is_empty());
+        assert_eq!(binary.info.image, None);
+
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0DIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        let decoded = decode_content(&png, png.len() as u64);
+        assert!(decoded.info.is_binary());
+        assert_eq!(
+            decoded.info.image,
+            Some(ImageInfo {
+                format: "png".to_string(),
+                width: 640,
+                height: 480
+            })
+        );
+
+        let gif = b"GIF89a\x20\x00\x10\x00\x00\x00\x00";
+        assert_eq!(
+            decode_content(gif, gif.len() as u64)
+                .info
+                .image
+                .map(|i| (i.width, i.height)),
+            Some((32, 16))
+        );
+
+        // SOI, APP0 (length 4), SOF0 with height 200 and width 300
+        let jpeg = b"\xFF\xD8\xFF\xE0\x00\x04\x00\x00\xFF\xC0\x00\x11\x08\x00\xC8\x01\x2C\x03";
+        assert_eq!(
+            decode_content(jpeg, jpeg.len() as u64)
+                .info
+                .image
+                .map(|i| (i.format, i.width, i.height)),
+            Some(("jpeg".to_string(), 300, 200))
+        );
+    }
+}
@@ -1,35 +1,266 @@
 //! Git integration for file diffs.
 //!
-//! Provides functionality to get file contents from HEAD and working directory
-//! for comparison in the diff viewer.
+//! Provides functionality to get file contents from a git revision (HEAD by default)
+//! and the working directory for comparison in the diff viewer. Content is classified
+//! first so binary files, images and oversized text aren't shipped whole to the UI.
+//! The comparison can also be returned as a structured diff computed server-side.
 
-use git2::Repository;
-use std::fs;
-use std::path::Path;
+use chrono::DateTime;
+use git2::{Blob, BranchType, Commit, Delta, DiffFindOptions, Oid, Repository, Sort};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
-/// Result of getting a git file diff - original (HEAD) and current content.
-#[derive(Clone, serde::Serialize)]
+use crate::diff_engine::{compute_diff, StructuredDiff, StructuredDiffOptions};
+use crate::file_content::{
+    decode_content, read_file_prefix, ContentInfo, DecodedContent, MAX_TEXT_BYTES,
+};
+
+/// Branches tried, in order, when the repository has no `origin/HEAD`.
+const DEFAULT_BRANCH_CANDIDATES: [&str; 2] = ["main", "master"];
+
+/// What a git file diff can show.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum GitDiffKind {
+    /// Both sides are text within the size cap
+    Text,
+    /// Text, but at least one side was truncated to a preview
+    Large,
+    /// At least one side is binary; no content is returned
+    Binary,
+    /// At least one side is a recognised image; see the content info for dimensions
+    Image,
+}
+
+/// Result of getting a git file diff - original (revision) and current content.
+#[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GitFileDiff {
-    /// Content of the file at HEAD (original)
+    /// What the diff can show; the content fields are empty for binary and image diffs
+    pub kind: GitDiffKind,
+    /// Content of the file at the compared revision (original), decoded to UTF-8
     pub original: String,
-    /// Current content of the file in working directory
+    /// Current content of the file in working directory, decoded to UTF-8
     pub current: String,
-    /// Whether the file exists at HEAD
+    /// Whether the file exists at the compared revision
+    pub exists_at_head: bool,
+    /// Whether the file exists in working directory
+    pub exists_in_workdir: bool,
+    /// Size, encoding and image details of the original content
+    pub original_info: ContentInfo,
+    /// Size, encoding and image details of the current content
+    pub current_info: ContentInfo,
+}
+
+/// Revision the working directory is compared against.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum GitRevision {
+    /// The current HEAD commit
+    #[default]
+    Head,
+    /// The staged content in the index
+    Index,
+    /// The tip of a local or remote-tracking branch
+    Branch { name: String },
+    /// A commit hash (full or abbreviated)
+    Commit { id: String },
+    /// The merge-base of HEAD and the default branch
+    MergeBase,
+    /// The commit that was HEAD when a session started: the newest commit on
+    /// `git_branch` (HEAD if unknown) made at or before `started_at` (ISO 8601)
+    SessionStart {
+        git_branch: Option<String>,
+        started_at: String,
+    },
+}
+
+/// Structured diff of a file between a git revision and the working directory.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStructuredDiff {
+    /// What the diff can show; there are no hunks for binary and image diffs
+    pub kind: GitDiffKind,
+    /// Hunks computed from the (possibly truncated) text of both sides
+    pub diff: StructuredDiff,
+    /// Path the original content was read from when git detects the file as renamed
+    pub renamed_from: Option<String>,
+    /// Whether the file (or its rename source) exists at the compared revision
     pub exists_at_head: bool,
     /// Whether the file exists in working directory
     pub exists_in_workdir: bool,
+    /// Size, encoding and image details of the original content
+    pub original_info: ContentInfo,
+    /// Size, encoding and image details of the current content
+    pub current_info: ContentInfo,
+}
+
+/// Both sides of a file comparison, decoded and classified.
+struct FileSides {
+    kind: GitDiffKind,
+    original: DecodedContent,
+    current: DecodedContent,
+    exists_at_head: bool,
+    exists_in_workdir: bool,
+    renamed_from: Option<String>,
 }
 
-/// Get the original (HEAD) and current content of a file for diff comparison.
+impl FileSides {
+    fn has_text(&self) -> bool {
+        matches!(self.kind, GitDiffKind::Text | GitDiffKind::Large)
+    }
+}
+
+/// Get the original (revision) and current content of a file for diff comparison.
 ///
 /// # Arguments
 /// * `project_path` - Path to the project/repository root (used as fallback)
 /// * `file_path` - Path to the file (can be absolute or relative to project)
+/// * `revision` - Revision to take the original content from
 ///
 /// This function discovers the git repository that actually contains the file,
 /// which may be different from project_path when editing files outside the project.
-pub fn get_git_file_diff(project_path: &str, file_path: &str) -> Result<GitFileDiff, String> {
+pub fn get_git_file_diff(
+    project_path: &str,
+    file_path: &str,
+    revision: &GitRevision,
+) -> Result<GitFileDiff, String> {
+    let sides = read_file_sides(project_path, file_path, revision, false)?;
+    let has_text = sides.has_text();
+
+    Ok(GitFileDiff {
+        kind: sides.kind,
+        original: if has_text {
+            sides.original.text
+        } else {
+            String::new()
+        },
+        current: if has_text {
+            sides.current.text
+        } else {
+            String::new()
+        },
+        exists_at_head: sides.exists_at_head,
+        exists_in_workdir: sides.exists_in_workdir,
+        original_info: sides.original.info,
+        current_info: sides.current.info,
+    })
+}
+
+/// Compute the structured diff of a file between a revision and the working directory.
+///
+/// When the file doesn't exist at the revision but git detects it as renamed from a
+/// path that does, the original content is taken from that path.
+pub fn get_git_structured_diff(
+    project_path: &str,
+    file_path: &str,
+    revision: &GitRevision,
+    options: &StructuredDiffOptions,
+) -> Result<GitStructuredDiff, String> {
+    let sides = read_file_sides(project_path, file_path, revision, true)?;
+    let diff = if sides.has_text() {
+        compute_diff(&sides.original.text, &sides.current.text, options)?
+    } else {
+        StructuredDiff::empty()
+    };
+
+    Ok(GitStructuredDiff {
+        kind: sides.kind,
+        diff,
+        renamed_from: sides.renamed_from,
+        exists_at_head: sides.exists_at_head,
+        exists_in_workdir: sides.exists_in_workdir,
+        original_info: sides.original.info,
+        current_info: sides.current.info,
+    })
+}
+
+/// Read and classify a file at a revision and in the working directory.
+fn read_file_sides(
+    project_path: &str,
+    file_path: &str,
+    revision: &GitRevision,
+    follow_renames: bool,
+) -> Result<FileSides, String> {
+    let (repo, relative_path, actual_file_path) =
+        open_repository_for_file(project_path, file_path)?;
+
+    // Try to get file content at the revision using the relative path
+    let commit = match revision {
+        GitRevision::Index => None,
+        _ => Some(resolve_revision(&repo, revision)?),
+    };
+    let mut original_blob = match &commit {
+        Some(commit) => find_commit_blob(&repo, commit, &relative_path)?,
+        None => find_index_blob(&repo, &relative_path)?,
+    };
+    let mut renamed_from = None;
+    if original_blob.is_none() && follow_renames {
+        if let Some((blob, old_path)) = find_rename_source(&repo, commit.as_ref(), &relative_path)?
+        {
+            original_blob = Some(blob);
+            renamed_from = Some(old_path);
+        }
+    }
+    let exists_at_head = original_blob.is_some();
+    // A missing side is empty text; the file is new or was deleted
+    let original = match &original_blob {
+        Some(blob) => decode_content(blob.content(), blob.size() as u64),
+        None => decode_content(&[], 0),
+    };
+
+    // Get current file content from working directory, reading no more than the cap
+    let exists_in_workdir = actual_file_path.exists();
+    let current = if exists_in_workdir {
+        let (bytes, size_bytes) = read_file_prefix(&actual_file_path, MAX_TEXT_BYTES)
+            .map_err(|e| format!("Failed to read current file: {}", e))?;
+        decode_content(&bytes, size_bytes)
+    } else {
+        decode_content(&[], 0)
+    };
+
+    let infos = [&original.info, &current.info];
+    let kind = if infos.iter().any(|info| info.image.is_some()) {
+        GitDiffKind::Image
+    } else if infos.iter().any(|info| info.is_binary()) {
+        GitDiffKind::Binary
+    } else if infos.iter().any(|info| info.truncated) {
+        GitDiffKind::Large
+    } else {
+        GitDiffKind::Text
+    };
+
+    Ok(FileSides {
+        kind,
+        original,
+        current,
+        exists_at_head,
+        exists_in_workdir,
+        renamed_from,
+    })
+}
+
+/// Get the content of a file at HEAD, or None if the file doesn't exist at HEAD.
+pub fn get_head_file_content(
+    project_path: &str,
+    file_path: &str,
+) -> Result<Option<String>, String> {
+    let (repo, relative_path, _) = open_repository_for_file(project_path, file_path)?;
+    read_head_blob(&repo, &relative_path)
+}
+
+/// Open the repository containing a file.
+///
+/// Returns the repository, the file's path relative to the repository workdir and
+/// the file's absolute path on disk.
+fn open_repository_for_file(
+    project_path: &str,
+    file_path: &str,
+) -> Result<(Repository, PathBuf, PathBuf), String> {
     // Determine the actual file path on disk
     let actual_file_path = if Path::new(file_path).is_absolute() {
         Path::new(file_path).to_path_buf()
@@ -68,47 +299,411 @@ pub fn get_git_file_diff(project_path: &str, file_path: &str) -> Result<GitFileD
         (repo, Path::new(file_path).to_path_buf())
     };
 
-    // Get HEAD commit
-    let head = repo.head().map_err(|e| format!("Failed to get HEAD: {}", e))?;
-    let head_commit = head
-        .peel_to_commit()
-        .map_err(|e| format!("Failed to get HEAD commit: {}", e))?;
-    let head_tree = head_commit
+    Ok((repo, relative_path, actual_file_path))
+}
+
+/// Read a file from the HEAD tree. Returns None if the file doesn't exist at HEAD.
+fn read_head_blob(repo: &Repository, relative_path: &Path) -> Result<Option<String>, String> {
+    let commit = resolve_revision(repo, &GitRevision::Head)?;
+    let blob = find_commit_blob(repo, &commit, relative_path)?;
+    Ok(blob.map(|blob| String::from_utf8_lossy(blob.content()).to_string()))
+}
+
+/// Find a file's blob in a commit's tree. Returns None if the file doesn't exist in it.
+fn find_commit_blob<'r>(
+    repo: &'r Repository,
+    commit: &Commit,
+    relative_path: &Path,
+) -> Result<Option<Blob<'r>>, String> {
+    let tree = commit
         .tree()
-        .map_err(|e| format!("Failed to get HEAD tree: {}", e))?;
-
-    // Try to get file content from HEAD using the relative path
-    let (original, exists_at_head) = match head_tree.get_path(&relative_path) {
-        Ok(entry) => {
-            let obj = entry
-                .to_object(&repo)
-                .map_err(|e| format!("Failed to get object: {}", e))?;
-            let blob = obj
-                .as_blob()
-                .ok_or_else(|| "Entry is not a blob".to_string())?;
-            let content = String::from_utf8_lossy(blob.content()).to_string();
-            (content, true)
-        }
-        Err(_) => {
-            // File doesn't exist at HEAD (new file)
-            (String::new(), false)
-        }
+        .map_err(|e| format!("Failed to get commit tree: {}", e))?;
+
+    let entry = match tree.get_path(relative_path) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let blob = repo
+        .find_blob(entry.id())
+        .map_err(|_| "Entry is not a blob".to_string())?;
+    Ok(Some(blob))
+}
+
+/// Find a file's staged blob in the index. Returns None if the file isn't staged.
+fn find_index_blob<'r>(
+    repo: &'r Repository,
+    relative_path: &Path,
+) -> Result<Option<Blob<'r>>, String> {
+    let index = repo
+        .index()
+        .map_err(|e| format!("Failed to read index: {}", e))?;
+    let Some(entry) = index.get_path(relative_path, 0) else {
+        return Ok(None);
     };
+    let blob = repo
+        .find_blob(entry.id)
+        .map_err(|e| format!("Failed to get blob: {}", e))?;
+    Ok(Some(blob))
+}
 
-    // Get current file content from working directory
-    let (current, exists_in_workdir) = if actual_file_path.exists() {
-        let content = fs::read_to_string(&actual_file_path)
-            .map_err(|e| format!("Failed to read current file: {}", e))?;
-        (content, true)
-    } else {
-        // File was deleted
-        (String::new(), false)
+/// Find the content a working directory file was renamed from.
+///
+/// Diffs the commit's tree (or the index when `commit` is None) against the working
+/// directory with rename detection, including untracked files so that a plain `mv`
+/// is recognised. Returns the source blob and its path.
+fn find_rename_source<'r>(
+    repo: &'r Repository,
+    commit: Option<&Commit>,
+    relative_path: &Path,
+) -> Result<Option<(Blob<'r>, String)>, String> {
+    let mut options = git2::DiffOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let mut diff = match commit {
+        Some(commit) => {
+            let tree = commit
+                .tree()
+                .map_err(|e| format!("Failed to get commit tree: {}", e))?;
+            repo.diff_tree_to_workdir_with_index(Some(&tree), Some(&mut options))
+        }
+        None => repo.diff_index_to_workdir(None, Some(&mut options)),
+    }
+    .map_err(|e| format!("Failed to diff working directory: {}", e))?;
+
+    diff.find_similar(Some(
+        DiffFindOptions::new().renames(true).for_untracked(true),
+    ))
+    .map_err(|e| format!("Failed to detect renames: {}", e))?;
+
+    let rename = diff.deltas().find(|delta| {
+        delta.status() == Delta::Renamed && delta.new_file().path() == Some(relative_path)
+    });
+    let Some(delta) = rename else {
+        return Ok(None);
+    };
+    let Some(old_path) = delta.old_file().path() else {
+        return Ok(None);
     };
+    let blob = repo
+        .find_blob(delta.old_file().id())
+        .map_err(|e| format!("Failed to get blob: {}", e))?;
+    Ok(Some((blob, old_path.to_string_lossy().to_string())))
+}
 
-    Ok(GitFileDiff {
-        original,
-        current,
-        exists_at_head,
-        exists_in_workdir,
-    })
+/// Resolve a revision (other than the index) to a commit.
+fn resolve_revision<'r>(
+    repo: &'r Repository,
+    revision: &GitRevision,
+) -> Result<Commit<'r>, String> {
+    match revision {
+        GitRevision::Head | GitRevision::Index => head_commit(repo),
+        GitRevision::Branch { name } => branch_commit(repo, name),
+        GitRevision::Commit { id } => repo
+            .revparse_single(id)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|e| format!("Failed to find commit {}: {}", id, e)),
+        GitRevision::MergeBase => {
+            let head = head_commit(repo)?;
+            let default = default_branch_commit(repo)?;
+            let base = repo
+                .merge_base(head.id(), default.id())
+                .map_err(|e| format!("Failed to find merge-base: {}", e))?;
+            find_commit(repo, base)
+        }
+        GitRevision::SessionStart {
+            git_branch,
+            started_at,
+        } => {
+            let started_at = DateTime::parse_from_rfc3339(started_at)
+                .map_err(|e| format!("Invalid session start time: {}", e))?;
+            // The session's branch may since have been deleted; fall back to HEAD
+            let tip = match git_branch {
+                Some(name) => branch_commit(repo, name).or_else(|_| head_commit(repo))?,
+                None => head_commit(repo)?,
+            };
+            commit_at_or_before(repo, tip.id(), started_at.timestamp())
+        }
+    }
+}
+
+fn head_commit(repo: &Repository) -> Result<Commit<'_>, String> {
+    let head = repo
+        .head()
+        .map_err(|e| format!("Failed to get HEAD: {}", e))?;
+    head.peel_to_commit()
+        .map_err(|e| format!("Failed to get HEAD commit: {}", e))
+}
+
+fn find_commit(repo: &Repository, oid: Oid) -> Result<Commit<'_>, String> {
+    repo.find_commit(oid)
+        .map_err(|e| format!("Failed to find commit {}: {}", oid, e))
+}
+
+/// Tip commit of a local branch, or a remote-tracking branch (e.g. "origin/main").
+fn branch_commit<'r>(repo: &'r Repository, name: &str) -> Result<Commit<'r>, String> {
+    let branch = repo
+        .find_branch(name, BranchType::Local)
+        .or_else(|_| repo.find_branch(name, BranchType::Remote))
+        .map_err(|e| format!("Failed to find branch {}: {}", name, e))?;
+    branch
+        .get()
+        .peel_to_commit()
+        .map_err(|e| format!("Failed to get commit of branch {}: {}", name, e))
+}
+
+/// Tip commit of the default branch: the target of `origin/HEAD`, else main or master.
+fn default_branch_commit(repo: &Repository) -> Result<Commit<'_>, String> {
+    if let Ok(reference) = repo.find_reference("refs/remotes/origin/HEAD") {
+        if let Ok(commit) = reference.resolve().and_then(|r| r.peel_to_commit()) {
+            return Ok(commit);
+        }
+    }
+    DEFAULT_BRANCH_CANDIDATES
+        .iter()
+        .find_map(|name| branch_commit(repo, name).ok())
+        .ok_or_else(|| "Failed to determine the default branch".to_string())
+}
+
+/// Newest commit reachable from `tip` whose commit time is at or before `timestamp` (seconds).
+fn commit_at_or_before(repo: &Repository, tip: Oid, timestamp: i64) -> Result<Commit<'_>, String> {
+    let mut revwalk = repo
+        .revwalk()
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+    revwalk
+        .set_sorting(Sort::TIME)
+        .and_then(|_| revwalk.push(tip))
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+
+    for oid in revwalk {
+        let oid = oid.map_err(|e| format!("Failed to walk history: {}", e))?;
+        let commit = find_commit(repo, oid)?;
+        if commit.time().seconds() <= timestamp {
+            return Ok(commit);
+        }
+    }
+    Err("No commit found before the session started".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_get_head_file_content() {
+        let dir = TempDir::new("git-head");
+
+        let repo = Repository::init(&dir).unwrap();
+        fs::write(dir.join("tracked.txt"), "committed\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("tracked.txt")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        // Working copy changes don't affect the HEAD content
+        fs::write(dir.join("tracked.txt"), "changed\n").unwrap();
+        fs::write(dir.join("untracked.txt"), "new\n").unwrap();
+
+        let project = dir.to_str().unwrap();
+        assert_eq!(
+            get_head_file_content(project, "tracked.txt")
+                .unwrap()
+                .as_deref(),
+            Some("committed\n")
+        );
+        assert_eq!(
+            get_head_file_content(project, "untracked.txt").unwrap(),
+            None
+        );
+    }
+
+    /// Commit `content` as tracked.txt on HEAD at the given commit time (seconds).
+    fn commit_file(repo: &Repository, content: &str, time: i64) -> Oid {
+        let workdir = repo.workdir().unwrap();
+        fs::write(workdir.join("tracked.txt"), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("tracked.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature =
+            git2::Signature::new("Test", "test@example.com", &git2::Time::new(time, 0)).unwrap();
+        let parents: Vec<Commit> = head_commit(repo).into_iter().collect();
+        let parents: Vec<&Commit> = parents.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            content,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    /// Write and stage `content` as tracked.txt without committing it.
+    fn stage_file(repo: &Repository, content: &str) {
+        fs::write(repo.workdir().unwrap().join("tracked.txt"), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("tracked.txt")).unwrap();
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn test_get_git_file_diff_at_revisions() {
+        let dir = TempDir::new("git-revs");
+
+        // main: v1 (10:00) -> v2 (11:00); feature branches off v2: v3 (12:00)
+        let repo = Repository::init(&dir).unwrap();
+        let v1 = commit_file(&repo, "v1\n", 1_759_658_400);
+        commit_file(&repo, "v2\n", 1_759_662_000);
+        let main_tip = head_commit(&repo).unwrap();
+        repo.branch("main", &main_tip, true).unwrap();
+        repo.branch("feature", &main_tip, false).unwrap();
+        repo.set_head("refs/heads/feature").unwrap();
+        commit_file(&repo, "v3\n", 1_759_665_600);
+
+        // Stage v4, then leave v5 in the working directory
+        stage_file(&repo, "v4\n");
+        fs::write(dir.join("tracked.txt"), "v5\n").unwrap();
+
+        let project = dir.to_str().unwrap();
+        let original = |revision: GitRevision| {
+            get_git_file_diff(project, "tracked.txt", &revision)
+                .unwrap()
+                .original
+        };
+
+        assert_eq!(original(GitRevision::Head), "v3\n");
+        assert_eq!(original(GitRevision::Index), "v4\n");
+        assert_eq!(
+            original(GitRevision::Branch {
+                name: "main".to_string()
+            }),
+            "v2\n"
+        );
+        assert_eq!(
+            original(GitRevision::Commit {
+                id: v1.to_string()[..7].to_string()
+            }),
+            "v1\n"
+        );
+        assert_eq!(original(GitRevision::MergeBase), "v2\n");
+        assert_eq!(
+            original(GitRevision::SessionStart {
+                git_branch: Some("feature".to_string()),
+                started_at: "2025-10-05T10:30:00Z".to_string(),
+            }),
+            "v1\n"
+        );
+        assert_eq!(
+            original(GitRevision::SessionStart {
+                git_branch: Some("deleted-branch".to_string()),
+                started_at: "2025-10-05T12:30:00Z".to_string(),
+            }),
+            "v3\n"
+        );
+
+        let diff = get_git_file_diff(project, "tracked.txt", &GitRevision::Index).unwrap();
+        assert_eq!(diff.current, "v5\n");
+        assert!(diff.exists_at_head);
+        assert!(get_git_file_diff(
+            project,
+            "tracked.txt",
+            &GitRevision::Branch {
+                name: "missing".to_string()
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_get_git_file_diff_kinds() {
+        let dir = TempDir::new("git-kinds");
+        let repo = Repository::init(&dir).unwrap();
+
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0DIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 16, 0, 0, 0, 8]);
+        fs::write(dir.join("logo.png"), &png).unwrap();
+        fs::write(dir.join("legacy.txt"), b"caf\xE9\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("logo.png")).unwrap();
+        index.add_path(Path::new("legacy.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        fs::write(dir.join("legacy.txt"), b"caf\xE9 cr\xE8me\n").unwrap();
+        fs::write(dir.join("big.log"), "x".repeat(MAX_TEXT_BYTES + 10)).unwrap();
+        fs::write(dir.join("data.bin"), b"\x00\x01\x02").unwrap();
+
+        let project = dir.to_str().unwrap();
+        let diff = |file: &str| get_git_file_diff(project, file, &GitRevision::Head).unwrap();
+
+        let legacy = diff("legacy.txt");
+        assert_eq!(legacy.kind, GitDiffKind::Text);
+        assert_eq!(legacy.original, "café\n");
+        assert_eq!(legacy.current, "café crème\n");
+
+        let logo = diff("logo.png");
+        assert_eq!(logo.kind, GitDiffKind::Image);
+        assert!(logo.original.is_empty());
+        let image = logo.current_info.image.unwrap();
+        assert_eq!((image.width, image.height), (16, 8));
+
+        let big = diff("big.log");
+        assert_eq!(big.kind, GitDiffKind::Large);
+        assert!(!big.exists_at_head);
+        assert_eq!(big.current.len(), MAX_TEXT_BYTES);
+        assert_eq!(big.current_info.size_bytes, MAX_TEXT_BYTES as u64 + 10);
+
+        let data = diff("data.bin");
+        assert_eq!(data.kind, GitDiffKind::Binary);
+        assert!(data.current.is_empty());
+        assert_eq!(data.current_info.size_bytes, 3);
+    }
+
+    #[test]
+    fn test_structured_diff_follows_renames() {
+        let dir = TempDir::new("git-rename");
+        let repo = Repository::init(&dir).unwrap();
+
+        let original: String = (1..=20)
+            .map(|i| format!("fn step_{}() {{}}\n", i))
+            .collect();
+        fs::write(dir.join("old_name.rs"), &original).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("old_name.rs")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        // Move the file without telling git, then change one line
+        fs::remove_file(dir.join("old_name.rs")).unwrap();
+        fs::write(
+            dir.join("new_name.rs"),
+            original.replace("step_7()", "step_seven()"),
+        )
+        .unwrap();
+
+        let project = dir.to_str().unwrap();
+        let options = StructuredDiffOptions::default();
+        let result =
+            get_git_structured_diff(project, "new_name.rs", &GitRevision::Head, &options).unwrap();
+        assert_eq!(result.kind, GitDiffKind::Text);
+        assert_eq!(result.renamed_from.as_deref(), Some("old_name.rs"));
+        assert!(result.exists_at_head);
+        assert_eq!((result.diff.additions, result.diff.deletions), (1, 1));
+        assert_eq!(result.diff.hunks[0].new_start, 4);
+
+        // The raw diff keeps treating the file as new
+        let raw = get_git_file_diff(project, "new_name.rs", &GitRevision::Head).unwrap();
+        assert!(!raw.exists_at_head);
+        assert!(raw.original.is_empty());
+    }
 }
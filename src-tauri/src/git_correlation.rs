@@ -0,0 +1,571 @@
+//! Correlation of session edits with git commits.
+//!
+//! Walks the commits made since a session started and matches the lines they added
+//! against the lines the session's edits introduced, to tell whether each edited file was
+//! committed, partially committed, is still uncommitted or was overwritten. Commits the
+//! agent made itself through Bash `git commit` calls are flagged.
+//!
+//! Lines are compared with surrounding whitespace trimmed, and only distinctive lines
+//! (not blank or bare punctuation) count, since those can't be traced to one edit.
+
+use chrono::DateTime;
+use git2::{Commit, Repository, Sort};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::claude_code::{
+    self, get_all_file_diffs_from_session_file, get_bash_commands_from_session_file, FileDiff,
+};
+use crate::shell_analyzer::find_git_commits;
+
+/// Allowed difference between session timestamps and commit times, in seconds.
+const CLOCK_SKEW_SECS: i64 = 60;
+/// How long after a Bash `git commit` call the resulting commit may be dated, in seconds.
+const AGENT_COMMIT_WINDOW_SECS: i64 = 300;
+
+/// Whether a file's session edits made it into git.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FileCommitStatus {
+    /// Every line written by the session was committed
+    Committed,
+    /// Some lines were committed, others weren't
+    PartiallyCommitted,
+    /// Lines are only in the working directory
+    Uncommitted,
+    /// The lines are gone from both HEAD and the working directory
+    Overwritten,
+}
+
+/// Commit status of one file edited in the session.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCommitCorrelation {
+    /// Path relative to the project root
+    pub file_path: String,
+    /// Whether the session's edits were committed
+    pub status: FileCommitStatus,
+    /// Commits that added lines written by the session, oldest first
+    pub commits: Vec<String>,
+    /// Number of lines written by the session that are committed
+    pub committed_lines: usize,
+    /// Number of distinct lines the session's edits left in the file
+    pub session_lines: usize,
+}
+
+/// A commit made since the session started that touched its files or was made by the agent.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorrelatedCommit {
+    /// Full commit hash
+    pub id: String,
+    /// First line of the commit message
+    pub summary: String,
+    /// Author name
+    pub author: String,
+    /// Commit time (ISO 8601)
+    pub timestamp: String,
+    /// Whether the agent made the commit through a Bash `git commit` call
+    pub by_agent: bool,
+    /// Session files the commit added lines to (relative to the project root)
+    pub files: Vec<String>,
+}
+
+/// How a session's edits relate to git history.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionCommitCorrelation {
+    /// One entry per edited file, sorted by path
+    pub files: Vec<FileCommitCorrelation>,
+    /// Relevant commits since the session started, oldest first
+    pub commits: Vec<CorrelatedCommit>,
+}
+
+/// A commit in the session's time window with the lines it added to session files.
+struct WindowCommit<'r> {
+    commit: Commit<'r>,
+    /// Relative path → trimmed lines added by the commit
+    added_lines: HashMap<String, HashSet<String>>,
+    by_agent: bool,
+}
+
+/// Correlate a session's edits with the git history of its project.
+pub fn correlate_session_commits(
+    project_path: &str,
+    session_id: &str,
+) -> Result<SessionCommitCorrelation, String> {
+    let session_file = claude_code::get_session_file_path(project_path, session_id)
+        .ok_or_else(|| format!("Session file not found for {}", session_id))?;
+    correlate_session_file(&session_file, project_path)
+}
+
+/// Correlate the edits in a session JSONL file with the git history of the project.
+pub fn correlate_session_file(
+    session_file: &Path,
+    project_path: &str,
+) -> Result<SessionCommitCorrelation, String> {
+    let file_diffs = get_all_file_diffs_from_session_file(session_file, project_path);
+    let commit_calls: Vec<(i64, Option<String>)> =
+        get_bash_commands_from_session_file(session_file)
+            .into_iter()
+            .filter_map(|bash| {
+                let time = parse_timestamp(bash.timestamp.as_deref())?;
+                let calls = find_git_commits(&bash.command);
+                Some(calls.into_iter().map(move |call| (time, call.message)))
+            })
+            .flatten()
+            .collect();
+
+    let session_start = file_diffs
+        .values()
+        .flatten()
+        .filter_map(|diff| parse_timestamp(diff.timestamp.as_deref()))
+        .chain(commit_calls.iter().map(|(time, _)| *time))
+        .min();
+    let Some(session_start) = session_start else {
+        return Ok(SessionCommitCorrelation {
+            files: Vec::new(),
+            commits: Vec::new(),
+        });
+    };
+
+    let repo = Repository::discover(project_path)
+        .map_err(|e| format!("Failed to open repository: {}", e))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| "Repository has no working directory".to_string())?
+        .to_path_buf();
+    // The project may be a subdirectory of the repository
+    let prefix = Path::new(project_path)
+        .strip_prefix(&workdir)
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let mut commits = window_commits(&repo, session_start - CLOCK_SKEW_SECS)?;
+    for commit in &mut commits {
+        for file_path in file_diffs.keys() {
+            let added = added_lines(&repo, &commit.commit, &prefix.join(file_path))?;
+            if !added.is_empty() {
+                commit.added_lines.insert(file_path.clone(), added);
+            }
+        }
+    }
+    flag_agent_commits(&mut commits, &commit_calls);
+
+    let head = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    let mut files = Vec::with_capacity(file_diffs.len());
+    for (file_path, diffs) in &file_diffs {
+        let repo_path = prefix.join(file_path);
+        let head_lines = match &head {
+            Some(commit) => blob_lines(&repo, commit, &repo_path)?,
+            None => HashSet::new(),
+        };
+        let workdir_lines = fs::read_to_string(workdir.join(&repo_path))
+            .map(|content| trimmed_lines(&content))
+            .unwrap_or_default();
+        files.push(correlate_file(
+            file_path,
+            diffs,
+            &commits,
+            &head_lines,
+            &workdir_lines,
+        ));
+    }
+    files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+
+    let commits = commits
+        .into_iter()
+        .filter(|c| c.by_agent || !c.added_lines.is_empty())
+        .map(|c| {
+            let mut touched: Vec<String> = c.added_lines.into_keys().collect();
+            touched.sort();
+            CorrelatedCommit {
+                id: c.commit.id().to_string(),
+                summary: c.commit.summary().unwrap_or_default().to_string(),
+                author: c.commit.author().name().unwrap_or_default().to_string(),
+                timestamp: DateTime::from_timestamp(c.commit.time().seconds(), 0)
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_default(),
+                by_agent: c.by_agent,
+                files: touched,
+            }
+        })
+        .collect();
+
+    Ok(SessionCommitCorrelation { files, commits })
+}
+
+/// Work out the commit status of one file from its diffs.
+fn correlate_file(
+    file_path: &str,
+    diffs: &[FileDiff],
+    commits: &[WindowCommit],
+    head_lines: &HashSet<String>,
+    workdir_lines: &HashSet<String>,
+) -> FileCommitCorrelation {
+    let session_lines = session_lines(diffs);
+
+    let mut committed_lines = 0;
+    let mut uncommitted_lines = 0;
+    let mut commit_ids: Vec<String> = Vec::new();
+    for line in &session_lines {
+        let committed_by = commits
+            .iter()
+            .find(|c| {
+                c.added_lines
+                    .get(file_path)
+                    .is_some_and(|a| a.contains(line))
+            })
+            .filter(|_| head_lines.contains(line));
+        match committed_by {
+            Some(commit) => {
+                committed_lines += 1;
+                let id = commit.commit.id().to_string();
+                if !commit_ids.contains(&id) {
+                    commit_ids.push(id);
+                }
+            }
+            None if workdir_lines.contains(line) => uncommitted_lines += 1,
+            None => {}
+        }
+    }
+
+    // Keep commits in history order rather than line order
+    let mut ordered: Vec<String> = commits
+        .iter()
+        .map(|c| c.commit.id().to_string())
+        .filter(|id| commit_ids.contains(id))
+        .collect();
+
+    let status = if session_lines.is_empty() {
+        // Nothing to trace: committed if any commit since the session added to the file
+        ordered = commits
+            .iter()
+            .filter(|c| c.added_lines.contains_key(file_path))
+            .map(|c| c.commit.id().to_string())
+            .collect();
+        if ordered.is_empty() {
+            FileCommitStatus::Uncommitted
+        } else {
+            FileCommitStatus::Committed
+        }
+    } else if committed_lines == session_lines.len() {
+        FileCommitStatus::Committed
+    } else if committed_lines > 0 {
+        FileCommitStatus::PartiallyCommitted
+    } else if uncommitted_lines > 0 {
+        FileCommitStatus::Uncommitted
+    } else {
+        FileCommitStatus::Overwritten
+    };
+
+    FileCommitCorrelation {
+        file_path: file_path.to_string(),
+        status,
+        commits: ordered,
+        committed_lines,
+        session_lines: session_lines.len(),
+    }
+}
+
+/// Distinctive lines the session's edits left in the file, after later edits
+/// removed some of them again.
+fn session_lines(diffs: &[FileDiff]) -> HashSet<String> {
+    let mut lines: HashSet<String> = HashSet::new();
+
+    for diff in diffs {
+        // Notebook cell sources are stored JSON-encoded in the file
+        if diff.cell_id.is_some() || diff.edit_mode.is_some() {
+            continue;
+        }
+        let old = trimmed_lines(&diff.old_string);
+        let new = trimmed_lines(&diff.new_string);
+
+        if diff.is_write {
+            lines.retain(|line| new.contains(line));
+        } else {
+            for line in old.difference(&new) {
+                lines.remove(line);
+            }
+        }
+        lines.extend(
+            new.difference(&old)
+                .filter(|line| is_distinctive(line))
+                .cloned(),
+        );
+    }
+
+    lines
+}
+
+/// Commits reachable from HEAD with a commit time at or after `since`, oldest first.
+fn window_commits(repo: &Repository, since: i64) -> Result<Vec<WindowCommit<'_>>, String> {
+    let Ok(head) = repo.head() else {
+        // No commits yet
+        return Ok(Vec::new());
+    };
+    let head = head
+        .peel_to_commit()
+        .map_err(|e| format!("Failed to get HEAD commit: {}", e))?;
+
+    let mut revwalk = repo
+        .revwalk()
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+    revwalk
+        .set_sorting(Sort::TIME)
+        .and_then(|_| revwalk.push(head.id()))
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let oid = oid.map_err(|e| format!("Failed to walk history: {}", e))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("Failed to find commit {}: {}", oid, e))?;
+        if commit.time().seconds() < since {
+            break;
+        }
+        commits.push(WindowCommit {
+            commit,
+            added_lines: HashMap::new(),
+            by_agent: false,
+        });
+    }
+
+    commits.reverse();
+    Ok(commits)
+}
+
+/// Match Bash `git commit` calls (time, message) to the commits they created.
+///
+/// A call matches the oldest unmatched commit dated shortly after it, preferring one
+/// whose summary equals the first line of the call's message.
+fn flag_agent_commits(commits: &mut [WindowCommit], calls: &[(i64, Option<String>)]) {
+    for (time, message) in calls {
+        let candidates: Vec<usize> = commits
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                let commit_time = c.commit.time().seconds();
+                !c.by_agent
+                    && commit_time >= time - CLOCK_SKEW_SECS
+                    && commit_time <= time + AGENT_COMMIT_WINDOW_SECS
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        let summary = message.as_deref().and_then(|m| m.lines().next());
+        let matched = match summary {
+            Some(summary) => candidates
+                .iter()
+                .find(|&&i| commits[i].commit.summary() == Some(summary))
+                .or(candidates.first()),
+            None => candidates.first(),
+        };
+        if let Some(&i) = matched {
+            commits[i].by_agent = true;
+        }
+    }
+}
+
+/// Trimmed lines a commit added to a file, compared with its first parent.
+fn added_lines(
+    repo: &Repository,
+    commit: &Commit,
+    repo_path: &Path,
+) -> Result<HashSet<String>, String> {
+    let after = blob_lines(repo, commit, repo_path)?;
+    if after.is_empty() {
+        return Ok(after);
+    }
+    let before = match commit.parent(0) {
+        Ok(parent) => blob_lines(repo, &parent, repo_path)?,
+        Err(_) => HashSet::new(),
+    };
+    Ok(after.difference(&before).cloned().collect())
+}
+
+/// Trimmed lines of a file in a commit's tree (empty if the file doesn't exist there).
+fn blob_lines(
+    repo: &Repository,
+    commit: &Commit,
+    repo_path: &Path,
+) -> Result<HashSet<String>, String> {
+    let tree = commit
+        .tree()
+        .map_err(|e| format!("Failed to get commit tree: {}", e))?;
+    let Ok(entry) = tree.get_path(repo_path) else {
+        return Ok(HashSet::new());
+    };
+    let Ok(blob) = repo.find_blob(entry.id()) else {
+        return Ok(HashSet::new());
+    };
+    Ok(trimmed_lines(&String::from_utf8_lossy(blob.content())))
+}
+
+fn trimmed_lines(content: &str) -> HashSet<String> {
+    content.lines().map(|l| l.trim().to_string()).collect()
+}
+
+/// Whether a line is specific enough to trace (not blank or just punctuation).
+fn is_distinctive(line: &str) -> bool {
+    line.len() >= 4 && line.chars().any(|c| c.is_alphanumeric())
+}
+
+fn parse_timestamp(timestamp: Option<&str>) -> Option<i64> {
+    DateTime::parse_from_rfc3339(timestamp?)
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+
+    /// Write files and commit them with the given message at a commit time (seconds).
+    fn commit(repo: &Repository, files: &[(&str, &str)], message: &str, time: i64) {
+        let workdir = repo.workdir().unwrap().to_path_buf();
+        let mut index = repo.index().unwrap();
+        for (path, content) in files {
+            fs::write(workdir.join(path), content).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature =
+            git2::Signature::new("Dev", "dev@example.com", &git2::Time::new(time, 0)).unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    fn tool_use(timestamp: &str, name: &str, input: serde_json::Value) -> String {
+        json!({
+            "type": "assistant", "timestamp": timestamp,
+            "message": {"content": [{"type": "tool_use", "name": name, "input": input}]}
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_correlate_session_file() {
+        let dir = TempDir::new("correlation");
+        let project = dir.to_str().unwrap();
+
+        // 2025-10-05T09:00:00Z
+        let start = 1_759_654_800;
+        let repo = Repository::init(&dir).unwrap();
+        let base = "fn base() {}\n";
+        commit(
+            &repo,
+            &[
+                ("a.rs", base),
+                ("b.rs", base),
+                ("c.rs", base),
+                ("d.rs", base),
+            ],
+            "Initial",
+            start - 3600,
+        );
+
+        let edit = |file: &str, new_string: &str| {
+            json!({
+                "file_path": format!("{}/{}", project, file),
+                "old_string": "fn base() {}",
+                "new_string": new_string
+            })
+        };
+        let session = [
+            tool_use(
+                "2025-10-05T09:00:00Z",
+                "Edit",
+                edit("a.rs", "fn base() {}\nfn alpha() {}"),
+            ),
+            tool_use(
+                "2025-10-05T09:00:10Z",
+                "Edit",
+                edit("b.rs", "fn base() {}\nfn beta_one() {}\nfn beta_two() {}"),
+            ),
+            tool_use(
+                "2025-10-05T09:00:20Z",
+                "Edit",
+                edit("c.rs", "fn base() {}\nfn gamma() {}"),
+            ),
+            tool_use(
+                "2025-10-05T09:00:30Z",
+                "Edit",
+                edit("d.rs", "fn base() {}\nfn delta() {}"),
+            ),
+            tool_use(
+                "2025-10-05T09:01:00Z",
+                "Bash",
+                json!({"command": "git add a.rs && git commit -m 'Add alpha'"}),
+            ),
+        ];
+        let session_file = dir.join("session.jsonl");
+        fs::write(&session_file, session.join("\n") + "\n").unwrap();
+
+        // The agent commits a.rs, the user later commits half of b.rs
+        let a = "fn base() {}\nfn alpha() {}\n";
+        commit(&repo, &[("a.rs", a)], "Add alpha", start + 62);
+        commit(
+            &repo,
+            &[("b.rs", "fn base() {}\nfn beta_one() {}\n")],
+            "Beta one",
+            start + 600,
+        );
+        fs::write(
+            dir.join("b.rs"),
+            "fn base() {}\nfn beta_one() {}\nfn beta_two() {}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("c.rs"), "fn base() {}\nfn gamma() {}\n").unwrap();
+        // d.rs was reverted
+        fs::write(dir.join("d.rs"), base).unwrap();
+
+        let result = correlate_session_file(&session_file, project).unwrap();
+
+        let statuses: Vec<(&str, FileCommitStatus, usize)> = result
+            .files
+            .iter()
+            .map(|f| (f.file_path.as_str(), f.status, f.commits.len()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("a.rs", FileCommitStatus::Committed, 1),
+                ("b.rs", FileCommitStatus::PartiallyCommitted, 1),
+                ("c.rs", FileCommitStatus::Uncommitted, 0),
+                ("d.rs", FileCommitStatus::Overwritten, 0),
+            ]
+        );
+        assert_eq!(result.files[1].committed_lines, 1);
+        assert_eq!(result.files[1].session_lines, 2);
+
+        let commits: Vec<(&str, bool, Vec<String>)> = result
+            .commits
+            .iter()
+            .map(|c| (c.summary.as_str(), c.by_agent, c.files.clone()))
+            .collect();
+        assert_eq!(
+            commits,
+            vec![
+                ("Add alpha", true, vec!["a.rs".to_string()]),
+                ("Beta one", false, vec!["b.rs".to_string()]),
+            ]
+        );
+        assert_eq!(result.files[0].commits[0], result.commits[0].id);
+    }
+}
@@ -1,23 +1,42 @@
 mod claude_code;
+mod diff_engine;
+mod file_content;
 mod git;
+mod git_correlation;
+mod patch;
 mod process;
+mod reconstruction;
+mod repo_status;
 mod search;
 mod session_index;
+mod shell_analyzer;
 mod terminal;
+#[cfg(test)]
+mod test_support;
+mod usage;
 mod watcher;
 
-use claude_code::{FileDiff, FileEdit, PolicyEvaluation, Project, Session};
-use git::GitFileDiff;
-use session_index::{get_edit_context, EditContext, IndexStatus};
+use claude_code::{DiscoveryRules, FileDiff, FileEdit, PolicyEvaluation, Project, Session};
+use diff_engine::StructuredDiffOptions;
+use git::{GitFileDiff, GitRevision, GitStructuredDiff};
+use git_correlation::SessionCommitCorrelation;
+use patch::{PatchExport, PatchFilter};
+use reconstruction::{EditStructuredDiff, FileReconstruction};
+use repo_status::RepoStatus;
+use session_index::{
+    get_edit_context, BlameSource, EditContext, FileBlame, FileHistoryEntry, IndexStatus,
+};
 use std::path::Path;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 use terminal::TerminalType;
+use usage::{PriceTable, SessionUsage};
 use watcher::WatcherState;
 
 /// Discover all Claude Code projects (lightweight - no session content parsing).
+/// Uses the default discovery rules (skip temp folders) unless rules are provided.
 #[tauri::command]
-fn get_projects() -> Vec<Project> {
-    claude_code::discover_projects()
+fn get_projects(rules: Option<DiscoveryRules>) -> Vec<Project> {
+    claude_code::discover_projects(&rules.unwrap_or_default())
 }
 
 /// Get full session details for a specific project (on-demand).
@@ -71,10 +90,99 @@ fn get_file_diffs(project_path: String, session_id: String, file_path: String) -
     claude_code::get_file_diffs(&project_path, &session_id, &file_path)
 }
 
-/// Get git diff for a file (HEAD vs working directory).
+/// Reconstruct a file's full content after each edit in a session.
 #[tauri::command]
-fn get_git_file_diff(project_path: String, file_path: String) -> Result<GitFileDiff, String> {
-    git::get_git_file_diff(&project_path, &file_path)
+fn reconstruct_file(
+    project_path: String,
+    session_id: String,
+    file_path: String,
+) -> Result<FileReconstruction, String> {
+    reconstruction::reconstruct_file(&project_path, &session_id, &file_path)
+}
+
+/// Get structured diffs (hunks with line numbers and word highlights) of a session's
+/// edits to a file, or of the single edit with the given sequence number.
+#[tauri::command]
+fn get_edit_structured_diffs(
+    project_path: String,
+    session_id: String,
+    file_path: String,
+    sequence: Option<u32>,
+    options: Option<StructuredDiffOptions>,
+) -> Result<Vec<EditStructuredDiff>, String> {
+    reconstruction::get_edit_structured_diffs(
+        &project_path,
+        &session_id,
+        &file_path,
+        sequence,
+        &options.unwrap_or_default(),
+    )
+}
+
+/// Export a session's edits as a unified patch written to `output_path`.
+#[tauri::command]
+fn export_session_patch(
+    project_path: String,
+    session_id: String,
+    output_path: String,
+    filter: Option<PatchFilter>,
+) -> Result<PatchExport, String> {
+    patch::export_session_patch(
+        &project_path,
+        &session_id,
+        &filter.unwrap_or_default(),
+        &output_path,
+    )
+}
+
+/// Get git diff for a file (revision vs working directory).
+/// Compares against HEAD unless another revision is given.
+#[tauri::command]
+fn get_git_file_diff(
+    project_path: String,
+    file_path: String,
+    revision: Option<GitRevision>,
+) -> Result<GitFileDiff, String> {
+    git::get_git_file_diff(&project_path, &file_path, &revision.unwrap_or_default())
+}
+
+/// Get the structured diff of a file (revision vs working directory), paged by hunk.
+/// Compares against HEAD unless another revision is given.
+#[tauri::command]
+fn get_git_structured_diff(
+    project_path: String,
+    file_path: String,
+    revision: Option<GitRevision>,
+    options: Option<StructuredDiffOptions>,
+) -> Result<GitStructuredDiff, String> {
+    git::get_git_structured_diff(
+        &project_path,
+        &file_path,
+        &revision.unwrap_or_default(),
+        &options.unwrap_or_default(),
+    )
+}
+
+/// Check which of a session's edits were committed, and which commits the agent made.
+#[tauri::command]
+fn get_session_commit_correlation(
+    project_path: String,
+    session_id: String,
+) -> Result<SessionCommitCorrelation, String> {
+    git_correlation::correlate_session_commits(&project_path, &session_id)
+}
+
+/// Get the git status overview of a project's repository.
+/// Dirty files list the sessions that edited them in the last few days.
+#[tauri::command]
+fn get_repo_status(
+    state: State<'_, WatcherState>,
+    project_path: String,
+) -> Result<RepoStatus, String> {
+    let since = chrono::Utc::now() - chrono::Duration::days(repo_status::RECENT_EDIT_DAYS);
+    let since = since.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let recent_editors = state.get_recent_file_editors(&project_path, &since)?;
+    repo_status::get_repo_status(&project_path, &recent_editors)
 }
 
 /// Get paginated events from a session for the log viewer.
@@ -89,6 +197,23 @@ fn get_session_events(
     claude_code::get_session_events(&project_path, &session_id, offset, limit)
 }
 
+/// Get the token usage and cost of a session per model, with a time series of its
+/// context size. Uses the default prices unless a price table is provided.
+#[tauri::command]
+fn get_session_usage(
+    project_path: String,
+    session_id: String,
+    prices: Option<PriceTable>,
+) -> Result<SessionUsage, String> {
+    usage::get_session_usage(&project_path, &session_id, &prices.unwrap_or_default())
+}
+
+/// Get the default price table, for editing.
+#[tauri::command]
+fn get_default_price_table() -> PriceTable {
+    PriceTable::default()
+}
+
 /// Get the raw JSON for a specific event by its byte offset.
 #[tauri::command]
 fn get_event_raw_json(
@@ -122,26 +247,89 @@ fn get_subagent_raw_json(
 
 /// Search session events for matching text.
 /// Supports boolean expressions: `error`, `error bash` (implicit AND),
-/// `error AND bash`, `error OR warning`.
+/// `error AND bash`, `error OR warning`, `"exact phrase"`, `NOT test` / `-test`,
+/// `(a OR b) AND c`, `prefix*` and field filters such as `tool:Bash`, `file:src/lib.rs`
+/// or `after:2026-10-01`, and `/regex/` terms. A leading `-` always negates, so short
+/// shell options are searched for quoted (`rm "-rf"`); `--long` options are plain terms.
+/// Matching is case-insensitive unless `options.caseSensitive` is set. Malformed queries
+/// return an error.
 #[tauri::command]
 fn search_session_events(
+    state: State<'_, WatcherState>,
     project_path: String,
     session_id: String,
     query: String,
+    options: Option<search::SearchOptions>,
     max_results: Option<u32>,
-) -> search::SearchResponse {
-    search::search_session(&project_path, &session_id, &query, max_results)
+) -> Result<search::SearchResponse, String> {
+    search::search_session(
+        &project_path,
+        &session_id,
+        &query,
+        &options.unwrap_or_default(),
+        max_results,
+        state.term_indices(),
+    )
 }
 
 /// Search sub-agent events for matching text.
 #[tauri::command]
 fn search_subagent_events(
+    state: State<'_, WatcherState>,
     project_path: String,
     agent_id: String,
     query: String,
+    options: Option<search::SearchOptions>,
+    max_results: Option<u32>,
+) -> Result<search::SearchResponse, String> {
+    search::search_subagent(
+        &project_path,
+        &agent_id,
+        &query,
+        &options.unwrap_or_default(),
+        max_results,
+        state.term_indices(),
+    )
+}
+
+/// Start a search as a background job and return its id, with the same query syntax
+/// as `search_session_events`. The scope is a session, a sub-agent, or every session and
+/// sub-agent of a project (or of all projects), searched in parallel.
+/// Matches are emitted in batches as `search-result` events and progress as
+/// `search-progress` events; the last progress event has `done` set.
+#[tauri::command]
+fn start_search(
+    app_handle: AppHandle,
+    state: State<'_, WatcherState>,
+    jobs: State<'_, search::SearchJobs>,
+    scope: search::SearchScope,
+    query: String,
+    options: Option<search::SearchOptions>,
     max_results: Option<u32>,
-) -> search::SearchResponse {
-    search::search_subagent(&project_path, &agent_id, &query, max_results)
+) -> Result<u64, String> {
+    jobs.start(
+        scope,
+        &query,
+        &options.unwrap_or_default(),
+        max_results,
+        state.term_indices_arc(),
+        move |event| {
+            let _ = match event {
+                search::SearchJobEvent::Result(payload) => {
+                    app_handle.emit("search-result", payload)
+                }
+                search::SearchJobEvent::Progress(payload) => {
+                    app_handle.emit("search-progress", payload)
+                }
+            };
+        },
+    )
+}
+
+/// Stop a background search. Returns false if it already finished.
+#[tauri::command]
+fn cancel_search(jobs: State<'_, search::SearchJobs>, search_id: u64) -> bool {
+    jobs.cancel(search_id)
 }
 
 /// Get full events for specific byte offsets (for search results).
@@ -224,6 +412,24 @@ fn get_index_status(
     state.get_index_status(&project_path, &session_id)
 }
 
+/// Get memory statistics for all loaded session indices.
+#[tauri::command]
+fn get_index_cache_stats(
+    state: State<'_, WatcherState>,
+) -> Result<session_index::IndexCacheStats, String> {
+    state.get_index_cache_stats()
+}
+
+/// Set the memory budget (in bytes) for loaded session indices.
+/// Least recently used indices are evicted when the budget is exceeded.
+#[tauri::command]
+fn set_index_memory_budget(
+    state: State<'_, WatcherState>,
+    budget_bytes: u64,
+) -> Result<(), String> {
+    state.set_index_memory_budget(budget_bytes as usize)
+}
+
 /// Get file edits from the cached session index (O(1) lookup).
 /// Falls back to scanning if index not available.
 #[tauri::command]
@@ -293,22 +499,55 @@ fn get_file_edit_context(
         .ok_or_else(|| format!("Edit index {} out of range for file {}", edit_index, file_path))?;
 
     // Get the session file path
-    let home = dirs::home_dir().ok_or_else(|| "Cannot find home directory".to_string())?;
-    let encoded_name = project_path.replace('/', "-");
-    let session_file = home
-        .join(".claude")
-        .join("projects")
-        .join(&encoded_name)
-        .join(format!("{}.jsonl", session_id));
-
-    if !session_file.exists() {
-        return Err(format!("Session file not found: {}", session_file.display()));
-    }
+    let session_file = claude_code::get_session_file_path(&project_path, &session_id)
+        .ok_or_else(|| format!("Session file not found for {}", session_id))?;
 
     // Get the edit context using the query function
     get_edit_context(&index, &session_file, edit_line)
 }
 
+/// Get every edit any session in a project made to a file, in chronological order.
+#[tauri::command]
+fn get_file_history(
+    state: State<'_, WatcherState>,
+    project_path: String,
+    file_path: String,
+) -> Result<Vec<FileHistoryEntry>, String> {
+    state.get_file_history(&project_path, &file_path)
+}
+
+/// Attribute each line of a file's working-tree content to the session edit that wrote it.
+/// Later sessions take precedence over earlier ones for lines they both wrote.
+#[tauri::command]
+fn get_file_blame(
+    state: State<'_, WatcherState>,
+    project_path: String,
+    file_path: String,
+    session_ids: Vec<String>,
+) -> Result<FileBlame, String> {
+    let current = std::fs::read_to_string(Path::new(&project_path).join(&file_path))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let mut sessions = Vec::with_capacity(session_ids.len());
+    for session_id in &session_ids {
+        let session_file = claude_code::get_session_file_path(&project_path, session_id)
+            .ok_or_else(|| format!("Session file not found for {}", session_id))?;
+        let index = state.get_or_open_index(&project_path, session_id)?;
+        sessions.push((session_id, index, session_file));
+    }
+
+    let sources: Vec<BlameSource> = sessions
+        .iter()
+        .map(|(session_id, index, session_file)| BlameSource {
+            session_id,
+            index,
+            session_file,
+        })
+        .collect();
+
+    session_index::blame_file(&current, &file_path, &project_path, &sources)
+}
+
 /// Get list of policy evaluations for a project.
 #[tauri::command]
 fn get_policy_evaluations(project_path: String) -> Vec<PolicyEvaluation> {
@@ -374,7 +613,15 @@ async fn reveal_in_file_manager(path: String) -> Result<(), String> {
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .manage(WatcherState::new())
+        .setup(|app| {
+            // Session and search indices are kept on disk so reopening a session is incremental
+            let app_data_dir = app.path().app_data_dir().ok();
+            let index_cache_dir = app_data_dir.as_ref().map(|d| d.join("index-cache"));
+            let search_index_dir = app_data_dir.as_ref().map(|d| d.join("search-index"));
+            app.manage(WatcherState::new(index_cache_dir, search_index_dir));
+            app.manage(search::SearchJobs::default());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_projects,
             get_project_sessions,
@@ -383,13 +630,23 @@ pub fn run() {
             launch_claude,
             get_session_file_edits,
             get_file_diffs,
+            reconstruct_file,
+            get_edit_structured_diffs,
+            export_session_patch,
             get_git_file_diff,
+            get_git_structured_diff,
+            get_session_commit_correlation,
+            get_repo_status,
             get_session_events,
+            get_session_usage,
+            get_default_price_table,
             get_event_raw_json,
             get_subagent_events,
             get_subagent_raw_json,
             search_session_events,
             search_subagent_events,
+            start_search,
+            cancel_search,
             get_events_by_offsets,
             watch_session,
             unwatch_session,
@@ -398,9 +655,13 @@ pub fn run() {
             watch_telemetry,
             unwatch_telemetry,
             get_index_status,
+            get_index_cache_stats,
+            set_index_memory_budget,
             get_indexed_file_edits,
             get_indexed_events,
             get_file_edit_context,
+            get_file_blame,
+            get_file_history,
             get_policy_evaluations,
             get_policy_evaluation,
             reveal_in_file_manager
@@ -0,0 +1,566 @@
+//! Session patch export.
+//!
+//! Turns the edits a session made into a git-compatible unified patch that can be
+//! applied elsewhere with `git apply`. File contents come from replaying the session's
+//! edits on top of git HEAD (see `reconstruction`), so the patch applies to a checkout
+//! of that commit.
+
+use chrono::{DateTime, FixedOffset};
+use git2::{DiffOptions, ObjectType, Oid, Patch};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::claude_code::{self, FileDiff, FileEditType};
+use crate::git;
+use crate::reconstruction::{replay_diffs, StepStatus};
+
+/// Optional restrictions on what goes into an exported patch.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PatchFilter {
+    /// Only include these relative paths (an entry ending in `/` includes a directory)
+    pub files: Option<Vec<String>>,
+    /// Only include edits made at or after this time (ISO 8601)
+    pub after: Option<String>,
+    /// Only include edits made at or before this time (ISO 8601)
+    pub before: Option<String>,
+}
+
+impl PatchFilter {
+    fn includes_file(&self, path: &str) -> bool {
+        match &self.files {
+            Some(files) => files
+                .iter()
+                .any(|f| f == path || (f.ends_with('/') && path.starts_with(f.as_str()))),
+            None => true,
+        }
+    }
+}
+
+/// Result of writing a session patch to disk.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchExport {
+    /// Where the patch was written
+    pub output_path: String,
+    /// Relative paths of the files in the patch
+    pub files: Vec<String>,
+    /// Files that were skipped or may not apply cleanly
+    pub warnings: Vec<String>,
+    /// Size of the patch in bytes
+    pub size_bytes: usize,
+}
+
+/// A unified patch built from a session.
+#[derive(Debug, Default)]
+pub struct SessionPatch {
+    /// Patch text
+    pub patch: String,
+    /// Relative paths of the files in the patch
+    pub files: Vec<String>,
+    /// Files that were skipped or may not apply cleanly
+    pub warnings: Vec<String>,
+}
+
+/// Build a patch of a session's edits and write it to `output_path`.
+pub fn export_session_patch(
+    project_path: &str,
+    session_id: &str,
+    filter: &PatchFilter,
+    output_path: &str,
+) -> Result<PatchExport, String> {
+    let session_file = claude_code::get_session_file_path(project_path, session_id)
+        .ok_or_else(|| format!("Session file not found for {}", session_id))?;
+
+    let patch = build_session_patch(&session_file, project_path, filter)?;
+    if patch.files.is_empty() {
+        return Err("No file changes match the filter".to_string());
+    }
+
+    fs::write(output_path, &patch.patch).map_err(|e| format!("Failed to write patch: {}", e))?;
+
+    Ok(PatchExport {
+        output_path: output_path.to_string(),
+        size_bytes: patch.patch.len(),
+        files: patch.files,
+        warnings: patch.warnings,
+    })
+}
+
+/// Build a unified patch of the edits in a session JSONL file.
+pub fn build_session_patch(
+    session_file: &Path,
+    project_path: &str,
+    filter: &PatchFilter,
+) -> Result<SessionPatch, String> {
+    let range = TimeRange::from_filter(filter)?;
+    let edits = claude_code::get_file_edits_from_session_file(session_file, project_path);
+    let mut diffs = claude_code::get_all_file_diffs_from_session_file(session_file, project_path);
+    let head_content = |path: &str| {
+        git::get_head_file_content(project_path, path)
+            .ok()
+            .flatten()
+    };
+
+    let mut result = SessionPatch::default();
+
+    for edit in &edits {
+        if !filter.includes_file(&edit.path) {
+            continue;
+        }
+        if Path::new(&edit.path).is_absolute() {
+            result
+                .warnings
+                .push(format!("Skipped {}: outside the project", edit.path));
+            continue;
+        }
+
+        let (old_path, old, new) = match edit.edit_type {
+            FileEditType::Deleted => {
+                if !range.contains(edit.last_edited_at.as_deref()) {
+                    continue;
+                }
+                // Nothing to delete if the file was created during the session
+                let Some(old) = head_content(&edit.path) else {
+                    continue;
+                };
+                (edit.path.as_str(), Some(old), None)
+            }
+            FileEditType::Renamed => {
+                let Some(from) = edit.renamed_from.as_deref() else {
+                    continue;
+                };
+                let mut file_diffs = diffs.remove(from).unwrap_or_default();
+                file_diffs.extend(diffs.remove(&edit.path).unwrap_or_default());
+
+                let replayed = replay_in_range(head_content(from), &file_diffs, &range);
+                match replayed {
+                    Some(replayed) => {
+                        result.warnings.extend(replayed.warning(&edit.path));
+                        (from, replayed.old, replayed.new)
+                    }
+                    // Rename without content changes in range
+                    None if range.contains(edit.last_edited_at.as_deref()) => {
+                        let content = head_content(from);
+                        (from, content.clone(), content)
+                    }
+                    None => continue,
+                }
+            }
+            FileEditType::Added | FileEditType::Modified => {
+                let file_diffs = diffs.remove(&edit.path).unwrap_or_default();
+                if file_diffs.is_empty() {
+                    if range.contains(edit.last_edited_at.as_deref()) {
+                        result.warnings.push(format!(
+                            "Skipped {}: only changed by shell commands",
+                            edit.path
+                        ));
+                    }
+                    continue;
+                }
+
+                let Some(replayed) = replay_in_range(head_content(&edit.path), &file_diffs, &range)
+                else {
+                    continue;
+                };
+                result.warnings.extend(replayed.warning(&edit.path));
+                (edit.path.as_str(), replayed.old, replayed.new)
+            }
+        };
+
+        if old.as_deref().is_some_and(is_binary) || new.as_deref().is_some_and(is_binary) {
+            result
+                .warnings
+                .push(format!("Skipped {}: binary content", edit.path));
+            continue;
+        }
+
+        if let Some(file_patch) =
+            format_file_patch(old_path, &edit.path, old.as_deref(), new.as_deref())?
+        {
+            result.patch.push_str(&file_patch);
+            result.files.push(edit.path.clone());
+        }
+    }
+
+    Ok(result)
+}
+
+/// File content before and after the in-range edits.
+struct ReplayedRange {
+    old: Option<String>,
+    new: Option<String>,
+    drift_count: usize,
+    skipped_count: usize,
+}
+
+impl ReplayedRange {
+    fn warning(&self, path: &str) -> Option<String> {
+        if self.skipped_count > 0 {
+            Some(format!(
+                "{}: {} notebook edit(s) can't be replayed; the patch is incomplete",
+                path, self.skipped_count
+            ))
+        } else if self.drift_count > 0 {
+            Some(format!(
+                "{}: {} edit(s) could not be located; the patch may not apply cleanly",
+                path, self.drift_count
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Replay the diffs before the range onto `base`, then the diffs inside it.
+/// Returns None when no diff falls inside the range.
+fn replay_in_range(
+    base: Option<String>,
+    diffs: &[FileDiff],
+    range: &TimeRange,
+) -> Option<ReplayedRange> {
+    let (earlier, inside): (Vec<FileDiff>, Vec<FileDiff>) = diffs
+        .iter()
+        .filter(|d| !range.is_after_end(d.timestamp.as_deref()))
+        .cloned()
+        .partition(|d| range.is_before_start(d.timestamp.as_deref()));
+
+    if inside.is_empty() {
+        return None;
+    }
+
+    let mut drift_count = 0;
+    let mut skipped_count = 0;
+    let mut count_issues = |statuses: &mut dyn Iterator<Item = StepStatus>| {
+        for status in statuses {
+            match status {
+                StepStatus::Drift => drift_count += 1,
+                StepStatus::Skipped => skipped_count += 1,
+                StepStatus::Applied => {}
+            }
+        }
+    };
+
+    let old = if earlier.is_empty() {
+        base
+    } else {
+        let replayed = replay_diffs(base, &earlier);
+        count_issues(&mut replayed.steps.iter().map(|s| s.status));
+        replayed.steps.last().map(|s| s.content.clone())
+    };
+
+    let replayed = replay_diffs(old.clone(), &inside);
+    count_issues(&mut replayed.steps.iter().map(|s| s.status));
+    let new = replayed.steps.last().map(|s| s.content.clone());
+
+    Some(ReplayedRange {
+        old,
+        new,
+        drift_count,
+        skipped_count,
+    })
+}
+
+/// Format the patch for one file. Returns None when nothing changed.
+fn format_file_patch(
+    old_path: &str,
+    new_path: &str,
+    old: Option<&str>,
+    new: Option<&str>,
+) -> Result<Option<String>, String> {
+    if old == new && old_path == new_path {
+        return Ok(None);
+    }
+
+    let old_content = old.unwrap_or("");
+    let new_content = new.unwrap_or("");
+    let hunks = unified_hunks(old_content, new_content)?;
+    let old_id = old.map_or(Ok(NULL_BLOB_ID.to_string()), blob_id)?;
+    let new_id = new.map_or(Ok(NULL_BLOB_ID.to_string()), blob_id)?;
+
+    let mut out = format!("diff --git a/{} b/{}\n", old_path, new_path);
+    match (old, new) {
+        (None, Some(_)) => {
+            out.push_str(&format!(
+                "new file mode {}\nindex {}..{}\n",
+                FILE_MODE, old_id, new_id
+            ));
+        }
+        (Some(_), None) => {
+            out.push_str(&format!(
+                "deleted file mode {}\nindex {}..{}\n",
+                FILE_MODE, old_id, new_id
+            ));
+        }
+        _ => {
+            if old_path != new_path {
+                out.push_str(&format!(
+                    "similarity index {}%\nrename from {}\nrename to {}\n",
+                    hunks.similarity, old_path, new_path
+                ));
+            }
+            // Pure rename
+            if old == new {
+                return Ok(Some(out));
+            }
+            out.push_str(&format!("index {}..{} {}\n", old_id, new_id, FILE_MODE));
+        }
+    }
+
+    // Empty file created or deleted: headers only
+    if hunks.text.is_empty() {
+        return Ok(Some(out));
+    }
+
+    let old_label = old.map_or("/dev/null".to_string(), |_| format!("a/{}", old_path));
+    let new_label = new.map_or("/dev/null".to_string(), |_| format!("b/{}", new_path));
+    out.push_str(&format!("--- {}\n+++ {}\n", old_label, new_label));
+    out.push_str(&hunks.text);
+
+    Ok(Some(out))
+}
+
+/// Mode written for every file (session edits don't carry permissions).
+const FILE_MODE: &str = "100644";
+
+/// Abbreviated id git uses for a missing blob.
+const NULL_BLOB_ID: &str = "0000000";
+
+/// Abbreviated git blob id of some content.
+fn blob_id(content: &str) -> Result<String, String> {
+    let oid = Oid::hash_object(ObjectType::Blob, content.as_bytes())
+        .map_err(|e| format!("Failed to hash file contents: {}", e))?;
+    Ok(oid.to_string()[..7].to_string())
+}
+
+/// Unified diff hunks between two texts.
+struct Hunks {
+    /// Everything from the first `@@` line
+    text: String,
+    /// Percentage of lines left unchanged, as in git's rename `similarity index`
+    similarity: usize,
+}
+
+fn unified_hunks(old: &str, new: &str) -> Result<Hunks, String> {
+    let mut opts = DiffOptions::new();
+    opts.context_lines(3);
+
+    let mut patch =
+        Patch::from_buffers(old.as_bytes(), None, new.as_bytes(), None, Some(&mut opts))
+            .map_err(|e| format!("Failed to diff file contents: {}", e))?;
+    let (_, _, deletions) = patch
+        .line_stats()
+        .map_err(|e| format!("Failed to diff file contents: {}", e))?;
+    let buf = patch
+        .to_buf()
+        .map_err(|e| format!("Failed to format patch: {}", e))?;
+    let text = String::from_utf8_lossy(&buf);
+
+    let hunks_start = if text.starts_with("@@") {
+        Some(0)
+    } else {
+        text.find("\n@@").map(|i| i + 1)
+    };
+
+    let old_lines = old.lines().count();
+    let total_lines = old_lines.max(new.lines().count()).max(1);
+    let similarity = old_lines.saturating_sub(deletions) * 100 / total_lines;
+
+    Ok(Hunks {
+        text: hunks_start.map_or(String::new(), |i| text[i..].to_string()),
+        similarity,
+    })
+}
+
+fn is_binary(content: &str) -> bool {
+    content.contains('\0')
+}
+
+/// Time bounds parsed from a PatchFilter. Missing timestamps count as inside the range.
+struct TimeRange {
+    start: Option<DateTime<FixedOffset>>,
+    end: Option<DateTime<FixedOffset>>,
+}
+
+impl TimeRange {
+    fn from_filter(filter: &PatchFilter) -> Result<Self, String> {
+        let parse = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|v| {
+                    DateTime::parse_from_rfc3339(v)
+                        .map_err(|e| format!("Invalid time filter '{}': {}", v, e))
+                })
+                .transpose()
+        };
+        Ok(TimeRange {
+            start: parse(&filter.after)?,
+            end: parse(&filter.before)?,
+        })
+    }
+
+    fn parse(timestamp: Option<&str>) -> Option<DateTime<FixedOffset>> {
+        timestamp.and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+    }
+
+    fn is_before_start(&self, timestamp: Option<&str>) -> bool {
+        matches!((self.start, Self::parse(timestamp)), (Some(start), Some(ts)) if ts < start)
+    }
+
+    fn is_after_end(&self, timestamp: Option<&str>) -> bool {
+        matches!((self.end, Self::parse(timestamp)), (Some(end), Some(ts)) if ts > end)
+    }
+
+    fn contains(&self, timestamp: Option<&str>) -> bool {
+        !self.is_before_start(timestamp) && !self.is_after_end(timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use git2::{ApplyLocation, Diff, Repository, Signature};
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    /// Create a git repository with committed files and a session file editing it.
+    fn fixture(name: &str) -> (TempDir, PathBuf, PathBuf) {
+        let dir = TempDir::new(&format!("patch-{}", name));
+        let repo_dir = dir.join("repo");
+        fs::create_dir_all(repo_dir.join("src")).unwrap();
+
+        let repo = Repository::init(&repo_dir).unwrap();
+        fs::write(repo_dir.join("src/lib.rs"), "fn one() {}\n\nfn two() {}\n").unwrap();
+        fs::write(repo_dir.join("old.txt"), "keep me\n").unwrap();
+        fs::write(repo_dir.join("gone.txt"), "bye\n").unwrap();
+        let mut index = repo.index().unwrap();
+        for path in ["src/lib.rs", "old.txt", "gone.txt"] {
+            index.add_path(Path::new(path)).unwrap();
+        }
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        let project = repo_dir.to_str().unwrap();
+        let tool_use = |i: usize, name: &str, input: serde_json::Value| {
+            json!({
+                "type": "assistant",
+                "uuid": format!("a{}", i),
+                "cwd": project,
+                "timestamp": format!("2025-10-03T12:00:{:02}.000Z", i * 10),
+                "message": {"content": [{"type": "tool_use", "name": name, "input": input}]}
+            })
+            .to_string()
+        };
+        let lines = [
+            tool_use(
+                1,
+                "Edit",
+                json!({
+                    "file_path": format!("{}/src/lib.rs", project),
+                    "old_string": "fn two() {}",
+                    "new_string": "fn two() -> u8 {\n    2\n}"
+                }),
+            ),
+            tool_use(
+                2,
+                "Write",
+                json!({
+                    "file_path": format!("{}/new.txt", project),
+                    "content": "fresh\n"
+                }),
+            ),
+            tool_use(
+                3,
+                "Bash",
+                json!({"command": "rm gone.txt && git mv old.txt renamed.txt"}),
+            ),
+            tool_use(
+                4,
+                "Edit",
+                json!({
+                    "file_path": format!("{}/renamed.txt", project),
+                    "old_string": "keep me",
+                    "new_string": "kept"
+                }),
+            ),
+        ];
+        let session_file = dir.join("session.jsonl");
+        fs::write(&session_file, lines.join("\n") + "\n").unwrap();
+
+        (dir, repo_dir, session_file)
+    }
+
+    #[test]
+    fn test_patch_applies_to_head() {
+        let (_dir, repo_dir, session_file) = fixture("apply");
+        let project = repo_dir.to_str().unwrap();
+
+        let patch = build_session_patch(&session_file, project, &PatchFilter::default()).unwrap();
+        assert_eq!(
+            patch.files,
+            vec!["gone.txt", "new.txt", "renamed.txt", "src/lib.rs"]
+        );
+        assert!(patch.warnings.is_empty(), "{:?}", patch.warnings);
+        assert!(patch.patch.contains(
+            "new file mode 100644\nindex 0000000..92d5444\n--- /dev/null\n+++ b/new.txt\n"
+        ));
+        assert!(patch.patch.contains(
+            "deleted file mode 100644\nindex b023018..0000000\n--- a/gone.txt\n+++ /dev/null\n"
+        ));
+        assert!(patch
+            .patch
+            .contains("similarity index 0%\nrename from old.txt\nrename to renamed.txt\n"));
+
+        // The patch applies to the untouched checkout and reproduces the session's result
+        let repo = Repository::open(&repo_dir).unwrap();
+        let diff = Diff::from_buffer(patch.patch.as_bytes()).unwrap();
+        repo.apply(&diff, ApplyLocation::WorkDir, None).unwrap();
+
+        let read = |path: &str| fs::read_to_string(repo_dir.join(path)).ok();
+        assert_eq!(
+            read("src/lib.rs").as_deref(),
+            Some("fn one() {}\n\nfn two() -> u8 {\n    2\n}\n")
+        );
+        assert_eq!(read("new.txt").as_deref(), Some("fresh\n"));
+        assert_eq!(read("renamed.txt").as_deref(), Some("kept\n"));
+        assert_eq!(read("old.txt"), None);
+        assert_eq!(read("gone.txt"), None);
+    }
+
+    #[test]
+    fn test_patch_filters() {
+        let (_dir, repo_dir, session_file) = fixture("filters");
+        let project = repo_dir.to_str().unwrap();
+
+        let filter = PatchFilter {
+            files: Some(vec!["src/".to_string(), "new.txt".to_string()]),
+            ..Default::default()
+        };
+        let patch = build_session_patch(&session_file, project, &filter).unwrap();
+        assert_eq!(patch.files, vec!["new.txt", "src/lib.rs"]);
+
+        let filter = PatchFilter {
+            after: Some("2025-10-03T12:00:25Z".to_string()),
+            ..Default::default()
+        };
+        let patch = build_session_patch(&session_file, project, &filter).unwrap();
+        assert_eq!(patch.files, vec!["gone.txt", "renamed.txt"]);
+
+        let filter = PatchFilter {
+            before: Some("2025-10-03T12:00:15Z".to_string()),
+            ..Default::default()
+        };
+        let patch = build_session_patch(&session_file, project, &filter).unwrap();
+        assert_eq!(patch.files, vec!["src/lib.rs"]);
+
+        let filter = PatchFilter {
+            after: Some("yesterday".to_string()),
+            ..Default::default()
+        };
+        assert!(build_session_patch(&session_file, project, &filter).is_err());
+    }
+}
@@ -0,0 +1,366 @@
+//! File content reconstruction.
+//!
+//! Replays a session's edits to one file to recover its full content after every step.
+//! Replay starts from the file at git HEAD, or from the first Write when the file isn't
+//! tracked. Steps whose `old_string` can't be found are flagged as drift, which usually
+//! means the file was changed outside the session.
+//!
+//! The snapshots also give each edit a structured diff with line numbers in the file.
+
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::claude_code::{self, FileDiff};
+use crate::diff_engine::{compute_diff, StructuredDiff, StructuredDiffOptions};
+use crate::git;
+
+/// Where the replay started from.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReconstructionBase {
+    /// The file's content at git HEAD
+    GitHead,
+    /// The content of the session's first Write
+    FirstWrite,
+    /// No known starting content (the first edit creates the file)
+    Empty,
+}
+
+/// Outcome of applying a single step.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    /// The edit applied cleanly
+    Applied,
+    /// old_string wasn't found; content is carried over unchanged
+    Drift,
+    /// The step can't be replayed on text content (NotebookEdit)
+    Skipped,
+}
+
+/// Full file content after one edit step.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSnapshot {
+    /// Sequence number of the diff this snapshot follows (matches FileDiff.sequence)
+    pub sequence: u32,
+    /// Timestamp of the edit (ISO 8601)
+    pub timestamp: Option<String>,
+    /// How the step applied
+    pub status: StepStatus,
+    /// Number of replacements made (0 for drift, Write and skipped steps)
+    pub replacements: usize,
+    /// Full file content after this step
+    pub content: String,
+}
+
+/// A file's content history across a session.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReconstruction {
+    /// Where the replay started from
+    pub base: ReconstructionBase,
+    /// Content before the first step
+    pub base_content: String,
+    /// One snapshot per diff, in order
+    pub steps: Vec<FileSnapshot>,
+    /// Number of steps flagged as drift
+    pub drift_count: usize,
+    /// Whether the final snapshot equals the file on disk (None if the file is missing)
+    pub matches_current: Option<bool>,
+}
+
+/// Reconstruct a file's content after each edit a session made to it.
+pub fn reconstruct_file(
+    project_path: &str,
+    session_id: &str,
+    file_path: &str,
+) -> Result<FileReconstruction, String> {
+    let diffs = claude_code::get_file_diffs(project_path, session_id, file_path);
+    if diffs.is_empty() {
+        return Err(format!("No edits found for file: {}", file_path));
+    }
+
+    // Files outside a git repository simply have no HEAD content
+    let head_content = git::get_head_file_content(project_path, file_path)
+        .ok()
+        .flatten();
+
+    let mut reconstruction = replay_diffs(head_content, &diffs);
+
+    let absolute_path = Path::new(project_path).join(file_path);
+    reconstruction.matches_current = fs::read_to_string(&absolute_path)
+        .ok()
+        .zip(reconstruction.steps.last())
+        .map(|(current, last)| current == last.content);
+
+    Ok(reconstruction)
+}
+
+/// Structured diff of one edit step.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditStructuredDiff {
+    /// Sequence number of the diff (matches FileDiff.sequence)
+    pub sequence: u32,
+    /// Timestamp of the edit (ISO 8601)
+    pub timestamp: Option<String>,
+    /// Whether line numbers refer to the whole file; false when the step couldn't be
+    /// replayed and old_string is diffed against new_string instead
+    pub anchored: bool,
+    pub diff: StructuredDiff,
+}
+
+/// Compute structured diffs of a session's edits to a file.
+///
+/// Steps that replayed cleanly are diffed against the file content before them, so hunks
+/// carry real line numbers. `sequence` limits the result to a single edit.
+pub fn get_edit_structured_diffs(
+    project_path: &str,
+    session_id: &str,
+    file_path: &str,
+    sequence: Option<u32>,
+    options: &StructuredDiffOptions,
+) -> Result<Vec<EditStructuredDiff>, String> {
+    let diffs = claude_code::get_file_diffs(project_path, session_id, file_path);
+    let head_content = git::get_head_file_content(project_path, file_path)
+        .ok()
+        .flatten();
+    structured_diffs(
+        &replay_diffs(head_content, &diffs),
+        &diffs,
+        sequence,
+        options,
+    )
+}
+
+fn structured_diffs(
+    reconstruction: &FileReconstruction,
+    diffs: &[FileDiff],
+    sequence: Option<u32>,
+    options: &StructuredDiffOptions,
+) -> Result<Vec<EditStructuredDiff>, String> {
+    let mut before = &reconstruction.base_content;
+    let mut result = Vec::new();
+
+    for (step, diff) in reconstruction.steps.iter().zip(diffs) {
+        if sequence.is_none_or(|s| s == step.sequence) {
+            let anchored = step.status == StepStatus::Applied;
+            let structured = if anchored {
+                compute_diff(before, &step.content, options)?
+            } else {
+                compute_diff(&diff.old_string, &diff.new_string, options)?
+            };
+            result.push(EditStructuredDiff {
+                sequence: step.sequence,
+                timestamp: step.timestamp.clone(),
+                anchored,
+                diff: structured,
+            });
+        }
+        before = &step.content;
+    }
+
+    Ok(result)
+}
+
+/// Replay diffs on top of the starting content.
+pub fn replay_diffs(head_content: Option<String>, diffs: &[FileDiff]) -> FileReconstruction {
+    let (base, base_content) = match head_content {
+        Some(content) => (ReconstructionBase::GitHead, content),
+        None if diffs.first().is_some_and(|d| d.is_write) => {
+            (ReconstructionBase::FirstWrite, String::new())
+        }
+        None => (ReconstructionBase::Empty, String::new()),
+    };
+
+    let mut content = base_content.clone();
+    let mut steps = Vec::with_capacity(diffs.len());
+
+    for diff in diffs {
+        let (status, replacements) = apply_diff(&mut content, diff);
+        steps.push(FileSnapshot {
+            sequence: diff.sequence,
+            timestamp: diff.timestamp.clone(),
+            status,
+            replacements,
+            content: content.clone(),
+        });
+    }
+
+    let drift_count = steps
+        .iter()
+        .filter(|s| s.status == StepStatus::Drift)
+        .count();
+
+    FileReconstruction {
+        base,
+        base_content,
+        steps,
+        drift_count,
+        matches_current: None,
+    }
+}
+
+/// Apply one diff to `content` in place, returning its status and replacement count.
+fn apply_diff(content: &mut String, diff: &FileDiff) -> (StepStatus, usize) {
+    if diff.cell_id.is_some() || diff.edit_mode.is_some() {
+        return (StepStatus::Skipped, 0);
+    }
+
+    if diff.is_write {
+        *content = diff.new_string.clone();
+        return (StepStatus::Applied, 0);
+    }
+
+    // An empty old_string creates the file; it only applies to an empty file
+    if diff.old_string.is_empty() {
+        if content.is_empty() {
+            *content = diff.new_string.clone();
+            return (StepStatus::Applied, 1);
+        }
+        return (StepStatus::Drift, 0);
+    }
+
+    if diff.replace_all {
+        let count = content.matches(diff.old_string.as_str()).count();
+        if count == 0 {
+            return (StepStatus::Drift, 0);
+        }
+        *content = content.replace(&diff.old_string, &diff.new_string);
+        return (StepStatus::Applied, count);
+    }
+
+    match content.find(&diff.old_string) {
+        Some(start) => {
+            content.replace_range(start..start + diff.old_string.len(), &diff.new_string);
+            (StepStatus::Applied, 1)
+        }
+        None => (StepStatus::Drift, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(sequence: u32, old_string: &str, new_string: &str) -> FileDiff {
+        FileDiff {
+            old_string: old_string.to_string(),
+            new_string: new_string.to_string(),
+            sequence,
+            timestamp: None,
+            is_write: false,
+            replace_all: false,
+            cell_id: None,
+            edit_mode: None,
+        }
+    }
+
+    #[test]
+    fn test_replay_from_head_with_replace_all() {
+        let head = "def load_cfg(path):\n    return cfg.read(path)\n\ncfg = load_cfg(DEFAULT_PATH)\nprint(cfg.name)\n";
+        let diffs = vec![
+            diff(0, "def load_cfg(path):", "def load_config(path):"),
+            diff(
+                1,
+                "cfg = load_cfg(DEFAULT_PATH)",
+                "config = load_config(DEFAULT_PATH)",
+            ),
+            FileDiff {
+                replace_all: true,
+                ..diff(2, "cfg.", "config.")
+            },
+        ];
+
+        let result = replay_diffs(Some(head.to_string()), &diffs);
+        assert_eq!(result.base, ReconstructionBase::GitHead);
+        assert_eq!(result.drift_count, 0);
+        assert_eq!(result.steps[2].replacements, 2);
+        assert_eq!(
+            result.steps[2].content,
+            "def load_config(path):\n    return config.read(path)\n\nconfig = load_config(DEFAULT_PATH)\nprint(config.name)\n"
+        );
+        assert!(result.steps[0].content.contains("load_cfg(DEFAULT_PATH)"));
+    }
+
+    #[test]
+    fn test_replay_from_first_write() {
+        let diffs = vec![
+            FileDiff {
+                is_write: true,
+                ..diff(0, "", "a = 1\nb = 2\n")
+            },
+            diff(1, "b = 2", "b = 3"),
+        ];
+
+        let result = replay_diffs(None, &diffs);
+        assert_eq!(result.base, ReconstructionBase::FirstWrite);
+        assert_eq!(result.steps[0].content, "a = 1\nb = 2\n");
+        assert_eq!(result.steps[1].content, "a = 1\nb = 3\n");
+    }
+
+    #[test]
+    fn test_replay_flags_drift() {
+        let diffs = vec![
+            diff(0, "", "\"\"\"New module.\"\"\"\n"),
+            diff(1, "missing", "x"),
+            diff(2, "New module.", "Module."),
+            FileDiff {
+                cell_id: Some("a1".to_string()),
+                edit_mode: Some("replace".to_string()),
+                ..diff(3, "", "cell")
+            },
+        ];
+
+        let result = replay_diffs(None, &diffs);
+        assert_eq!(result.base, ReconstructionBase::Empty);
+        let statuses: Vec<StepStatus> = result.steps.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                StepStatus::Applied,
+                StepStatus::Drift,
+                StepStatus::Applied,
+                StepStatus::Skipped
+            ]
+        );
+        assert_eq!(result.drift_count, 1);
+        assert_eq!(result.steps[1].content, result.steps[0].content);
+        assert_eq!(result.steps[3].content, "\"\"\"Module.\"\"\"\n");
+    }
+
+    #[test]
+    fn test_structured_diffs_use_file_line_numbers() {
+        let head = "one\ntwo\nthree\nfour\nfive\n";
+        let diffs = vec![
+            diff(0, "four", "FOUR"),
+            diff(1, "missing", "x"),
+            diff(2, "one\n", ""),
+        ];
+        let reconstruction = replay_diffs(Some(head.to_string()), &diffs);
+        let options = StructuredDiffOptions::default();
+
+        let result = structured_diffs(&reconstruction, &diffs, None, &options).unwrap();
+        assert_eq!(result.len(), 3);
+        assert!(result[0].anchored);
+        assert_eq!(result[0].diff.hunks[0].old_start, 1);
+        let added: Vec<(Option<u32>, &str)> = result[0].diff.hunks[0]
+            .lines
+            .iter()
+            .filter(|l| l.new_line.is_some() && l.old_line.is_none())
+            .map(|l| (l.new_line, l.content.as_str()))
+            .collect();
+        assert_eq!(added, vec![(Some(4), "FOUR")]);
+
+        // Drift falls back to the edit's own strings
+        assert!(!result[1].anchored);
+        assert_eq!(result[1].diff.hunks[0].lines.len(), 2);
+
+        let single = structured_diffs(&reconstruction, &diffs, Some(2), &options).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].sequence, 2);
+        assert_eq!((single[0].diff.additions, single[0].diff.deletions), (0, 1));
+    }
+}
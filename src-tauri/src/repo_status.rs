@@ -0,0 +1,354 @@
+//! Repository status overview.
+//!
+//! Summarises a project's git repository for the project detail page: current branch,
+//! ahead/behind counts against its upstream, staged, unstaged and untracked files with
+//! line counts, stashes and linked worktrees. Dirty files are cross-referenced with the
+//! sessions that recently edited them.
+
+use git2::{
+    Branch, Delta, Diff, DiffFindOptions, DiffOptions, Patch, Repository, WorktreeLockStatus,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// How many days back a session edit counts as recent.
+pub const RECENT_EDIT_DAYS: i64 = 7;
+
+/// Git status overview of a project's repository.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoStatus {
+    /// Absolute path of the repository's working directory
+    pub workdir: String,
+    /// Current branch (None when HEAD is detached or unborn)
+    pub branch: Option<String>,
+    /// Abbreviated id of the HEAD commit (None before the first commit)
+    pub head_commit: Option<String>,
+    /// Upstream branch of the current branch, e.g. "origin/main"
+    pub upstream: Option<String>,
+    /// Commits on the branch that aren't on its upstream
+    pub ahead: usize,
+    /// Commits on the upstream that aren't on the branch
+    pub behind: usize,
+    /// Changes staged in the index
+    pub staged: Vec<StatusFile>,
+    /// Changes in the working directory that aren't staged
+    pub unstaged: Vec<StatusFile>,
+    /// Files not tracked by git
+    pub untracked: Vec<StatusFile>,
+    /// Number of stash entries
+    pub stash_count: usize,
+    /// Linked worktrees (the main working directory isn't included)
+    pub worktrees: Vec<WorktreeInfo>,
+}
+
+/// Kind of change to a dirty file.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    TypeChange,
+    Untracked,
+}
+
+/// A dirty file in the status overview.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusFile {
+    /// Path relative to the repository root
+    pub path: String,
+    /// Previous path (renames only)
+    pub old_path: Option<String>,
+    /// Kind of change
+    pub change: FileChangeKind,
+    /// Lines added (0 for binary files)
+    pub additions: usize,
+    /// Lines deleted (0 for binary files)
+    pub deletions: usize,
+    /// Whether git considers the file binary
+    pub binary: bool,
+    /// Sessions that edited the file recently, most recent first
+    pub edited_by_sessions: Vec<String>,
+}
+
+/// A linked worktree.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeInfo {
+    /// Worktree name
+    pub name: String,
+    /// Absolute path of the worktree
+    pub path: String,
+    /// Branch checked out in the worktree (None if detached or missing)
+    pub branch: Option<String>,
+    /// Whether the worktree is locked
+    pub locked: bool,
+    /// Whether the worktree's directory no longer exists
+    pub missing: bool,
+}
+
+/// Get the status overview of the repository containing `project_path`.
+///
+/// `recent_editors` maps project-relative file paths to the sessions that recently
+/// edited them, most recent first.
+pub fn get_repo_status(
+    project_path: &str,
+    recent_editors: &HashMap<String, Vec<String>>,
+) -> Result<RepoStatus, String> {
+    let mut repo = Repository::discover(project_path)
+        .map_err(|e| format!("Failed to open repository: {}", e))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| "Repository has no working directory".to_string())?
+        .to_path_buf();
+    // The project may be a subdirectory of the repository
+    let prefix = Path::new(project_path)
+        .strip_prefix(&workdir)
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let mut stash_count = 0;
+    // A repository without stashes has no refs/stash; that's just zero
+    let _ = repo.stash_foreach(|_, _, _| {
+        stash_count += 1;
+        true
+    });
+
+    let head = repo.head().ok();
+    let branch = head
+        .as_ref()
+        .filter(|h| h.is_branch())
+        .and_then(|h| h.shorthand())
+        .map(String::from);
+    let head_commit = head
+        .as_ref()
+        .and_then(|h| h.peel_to_commit().ok())
+        .map(|c| c.id().to_string()[..7].to_string());
+
+    let (upstream, ahead, behind) = match head.filter(|h| h.is_branch()) {
+        Some(head) => upstream_status(&repo, Branch::wrap(head))?,
+        None => (None, 0, 0),
+    };
+
+    let editors_for = |path: &str| -> Vec<String> {
+        Path::new(path)
+            .strip_prefix(&prefix)
+            .ok()
+            .and_then(|rel| recent_editors.get(rel.to_string_lossy().as_ref()))
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    let mut staged_diff = repo
+        .diff_tree_to_index(head_tree.as_ref(), None, None)
+        .map_err(|e| format!("Failed to diff index: {}", e))?;
+    staged_diff
+        .find_similar(Some(DiffFindOptions::new().renames(true)))
+        .map_err(|e| format!("Failed to detect renames: {}", e))?;
+    let staged = status_files(&staged_diff, &editors_for)?;
+
+    let mut options = DiffOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    let workdir_diff = repo
+        .diff_index_to_workdir(None, Some(&mut options))
+        .map_err(|e| format!("Failed to diff working directory: {}", e))?;
+    let (untracked, unstaged): (Vec<StatusFile>, Vec<StatusFile>) =
+        status_files(&workdir_diff, &editors_for)?
+            .into_iter()
+            .partition(|f| f.change == FileChangeKind::Untracked);
+
+    let worktrees = worktrees(&repo)?;
+
+    Ok(RepoStatus {
+        workdir: workdir.to_string_lossy().to_string(),
+        branch,
+        head_commit,
+        upstream,
+        ahead,
+        behind,
+        staged,
+        unstaged,
+        untracked,
+        stash_count,
+        worktrees,
+    })
+}
+
+/// Upstream name and ahead/behind counts of a branch (None and zeros without upstream).
+fn upstream_status(
+    repo: &Repository,
+    branch: Branch,
+) -> Result<(Option<String>, usize, usize), String> {
+    let Ok(upstream) = branch.upstream() else {
+        return Ok((None, 0, 0));
+    };
+    let name = upstream.name().ok().flatten().map(String::from);
+
+    let (Some(local), Some(remote)) = (branch.get().target(), upstream.get().target()) else {
+        return Ok((name, 0, 0));
+    };
+    let (ahead, behind) = repo
+        .graph_ahead_behind(local, remote)
+        .map_err(|e| format!("Failed to compare with upstream: {}", e))?;
+    Ok((name, ahead, behind))
+}
+
+/// Files of a diff with their line counts.
+fn status_files(
+    diff: &Diff,
+    editors_for: &impl Fn(&str) -> Vec<String>,
+) -> Result<Vec<StatusFile>, String> {
+    let mut files = Vec::with_capacity(diff.deltas().len());
+
+    for (idx, delta) in diff.deltas().enumerate() {
+        let change = match delta.status() {
+            Delta::Added | Delta::Copied => FileChangeKind::Added,
+            Delta::Deleted => FileChangeKind::Deleted,
+            Delta::Renamed => FileChangeKind::Renamed,
+            Delta::Typechange => FileChangeKind::TypeChange,
+            Delta::Untracked => FileChangeKind::Untracked,
+            Delta::Modified => FileChangeKind::Modified,
+            // Unmodified, ignored and unreadable entries aren't dirty
+            _ => continue,
+        };
+
+        let new_path = delta
+            .new_file()
+            .path()
+            .map(|p| p.to_string_lossy().to_string());
+        let old_path = delta
+            .old_file()
+            .path()
+            .map(|p| p.to_string_lossy().to_string());
+        let Some(path) = new_path.clone().or_else(|| old_path.clone()) else {
+            continue;
+        };
+
+        let binary = delta.flags().is_binary();
+        let (additions, deletions) = match Patch::from_diff(diff, idx) {
+            Ok(Some(patch)) if !binary => {
+                let (_, additions, deletions) = patch
+                    .line_stats()
+                    .map_err(|e| format!("Failed to count lines: {}", e))?;
+                (additions, deletions)
+            }
+            _ => (0, 0),
+        };
+
+        files.push(StatusFile {
+            edited_by_sessions: editors_for(&path),
+            path,
+            old_path: old_path.filter(|_| change == FileChangeKind::Renamed),
+            change,
+            additions,
+            deletions,
+            binary,
+        });
+    }
+
+    Ok(files)
+}
+
+/// Linked worktrees of a repository.
+fn worktrees(repo: &Repository) -> Result<Vec<WorktreeInfo>, String> {
+    let names = repo
+        .worktrees()
+        .map_err(|e| format!("Failed to list worktrees: {}", e))?;
+
+    let mut worktrees = Vec::with_capacity(names.len());
+    for name in names.iter().flatten() {
+        let Ok(worktree) = repo.find_worktree(name) else {
+            continue;
+        };
+        let path = worktree.path();
+        let missing = !path.exists();
+        let branch = Repository::open(path).ok().and_then(|wt_repo| {
+            let head = wt_repo.head().ok()?;
+            head.is_branch()
+                .then(|| head.shorthand().map(String::from))
+                .flatten()
+        });
+
+        worktrees.push(WorktreeInfo {
+            name: name.to_string(),
+            path: path.to_string_lossy().to_string(),
+            branch,
+            locked: matches!(worktree.is_locked(), Ok(WorktreeLockStatus::Locked(_))),
+            missing,
+        });
+    }
+
+    Ok(worktrees)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_repo_status() {
+        let dir = TempDir::new("repo-status");
+        fs::create_dir_all(dir.join("app")).unwrap();
+
+        let mut repo = Repository::init(&dir).unwrap();
+        fs::write(dir.join("app/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.join("app/old.rs"), "mod old;\n").unwrap();
+        fs::write(dir.join("README.md"), "# Demo\n").unwrap();
+        let mut index = repo.index().unwrap();
+        for path in ["app/main.rs", "app/old.rs", "README.md"] {
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Dev", "dev@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        drop(tree);
+
+        // Stage a rename, leave an unstaged edit, add an untracked file and a stash
+        fs::write(dir.join("README.md"), "# Demo\nStashed\n").unwrap();
+        repo.stash_save(&signature, "wip", None).unwrap();
+        fs::rename(dir.join("app/old.rs"), dir.join("app/new.rs")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new("app/old.rs")).unwrap();
+        index.add_path(Path::new("app/new.rs")).unwrap();
+        index.write().unwrap();
+        fs::write(dir.join("app/main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        fs::write(dir.join("app/notes.txt"), "one\ntwo\n").unwrap();
+
+        let project = dir.join("app");
+        let editors = HashMap::from([("main.rs".to_string(), vec!["s1".to_string()])]);
+        let status = get_repo_status(project.to_str().unwrap(), &editors).unwrap();
+
+        assert!(status.branch.is_some());
+        assert_eq!(status.upstream, None);
+        assert_eq!(status.stash_count, 1);
+        assert!(status.worktrees.is_empty());
+
+        assert_eq!(status.staged.len(), 1);
+        assert_eq!(status.staged[0].change, FileChangeKind::Renamed);
+        assert_eq!(status.staged[0].path, "app/new.rs");
+        assert_eq!(status.staged[0].old_path.as_deref(), Some("app/old.rs"));
+
+        assert_eq!(status.unstaged.len(), 1);
+        let main = &status.unstaged[0];
+        assert_eq!(main.path, "app/main.rs");
+        assert_eq!((main.additions, main.deletions), (3, 1));
+        assert_eq!(main.edited_by_sessions, vec!["s1"]);
+
+        assert_eq!(status.untracked.len(), 1);
+        assert_eq!(status.untracked[0].path, "app/notes.txt");
+        assert_eq!(status.untracked[0].additions, 2);
+        assert!(status.untracked[0].edited_by_sessions.is_empty());
+    }
+}
@@ -6,12 +6,86 @@
 //! - `error AND bash` - explicit AND
 //! - `error OR warning` - explicit OR
 //! - `error AND bash OR write` - mixed (AND binds tighter than OR)
+//! - `"permission denied"` - exact phrase
+//! - `error NOT test` or `error -test` - negation; quote a short shell option to search
+//!   for it (`rm "-rf"`), while `--long` options are plain terms
+//! - `(bash OR write) AND timeout` - grouping
+//! - `config*` - words starting with a prefix
+//! - `tool:Bash cargo` - field filters on the parsed event: `type:`, `subtype:`,
+//!   `tool:`, `file:`, `user:` (human, tool or meta), `agent:`, `after:` and `before:`
+//! - `/E\d{4}/` - regular expression
+//!
+//! Matching is case-insensitive unless `SearchOptions::case_sensitive` is set.
+//! With `SearchOptions::fuzzy`, plain terms tolerate typos: each of their words may be
+//! a few edits away from a word of the line, and matches report how similar they are.
+//! Each match reports the content block its snippet comes from: message text,
+//! thinking, a field of a tool call's input or a tool result.
+//!
+//! `SearchJobs` runs searches as cancellable background jobs over a session, a
+//! sub-agent, or every session and sub-agent file of one project or of all projects.
+//! Files are searched on a pool of threads sharing one result limit, and matches and
+//! progress are streamed to the caller as they are found.
+//!
+//! Files are searched through their `TermIndex`: the query's terms are looked up in
+//! the postings to find candidate lines, and only those lines are read and matched
+//! exactly. Each match gets a relevance score from term frequency (tf-idf over the
+//! file's lines) and the recency of the event.
+//!
+//! Malformed queries (unbalanced parentheses or quotes, dangling operators) are
+//! rejected with a `SearchParseError` instead of being guessed at.
 
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::iter::{Enumerate, Peekable};
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::claude_code::{
+    discover_projects, get_session_file_path, get_session_files, get_subagent_file_path,
+    get_subagent_files, parse_session_event, subagent_parent_session, DiscoveryRules, SessionEvent,
+};
+use crate::session_index::{find_fuzzy, tokenize, TermIndex, TermIndexStore};
+
+/// Field names accepted before a `:` in queries.
+const FIELD_NAMES: [&str; 8] = [
+    "type", "subtype", "tool", "file", "user", "agent", "after", "before",
+];
+/// Longest regex accepted in a query, in characters.
+const MAX_REGEX_LEN: usize = 1000;
+/// Limit on the compiled size of a regex. The regex engine runs in linear time,
+/// so bounding its size bounds the cost of every match.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Default limit on the matches returned by a search of one file.
+const DEFAULT_MAX_RESULTS: usize = 10000;
+/// Default limit on the matches returned by a project-wide search.
+const DEFAULT_PROJECT_MAX_RESULTS: usize = 1000;
+/// Upper bound on the threads used by a project-wide search.
+const MAX_SEARCH_THREADS: usize = 8;
+/// Candidate lines scanned between progress reports (and cancellation checks).
+const PROGRESS_INTERVAL_LINES: usize = 1000;
+/// Longest time between progress reports while scanning candidate lines.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Age at which an event's recency boost has halved.
+const RECENCY_HALF_LIFE_DAYS: f32 = 30.0;
+/// Shortest fuzzy word (in chars) allowed one edit; shorter words must match exactly.
+const FUZZY_ONE_EDIT_LEN: usize = 4;
+/// Shortest fuzzy word (in chars) allowed two edits.
+const FUZZY_TWO_EDITS_LEN: usize = 8;
+
+/// Remaining characters of a query being tokenized, with their positions.
+type QueryChars<'a> = Peekable<Enumerate<Chars<'a>>>;
 
 /// A match result with line number, byte offset, and snippet.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,8 +95,55 @@ pub struct SearchMatch {
     pub sequence: u32,
     /// Byte offset in file for loading full JSON.
     pub byte_offset: u64,
+    /// Content block the snippet was taken from.
+    pub block: MatchBlock,
     /// Snippet of text showing match context.
     pub snippet: String,
+    /// Matched ranges within the snippet.
+    pub highlights: Vec<MatchSpan>,
+    /// Relevance from term frequency, recency and similarity (higher is more relevant).
+    pub score: f32,
+    /// How closely the line matches the fuzzy terms of the query, from 0 to 1
+    /// (1 for exact matches and queries without fuzzy terms).
+    pub similarity: f32,
+}
+
+/// Content block of an event that a match was found in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum MatchBlock {
+    /// Message text, system content or a summary
+    Text,
+    /// Extended thinking
+    Thinking,
+    /// One field of a tool call's input (non-string values as JSON)
+    ToolInput { tool: String, field: String },
+    /// Output returned by a tool
+    ToolResult,
+    /// The raw JSON line, when it has no readable content
+    Raw,
+}
+
+/// A matched range of a snippet, in UTF-16 code units (JavaScript string offsets).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// How a query is matched.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchOptions {
+    /// Match terms, phrases and regexes case-sensitively.
+    pub case_sensitive: bool,
+    /// Match plain terms allowing typos (phrases, prefixes and regexes stay exact).
+    pub fuzzy: bool,
 }
 
 /// Search response returned to frontend.
@@ -37,12 +158,327 @@ pub struct SearchResponse {
     pub truncated: bool,
 }
 
+/// A session or sub-agent file to search in a project-wide search.
+#[derive(Debug, Clone)]
+pub struct SearchTarget {
+    pub project_path: String,
+    /// Session ID (for sub-agents, the parent session when known).
+    pub session_id: Option<String>,
+    /// Sub-agent ID, for sub-agent files.
+    pub agent_id: Option<String>,
+    pub path: PathBuf,
+}
+
+/// Matches from one session or sub-agent file of a search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSearchResult {
+    pub project_path: String,
+    /// Session ID (for sub-agents, the parent session when known).
+    pub session_id: Option<String>,
+    /// Sub-agent ID, for sub-agent files.
+    pub agent_id: Option<String>,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// A malformed query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchParseError {
+    /// What is wrong with the query.
+    pub message: String,
+    /// Character offset in the query where the problem was found.
+    pub position: usize,
+}
+
+impl SearchParseError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+impl fmt::Display for SearchParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at position {})", self.message, self.position)
+    }
+}
+
 /// Token from query tokenization.
 #[derive(Debug, Clone, PartialEq)]
 enum Token {
-    Term(String),
+    /// A bare word; `prefix` is set when it ended in an unescaped `*`.
+    Word {
+        text: String,
+        prefix: bool,
+    },
+    /// A double-quoted phrase.
+    Phrase(String),
+    /// A `/pattern/` regex, not yet compiled.
+    Regex(String),
+    /// A `name:value` field filter.
+    Field(FieldFilter),
     And,
     Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word { text, .. } => format!("'{}'", text),
+            Token::Phrase(text) => format!("\"{}\"", text),
+            Token::Regex(pattern) => format!("/{}/", pattern),
+            Token::Field(_) => "a field filter".to_string(),
+            Token::And => "AND".to_string(),
+            Token::Or => "OR".to_string(),
+            Token::Not => "NOT".to_string(),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+        }
+    }
+}
+
+/// Kind of `user` line selected by `user:`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserKind {
+    /// A prompt typed by the human
+    Human,
+    /// A tool result sent back to the model
+    Tool,
+    /// Injected context (isMeta)
+    Meta,
+}
+
+/// A filter on a field of the parsed event (`name:value`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldFilter {
+    /// `type:` - event type ("user", "assistant", "system", "summary")
+    Type(String),
+    /// `subtype:` - system event subtype (e.g. "compact_boundary")
+    Subtype(String),
+    /// `tool:` - name of a tool called by the event
+    Tool(String),
+    /// `file:` - part of a path passed to a tool (file_path, notebook_path or path)
+    File(String),
+    /// `user:` - kind of user line
+    User(UserKind),
+    /// `agent:` - ID (or ID prefix) of the sub-agent the event launched or belongs to
+    Agent(String),
+    /// `after:` - events at or after a time
+    After(DateTime<Utc>),
+    /// `before:` - events before a time
+    Before(DateTime<Utc>),
+}
+
+impl FieldFilter {
+    /// Build a filter from a field name and its value.
+    fn parse(name: &str, value: &str, position: usize) -> Result<Self, SearchParseError> {
+        if value.is_empty() {
+            return Err(SearchParseError::new(
+                format!("Missing value for {}:", name),
+                position,
+            ));
+        }
+        let value_lower = value.to_lowercase();
+        Ok(match name {
+            "type" => FieldFilter::Type(value_lower),
+            "subtype" => FieldFilter::Subtype(value_lower),
+            "tool" => FieldFilter::Tool(value_lower),
+            "file" => FieldFilter::File(value_lower),
+            "agent" => FieldFilter::Agent(value_lower),
+            "user" => FieldFilter::User(match value_lower.as_str() {
+                "human" => UserKind::Human,
+                "tool" => UserKind::Tool,
+                "meta" => UserKind::Meta,
+                _ => {
+                    return Err(SearchParseError::new(
+                        format!(
+                            "Unknown user kind '{}' (expected human, tool or meta)",
+                            value
+                        ),
+                        position,
+                    ))
+                }
+            }),
+            "after" | "before" => {
+                let time = parse_time(value).ok_or_else(|| {
+                    SearchParseError::new(
+                        format!(
+                            "Invalid date '{}' (expected YYYY-MM-DD or an ISO 8601 time)",
+                            value
+                        ),
+                        position,
+                    )
+                })?;
+                if name == "after" {
+                    FieldFilter::After(time)
+                } else {
+                    FieldFilter::Before(time)
+                }
+            }
+            _ => unreachable!("field names are checked by the tokenizer"),
+        })
+    }
+
+    fn matches(&self, line: &LineContext) -> bool {
+        let Some(event) = line.event() else {
+            return false;
+        };
+        match self {
+            FieldFilter::Type(value) => event.event_type.eq_ignore_ascii_case(value),
+            FieldFilter::Subtype(value) => event
+                .subtype
+                .as_deref()
+                .is_some_and(|s| s.eq_ignore_ascii_case(value)),
+            FieldFilter::Tool(value) => tool_uses(line.json()).any(|block| {
+                block["name"]
+                    .as_str()
+                    .is_some_and(|n| n.eq_ignore_ascii_case(value))
+            }),
+            FieldFilter::File(value) => tool_uses(line.json()).any(|block| {
+                ["file_path", "notebook_path", "path"].iter().any(|key| {
+                    block["input"][key]
+                        .as_str()
+                        .is_some_and(|path| path.to_lowercase().contains(value.as_str()))
+                })
+            }),
+            FieldFilter::User(kind) => {
+                event.event_type == "user"
+                    && match kind {
+                        UserKind::Human => {
+                            event.user_type.as_deref() == Some("external")
+                                && !event.is_tool_result
+                                && !event.is_meta
+                                && event.is_compact_summary != Some(true)
+                        }
+                        UserKind::Tool => event.is_tool_result,
+                        UserKind::Meta => event.is_meta,
+                    }
+            }
+            FieldFilter::Agent(value) => event
+                .launched_agent_id
+                .as_deref()
+                .or_else(|| line.json().and_then(|json| json["agentId"].as_str()))
+                .is_some_and(|id| id.to_lowercase().starts_with(value.as_str())),
+            FieldFilter::After(time) => event_time(event).is_some_and(|t| t >= *time),
+            FieldFilter::Before(time) => event_time(event).is_some_and(|t| t < *time),
+        }
+    }
+}
+
+/// Parse a date (midnight UTC) or an RFC 3339 time.
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+fn event_time(event: &SessionEvent) -> Option<DateTime<Utc>> {
+    let timestamp = event.timestamp.as_deref()?;
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// The tool_use blocks of a line's message content.
+fn tool_uses(json: Option<&Value>) -> impl Iterator<Item = &Value> {
+    json.and_then(|json| json["message"]["content"].as_array())
+        .into_iter()
+        .flatten()
+        .filter(|block| block["type"] == "tool_use")
+}
+
+/// A line being matched. The lowercased text, JSON and parsed event are built on
+/// first use, so text-only queries never parse the line.
+struct LineContext<'a> {
+    line: &'a str,
+    case_sensitive: bool,
+    lower: OnceCell<String>,
+    /// Distinct words of `text()`, for fuzzy terms.
+    words: OnceCell<Vec<Vec<char>>>,
+    json: OnceCell<Option<Value>>,
+    event: OnceCell<Option<SessionEvent>>,
+}
+
+impl<'a> LineContext<'a> {
+    fn new(line: &'a str, case_sensitive: bool) -> Self {
+        Self {
+            line,
+            case_sensitive,
+            lower: OnceCell::new(),
+            words: OnceCell::new(),
+            json: OnceCell::new(),
+            event: OnceCell::new(),
+        }
+    }
+
+    /// The line as terms are matched against it: lowercased unless case-sensitive.
+    fn text(&self) -> &str {
+        if self.case_sensitive {
+            return self.line;
+        }
+        self.lower.get_or_init(|| self.line.to_lowercase())
+    }
+
+    fn words(&self) -> &[Vec<char>] {
+        self.words.get_or_init(|| {
+            let mut words: Vec<&str> = tokenize(self.text()).collect();
+            words.sort_unstable();
+            words.dedup();
+            words.into_iter().map(|w| w.chars().collect()).collect()
+        })
+    }
+
+    /// Fewest edits of a fuzzy word to a word of the line, if within its limit.
+    fn fuzzy_edits(&self, word: &FuzzyWord) -> Option<usize> {
+        self.words()
+            .iter()
+            .filter_map(|text| find_fuzzy(&word.chars, text, word.max_edits))
+            .map(|(edits, _)| edits)
+            .min()
+    }
+
+    fn json(&self) -> Option<&Value> {
+        self.json
+            .get_or_init(|| serde_json::from_str(self.line).ok())
+            .as_ref()
+    }
+
+    fn event(&self) -> Option<&SessionEvent> {
+        self.event
+            .get_or_init(|| parse_session_event(self.line, 0, 0))
+            .as_ref()
+    }
+}
+
+/// A word of a fuzzy term, with the edits it tolerates (more for longer words).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyWord {
+    pub text: String,
+    chars: Vec<char>,
+    pub max_edits: usize,
+}
+
+impl FuzzyWord {
+    fn new(text: &str) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let max_edits = match chars.len() {
+            len if len >= FUZZY_TWO_EDITS_LEN => 2,
+            len if len >= FUZZY_ONE_EDIT_LEN => 1,
+            _ => 0,
+        };
+        Self {
+            text: text.to_string(),
+            chars,
+            max_edits,
+        }
+    }
 }
 
 /// Boolean expression AST for search queries.
@@ -50,133 +486,647 @@ enum Token {
 pub enum SearchExpr {
     /// Single search term (case-insensitive substring match).
     Term(String),
+    /// Term in fuzzy mode, split into words: each must occur inside a word of the
+    /// line within its `max_edits`.
+    Fuzzy(Vec<FuzzyWord>),
+    /// Quoted phrase, matched exactly including its spaces.
+    Phrase(String),
+    /// Term ending in `*`: matches words starting with it.
+    Prefix(String),
+    /// Regular expression, matched against the raw line.
+    Regex(Regex),
+    /// Filter on a field of the parsed event.
+    Field(FieldFilter),
+    /// The expression must not match.
+    Not(Box<SearchExpr>),
     /// Both expressions must match.
     And(Box<SearchExpr>, Box<SearchExpr>),
     /// Either expression must match.
     Or(Box<SearchExpr>, Box<SearchExpr>),
 }
 
+/// Parser over the token stream, tracking each token's position for errors.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    /// Length of the query in characters, reported for errors at the end.
+    end: usize,
+    /// Whether regexes are compiled case-sensitively.
+    case_sensitive: bool,
+    /// Whether plain terms are parsed as fuzzy terms.
+    fuzzy: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    /// Position of the next token, or of the end of the query.
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(_, at)| *at)
+    }
+
+    /// Error for a missing operand after the operator just consumed.
+    fn expected_after(&self, what: &str) -> SearchParseError {
+        match self.peek() {
+            Some(token) => SearchParseError::new(
+                format!(
+                    "Expected a search term after {}, found {}",
+                    what,
+                    token.describe()
+                ),
+                self.position(),
+            ),
+            None => {
+                SearchParseError::new(format!("Expected a search term after {}", what), self.end)
+            }
+        }
+    }
+}
+
 impl SearchExpr {
     /// Parse a query string into a SearchExpr AST.
+    /// Returns `Ok(None)` for an empty query.
     ///
     /// Grammar (implicit AND between terms, explicit OR):
     /// ```text
     /// expr     -> or_expr
     /// or_expr  -> and_expr ("OR" and_expr)*
-    /// and_expr -> term (["AND"] term)*
-    /// term     -> word
+    /// and_expr -> unary (["AND"] unary)*
+    /// unary    -> ("NOT" | "-") unary | primary
+    /// primary  -> word | word* | "phrase" | /regex/ | field:value | "(" or_expr ")"
     /// ```
     ///
+    /// A backslash escapes the next character, so `\AND` and `\(` are plain terms.
+    /// Inside a regex it is passed through, except that `\/` is a literal slash.
+    ///
     /// Examples:
     /// - `error` -> Term("error")
     /// - `error bash` -> And(Term("error"), Term("bash"))
-    /// - `error AND bash` -> And(Term("error"), Term("bash"))
     /// - `error OR warning` -> Or(Term("error"), Term("warning"))
     /// - `error AND bash OR write` -> Or(And(Term("error"), Term("bash")), Term("write"))
-    pub fn parse(query: &str) -> Option<SearchExpr> {
-        let tokens = Self::tokenize(query);
+    /// - `"permission denied"` -> Phrase("permission denied")
+    /// - `error NOT test` / `error -test` -> And(Term("error"), Not(Term("test")))
+    /// - `(bash OR write) AND timeout` -> And(Or(..), Term("timeout"))
+    /// - `config*` -> Prefix("config")
+    /// - `tool:Bash cargo` -> And(Field(Tool("bash")), Term("cargo"))
+    /// - `/E\d{4}/` -> Regex(E\d{4})
+    #[cfg(test)]
+    pub fn parse(query: &str) -> Result<Option<SearchExpr>, SearchParseError> {
+        Self::parse_with(query, &SearchOptions::default())
+    }
+
+    /// Parse a query string for the given options.
+    /// Terms are lowercased unless the search is case-sensitive.
+    pub fn parse_with(
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Option<SearchExpr>, SearchParseError> {
+        let tokens = Self::tokenize(query, options.case_sensitive)?;
         if tokens.is_empty() {
-            return None;
+            return Ok(None);
+        }
+
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: query.chars().count(),
+            case_sensitive: options.case_sensitive,
+            fuzzy: options.fuzzy,
+        };
+        let expr = Self::parse_or_expr(&mut parser)?;
+        match parser.peek() {
+            None => Ok(Some(expr)),
+            Some(Token::RParen) => Err(SearchParseError::new("Unmatched ')'", parser.position())),
+            Some(token) => Err(SearchParseError::new(
+                format!("Unexpected {}", token.describe()),
+                parser.position(),
+            )),
         }
-        let mut pos = 0;
-        Self::parse_or_expr(&tokens, &mut pos)
     }
 
-    /// Tokenize query into terms and operators.
-    /// AND/OR (uppercase) are operators, everything else is a term.
-    fn tokenize(query: &str) -> Vec<Token> {
+    /// Whether the `-` at the front of `chars` negates what follows.
+    ///
+    /// It does before a word character, `(` or `"`, so short shell options must be
+    /// quoted to be searched for (`"-rf"`). Anything else, such as `--long` options or a
+    /// lone `-`, is read as a literal term.
+    fn is_negation(chars: &QueryChars<'_>) -> bool {
+        let mut ahead = chars.clone();
+        ahead.next(); // '-'
+        matches!(ahead.peek(), Some(&(_, next)) if next.is_alphanumeric() || matches!(next, '_' | '(' | '"'))
+    }
+
+    /// Tokenize query into terms, phrases, operators and parentheses.
+    /// AND/OR/NOT (uppercase, unescaped) are operators; a leading `-` negates a term
+    /// (see [`Self::is_negation`]).
+    /// A word starting with a known field name and `:` is a field filter, whose value
+    /// may be quoted (`file:"my notes.md"`). A word that starts and ends with `/` is a
+    /// regex, so paths like `/usr/bin` stay plain terms.
+    fn tokenize(
+        query: &str,
+        case_sensitive: bool,
+    ) -> Result<Vec<(Token, usize)>, SearchParseError> {
+        let normalize = |text: String| {
+            if case_sensitive {
+                text
+            } else {
+                text.to_lowercase()
+            }
+        };
         let mut tokens = Vec::new();
-        for word in query.split_whitespace() {
-            match word {
-                "AND" => tokens.push(Token::And),
-                "OR" => tokens.push(Token::Or),
-                _ => tokens.push(Token::Term(word.to_lowercase())),
+        let mut chars = query.chars().enumerate().peekable();
+
+        while let Some(&(at, c)) = chars.peek() {
+            match c {
+                c if c.is_whitespace() => {
+                    chars.next();
+                }
+                '(' => {
+                    chars.next();
+                    tokens.push((Token::LParen, at));
+                }
+                ')' => {
+                    chars.next();
+                    tokens.push((Token::RParen, at));
+                }
+                '"' => {
+                    chars.next();
+                    let mut phrase = String::new();
+                    let mut closed = false;
+                    while let Some((_, c)) = chars.next() {
+                        match c {
+                            '\\' => phrase.extend(chars.next().map(|(_, c)| c)),
+                            '"' => {
+                                closed = true;
+                                break;
+                            }
+                            c => phrase.push(c),
+                        }
+                    }
+                    if !closed {
+                        return Err(SearchParseError::new("Unterminated quote", at));
+                    }
+                    if phrase.is_empty() {
+                        return Err(SearchParseError::new("Empty phrase", at));
+                    }
+                    tokens.push((Token::Phrase(normalize(phrase)), at));
+                }
+                _ => {
+                    // A leading '-' negates the term, phrase or group that follows it;
+                    // otherwise it is read as part of a literal term
+                    if c == '-' && Self::is_negation(&chars) {
+                        chars.next();
+                        tokens.push((Token::Not, at));
+                        continue;
+                    }
+
+                    if c == '/' {
+                        if let Some((pattern, rest)) = Self::scan_regex(&chars) {
+                            chars = rest;
+                            tokens.push((Token::Regex(pattern), at));
+                            continue;
+                        }
+                    }
+
+                    let mut word = String::new();
+                    let mut escaped = false;
+                    let mut prefix = false;
+                    // Length of the word when its first unescaped ':' was read
+                    let mut field_split = None;
+                    while let Some(&(char_at, c)) = chars.peek() {
+                        if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                            break;
+                        }
+                        chars.next();
+                        if prefix {
+                            return Err(SearchParseError::new(
+                                "Wildcards are only supported at the end of a term",
+                                char_at - 1,
+                            ));
+                        }
+                        match c {
+                            '\\' => match chars.next() {
+                                Some((_, c)) => {
+                                    word.push(c);
+                                    escaped = true;
+                                }
+                                None => {
+                                    return Err(SearchParseError::new(
+                                        "Nothing to escape after '\\'",
+                                        char_at,
+                                    ))
+                                }
+                            },
+                            '*' => prefix = true,
+                            ':' if field_split.is_none() && !escaped => {
+                                field_split = Some(word.len());
+                                word.push(':');
+                            }
+                            c => word.push(c),
+                        }
+                    }
+
+                    let field = field_split
+                        .map(|split| (&word[..split], &word[split + 1..]))
+                        .filter(|(name, _)| FIELD_NAMES.contains(name));
+                    if let Some((name, value)) = field {
+                        if prefix {
+                            return Err(SearchParseError::new(
+                                "Wildcards are not supported in field filters",
+                                at,
+                            ));
+                        }
+                        // A quoted value directly follows the colon
+                        let mut value = value.to_string();
+                        if value.is_empty() && chars.peek().is_some_and(|&(_, c)| c == '"') {
+                            let (quote_at, _) = chars.next().unwrap_or_default();
+                            let mut closed = false;
+                            for (_, c) in chars.by_ref() {
+                                if c == '"' {
+                                    closed = true;
+                                    break;
+                                }
+                                value.push(c);
+                            }
+                            if !closed {
+                                return Err(SearchParseError::new("Unterminated quote", quote_at));
+                            }
+                        }
+                        tokens.push((Token::Field(FieldFilter::parse(name, &value, at)?), at));
+                        continue;
+                    }
+
+                    let token = match word.as_str() {
+                        "" if prefix => {
+                            return Err(SearchParseError::new("A wildcard needs a prefix", at))
+                        }
+                        "AND" if !escaped && !prefix => Token::And,
+                        "OR" if !escaped && !prefix => Token::Or,
+                        "NOT" if !escaped && !prefix => Token::Not,
+                        _ => Token::Word {
+                            text: normalize(word),
+                            prefix,
+                        },
+                    };
+                    tokens.push((token, at));
+                }
             }
         }
-        tokens
+
+        Ok(tokens)
     }
 
-    /// Parse OR expression (lowest precedence).
-    fn parse_or_expr(tokens: &[Token], pos: &mut usize) -> Option<SearchExpr> {
-        let mut left = Self::parse_and_expr(tokens, pos)?;
-
-        while *pos < tokens.len() {
-            if matches!(tokens.get(*pos), Some(Token::Or)) {
-                *pos += 1;
-                // If nothing after OR, just ignore it (trailing operator)
-                if let Some(right) = Self::parse_and_expr(tokens, pos) {
-                    left = SearchExpr::Or(Box::new(left), Box::new(right));
-                } else {
-                    break;
+    /// Read a `/pattern/` regex at the start of `chars`.
+    /// Returns None unless the closing `/` ends the word.
+    fn scan_regex<'q>(chars: &QueryChars<'q>) -> Option<(String, QueryChars<'q>)> {
+        let mut ahead = chars.clone();
+        ahead.next(); // opening '/'
+        let mut pattern = String::new();
+        while let Some((_, c)) = ahead.next() {
+            match c {
+                '\\' => match ahead.next()? {
+                    (_, '/') => pattern.push('/'),
+                    (_, c) => {
+                        pattern.push('\\');
+                        pattern.push(c);
+                    }
+                },
+                '/' => {
+                    let ends_word = ahead
+                        .peek()
+                        .is_none_or(|&(_, c)| c.is_whitespace() || c == ')');
+                    return (ends_word && !pattern.is_empty()).then_some((pattern, ahead));
                 }
-            } else {
-                break;
+                c => pattern.push(c),
             }
         }
+        None
+    }
+
+    /// Parse OR expression (lowest precedence).
+    fn parse_or_expr(parser: &mut Parser) -> Result<SearchExpr, SearchParseError> {
+        let mut left = Self::parse_and_expr(parser)?;
 
-        Some(left)
+        while parser.peek() == Some(&Token::Or) {
+            parser.pos += 1;
+            if !Self::starts_operand(parser.peek()) {
+                return Err(parser.expected_after("OR"));
+            }
+            let right = Self::parse_and_expr(parser)?;
+            left = SearchExpr::Or(Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
     }
 
     /// Parse AND expression (higher precedence than OR).
     /// Handles both explicit AND and implicit AND (adjacent terms).
-    fn parse_and_expr(tokens: &[Token], pos: &mut usize) -> Option<SearchExpr> {
-        let mut left = Self::parse_term(tokens, pos)?;
+    fn parse_and_expr(parser: &mut Parser) -> Result<SearchExpr, SearchParseError> {
+        let mut left = Self::parse_unary(parser)?;
 
-        while *pos < tokens.len() {
-            match tokens.get(*pos) {
+        loop {
+            match parser.peek() {
                 Some(Token::And) => {
                     // Explicit AND
-                    *pos += 1;
-                    let right = Self::parse_term(tokens, pos)?;
-                    left = SearchExpr::And(Box::new(left), Box::new(right));
-                }
-                Some(Token::Term(_)) => {
-                    // Implicit AND (adjacent terms)
-                    let right = Self::parse_term(tokens, pos)?;
-                    left = SearchExpr::And(Box::new(left), Box::new(right));
+                    parser.pos += 1;
+                    if !Self::starts_operand(parser.peek()) {
+                        return Err(parser.expected_after("AND"));
+                    }
                 }
-                _ => break, // OR or end
+                // Implicit AND (adjacent terms)
+                token if Self::starts_operand(token) => {}
+                _ => break, // OR, ')' or end
             }
+            let right = Self::parse_unary(parser)?;
+            left = SearchExpr::And(Box::new(left), Box::new(right));
         }
 
-        Some(left)
+        Ok(left)
     }
 
-    /// Parse a single term.
-    fn parse_term(tokens: &[Token], pos: &mut usize) -> Option<SearchExpr> {
-        match tokens.get(*pos) {
-            Some(Token::Term(s)) => {
-                *pos += 1;
-                Some(SearchExpr::Term(s.clone()))
+    /// Parse a possibly negated operand.
+    fn parse_unary(parser: &mut Parser) -> Result<SearchExpr, SearchParseError> {
+        if parser.peek() == Some(&Token::Not) {
+            parser.pos += 1;
+            if !Self::starts_operand(parser.peek()) {
+                return Err(parser.expected_after("NOT"));
             }
-            Some(Token::And) | Some(Token::Or) => {
-                // Orphan operator - skip it and try next
-                *pos += 1;
-                if *pos < tokens.len() {
-                    Self::parse_term(tokens, pos)
+            let inner = Self::parse_unary(parser)?;
+            return Ok(SearchExpr::Not(Box::new(inner)));
+        }
+        Self::parse_primary(parser)
+    }
+
+    /// Parse a term, phrase or parenthesized group.
+    fn parse_primary(parser: &mut Parser) -> Result<SearchExpr, SearchParseError> {
+        let position = parser.position();
+        let token = parser.peek().cloned();
+        parser.pos += 1;
+
+        match token {
+            Some(Token::Word { text, prefix }) => {
+                if prefix {
+                    return Ok(SearchExpr::Prefix(text));
+                }
+                // Terms of symbols only have no words to match loosely
+                let words: Vec<FuzzyWord> = tokenize(&text).map(FuzzyWord::new).collect();
+                Ok(if parser.fuzzy && !words.is_empty() {
+                    SearchExpr::Fuzzy(words)
                 } else {
-                    None
+                    SearchExpr::Term(text)
+                })
+            }
+            Some(Token::Phrase(text)) => Ok(SearchExpr::Phrase(text)),
+            Some(Token::Regex(pattern)) => {
+                if pattern.chars().count() > MAX_REGEX_LEN {
+                    return Err(SearchParseError::new(
+                        format!("Regex is longer than {} characters", MAX_REGEX_LEN),
+                        position,
+                    ));
                 }
+                build_regex(&pattern, parser.case_sensitive)
+                    .map(SearchExpr::Regex)
+                    .map_err(|e| SearchParseError::new(regex_error_message(&e), position))
             }
-            None => None,
+            Some(Token::Field(filter)) => Ok(SearchExpr::Field(filter)),
+            Some(Token::LParen) => {
+                if parser.peek() == Some(&Token::RParen) {
+                    return Err(SearchParseError::new("Empty parentheses", position));
+                }
+                let inner = Self::parse_or_expr(parser)?;
+                if parser.peek() != Some(&Token::RParen) {
+                    return Err(SearchParseError::new("Missing closing ')'", position));
+                }
+                parser.pos += 1;
+                Ok(inner)
+            }
+            Some(token) => Err(SearchParseError::new(
+                format!("Expected a search term, found {}", token.describe()),
+                position,
+            )),
+            None => Err(SearchParseError::new("Expected a search term", position)),
         }
     }
 
+    /// Whether a token can start an operand of AND/OR/NOT.
+    fn starts_operand(token: Option<&Token>) -> bool {
+        matches!(
+            token,
+            Some(
+                Token::Word { .. }
+                    | Token::Phrase(_)
+                    | Token::Regex(_)
+                    | Token::Field(_)
+                    | Token::Not
+                    | Token::LParen
+            )
+        )
+    }
+
     /// Check if this expression matches a line (case-insensitive).
+    /// Field filters only match JSONL event lines.
+    #[cfg(test)]
     pub fn matches(&self, line: &str) -> bool {
-        let line_lower = line.to_lowercase();
-        self.matches_impl(&line_lower)
+        self.matches_impl(&LineContext::new(line, false))
     }
 
-    fn matches_impl(&self, line: &str) -> bool {
+    fn matches_impl(&self, line: &LineContext) -> bool {
         match self {
-            SearchExpr::Term(term) => line.contains(term),
+            SearchExpr::Term(term) | SearchExpr::Phrase(term) => {
+                line.text().contains(term.as_str())
+            }
+            SearchExpr::Fuzzy(words) => words.iter().all(|w| line.fuzzy_edits(w).is_some()),
+            SearchExpr::Prefix(prefix) => find_prefix(line.text(), prefix).is_some(),
+            SearchExpr::Regex(regex) => regex.is_match(line.line),
+            SearchExpr::Field(filter) => filter.matches(line),
+            SearchExpr::Not(inner) => !inner.matches_impl(line),
             SearchExpr::And(left, right) => left.matches_impl(line) && right.matches_impl(line),
             SearchExpr::Or(left, right) => left.matches_impl(line) || right.matches_impl(line),
         }
     }
 }
 
+/// Find `prefix` at the start of a word (not preceded by a letter, digit or `_`).
+fn find_prefix(text: &str, prefix: &str) -> Option<usize> {
+    text.match_indices(prefix).map(|(at, _)| at).find(|&at| {
+        !text[..at]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// Compile a regex with the search size limits.
+fn build_regex(pattern: &str, case_sensitive: bool) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!case_sensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .build()
+}
+
+/// One-line description of a regex compile error.
+fn regex_error_message(error: &regex::Error) -> String {
+    match error {
+        // The last line holds the reason; earlier lines repeat the pattern
+        regex::Error::Syntax(details) => {
+            let reason = details.lines().last().unwrap_or(details);
+            format!("Invalid regex: {}", reason.trim_start_matches("error: "))
+        }
+        regex::Error::CompiledTooBig(limit) => {
+            format!(
+                "Regex is too complex (compiled size exceeds {} bytes)",
+                limit
+            )
+        }
+        _ => format!("Invalid regex: {}", error),
+    }
+}
+
+/// A parsed query ready to run: the expression, whether it is case-sensitive, a
+/// regex finding every span to highlight (terms, phrases, prefixes and regexes),
+/// and the words of its fuzzy terms, which are highlighted separately.
+pub struct SearchQuery {
+    expr: SearchExpr,
+    case_sensitive: bool,
+    highlighter: Option<Regex>,
+    fuzzy_words: Vec<FuzzyWord>,
+}
+
+impl SearchQuery {
+    /// Parse a query, compiling its regexes once. Returns `Ok(None)` for an empty query.
+    pub fn parse(query: &str, options: &SearchOptions) -> Result<Option<Self>, SearchParseError> {
+        let Some(expr) = SearchExpr::parse_with(query, options)? else {
+            return Ok(None);
+        };
+
+        let mut patterns = Vec::new();
+        let mut fuzzy_words = Vec::new();
+        highlight_patterns(&expr, &mut patterns, &mut fuzzy_words);
+        // Longer alternatives first, so a term doesn't hide a longer one it starts
+        patterns.sort_by_key(|p| std::cmp::Reverse(p.len()));
+        let highlighter = if patterns.is_empty() {
+            None
+        } else {
+            build_regex(&patterns.join("|"), options.case_sensitive).ok()
+        };
+
+        Ok(Some(Self {
+            expr,
+            case_sensitive: options.case_sensitive,
+            highlighter,
+            fuzzy_words,
+        }))
+    }
+
+    /// Check if the query matches a line.
+    #[cfg(test)]
+    pub fn matches(&self, line: &str) -> bool {
+        self.match_line(line).is_some()
+    }
+
+    /// Check if the query matches a line, and how similar the line is to its fuzzy
+    /// terms: 1 minus the share of each word's chars that had to be edited, averaged
+    /// over the fuzzy words found (1 if there are none).
+    pub fn match_line(&self, line: &str) -> Option<f32> {
+        let line = LineContext::new(line, self.case_sensitive);
+        if !self.expr.matches_impl(&line) {
+            return None;
+        }
+
+        let similarities: Vec<f32> = self
+            .fuzzy_words
+            .iter()
+            .filter_map(|word| {
+                let edits = line.fuzzy_edits(word)?;
+                Some(1.0 - edits as f32 / word.chars.len() as f32)
+            })
+            .collect();
+        if similarities.is_empty() {
+            return Some(1.0);
+        }
+        Some(similarities.iter().sum::<f32>() / similarities.len() as f32)
+    }
+
+    /// Byte ranges of `text` to highlight, sorted and non-overlapping: the highlighter's
+    /// matches and the closest occurrence of each fuzzy word in each word of `text`.
+    fn highlight_spans(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut spans: Vec<(usize, usize)> = self
+            .highlighter
+            .iter()
+            .flat_map(|regex| regex.find_iter(text))
+            .filter(|m| !m.is_empty())
+            .map(|m| (m.start(), m.end()))
+            .collect();
+
+        if !self.fuzzy_words.is_empty() {
+            for word in tokenize(text) {
+                let word_start = word.as_ptr() as usize - text.as_ptr() as usize;
+                // Fold case char by char, so char positions stay those of `word`
+                let chars: Vec<char> = word
+                    .chars()
+                    .map(|c| match self.case_sensitive {
+                        true => c,
+                        false => c.to_lowercase().next().unwrap_or(c),
+                    })
+                    .collect();
+                let byte_at = |char_at: usize| {
+                    word_start
+                        + word
+                            .char_indices()
+                            .nth(char_at)
+                            .map_or(word.len(), |(i, _)| i)
+                };
+                for fuzzy in &self.fuzzy_words {
+                    if let Some((_, range)) = find_fuzzy(&fuzzy.chars, &chars, fuzzy.max_edits) {
+                        if !range.is_empty() {
+                            spans.push((byte_at(range.start), byte_at(range.end)));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Merge overlapping spans
+        spans.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(spans.len());
+        for (start, end) in spans {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+
+    /// Snippet of `text` around its first highlighted span, with the spans inside it.
+    fn snippet(&self, text: &str, context_chars: usize) -> (String, Vec<MatchSpan>) {
+        build_snippet(text, self.highlight_spans(text), context_chars)
+    }
+
+    /// The first block of `line` containing a highlighted span. A match found only in
+    /// the JSON around the blocks (keys, ids) is reported as the raw line; queries with
+    /// nothing to highlight (e.g. only field filters) use the first block.
+    fn matched_block<'b>(
+        &self,
+        blocks: &'b [(MatchBlock, String)],
+        line: &'b str,
+    ) -> (MatchBlock, &'b str) {
+        if let Some((block, text)) = blocks
+            .iter()
+            .find(|(_, text)| !self.highlight_spans(text).is_empty())
+        {
+            return (block.clone(), text);
+        }
+        if self.highlight_spans(line).is_empty() {
+            (blocks[0].0.clone(), &blocks[0].1)
+        } else {
+            (MatchBlock::Raw, line)
+        }
+    }
+}
+
 /// Search a session file for matching events.
 ///
 /// Returns matching sequences in ascending order (oldest first).
@@ -184,8 +1134,10 @@ pub fn search_session(
     project_path: &str,
     session_id: &str,
     query: &str,
+    options: &SearchOptions,
     max_results: Option<u32>,
-) -> SearchResponse {
+    term_indices: &TermIndexStore,
+) -> Result<SearchResponse, String> {
     let empty_response = SearchResponse {
         matches: Vec::new(),
         total_searched: 0,
@@ -193,18 +1145,24 @@ pub fn search_session(
     };
 
     // Parse query
-    let expr = match SearchExpr::parse(query) {
-        Some(e) => e,
-        None => return empty_response,
-    };
+    let query =
+        match SearchQuery::parse(query, options).map_err(|e| format!("Invalid query: {}", e))? {
+            Some(q) => q,
+            None => return Ok(empty_response),
+        };
 
     // Get session file path
     let session_file = match crate::claude_code::get_session_file_path(project_path, session_id) {
         Some(p) => p,
-        None => return empty_response,
+        None => return Ok(empty_response),
     };
 
-    search_file(&session_file, &expr, max_results)
+    Ok(search_file(
+        &session_file,
+        &query,
+        max_results,
+        term_indices,
+    ))
 }
 
 /// Search a sub-agent file for matching events.
@@ -212,8 +1170,10 @@ pub fn search_subagent(
     project_path: &str,
     agent_id: &str,
     query: &str,
+    options: &SearchOptions,
     max_results: Option<u32>,
-) -> SearchResponse {
+    term_indices: &TermIndexStore,
+) -> Result<SearchResponse, String> {
     let empty_response = SearchResponse {
         matches: Vec::new(),
         total_searched: 0,
@@ -221,101 +1181,529 @@ pub fn search_subagent(
     };
 
     // Parse query
-    let expr = match SearchExpr::parse(query) {
-        Some(e) => e,
-        None => return empty_response,
-    };
+    let query =
+        match SearchQuery::parse(query, options).map_err(|e| format!("Invalid query: {}", e))? {
+            Some(q) => q,
+            None => return Ok(empty_response),
+        };
 
     // Get sub-agent file path
     let agent_file = match crate::claude_code::get_subagent_file_path(project_path, agent_id) {
         Some(p) => p,
-        None => return empty_response,
+        None => return Ok(empty_response),
+    };
+
+    Ok(search_file(&agent_file, &query, max_results, term_indices))
+}
+
+/// Where a background search looks.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SearchScope {
+    /// One session
+    Session {
+        project_path: String,
+        session_id: String,
+    },
+    /// One sub-agent
+    Subagent {
+        project_path: String,
+        agent_id: String,
+    },
+    /// Every session and sub-agent of a project, or of all projects when
+    /// `project_path` is None
+    Projects { project_path: Option<String> },
+}
+
+impl SearchScope {
+    /// The files to search, in order of priority.
+    fn targets(&self) -> Vec<SearchTarget> {
+        match self {
+            SearchScope::Session {
+                project_path,
+                session_id,
+            } => get_session_file_path(project_path, session_id)
+                .map(|path| SearchTarget {
+                    project_path: project_path.clone(),
+                    session_id: Some(session_id.clone()),
+                    agent_id: None,
+                    path,
+                })
+                .into_iter()
+                .collect(),
+            SearchScope::Subagent {
+                project_path,
+                agent_id,
+            } => get_subagent_file_path(project_path, agent_id)
+                .map(|path| SearchTarget {
+                    project_path: project_path.clone(),
+                    session_id: subagent_parent_session(&path),
+                    agent_id: Some(agent_id.clone()),
+                    path,
+                })
+                .into_iter()
+                .collect(),
+            SearchScope::Projects { project_path } => {
+                let project_paths = match project_path {
+                    Some(p) => vec![p.clone()],
+                    None => discover_projects(&DiscoveryRules::default())
+                        .into_iter()
+                        .map(|p| p.project_path)
+                        .collect(),
+                };
+                project_paths
+                    .iter()
+                    .flat_map(|p| project_search_targets(p))
+                    .collect()
+            }
+        }
+    }
+
+    /// Limit on the matches when none is given.
+    fn default_max_results(&self) -> usize {
+        match self {
+            SearchScope::Projects { .. } => DEFAULT_PROJECT_MAX_RESULTS,
+            _ => DEFAULT_MAX_RESULTS,
+        }
+    }
+}
+
+/// Progress of a background search.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchProgress {
+    /// Files searched so far.
+    pub files_searched: u32,
+    /// Files the search covers.
+    pub total_files: u32,
+    /// Lines searched so far, across all files.
+    pub lines_searched: u32,
+    /// Bytes of files indexed so far, for files whose search index had to be built
+    /// or brought up to date (reported while indexing).
+    pub bytes_indexed: u64,
+    /// Matches found so far, up to the limit on results.
+    pub matches_found: u32,
+    /// Once the limit on results is reached, the lowest score still kept: earlier
+    /// streamed matches scoring below it have been displaced by better ones.
+    pub min_score: Option<f32>,
+    /// Whether the search has finished (this is its last progress event).
+    pub done: bool,
+    /// Whether the search was stopped by `cancel_search`.
+    pub cancelled: bool,
+    /// Whether search was truncated (hit max_results limit).
+    pub truncated: bool,
+}
+
+/// Payload of the `search-result` event: new matches of a background search in one file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultEvent {
+    pub search_id: u64,
+    #[serde(flatten)]
+    pub result: FileSearchResult,
+}
+
+/// Payload of the `search-progress` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchProgressEvent {
+    pub search_id: u64,
+    #[serde(flatten)]
+    pub progress: SearchProgress,
+}
+
+/// An event of a background search, to be forwarded to the frontend.
+pub enum SearchJobEvent {
+    Result(SearchResultEvent),
+    Progress(SearchProgressEvent),
+}
+
+/// Background searches that are still running, by id.
+/// Cloning shares the same set of jobs.
+#[derive(Clone, Default)]
+pub struct SearchJobs {
+    next_id: Arc<AtomicU64>,
+    /// search id → cancellation flag
+    running: Arc<Mutex<HashMap<u64, Arc<AtomicBool>>>>,
+}
+
+impl SearchJobs {
+    /// Start a search on a background thread and return its id.
+    ///
+    /// Malformed queries are rejected here; everything else is reported through
+    /// `emit`: batches of matches as they are found, progress as lines are scanned,
+    /// and a final progress event with `done` set.
+    pub fn start(
+        &self,
+        scope: SearchScope,
+        query: &str,
+        options: &SearchOptions,
+        max_results: Option<u32>,
+        term_indices: Arc<TermIndexStore>,
+        emit: impl Fn(SearchJobEvent) + Send + Sync + 'static,
+    ) -> Result<u64, String> {
+        let parsed =
+            SearchQuery::parse(query, options).map_err(|e| format!("Invalid query: {}", e))?;
+        let max_results = max_results.map_or(scope.default_max_results(), |n| n as usize);
+        self.spawn(
+            move || scope.targets(),
+            parsed,
+            max_results,
+            term_indices,
+            emit,
+        )
+    }
+
+    /// Register a job and run it on a new thread over the files returned by `targets`.
+    fn spawn(
+        &self,
+        targets: impl FnOnce() -> Vec<SearchTarget> + Send + 'static,
+        query: Option<SearchQuery>,
+        max_results: usize,
+        term_indices: Arc<TermIndexStore>,
+        emit: impl Fn(SearchJobEvent) + Send + Sync + 'static,
+    ) -> Result<u64, String> {
+        let search_id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.running
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(search_id, Arc::clone(&cancelled));
+
+        let running = Arc::clone(&self.running);
+        std::thread::spawn(move || {
+            let emit_progress = |progress: &SearchProgress| {
+                emit(SearchJobEvent::Progress(SearchProgressEvent {
+                    search_id,
+                    progress: progress.clone(),
+                }))
+            };
+
+            let mut progress = match query {
+                Some(query) => {
+                    let hooks = SearchHooks {
+                        on_result: &|result| {
+                            emit(SearchJobEvent::Result(SearchResultEvent {
+                                search_id,
+                                result: result.clone(),
+                            }))
+                        },
+                        on_progress: &emit_progress,
+                        cancelled: &cancelled,
+                    };
+                    search_targets(targets(), &query, max_results, &term_indices, &hooks)
+                }
+                None => SearchProgress::default(),
+            };
+
+            if let Ok(mut running) = running.lock() {
+                running.remove(&search_id);
+            }
+            progress.done = true;
+            progress.cancelled = cancelled.load(Ordering::SeqCst);
+            emit_progress(&progress);
+        });
+
+        Ok(search_id)
+    }
+
+    /// Ask a running search to stop. Returns false if it is not running.
+    pub fn cancel(&self, search_id: u64) -> bool {
+        let running = match self.running.lock() {
+            Ok(running) => running,
+            Err(_) => return false,
+        };
+        match running.get(&search_id) {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Streaming and cancellation hooks of a multi-file search.
+struct SearchHooks<'a> {
+    /// Receives each new batch of a file's matches
+    on_result: &'a (dyn Fn(&FileSearchResult) + Sync),
+    /// Receives progress as lines are scanned
+    on_progress: &'a (dyn Fn(&SearchProgress) + Sync),
+    /// Stops the search when set
+    cancelled: &'a AtomicBool,
+}
+
+/// List the session and sub-agent files of a project, most recently modified first.
+fn project_search_targets(project_path: &str) -> Vec<SearchTarget> {
+    let sessions = get_session_files(project_path)
+        .into_iter()
+        .map(|(session_id, path)| SearchTarget {
+            project_path: project_path.to_string(),
+            session_id: Some(session_id),
+            agent_id: None,
+            path,
+        });
+    let subagents = get_subagent_files(project_path)
+        .into_iter()
+        .map(|(agent_id, path)| SearchTarget {
+            project_path: project_path.to_string(),
+            session_id: subagent_parent_session(&path),
+            agent_id: Some(agent_id),
+            path,
+        });
+
+    let mut targets: Vec<(SystemTime, SearchTarget)> = sessions
+        .chain(subagents)
+        .map(|target| {
+            let modified = std::fs::metadata(&target.path)
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            (modified, target)
+        })
+        .collect();
+    targets.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    targets.into_iter().map(|(_, target)| target).collect()
+}
+
+/// Search `targets` in parallel, in order of priority, for the `max_results`
+/// best-scoring matches in total, until done or cancelled. Matches are streamed
+/// through `hooks` in batches as they are found, each carrying its score for ranking;
+/// a streamed match is displaced by better ones once its score falls below the
+/// `min_score` of a later progress event. Returns the final counts.
+fn search_targets(
+    targets: Vec<SearchTarget>,
+    query: &SearchQuery,
+    max_results: usize,
+    term_indices: &TermIndexStore,
+    hooks: &SearchHooks,
+) -> SearchProgress {
+    let next = AtomicUsize::new(0);
+    let top = Mutex::new(TopScores::new(max_results));
+    let truncated = AtomicBool::new(false);
+    let files_searched = AtomicUsize::new(0);
+    let total_searched = AtomicUsize::new(0);
+    let bytes_indexed = AtomicU64::new(0);
+
+    let progress = || {
+        let (matches_found, min_score) = top
+            .lock()
+            .map_or((0, None), |top| (top.len() as u32, top.floor()));
+        SearchProgress {
+            files_searched: files_searched.load(Ordering::SeqCst) as u32,
+            total_files: targets.len() as u32,
+            lines_searched: total_searched.load(Ordering::SeqCst) as u32,
+            bytes_indexed: bytes_indexed.load(Ordering::SeqCst),
+            matches_found,
+            min_score,
+            truncated: truncated.load(Ordering::SeqCst),
+            ..SearchProgress::default()
+        }
     };
 
-    search_file(&agent_file, &expr, max_results)
+    // Report the search has started before any file is indexed or read
+    (hooks.on_progress)(&progress());
+
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .clamp(1, MAX_SEARCH_THREADS)
+        .min(targets.len());
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(target) = targets.get(index) else {
+                    break;
+                };
+                if hooks.cancelled.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let file_result = |matches: &[SearchMatch]| FileSearchResult {
+                    project_path: target.project_path.clone(),
+                    session_id: target.session_id.clone(),
+                    agent_id: target.agent_id.clone(),
+                    matches: matches.to_vec(),
+                };
+
+                let mut matches = Vec::new();
+                // Matches already streamed, and lines and bytes already counted
+                let mut streamed = 0;
+                let mut lines_counted = 0;
+                let mut bytes_counted = 0;
+                let scanned = scan_file(
+                    &target.path,
+                    query,
+                    term_indices,
+                    &top,
+                    |event| match event {
+                        ScanEvent::Match(m) => {
+                            matches.push(m);
+                            true
+                        }
+                        ScanEvent::Progress(lines) => {
+                            total_searched
+                                .fetch_add((lines - lines_counted) as usize, Ordering::SeqCst);
+                            lines_counted = lines;
+                            if matches.len() > streamed {
+                                (hooks.on_result)(&file_result(&matches[streamed..]));
+                                streamed = matches.len();
+                            }
+                            (hooks.on_progress)(&progress());
+                            !hooks.cancelled.load(Ordering::SeqCst)
+                        }
+                        ScanEvent::Indexing(bytes) => {
+                            bytes_indexed.fetch_add(bytes - bytes_counted, Ordering::SeqCst);
+                            bytes_counted = bytes;
+                            (hooks.on_progress)(&progress());
+                            !hooks.cancelled.load(Ordering::SeqCst)
+                        }
+                    },
+                );
+                match scanned {
+                    Some(ScanOutcome::Finished {
+                        total_lines,
+                        left_out,
+                    }) => {
+                        if left_out {
+                            truncated.store(true, Ordering::SeqCst);
+                        }
+                        files_searched.fetch_add(1, Ordering::SeqCst);
+                        total_searched
+                            .fetch_add((total_lines - lines_counted) as usize, Ordering::SeqCst);
+                    }
+                    // A file left half-read is not searched, but its lines read were
+                    Some(ScanOutcome::Cancelled { lines_read }) => {
+                        total_searched
+                            .fetch_add((lines_read - lines_counted) as usize, Ordering::SeqCst);
+                    }
+                    None => {}
+                }
+                if matches.len() > streamed {
+                    (hooks.on_result)(&file_result(&matches[streamed..]));
+                }
+                (hooks.on_progress)(&progress());
+            });
+        }
+    });
+
+    progress()
 }
 
-/// Extract all search terms from an expression.
-fn collect_terms(expr: &SearchExpr) -> Vec<String> {
+/// Collect regex patterns for the spans to highlight, and the words of fuzzy terms
+/// (negated terms are skipped).
+fn highlight_patterns(
+    expr: &SearchExpr,
+    patterns: &mut Vec<String>,
+    fuzzy_words: &mut Vec<FuzzyWord>,
+) {
     match expr {
-        SearchExpr::Term(t) => vec![t.clone()],
+        SearchExpr::Term(t) | SearchExpr::Phrase(t) => patterns.push(regex::escape(t)),
+        SearchExpr::Fuzzy(words) => fuzzy_words.extend(words.iter().cloned()),
+        SearchExpr::Prefix(t) => {
+            // Words start at a word boundary, unless the prefix itself starts with a symbol
+            let boundary = t.starts_with(|c: char| c.is_alphanumeric() || c == '_');
+            let escaped = regex::escape(t);
+            patterns.push(if boundary {
+                format!(r"\b{}", escaped)
+            } else {
+                escaped
+            });
+        }
+        SearchExpr::Regex(regex) => patterns.push(format!("(?:{})", regex.as_str())),
+        SearchExpr::Not(_) | SearchExpr::Field(_) => {}
         SearchExpr::And(left, right) | SearchExpr::Or(left, right) => {
-            let mut terms = collect_terms(left);
-            terms.extend(collect_terms(right));
-            terms
+            highlight_patterns(left, patterns, fuzzy_words);
+            highlight_patterns(right, patterns, fuzzy_words);
         }
     }
 }
 
-/// Extract text content from a JSON event line.
-fn extract_text_from_json(line: &str) -> String {
+/// Extract every searchable content block of a JSON event line, in order.
+/// Always returns at least one block (the raw line when nothing readable is found).
+fn extract_blocks(line: &str) -> Vec<(MatchBlock, String)> {
     let json: Value = match serde_json::from_str(line) {
         Ok(v) => v,
-        Err(_) => return line.to_string(),
+        Err(_) => return vec![(MatchBlock::Raw, line.to_string())],
     };
 
-    // Try message.content first (assistant/user messages)
-    if let Some(content) = json.get("message").and_then(|m| m.get("content")) {
-        if let Some(text) = extract_text_from_content(content) {
-            return text;
+    let mut blocks = Vec::new();
+
+    // message.content (assistant/user messages): a string or an array of blocks
+    match &json["message"]["content"] {
+        Value::String(text) => blocks.push((MatchBlock::Text, text.clone())),
+        Value::Array(items) => {
+            for item in items {
+                extract_content_block(item, &mut blocks);
+            }
         }
+        _ => {}
     }
 
-    // Try content directly (system messages)
-    if let Some(content) = json.get("content").and_then(|c| c.as_str()) {
-        return content.to_string();
+    // content directly (system messages)
+    if let Some(content) = json["content"].as_str() {
+        blocks.push((MatchBlock::Text, content.to_string()));
     }
 
-    // Try summary (summary events)
-    if let Some(summary) = json.get("summary").and_then(|s| s.as_str()) {
-        return summary.to_string();
+    // summary (summary events)
+    if let Some(summary) = json["summary"].as_str() {
+        blocks.push((MatchBlock::Text, summary.to_string()));
     }
 
     // Fallback to full JSON
-    line.to_string()
-}
-
-/// Extract text from content field (can be string or array of content blocks).
-fn extract_text_from_content(content: &Value) -> Option<String> {
-    match content {
-        Value::String(s) => Some(s.clone()),
-        Value::Array(arr) => {
-            // Look for text content first
-            for item in arr {
-                if let Some(obj) = item.as_object() {
-                    if obj.get("type").and_then(|t| t.as_str()) == Some("text") {
-                        if let Some(text) = obj.get("text").and_then(|t| t.as_str()) {
-                            return Some(text.to_string());
-                        }
-                    }
-                }
+    if blocks.is_empty() {
+        blocks.push((MatchBlock::Raw, line.to_string()));
+    }
+    blocks
+}
+
+/// Extract the searchable text of one message content block.
+fn extract_content_block(item: &Value, blocks: &mut Vec<(MatchBlock, String)>) {
+    match item["type"].as_str() {
+        Some("text") => {
+            if let Some(text) = item["text"].as_str() {
+                blocks.push((MatchBlock::Text, text.to_string()));
             }
-            // Check for thinking
-            for item in arr {
-                if let Some(obj) = item.as_object() {
-                    if obj.get("type").and_then(|t| t.as_str()) == Some("thinking") {
-                        if let Some(thinking) = obj.get("thinking").and_then(|t| t.as_str()) {
-                            return Some(thinking.to_string());
-                        }
-                    }
-                }
+        }
+        Some("thinking") => {
+            if let Some(thinking) = item["thinking"].as_str() {
+                blocks.push((MatchBlock::Thinking, thinking.to_string()));
             }
-            // Check for tool_use
-            for item in arr {
-                if let Some(obj) = item.as_object() {
-                    if obj.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
-                        if let Some(name) = obj.get("name").and_then(|n| n.as_str()) {
-                            if let Some(input) = obj.get("input") {
-                                return Some(format!("[{}] {}", name, input));
-                            }
-                            return Some(format!("[{}]", name));
-                        }
+        }
+        Some("tool_use") => {
+            let tool = item["name"].as_str().unwrap_or_default();
+            let Some(input) = item["input"].as_object() else {
+                return;
+            };
+            for (field, value) in input {
+                let text = match value {
+                    Value::String(text) => text.clone(),
+                    Value::Null => continue,
+                    other => other.to_string(),
+                };
+                let block = MatchBlock::ToolInput {
+                    tool: tool.to_string(),
+                    field: field.clone(),
+                };
+                blocks.push((block, text));
+            }
+        }
+        Some("tool_result") => match &item["content"] {
+            Value::String(text) => blocks.push((MatchBlock::ToolResult, text.clone())),
+            Value::Array(parts) => {
+                for part in parts {
+                    if let Some(text) = part["text"].as_str() {
+                        blocks.push((MatchBlock::ToolResult, text.to_string()));
                     }
                 }
             }
-            None
-        }
-        _ => None,
+            _ => {}
+        },
+        _ => {}
     }
 }
 
@@ -343,25 +1731,16 @@ fn ceil_char_boundary(s: &str, index: usize) -> usize {
     i
 }
 
-/// Build a snippet with context around the first matched term.
-fn build_snippet(text: &str, terms: &[String], context_chars: usize) -> String {
-    let text_lower = text.to_lowercase();
-
-    // Find the earliest matching term position
-    let mut earliest_pos: Option<usize> = None;
-    for term in terms {
-        if let Some(pos) = text_lower.find(term) {
-            earliest_pos = Some(match earliest_pos {
-                Some(e) if e < pos => e,
-                _ => pos,
-            });
-        }
-    }
-
-    let pos = match earliest_pos {
-        Some(p) => p,
-        None => 0, // Fallback to start if no term found (shouldn't happen)
-    };
+/// Build a snippet with context around the first highlighted span, given the
+/// sorted, non-overlapping byte ranges of `text` to highlight (exact and fuzzy).
+/// Returns the snippet and the highlighted spans within it.
+fn build_snippet(
+    text: &str,
+    spans: Vec<(usize, usize)>,
+    context_chars: usize,
+) -> (String, Vec<MatchSpan>) {
+    // Fallback to start if nothing is highlighted (e.g. a query of field filters)
+    let pos = spans.first().map_or(0, |&(start, _)| start);
 
     // Calculate snippet bounds (ensure valid UTF-8 boundaries)
     let start = floor_char_boundary(text, pos.saturating_sub(context_chars));
@@ -379,87 +1758,430 @@ fn build_snippet(text: &str, terms: &[String], context_chars: usize) -> String {
     let start = floor_char_boundary(text, start);
     let end = ceil_char_boundary(text, end);
 
+    let slice = &text[start..end];
+    let body_start = start + (slice.len() - slice.trim_start().len());
+    let body = slice.trim();
+    let body_end = body_start + body.len();
+
     let mut snippet = String::new();
     if start > 0 {
         snippet.push_str("...");
     }
-    snippet.push_str(text[start..end].trim());
+    let body_offset = snippet.len();
+    snippet.push_str(body);
     if end < text.len() {
         snippet.push_str("...");
     }
 
-    snippet
+    let utf16_at = |at: usize| body_offset + text[body_start..at].encode_utf16().count();
+    let highlights = spans
+        .into_iter()
+        .filter(|&(span_start, span_end)| span_start < body_end && span_end > body_start)
+        .map(|(span_start, span_end)| MatchSpan {
+            start: utf16_at(span_start.max(body_start)),
+            end: utf16_at(span_end.min(body_end)),
+        })
+        .collect();
+
+    (snippet, highlights)
 }
 
-/// Search a file for matching lines.
-fn search_file(file_path: &Path, expr: &SearchExpr, max_results: Option<u32>) -> SearchResponse {
-    let empty_response = SearchResponse {
-        matches: Vec::new(),
-        total_searched: 0,
-        truncated: false,
-    };
+/// Search a file for its best-scoring matching events, returned in file order.
+fn search_file(
+    file_path: &Path,
+    query: &SearchQuery,
+    max_results: Option<u32>,
+    term_indices: &TermIndexStore,
+) -> SearchResponse {
+    let max_results = max_results.map_or(DEFAULT_MAX_RESULTS, |n| n as usize);
+    let top = Mutex::new(TopScores::new(max_results));
+    let mut matches = Vec::new();
 
-    let file = match File::open(file_path) {
-        Ok(f) => f,
-        Err(_) => return empty_response,
-    };
+    let scanned = scan_file(file_path, query, term_indices, &top, |event| {
+        if let ScanEvent::Match(m) = event {
+            matches.push(m);
+        }
+        true
+    });
+
+    // Matches kept when found may have been displaced by better ones since
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    matches.truncate(max_results);
+    matches.sort_by_key(|m| m.sequence);
+
+    match scanned {
+        Some(ScanOutcome::Finished {
+            total_lines,
+            left_out,
+        }) => SearchResponse {
+            matches,
+            total_searched: total_lines,
+            truncated: left_out,
+        },
+        Some(ScanOutcome::Cancelled { lines_read }) => SearchResponse {
+            matches,
+            total_searched: lines_read,
+            truncated: false,
+        },
+        None => SearchResponse {
+            matches: Vec::new(),
+            total_searched: 0,
+            truncated: false,
+        },
+    }
+}
 
-    let reader = BufReader::new(file);
-    let max_results = max_results.unwrap_or(10000) as usize;
-    let mut matches = Vec::new();
-    let mut byte_offset: u64 = 0;
-    let mut total_searched: u32 = 0;
-    let terms = collect_terms(expr);
+/// A line that may match a query, with its relevance score.
+struct Candidate {
+    sequence: u32,
+    byte_offset: u64,
+    score: f32,
+}
+
+/// A match score, ordered by `f32::total_cmp`.
+#[derive(PartialEq)]
+struct Score(f32);
 
-    for (sequence, line_result) in reader.lines().enumerate() {
-        let line = match line_result {
-            Ok(l) => l,
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Scores of the best matches of a search so far, up to its limit on results.
+struct TopScores {
+    limit: usize,
+    /// The kept scores, lowest on top
+    scores: BinaryHeap<Reverse<Score>>,
+}
+
+impl TopScores {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            scores: BinaryHeap::new(),
+        }
+    }
+
+    /// Number of matches kept.
+    fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Matches that can still be kept without displacing any.
+    fn room(&self) -> usize {
+        self.limit - self.scores.len()
+    }
+
+    /// Score a match must exceed to be kept, once the limit is reached.
+    fn floor(&self) -> Option<f32> {
+        if self.limit == 0 {
+            return Some(f32::INFINITY);
+        }
+        match self.scores.peek() {
+            Some(Reverse(Score(lowest))) if self.room() == 0 => Some(*lowest),
+            _ => None,
+        }
+    }
+
+    /// Keep a match's score if it is among the best so far, displacing the lowest
+    /// one if the limit is reached. Returns whether it was kept.
+    fn offer(&mut self, score: f32) -> bool {
+        if let Some(floor) = self.floor() {
+            if score <= floor {
+                return false;
+            }
+            self.scores.pop();
+        }
+        self.scores.push(Reverse(Score(score)));
+        true
+    }
+}
+
+/// What a file scan reports to its caller.
+enum ScanEvent {
+    /// A matching line, among the best matches so far
+    Match(SearchMatch),
+    /// Candidate lines of the file read so far (sent every `PROGRESS_INTERVAL_LINES`,
+    /// or `PROGRESS_INTERVAL` if that comes first)
+    Progress(u32),
+    /// Bytes of the file indexed so far, while its term index is built or updated
+    Indexing(u64),
+}
+
+/// How a file scan ended.
+enum ScanOutcome {
+    /// Every candidate line was considered
+    Finished {
+        /// Lines in the file
+        total_lines: u32,
+        /// Whether matches may have been left out for the limit on results
+        left_out: bool,
+    },
+    /// Stopped by the caller after reading this many candidate lines
+    Cancelled { lines_read: u32 },
+}
+
+/// Find the candidate lines of a file in its term index, then report each one that
+/// matches and is among the best so far according to `top`, and the progress (of
+/// indexing, then of reading candidates), to `on_event` until it returns false.
+///
+/// Candidates are read in file order when they all fit in `top`, otherwise best first,
+/// stopping at the first one that cannot beat the lowest kept score (a candidate's score
+/// bounds the score of its match).
+/// Returns how the scan ended, or `None` if the file cannot be indexed or opened.
+fn scan_file(
+    file_path: &Path,
+    query: &SearchQuery,
+    term_indices: &TermIndexStore,
+    top: &Mutex<TopScores>,
+    mut on_event: impl FnMut(ScanEvent) -> bool,
+) -> Option<ScanOutcome> {
+    let now = Utc::now().timestamp();
+    let (mut candidates, total_lines) = term_indices
+        .with_index(
+            file_path,
+            &mut |bytes| on_event(ScanEvent::Indexing(bytes)),
+            |index| (plan_candidates(index, query, now), index.total_lines()),
+        )
+        .ok()?;
+
+    let best_first = candidates.len() > top.lock().ok()?.room();
+    if best_first {
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    }
+
+    let mut reader = BufReader::new(File::open(file_path).ok()?);
+    // Offset the reader is at, to skip seeking between consecutive lines
+    let mut position = 0;
+    let mut line = String::new();
+    let mut left_out = false;
+    let mut last_report = Instant::now();
+
+    for (i, candidate) in candidates.into_iter().enumerate() {
+        if i % PROGRESS_INTERVAL_LINES == PROGRESS_INTERVAL_LINES - 1
+            || last_report.elapsed() >= PROGRESS_INTERVAL
+        {
+            last_report = Instant::now();
+            if !on_event(ScanEvent::Progress(i as u32)) {
+                return Some(ScanOutcome::Cancelled {
+                    lines_read: i as u32,
+                });
+            }
+        }
+        if let Some(floor) = top.lock().ok()?.floor() {
+            if candidate.score <= floor {
+                left_out = true;
+                if best_first {
+                    break;
+                }
+                continue;
+            }
+        }
+        if candidate.byte_offset != position {
+            reader.seek(SeekFrom::Start(candidate.byte_offset)).ok()?;
+        }
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(read) => position = candidate.byte_offset + read as u64,
             Err(_) => {
-                byte_offset += 1; // Account for newline on error
+                position = u64::MAX; // Force a seek for the next line
                 continue;
             }
-        };
+        }
+        let text = line.strip_suffix('\n').unwrap_or(&line);
+        let text = text.strip_suffix('\r').unwrap_or(text);
 
-        let line_len = line.len() as u64 + 1; // +1 for newline
+        if let Some(similarity) = query.match_line(text) {
+            let score = candidate.score * similarity;
+            if !top.lock().ok()?.offer(score) {
+                left_out = true;
+                continue;
+            }
 
-        if expr.matches(&line) {
-            // Extract text and build snippet
-            let text = extract_text_from_json(&line);
-            let snippet = build_snippet(&text, &terms, 60);
+            // Find the block that matched and build its snippet
+            let blocks = extract_blocks(text);
+            let (block, block_text) = query.matched_block(&blocks, text);
+            let (snippet, highlights) = query.snippet(block_text, 60);
 
-            matches.push(SearchMatch {
-                sequence: sequence as u32,
-                byte_offset,
+            let keep_going = on_event(ScanEvent::Match(SearchMatch {
+                sequence: candidate.sequence,
+                byte_offset: candidate.byte_offset,
+                block,
                 snippet,
+                highlights,
+                score,
+                similarity,
+            }));
+            if !keep_going {
+                return Some(ScanOutcome::Cancelled {
+                    lines_read: i as u32 + 1,
+                });
+            }
+        }
+    }
+
+    Some(ScanOutcome::Finished {
+        total_lines,
+        left_out,
+    })
+}
+
+/// Lines of an indexed file that may match the query, in file order, scored by
+/// tf-idf of the query's terms and by recency relative to `now` (Unix seconds).
+fn plan_candidates(index: &TermIndex, query: &SearchQuery, now: i64) -> Vec<Candidate> {
+    let mut postings = HashMap::new();
+    let lines = candidate_lines(&query.expr, index, &mut postings)
+        .unwrap_or_else(|| (0..index.total_lines()).collect());
+
+    let total_lines = index.total_lines().max(1) as f32;
+    let mut relevance: HashMap<u32, f32> = HashMap::new();
+    for fragment_postings in postings.values() {
+        let idf = (1.0 + total_lines / fragment_postings.len().max(1) as f32).ln();
+        for &(line, count) in fragment_postings {
+            *relevance.entry(line).or_default() += (1.0 + (count as f32).ln()) * idf;
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|sequence| {
+            let line = sequence as usize;
+            let recency = index.line_times[line].map_or(1.0, |time| {
+                let age_days = (now - time).max(0) as f32 / 86_400.0;
+                1.0 + 0.5f32.powf(age_days / RECENCY_HALF_LIFE_DAYS)
             });
+            Candidate {
+                sequence,
+                byte_offset: index.line_offsets[line].0,
+                score: relevance.get(&sequence).copied().unwrap_or(1.0) * recency,
+            }
+        })
+        .collect()
+}
 
-            if matches.len() >= max_results {
-                return SearchResponse {
-                    matches,
-                    total_searched,
-                    truncated: true,
-                };
+/// Lines (sorted) that can match an expression according to the postings, or `None`
+/// if the postings cannot narrow it down (negations, field filters, regexes, and terms
+/// that may be found in an id or timestamp, which are not indexed).
+/// Every line a positive term occurs on is a candidate, so no match is dropped.
+/// The postings of each fragment looked up are kept in `postings` for scoring.
+fn candidate_lines(
+    expr: &SearchExpr,
+    index: &TermIndex,
+    postings: &mut HashMap<String, Vec<(u32, u32)>>,
+) -> Option<Vec<u32>> {
+    match expr {
+        SearchExpr::Term(text) | SearchExpr::Phrase(text) | SearchExpr::Prefix(text) => {
+            if may_be_opaque(text) {
+                return None;
             }
+            // The line must contain every fragment of the text (each inside some term)
+            let text = text.to_lowercase();
+            let mut lines: Option<Vec<u32>> = None;
+            for fragment in tokenize(&text) {
+                let fragment_postings = postings
+                    .entry(fragment.to_string())
+                    .or_insert_with(|| index.fragment_postings(fragment));
+                let fragment_lines: Vec<u32> =
+                    fragment_postings.iter().map(|&(line, _)| line).collect();
+                lines = Some(match lines {
+                    Some(lines) => intersect(&lines, &fragment_lines),
+                    None => fragment_lines,
+                });
+            }
+            lines
+        }
+        SearchExpr::Fuzzy(words) => {
+            // The line must contain every word, give or take its edits, inside some term
+            if words.iter().any(|word| may_be_opaque(&word.text)) {
+                return None;
+            }
+            let mut lines: Option<Vec<u32>> = None;
+            for word in words {
+                let text = word.text.to_lowercase();
+                let word_postings = postings
+                    .entry(format!("~{}", text))
+                    .or_insert_with(|| index.fuzzy_postings(&text, word.max_edits));
+                let word_lines: Vec<u32> = word_postings.iter().map(|&(line, _)| line).collect();
+                lines = Some(match lines {
+                    Some(lines) => intersect(&lines, &word_lines),
+                    None => word_lines,
+                });
+            }
+            lines
+        }
+        SearchExpr::Regex(_) | SearchExpr::Field(_) | SearchExpr::Not(_) => None,
+        SearchExpr::And(left, right) => {
+            let left = candidate_lines(left, index, postings);
+            let right = candidate_lines(right, index, postings);
+            match (left, right) {
+                (Some(left), Some(right)) => Some(intersect(&left, &right)),
+                (lines, None) | (None, lines) => lines,
+            }
+        }
+        SearchExpr::Or(left, right) => {
+            let left = candidate_lines(left, index, postings)?;
+            let right = candidate_lines(right, index, postings)?;
+            let mut lines = [left, right].concat();
+            lines.sort_unstable();
+            lines.dedup();
+            Some(lines)
         }
-
-        byte_offset += line_len;
-        total_searched += 1;
     }
+}
 
-    SearchResponse {
-        matches,
-        total_searched,
-        truncated: false,
+/// Whether a term may be part of an opaque value (id, timestamp), which the index
+/// leaves out: ids and timestamps all contain digits, so terms with one are scanned for.
+fn may_be_opaque(text: &str) -> bool {
+    text.chars().any(|c| c.is_ascii_digit())
+}
+
+/// Intersection of two sorted line lists.
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                result.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
     }
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::session_index::{
+        IndexRegistry, DEFAULT_INDEX_MEMORY_BUDGET, DEFAULT_TERM_INDEX_MEMORY_BUDGET,
+    };
+    use crate::test_support::TempDir;
+
+    /// A term index store that keeps indices in memory only.
+    fn memory_term_indices() -> TermIndexStore {
+        let registry = IndexRegistry::new(
+            DEFAULT_INDEX_MEMORY_BUDGET,
+            DEFAULT_TERM_INDEX_MEMORY_BUDGET,
+        );
+        TermIndexStore::new(None, Arc::new(Mutex::new(registry)))
+    }
 
     #[test]
     fn test_parse_single_term() {
-        let expr = SearchExpr::parse("error").unwrap();
+        let expr = SearchExpr::parse("error").unwrap().unwrap();
         assert!(expr.matches("This is an error message"));
         assert!(expr.matches("ERROR in caps"));
         assert!(!expr.matches("This is fine"));
@@ -467,7 +2189,7 @@ mod tests {
 
     #[test]
     fn test_parse_implicit_and() {
-        let expr = SearchExpr::parse("error bash").unwrap();
+        let expr = SearchExpr::parse("error bash").unwrap().unwrap();
         assert!(expr.matches("error in bash command"));
         assert!(expr.matches("bash threw an error"));
         assert!(!expr.matches("error in python"));
@@ -476,14 +2198,14 @@ mod tests {
 
     #[test]
     fn test_parse_explicit_and() {
-        let expr = SearchExpr::parse("error AND bash").unwrap();
+        let expr = SearchExpr::parse("error AND bash").unwrap().unwrap();
         assert!(expr.matches("error in bash command"));
         assert!(!expr.matches("error in python"));
     }
 
     #[test]
     fn test_parse_or() {
-        let expr = SearchExpr::parse("error OR warning").unwrap();
+        let expr = SearchExpr::parse("error OR warning").unwrap().unwrap();
         assert!(expr.matches("This is an error"));
         assert!(expr.matches("This is a warning"));
         assert!(!expr.matches("This is fine"));
@@ -492,7 +2214,9 @@ mod tests {
     #[test]
     fn test_parse_and_or_precedence() {
         // "error AND bash OR write" should be "(error AND bash) OR write"
-        let expr = SearchExpr::parse("error AND bash OR write").unwrap();
+        let expr = SearchExpr::parse("error AND bash OR write")
+            .unwrap()
+            .unwrap();
         assert!(expr.matches("error in bash")); // matches left side
         assert!(expr.matches("write to file")); // matches right side
         assert!(!expr.matches("error in python")); // doesn't match either
@@ -500,7 +2224,9 @@ mod tests {
 
     #[test]
     fn test_parse_multiple_or() {
-        let expr = SearchExpr::parse("error OR warning OR info").unwrap();
+        let expr = SearchExpr::parse("error OR warning OR info")
+            .unwrap()
+            .unwrap();
         assert!(expr.matches("error occurred"));
         assert!(expr.matches("warning issued"));
         assert!(expr.matches("info message"));
@@ -509,7 +2235,7 @@ mod tests {
 
     #[test]
     fn test_case_insensitive() {
-        let expr = SearchExpr::parse("Error").unwrap();
+        let expr = SearchExpr::parse("Error").unwrap().unwrap();
         assert!(expr.matches("ERROR"));
         assert!(expr.matches("error"));
         assert!(expr.matches("ErRoR"));
@@ -517,20 +2243,676 @@ mod tests {
 
     #[test]
     fn test_empty_query() {
-        assert!(SearchExpr::parse("").is_none());
-        assert!(SearchExpr::parse("   ").is_none());
+        assert!(SearchExpr::parse("").unwrap().is_none());
+        assert!(SearchExpr::parse("   ").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |query: &str| SearchExpr::parse(query).unwrap_err();
+
+        assert_eq!(
+            error("AND error"),
+            SearchParseError::new("Expected a search term, found AND", 0)
+        );
+        assert_eq!(
+            error("error OR"),
+            SearchParseError::new("Expected a search term after OR", 8)
+        );
+        assert_eq!(
+            error("(bash OR write"),
+            SearchParseError::new("Missing closing ')'", 0)
+        );
+        assert_eq!(error("bash)"), SearchParseError::new("Unmatched ')'", 4));
+        assert_eq!(
+            error("say \"hello"),
+            SearchParseError::new("Unterminated quote", 4)
+        );
+        assert_eq!(error("()").message, "Empty parentheses");
+        assert_eq!(
+            error("error NOT OR x").message,
+            "Expected a search term after NOT, found OR"
+        );
+        assert_eq!(
+            error("co*fig").message,
+            "Wildcards are only supported at the end of a term"
+        );
+        assert_eq!(error("*").message, "A wildcard needs a prefix");
+    }
+
+    #[test]
+    fn test_phrases_and_escapes() {
+        let expr = SearchExpr::parse("\"Permission denied\"").unwrap().unwrap();
+        assert!(expr.matches("bash: permission denied (os error 13)"));
+        assert!(!expr.matches("denied permission"));
+
+        // Escaped and quoted operators are plain terms
+        let expr = SearchExpr::parse("\\AND \\(x").unwrap().unwrap();
+        assert!(expr.matches("left and (x right"));
+        let expr = SearchExpr::parse("\"OR\" fatal").unwrap().unwrap();
+        assert!(expr.matches("fatal or"));
+        assert!(!expr.matches("fatal"));
+    }
+
+    #[test]
+    fn test_not_and_grouping() {
+        for query in ["error NOT test", "error -test"] {
+            let expr = SearchExpr::parse(query).unwrap().unwrap();
+            assert!(expr.matches("error in build"));
+            assert!(!expr.matches("error in test"));
+        }
+
+        let expr = SearchExpr::parse("(bash OR write) AND timeout")
+            .unwrap()
+            .unwrap();
+        assert!(expr.matches("bash timeout"));
+        assert!(expr.matches("write timeout"));
+        assert!(!expr.matches("bash failed"));
+        assert!(!expr.matches("read timeout"));
+
+        let expr = SearchExpr::parse("-(warning OR info) log")
+            .unwrap()
+            .unwrap();
+        assert!(expr.matches("error log"));
+        assert!(!expr.matches("info log"));
+
+        // A lone '-' is a term
+        assert!(SearchExpr::parse("a - b")
+            .unwrap()
+            .unwrap()
+            .matches("a - b"));
+
+        // Long options are literal terms; short ones are searched for quoted
+        let expr = SearchExpr::parse("git push --force").unwrap().unwrap();
+        assert!(expr.matches("git push --force origin main"));
+        assert!(!expr.matches("git push origin main"));
+        let expr = SearchExpr::parse("rm \"-rf\"").unwrap().unwrap();
+        assert!(expr.matches("rm -rf target"));
+        assert!(!expr.matches("rm target"));
+        assert!(!expr.matches("rm -rv target"));
+        let expr = SearchExpr::parse("rm NOT \"-rf\"").unwrap().unwrap();
+        assert!(expr.matches("rm target"));
+        assert!(!expr.matches("rm -rf target"));
+
+        // A single '-' negates any word, however short, phrase or group
+        let expr = SearchExpr::parse("error -\"in test\" -warning -bug -db")
+            .unwrap()
+            .unwrap();
+        assert!(expr.matches("error in build"));
+        assert!(!expr.matches("error in test"));
+        assert!(!expr.matches("warning: error"));
+        assert!(!expr.matches("error: bug"));
+        assert!(!expr.matches("db error"));
+        let expr = SearchExpr::parse("-log").unwrap().unwrap();
+        assert!(expr.matches("error"));
+        assert!(!expr.matches("see the log"));
+    }
+
+    #[test]
+    fn test_prefix_wildcard() {
+        let expr = SearchExpr::parse("config*").unwrap().unwrap();
+        assert!(expr.matches("load configuration"));
+        assert!(expr.matches("[Config] value"));
+        assert!(!expr.matches("reconfigure"));
+
+        let query = SearchQuery::parse("config* -test", &SearchOptions::default())
+            .unwrap()
+            .unwrap();
+        let (snippet, highlights) = query.snippet("reconfigure the Config for test", 60);
+        assert_eq!(highlighted(&snippet, &highlights), vec!["Config"]);
+    }
+
+    #[test]
+    fn test_field_filters() {
+        let bash = r#"{"type":"assistant","timestamp":"2026-10-02T09:00:00.000Z","message":{"content":[{"type":"tool_use","name":"Bash","input":{"command":"cargo test"}}]}}"#;
+        let edit = r#"{"type":"assistant","timestamp":"2026-09-30T09:00:00.000Z","message":{"content":[{"type":"tool_use","name":"Edit","input":{"file_path":"/proj/src/lib.rs","old_string":"a","new_string":"cargo"}}]}}"#;
+        let prompt = r#"{"type":"user","userType":"external","timestamp":"2026-10-01T12:00:00.000Z","message":{"content":"run cargo please"}}"#;
+        let result = r#"{"type":"user","userType":"external","message":{"content":[{"type":"tool_result","content":"ok"}]},"toolUseResult":{"agentId":"a1b2c3","status":"completed"}}"#;
+        let compact =
+            r#"{"type":"system","subtype":"compact_boundary","content":"Conversation compacted"}"#;
+        let lines = [bash, edit, prompt, result, compact];
+
+        let matching = |query: &str| -> Vec<usize> {
+            let expr = SearchExpr::parse(query).unwrap().unwrap();
+            (0..lines.len())
+                .filter(|&i| expr.matches(lines[i]))
+                .collect()
+        };
+
+        assert_eq!(matching("tool:bash cargo"), vec![0]);
+        assert_eq!(matching("cargo -tool:Bash"), vec![1, 2]);
+        assert_eq!(matching("file:src/lib.rs"), vec![1]);
+        assert_eq!(matching("type:user"), vec![2, 3]);
+        assert_eq!(matching("user:human"), vec![2]);
+        assert_eq!(matching("user:tool"), vec![3]);
+        assert_eq!(matching("agent:a1b2"), vec![3]);
+        assert_eq!(matching("subtype:compact_boundary"), vec![4]);
+        assert_eq!(matching("after:2026-10-01"), vec![0, 2]);
+        assert_eq!(matching("before:2026-10-01T12:00:00Z"), vec![1]);
+        assert_eq!(
+            matching("type:assistant (file:\"lib.rs\" OR tool:bash)"),
+            vec![0, 1]
+        );
+
+        // Unknown fields are plain terms
+        assert!(SearchExpr::parse("http://localhost")
+            .unwrap()
+            .unwrap()
+            .matches("open http://localhost"));
+
+        let error = |query: &str| SearchExpr::parse(query).unwrap_err().message;
+        assert_eq!(error("type:"), "Missing value for type:");
+        assert_eq!(
+            error("user:robot"),
+            "Unknown user kind 'robot' (expected human, tool or meta)"
+        );
+        assert_eq!(
+            error("after:yesterday"),
+            "Invalid date 'yesterday' (expected YYYY-MM-DD or an ISO 8601 time)"
+        );
+        assert_eq!(
+            error("tool:mcp*"),
+            "Wildcards are not supported in field filters"
+        );
+    }
+
+    /// The highlighted parts of a snippet.
+    fn highlighted(snippet: &str, highlights: &[MatchSpan]) -> Vec<String> {
+        let units: Vec<u16> = snippet.encode_utf16().collect();
+        highlights
+            .iter()
+            .map(|span| String::from_utf16(&units[span.start..span.end]).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_regex_terms() {
+        let expr = SearchExpr::parse("/E\\d{4}/ rustc").unwrap().unwrap();
+        assert!(expr.matches("rustc: error[E0425]: cannot find value"));
+        assert!(expr.matches("rustc e0599")); // case-insensitive by default
+        assert!(!expr.matches("rustc E42"));
+
+        // Escaped slashes, negation and grouping
+        let expr = SearchExpr::parse("-(/src\\/.*\\.rs/) cargo")
+            .unwrap()
+            .unwrap();
+        assert!(expr.matches("cargo build docs/readme.md"));
+        assert!(!expr.matches("cargo build src/lib.rs"));
+
+        // A slash that doesn't end the word keeps paths as plain terms
+        let expr = SearchExpr::parse("/usr/bin").unwrap().unwrap();
+        assert!(expr.matches("ls /usr/bin"));
+        assert!(!expr.matches("usr"));
+
+        let error = SearchExpr::parse("x /E\\d{4/").unwrap_err();
+        assert_eq!(
+            error,
+            SearchParseError::new("Invalid regex: unclosed counted repetition", 2)
+        );
+        let long = format!("/{}/", "a".repeat(MAX_REGEX_LEN + 1));
+        assert_eq!(
+            SearchExpr::parse(&long).unwrap_err().message,
+            "Regex is longer than 1000 characters"
+        );
+        assert!(SearchExpr::parse("/\\w{1000}{1000}/")
+            .unwrap_err()
+            .message
+            .starts_with("Regex is too complex"));
     }
 
     #[test]
-    fn test_orphan_operators() {
-        // Orphan AND at start - should skip and parse rest
-        let expr = SearchExpr::parse("AND error");
-        assert!(expr.is_some());
-        assert!(expr.unwrap().matches("error here"));
+    fn test_case_sensitive() {
+        let options = SearchOptions {
+            case_sensitive: true,
+            ..SearchOptions::default()
+        };
+        let query = SearchQuery::parse("SessionEvent OR /^Edit/", &options)
+            .unwrap()
+            .unwrap();
+        assert!(query.matches("struct SessionEvent"));
+        assert!(!query.matches("struct sessionevent"));
+        assert!(query.matches("Edit tool"));
+        assert!(!query.matches("edit tool"));
+
+        let (snippet, highlights) = query.snippet("Edit the SessionEvent, not sessionevent", 60);
+        assert_eq!(
+            highlighted(&snippet, &highlights),
+            vec!["Edit", "SessionEvent"]
+        );
+    }
 
-        // Orphan OR at end - should parse what's before
-        let expr = SearchExpr::parse("error OR");
-        assert!(expr.is_some());
+    #[test]
+    fn test_fuzzy_terms() {
+        let fuzzy = SearchOptions {
+            fuzzy: true,
+            ..SearchOptions::default()
+        };
+        let query = SearchQuery::parse("get_sesion_events", &fuzzy)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            query.match_line("call get_session_events()"),
+            Some(1.0 - 1.0 / 17.0)
+        );
+        assert_eq!(query.match_line("call get_sesion_events()"), Some(1.0));
+        assert_eq!(query.match_line("call get_events()"), None);
+        let (snippet, highlights) = query.snippet("Then GET_SESSION_EVENTS(path) fails", 60);
+        assert_eq!(
+            highlighted(&snippet, &highlights),
+            vec!["GET_SESSION_EVENTS"]
+        );
+
+        // Longer words tolerate more edits, short ones none
+        let query = SearchQuery::parse("refersh tokn bug", &fuzzy)
+            .unwrap()
+            .unwrap();
+        assert!(query.matches("refresh the token: bug"));
+        assert!(!query.matches("refresh the token: bag"));
+        assert!(!query.matches("rafrash the token: bug"));
+
+        // Phrases, prefixes and negated terms are not fuzzy, and only fuzzy words count
+        // towards similarity
+        let query = SearchQuery::parse("\"oauth flow\" -tokn sesion", &fuzzy)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            query.match_line("oauth flow session"),
+            Some(1.0 - 1.0 / 6.0)
+        );
+        assert!(!query.matches("oauth flaw session"));
+        assert!(!query.matches("oauth flow session token"));
+        let (snippet, highlights) = query.snippet("the oauth flow of a session", 60);
+        assert_eq!(
+            highlighted(&snippet, &highlights),
+            vec!["oauth flow", "session"]
+        );
+
+        // Without fuzzy mode, typos don't match
+        let query = SearchQuery::parse("get_sesion_events", &SearchOptions::default())
+            .unwrap()
+            .unwrap();
+        assert!(!query.matches("call get_session_events()"));
+    }
+
+    #[test]
+    fn test_snippet_uses_match_spans() {
+        let query = SearchQuery::parse("cargo OR /E\\d{4}/", &SearchOptions::default())
+            .unwrap()
+            .unwrap();
+        let text = format!(
+            "{} ran Cargo test: error[E0425] in naïve.rs",
+            "x ".repeat(50)
+        );
+        let (snippet, highlights) = query.snippet(&text, 20);
+        assert!(snippet.starts_with("..."));
+        assert_eq!(highlighted(&snippet, &highlights), vec!["Cargo", "E0425"]);
+
+        // Field-only queries have nothing to highlight
+        let query = SearchQuery::parse("type:user", &SearchOptions::default())
+            .unwrap()
+            .unwrap();
+        let (snippet, highlights) = query.snippet("hello world", 20);
+        assert_eq!(snippet, "hello world");
+        assert!(highlights.is_empty());
+    }
+
+    #[test]
+    fn test_matched_blocks() {
+        let assistant = r#"{"type":"assistant","message":{"content":[{"type":"thinking","thinking":"Run the tests first"},{"type":"text","text":"Running the tests."},{"type":"text","text":"Then fix the OAuth refresh bug."},{"type":"tool_use","name":"Bash","input":{"command":"cargo test --lib","timeout":120000}}]}}"#;
+        let result = r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"t1","content":[{"type":"text","text":"test result: ok. 75 passed"}]}]}}"#;
+        let snapshot = r#"{"type":"file-history-snapshot","messageId":"m1"}"#;
+
+        let find = |query: &str, line: &str| -> (MatchBlock, String) {
+            let query = SearchQuery::parse(query, &SearchOptions::default())
+                .unwrap()
+                .unwrap();
+            assert!(query.matches(line));
+            let blocks = extract_blocks(line);
+            let (block, text) = query.matched_block(&blocks, line);
+            (block, query.snippet(text, 60).0)
+        };
+
+        assert_eq!(
+            find("oauth", assistant),
+            (
+                MatchBlock::Text,
+                "Then fix the OAuth refresh bug.".to_string()
+            )
+        );
+        assert_eq!(find("first", assistant).0, MatchBlock::Thinking);
+        assert_eq!(
+            find("cargo", assistant),
+            (
+                MatchBlock::ToolInput {
+                    tool: "Bash".to_string(),
+                    field: "command".to_string()
+                },
+                "cargo test --lib".to_string()
+            )
+        );
+        assert_eq!(
+            find("120000", assistant).0,
+            MatchBlock::ToolInput {
+                tool: "Bash".to_string(),
+                field: "timeout".to_string()
+            }
+        );
+        assert_eq!(
+            find("passed", result),
+            (
+                MatchBlock::ToolResult,
+                "test result: ok. 75 passed".to_string()
+            )
+        );
+        // Matches outside any block are reported in the raw line
+        let (block, snippet) = find("tool_use", assistant);
+        assert_eq!(block, MatchBlock::Raw);
+        assert!(snippet.contains(r#"{"type":"tool_use","name":"Bash""#));
+        assert_eq!(find("messageid", snapshot).0, MatchBlock::Raw);
+        // Queries with nothing to highlight use the first block
+        assert_eq!(
+            find("type:assistant", assistant),
+            (MatchBlock::Thinking, "Run the tests first".to_string())
+        );
+    }
+
+    #[test]
+    fn test_search_targets() {
+        let dir = TempDir::new("search");
+        let line = |text: &str| format!(r#"{{"type":"user","message":{{"content":"{}"}}}}"#, text);
+        let files = [
+            (
+                "a.jsonl",
+                vec![line("oauth refresh bug"), line("unrelated")],
+            ),
+            ("b.jsonl", vec![line("nothing here")]),
+            (
+                "agent-c.jsonl",
+                vec![line("OAuth again"), line("oauth once more")],
+            ),
+        ];
+        let targets: Vec<SearchTarget> = files
+            .iter()
+            .map(|(name, lines)| {
+                let path = dir.join(name);
+                std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+                SearchTarget {
+                    project_path: "/project".to_string(),
+                    session_id: Some("s1".to_string()),
+                    agent_id: name.strip_prefix("agent-").map(|n| n.replace(".jsonl", "")),
+                    path,
+                }
+            })
+            .collect();
+        let parse = || SearchQuery::parse("oauth", &SearchOptions::default()).unwrap();
+        let query = parse().unwrap();
+
+        let term_indices = memory_term_indices();
+
+        let streamed: Mutex<Vec<(Option<String>, usize)>> = Mutex::new(Vec::new());
+        let scores: Mutex<Vec<f32>> = Mutex::new(Vec::new());
+        let first_progress: Mutex<Option<SearchProgress>> = Mutex::new(None);
+        let not_cancelled = AtomicBool::new(false);
+        let hooks = SearchHooks {
+            on_result: &|result| {
+                streamed
+                    .lock()
+                    .unwrap()
+                    .push((result.agent_id.clone(), result.matches.len()));
+                scores
+                    .lock()
+                    .unwrap()
+                    .extend(result.matches.iter().map(|m| m.score));
+            },
+            on_progress: &|progress| {
+                let mut first = first_progress.lock().unwrap();
+                first.get_or_insert_with(|| progress.clone());
+            },
+            cancelled: &not_cancelled,
+        };
+        let progress = search_targets(targets.clone(), &query, 10, &term_indices, &hooks);
+        // Progress is reported before the first file is indexed
+        let first = first_progress.lock().unwrap().take().unwrap();
+        assert_eq!((first.files_searched, first.lines_searched), (0, 0));
+        assert!(!progress.truncated);
+        assert_eq!(progress.files_searched, 3);
+        assert_eq!(progress.total_files, 3);
+        assert_eq!(progress.lines_searched, 5);
+        assert_eq!(progress.matches_found, 3);
+        assert_eq!(progress.min_score, None);
+        let mut grouped = std::mem::take(&mut *streamed.lock().unwrap());
+        grouped.sort();
+        assert_eq!(grouped, vec![(None, 1), (Some("c".to_string()), 2)]);
+
+        // The limit is shared by all files, keeping the best scores
+        scores.lock().unwrap().clear();
+        let progress = search_targets(targets.clone(), &query, 2, &term_indices, &hooks);
+        assert!(progress.truncated);
+        assert_eq!(progress.matches_found, 2);
+        let min_score = progress.min_score.unwrap();
+        let mut scores = std::mem::take(&mut *scores.lock().unwrap());
+        let kept = scores.iter().filter(|&&score| score >= min_score).count();
+        assert_eq!(kept, 2);
+        scores.sort_by(|a, b| b.total_cmp(a));
+        assert_eq!(scores[1], min_score);
+
+        // Background jobs stream the same matches, then report they are done
+        let jobs = SearchJobs::default();
+        let term_indices = Arc::new(term_indices);
+        let (events, received) = std::sync::mpsc::channel();
+        let events = Mutex::new(events);
+        let job_targets = targets.clone();
+        let search_id = jobs
+            .spawn(
+                move || job_targets,
+                parse(),
+                10,
+                Arc::clone(&term_indices),
+                move |event| {
+                    let _ = events.lock().unwrap().send(event);
+                },
+            )
+            .unwrap();
+        let mut found = 0;
+        let last = loop {
+            match received.recv().unwrap() {
+                SearchJobEvent::Result(event) => {
+                    assert_eq!(event.search_id, search_id);
+                    found += event.result.matches.len();
+                }
+                SearchJobEvent::Progress(event) if event.progress.done => break event.progress,
+                SearchJobEvent::Progress(_) => {}
+            }
+        };
+        assert_eq!(found, 3);
+        assert_eq!((last.matches_found, last.cancelled), (3, false));
+        assert!(!jobs.cancel(search_id));
+
+        // A cancelled job stops before searching further files
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let (events, received) = std::sync::mpsc::channel();
+        let events = Mutex::new(events);
+        let search_id = jobs
+            .spawn(
+                move || {
+                    let _ = wait.recv();
+                    targets
+                },
+                parse(),
+                10,
+                term_indices,
+                move |event| {
+                    let _ = events.lock().unwrap().send(event);
+                },
+            )
+            .unwrap();
+        assert!(jobs.cancel(search_id));
+        release.send(()).unwrap();
+        let last = loop {
+            if let SearchJobEvent::Progress(event) = received.recv().unwrap() {
+                if event.progress.done {
+                    break event.progress;
+                }
+            }
+        };
+        assert!(last.cancelled);
+        assert_eq!(last.files_searched, 0);
+    }
+
+    #[test]
+    fn test_search_targets_cancelled_mid_file() {
+        let dir = TempDir::new("search-cancel");
+        let path = dir.join("session.jsonl");
+        let lines: Vec<String> = (0..PROGRESS_INTERVAL_LINES * 3)
+            .map(|i| format!(r#"{{"type":"user","message":{{"content":"oauth {}"}}}}"#, i))
+            .collect();
+        std::fs::write(&path, lines.join("\n") + "\n").unwrap();
+        let targets = vec![SearchTarget {
+            project_path: "/project".to_string(),
+            session_id: Some("s1".to_string()),
+            agent_id: None,
+            path,
+        }];
+        let query = SearchQuery::parse("oauth", &SearchOptions::default())
+            .unwrap()
+            .unwrap();
+        let term_indices = memory_term_indices();
+
+        // Cancel at the first progress report after candidate lines were read
+        let cancelled = AtomicBool::new(false);
+        let hooks = SearchHooks {
+            on_result: &|_| {},
+            on_progress: &|progress| {
+                if progress.lines_searched > 0 {
+                    cancelled.store(true, Ordering::SeqCst);
+                }
+            },
+            cancelled: &cancelled,
+        };
+        let progress = search_targets(targets, &query, 10_000, &term_indices, &hooks);
+        // The half-read file is not counted as searched, nor the search as truncated
+        assert_eq!(progress.files_searched, 0);
+        assert!(progress.lines_searched > 0);
+        assert!((progress.lines_searched as usize) < lines.len());
+        assert_eq!(progress.matches_found, progress.lines_searched);
+        assert!(!progress.truncated);
+    }
+
+    #[test]
+    fn test_indexed_search_matches_full_scan() {
+        let dir = TempDir::new("search-index");
+        let file = dir.join("session.jsonl");
+        let lines = [
+            r#"{"type":"user","timestamp":"2020-01-01T00:00:00Z","message":{"content":"The OAuth refresh bug"}}"#,
+            r#"{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Bash","input":{"command":"cargo test oauth::refresh"}}]}}"#,
+            r#"{"type":"user","message":{"content":"src/lib.rs fails with E0308"}}"#,
+            r#"{"type":"assistant","timestamp":"2020-01-01T00:00:00Z","message":{"content":"refresh refresh refresh the token"}}"#,
+            r#"{"type":"summary","summary":"Unrelated work"}"#,
+        ];
+        std::fs::write(&file, lines.join("\n") + "\n").unwrap();
+        let mut index = TermIndex::empty();
+        index.update_with_progress(&file, &mut |_| true).unwrap();
+        let term_indices = memory_term_indices();
+
+        for query in [
+            "oauth",
+            "auth refresh",
+            "\"refresh bug\"",
+            "refr*",
+            "lib.rs",
+            "oauth OR token",
+            "refresh -token",
+            "tool:bash refresh",
+            "/E\\d{4}/",
+            "missing",
+            "~refersh tokn",
+            "~oauht OR lib.rs",
+            "~refrsh -tokne",
+        ] {
+            // A leading `~` runs the rest of the query in fuzzy mode
+            let options = SearchOptions {
+                fuzzy: query.starts_with('~'),
+                ..SearchOptions::default()
+            };
+            let query = query.trim_start_matches('~');
+            let parsed = SearchQuery::parse(query, &options).unwrap().unwrap();
+            let expected: Vec<u32> = (0..lines.len() as u32)
+                .filter(|&i| parsed.matches(lines[i as usize]))
+                .collect();
+            let found: Vec<u32> = search_file(&file, &parsed, None, &term_indices)
+                .matches
+                .iter()
+                .map(|m| m.sequence)
+                .collect();
+            assert_eq!(found, expected, "query {:?}", query);
+        }
+
+        // Postings narrow terms down; negations leave every line a candidate
+        let candidates = |query: &str| -> Vec<u32> {
+            let parsed = SearchQuery::parse(query, &SearchOptions::default())
+                .unwrap()
+                .unwrap();
+            plan_candidates(&index, &parsed, 0)
+                .iter()
+                .map(|c| c.sequence)
+                .collect()
+        };
+        assert_eq!(candidates("oauth refresh"), vec![0, 1]);
+        assert_eq!(candidates("lib.rs"), vec![2]);
+        assert_eq!(candidates("-oauth"), vec![0, 1, 2, 3, 4]);
+
+        // Repeated terms rank higher, and so do recent events
+        let parsed = SearchQuery::parse("refresh", &SearchOptions::default())
+            .unwrap()
+            .unwrap();
+        let now = DateTime::parse_from_rfc3339("2020-01-01T00:00:00Z")
+            .unwrap()
+            .timestamp();
+        let scores: Vec<f32> = plan_candidates(&index, &parsed, now)
+            .iter()
+            .map(|c| c.score)
+            .collect();
+        assert!(scores[2] > scores[0], "{:?}", scores);
+        assert!(scores[0] > scores[1], "{:?}", scores);
+
+        // A limit keeps the best-scoring matches, not the first ones
+        let response = search_file(&file, &parsed, Some(1), &term_indices);
+        let found: Vec<u32> = response.matches.iter().map(|m| m.sequence).collect();
+        assert_eq!(found, vec![3]);
+        assert!(response.truncated);
+    }
+
+    #[test]
+    fn test_indexed_search_finds_ids_and_timestamps() {
+        let dir = TempDir::new("search-opaque");
+        let file = dir.join("session.jsonl");
+        let lines = [
+            r#"{"type":"assistant","uuid":"4f1c9a2e-77b0-4c1e-9d3a-1b2c3d4e5f60","timestamp":"2025-03-14T09:26:53.589Z","message":{"id":"msg_01XyZ","content":[{"type":"tool_use","id":"toolu_01AbC","name":"Bash","input":{"command":"ls"}}]}}"#,
+            r#"{"type":"user","message":{"content":[{"type":"tool_result","tool_use_id":"toolu_01AbC","content":"src"}]}}"#,
+            r#"{"type":"user","message":{"content":"Unrelated"}}"#,
+        ];
+        std::fs::write(&file, lines.join("\n") + "\n").unwrap();
+        let term_indices = memory_term_indices();
+
+        for (query, expected) in [
+            ("toolu_01AbC", vec![0, 1]),
+            ("msg_01XyZ", vec![0]),
+            ("4f1c9a2e-77b0-4c1e-9d3a-1b2c3d4e5f60", vec![0]),
+            ("\"2025-03-14T09:26\"", vec![0]),
+            ("toolu_01*", vec![0, 1]),
+            ("/msg_\\w+/", vec![0]),
+            ("toolu_01AbC tool:bash", vec![0]),
+        ] {
+            let parsed = SearchQuery::parse(query, &SearchOptions::default())
+                .unwrap()
+                .unwrap();
+            let found: Vec<u32> = search_file(&file, &parsed, None, &term_indices)
+                .matches
+                .iter()
+                .map(|m| m.sequence)
+                .collect();
+            assert_eq!(found, expected, "query {:?}", query);
+        }
     }
 
     #[test]
@@ -538,20 +2920,26 @@ mod tests {
         // Test that build_snippet handles multi-byte UTF-8 characters without panicking
         // The box-drawing character '─' is 3 bytes (E2 94 80)
         let text = "prefix ─────────────────────────────────────── error ─────────────────────────────────────── suffix";
-        let terms = vec!["error".to_string()];
+        let query = SearchQuery::parse("error", &SearchOptions::default())
+            .unwrap()
+            .unwrap();
 
         // Should not panic - this was the bug that caused the crash
-        let snippet = build_snippet(text, &terms, 30);
+        let (snippet, highlights) = query.snippet(text, 30);
         assert!(snippet.contains("error"));
+        assert_eq!(highlighted(&snippet, &highlights), vec!["error"]);
     }
 
     #[test]
     fn test_snippet_emoji() {
         // Test with emoji (4-byte UTF-8)
         let text = "Hello 🎉🎊🎈 world error 🚀🌟 end";
-        let terms = vec!["error".to_string()];
+        let query = SearchQuery::parse("error", &SearchOptions::default())
+            .unwrap()
+            .unwrap();
 
-        let snippet = build_snippet(text, &terms, 20);
+        let (snippet, highlights) = query.snippet(text, 20);
         assert!(snippet.contains("error"));
+        assert_eq!(highlighted(&snippet, &highlights), vec!["error"]);
     }
 }
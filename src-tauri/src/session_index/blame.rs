@@ -0,0 +1,401 @@
+//! Per-line blame for session edits.
+//!
+//! Maps each line of a file's working-tree content to the session edit that wrote it,
+//! using the edit positions recorded in session indices (`file_to_edit_lines`).
+//!
+//! Matching runs in two passes, oldest edit first so later edits win:
+//! 1. Distinctive added lines are matched individually (low confidence).
+//! 2. Each edit's `new_string` is matched as a contiguous block (high confidence),
+//!    overriding line matches.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::claude_code::{extract_tool_file_changes, make_relative_path, EditConfidence};
+
+use super::types::SessionIndex;
+
+/// A session whose edits take part in a blame.
+pub struct BlameSource<'a> {
+    pub session_id: &'a str,
+    pub index: &'a SessionIndex,
+    pub session_file: &'a Path,
+}
+
+/// The session edit a line is attributed to.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LineBlame {
+    /// Session that made the edit
+    pub session_id: String,
+    /// Line (sequence number) of the edit event in the session file
+    pub sequence: u32,
+    /// Position of the edit among the file's edits (matches FileDiff.sequence)
+    pub edit_index: u32,
+    /// Line of the human message that triggered the edit
+    pub trigger_line: Option<u32>,
+    /// Timestamp of the edit (ISO 8601)
+    pub timestamp: Option<String>,
+    /// High for an exact block match, Low for a single-line match
+    pub confidence: EditConfidence,
+}
+
+/// Blame for every line of a file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileBlame {
+    /// One entry per line of the current content (None = not written by these sessions)
+    pub lines: Vec<Option<LineBlame>>,
+    /// Number of attributed lines
+    pub attributed_count: usize,
+}
+
+/// An edit event's timestamp and its (old_string, new_string) changes to the blamed file.
+type EventChanges = (Option<String>, Vec<(String, String)>);
+
+/// A single recorded edit to the blamed file.
+struct BlameEdit {
+    session_id: String,
+    sequence: u32,
+    edit_index: u32,
+    trigger_line: Option<u32>,
+    timestamp: Option<String>,
+    old_string: String,
+    new_string: String,
+}
+
+impl BlameEdit {
+    fn blame(&self, confidence: EditConfidence) -> LineBlame {
+        LineBlame {
+            session_id: self.session_id.clone(),
+            sequence: self.sequence,
+            edit_index: self.edit_index,
+            trigger_line: self.trigger_line,
+            timestamp: self.timestamp.clone(),
+            confidence,
+        }
+    }
+}
+
+/// Attribute each line of `current` to the latest session edit that wrote it.
+///
+/// `file_path` is relative to `project_path`, as in `file_to_edit_lines`.
+pub fn blame_file(
+    current: &str,
+    file_path: &str,
+    project_path: &str,
+    sources: &[BlameSource],
+) -> Result<FileBlame, String> {
+    let mut edits = Vec::new();
+    for source in sources {
+        edits.extend(load_edits(source, file_path, project_path)?);
+    }
+    // ISO 8601 timestamps from the same writer sort chronologically; ties keep session order
+    edits.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+    let current_lines: Vec<&str> = current.lines().collect();
+    let mut lines: Vec<Option<LineBlame>> = vec![None; current_lines.len()];
+
+    // Pass 1: individual distinctive lines
+    for edit in &edits {
+        for added in added_lines(edit) {
+            if !is_distinctive(added) {
+                continue;
+            }
+            for (i, line) in current_lines.iter().enumerate() {
+                if line.trim_end() == added.trim_end() {
+                    lines[i] = Some(edit.blame(EditConfidence::Low));
+                }
+            }
+        }
+    }
+
+    // Pass 2: whole blocks
+    for edit in &edits {
+        let new_lines: Vec<&str> = edit.new_string.lines().collect();
+        let added = added_lines(edit);
+        for start in find_blocks(&current_lines, &new_lines) {
+            for (offset, new_line) in new_lines.iter().enumerate() {
+                if added.contains(new_line) {
+                    lines[start + offset] = Some(edit.blame(EditConfidence::High));
+                }
+            }
+        }
+    }
+
+    let attributed_count = lines.iter().filter(|l| l.is_some()).count();
+    Ok(FileBlame {
+        lines,
+        attributed_count,
+    })
+}
+
+/// Load the edits a session made to `file_path` from the session file.
+fn load_edits(
+    source: &BlameSource,
+    file_path: &str,
+    project_path: &str,
+) -> Result<Vec<BlameEdit>, String> {
+    let Some(edit_lines) = source.index.file_to_edit_lines.get(file_path) else {
+        return Ok(Vec::new());
+    };
+
+    let mut file = File::open(source.session_file)
+        .map_err(|e| format!("Failed to open session file: {}", e))?;
+    let mut edits = Vec::with_capacity(edit_lines.len());
+    let mut changes_by_line: HashMap<u32, EventChanges> = HashMap::new();
+    let mut used_by_line: HashMap<u32, usize> = HashMap::new();
+
+    for (edit_index, &sequence) in edit_lines.iter().enumerate() {
+        let (timestamp, changes) = match changes_by_line.entry(sequence) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(read_file_changes(
+                &mut file,
+                source.index,
+                sequence,
+                file_path,
+                project_path,
+            )?),
+        };
+
+        // MultiEdit events appear once per sub-edit, in order
+        let used = used_by_line.entry(sequence).or_insert(0);
+        let Some((old_string, new_string)) = changes.get(*used) else {
+            continue;
+        };
+        *used += 1;
+
+        edits.push(BlameEdit {
+            session_id: source.session_id.to_string(),
+            sequence,
+            edit_index: edit_index as u32,
+            trigger_line: source.index.find_human_boundary(sequence),
+            timestamp: timestamp.clone(),
+            old_string: old_string.clone(),
+            new_string: new_string.clone(),
+        });
+    }
+
+    Ok(edits)
+}
+
+/// Read an event line and return its timestamp and (old_string, new_string) changes to a file.
+fn read_file_changes(
+    file: &mut File,
+    index: &SessionIndex,
+    sequence: u32,
+    file_path: &str,
+    project_path: &str,
+) -> Result<EventChanges, String> {
+    let Some(&(offset, _)) = index.line_offsets.get(sequence as usize) else {
+        return Ok((None, Vec::new()));
+    };
+
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| format!("Failed to seek: {}", e))?;
+    let mut line = String::new();
+    BufReader::new(&*file)
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read line: {}", e))?;
+
+    let Ok(entry) = serde_json::from_str::<Value>(&line) else {
+        return Ok((None, Vec::new()));
+    };
+    let timestamp = entry
+        .get("timestamp")
+        .and_then(|v| v.as_str())
+        .map(String::from);
+
+    let mut changes = Vec::new();
+    let items = entry
+        .pointer("/message/content")
+        .and_then(|v| v.as_array())
+        .map(|a| a.as_slice())
+        .unwrap_or_default();
+    for item in items {
+        if item.get("type").and_then(|v| v.as_str()) != Some("tool_use") {
+            continue;
+        }
+        let (Some(name), Some(input)) =
+            (item.get("name").and_then(|v| v.as_str()), item.get("input"))
+        else {
+            continue;
+        };
+        for change in extract_tool_file_changes(name, input) {
+            if make_relative_path(&change.file_path, project_path) == file_path {
+                changes.push((change.old_string, change.new_string));
+            }
+        }
+    }
+
+    Ok((timestamp, changes))
+}
+
+/// Lines of an edit's new_string that weren't already in its old_string.
+fn added_lines(edit: &BlameEdit) -> Vec<&str> {
+    let mut old_counts: HashMap<&str, usize> = HashMap::new();
+    for line in edit.old_string.lines() {
+        *old_counts.entry(line).or_insert(0) += 1;
+    }
+
+    edit.new_string
+        .lines()
+        .filter(|line| match old_counts.get_mut(line) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+/// Whether a line is specific enough to match on its own (not blank or just punctuation).
+fn is_distinctive(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.len() >= 4 && trimmed.chars().any(|c| c.is_alphanumeric())
+}
+
+/// Start positions where `block` occurs in `lines`.
+///
+/// The first and last block lines may be partial (edits don't have to start or end on
+/// line boundaries), so they match as a suffix and prefix. A single-line block must be
+/// a whole line.
+fn find_blocks(lines: &[&str], block: &[&str]) -> Vec<usize> {
+    if block.is_empty() || block.len() > lines.len() {
+        return Vec::new();
+    }
+    if block.len() == 1 {
+        let target = block[0].trim_end();
+        if !is_distinctive(target) {
+            return Vec::new();
+        }
+        return (0..lines.len())
+            .filter(|&i| lines[i].trim_end() == target)
+            .collect();
+    }
+
+    let last = block.len() - 1;
+    (0..=lines.len() - block.len())
+        .filter(|&start| {
+            lines[start].ends_with(block[0])
+                && lines[start + last].starts_with(block[last])
+                && (1..last).all(|i| lines[start + i] == block[i])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::builder::build_session_index;
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn write_session(name: &str, edits: &[(&str, &str, &str)]) -> (TempDir, PathBuf) {
+        let dir = TempDir::new(&format!("blame-{}", name));
+
+        let mut lines = Vec::new();
+        for (i, (prompt, old_string, new_string)) in edits.iter().enumerate() {
+            lines.push(
+                json!({
+                    "type": "user", "userType": "external", "uuid": format!("u{}", i),
+                    "timestamp": format!("2025-10-04T08:00:{:02}.000Z", i * 2),
+                    "message": {"content": prompt}
+                })
+                .to_string(),
+            );
+            lines.push(
+                json!({
+                    "type": "assistant", "uuid": format!("a{}", i), "parentUuid": format!("u{}", i),
+                    "timestamp": format!("2025-10-04T08:00:{:02}.000Z", i * 2 + 1),
+                    "message": {"content": [{"type": "tool_use", "name": "Edit", "input": {
+                        "file_path": "/proj/src/app.py",
+                        "old_string": old_string,
+                        "new_string": new_string
+                    }}]}
+                })
+                .to_string(),
+            );
+        }
+
+        let session_file = dir.join("session.jsonl");
+        fs::write(&session_file, lines.join("\n") + "\n").unwrap();
+        (dir, session_file)
+    }
+
+    #[test]
+    fn test_blame_attributes_latest_edit() {
+        let (_dir, session_file) = write_session(
+            "latest",
+            &[
+                (
+                    "add greeting",
+                    "def main():\n    pass",
+                    "def main():\n    greet()\n    pass",
+                ),
+                ("rename", "    greet()", "    greet_user()"),
+            ],
+        );
+        let index = build_session_index(&session_file, "/proj").unwrap();
+        let sources = [BlameSource {
+            session_id: "s1",
+            index: &index,
+            session_file: &session_file,
+        }];
+
+        let current = "import os\n\ndef main():\n    greet_user()\n    pass\n";
+        let blame = blame_file(current, "src/app.py", "/proj", &sources).unwrap();
+
+        assert_eq!(blame.lines.len(), 5);
+        assert_eq!(blame.attributed_count, 1);
+        let line = blame.lines[3].as_ref().unwrap();
+        assert_eq!(line.session_id, "s1");
+        assert_eq!(line.sequence, 3);
+        assert_eq!(line.edit_index, 1);
+        assert_eq!(line.trigger_line, Some(2));
+        assert_eq!(line.confidence, EditConfidence::High);
+        assert!(blame.lines[2].is_none());
+    }
+
+    #[test]
+    fn test_blame_falls_back_to_line_matches() {
+        let (_dir, session_file) = write_session(
+            "fallback",
+            &[("add config", "", "TIMEOUT = 30\nRETRIES = 3\n}\n")],
+        );
+        let index = build_session_index(&session_file, "/proj").unwrap();
+        let sources = [BlameSource {
+            session_id: "s1",
+            index: &index,
+            session_file: &session_file,
+        }];
+
+        // The block was later split by an out-of-session change
+        let current = "TIMEOUT = 30\nDEBUG = True\nRETRIES = 3\n}\n";
+        let blame = blame_file(current, "src/app.py", "/proj", &sources).unwrap();
+
+        let confidences: Vec<Option<EditConfidence>> = blame
+            .lines
+            .iter()
+            .map(|l| l.as_ref().map(|l| l.confidence))
+            .collect();
+        assert_eq!(
+            confidences,
+            vec![
+                Some(EditConfidence::Low),
+                None,
+                Some(EditConfidence::Low),
+                None
+            ]
+        );
+        assert_eq!(blame.lines[0].as_ref().unwrap().trigger_line, Some(0));
+    }
+}
@@ -4,12 +4,11 @@
 
 use serde::Deserialize;
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 use std::path::Path;
 
-use crate::claude_code::{FileEdit, FileEditType};
+use crate::claude_code::FileEditTracker;
 
 use super::types::{EditMetadata, SessionIndex};
 
@@ -38,9 +37,7 @@ pub fn build_session_index(session_file: &Path, project_path: &str) -> Result<Se
         .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
 
     // Track file edits (need to determine added vs modified)
-    let mut file_operations: HashMap<String, FileEditType> = HashMap::new();
-    let mut files_with_prior_content: HashSet<String> = HashSet::new();
-    let mut file_timestamps: HashMap<String, String> = HashMap::new();
+    let mut tracker = FileEditTracker::new();
 
     let mut byte_offset: u64 = 0;
 
@@ -86,10 +83,9 @@ pub fn build_session_index(session_file: &Path, project_path: &str) -> Result<Se
                                     entry.uuid.as_deref(),
                                     entry.parent_uuid.as_deref(),
                                     entry.timestamp.as_deref(),
+                                    entry.cwd.as_deref(),
                                     &mut index,
-                                    &mut file_operations,
-                                    &mut files_with_prior_content,
-                                    &mut file_timestamps,
+                                    &mut tracker,
                                 );
                             }
                         }
@@ -102,12 +98,8 @@ pub fn build_session_index(session_file: &Path, project_path: &str) -> Result<Se
     }
 
     // Build final file edits list
-    finalize_file_edits(
-        &mut index,
-        file_operations,
-        files_with_prior_content,
-        file_timestamps,
-    );
+    index.known_dirs = tracker.take_known_dirs();
+    index.file_edits = tracker.finish();
 
     // Sort human message lines for binary search
     index.human_message_lines.sort();
@@ -163,10 +155,9 @@ fn process_tool_use(
     uuid: Option<&str>,
     _parent_uuid: Option<&str>,
     timestamp: Option<&str>,
+    cwd: Option<&str>,
     index: &mut SessionIndex,
-    file_operations: &mut HashMap<String, FileEditType>,
-    files_with_prior_content: &mut HashSet<String>,
-    file_timestamps: &mut HashMap<String, String>,
+    tracker: &mut FileEditTracker,
 ) {
     // Check if this is a tool_use
     if item.get("type").and_then(|v| v.as_str()) != Some("tool_use") {
@@ -183,113 +174,21 @@ fn process_tool_use(
         None => return,
     };
 
-    match tool_name {
-        "Edit" => {
-            if let Some(file_path) = input.get("file_path").and_then(|v| v.as_str()) {
-                let rel_path = make_relative_path(file_path, project_path);
-
-                // Check if this edit has old_string content (indicates existing file)
-                if let Some(old_str) = input.get("old_string").and_then(|v| v.as_str()) {
-                    if !old_str.is_empty() {
-                        files_with_prior_content.insert(rel_path.clone());
-                    }
-                }
-
-                // Mark as modified
-                file_operations.insert(rel_path.clone(), FileEditType::Modified);
-
-                // Track timestamp
-                if let Some(ts) = timestamp {
-                    file_timestamps.insert(rel_path.clone(), ts.to_string());
-                }
-
-                // Record edit metadata
-                index.edit_metadata.insert(
-                    sequence,
-                    EditMetadata {
-                        uuid: uuid.map(String::from),
-                    },
-                );
-
-                // Track line for this file
-                index
-                    .file_to_edit_lines
-                    .entry(rel_path)
-                    .or_default()
-                    .push(sequence);
-            }
-        }
-        "Write" => {
-            if let Some(file_path) = input.get("file_path").and_then(|v| v.as_str()) {
-                let rel_path = make_relative_path(file_path, project_path);
-
-                // Write to a file that wasn't previously edited = added
-                if !file_operations.contains_key(&rel_path) {
-                    file_operations.insert(rel_path.clone(), FileEditType::Added);
-                }
-
-                // Track timestamp
-                if let Some(ts) = timestamp {
-                    file_timestamps.insert(rel_path.clone(), ts.to_string());
-                }
-
-                // Record edit metadata
-                index.edit_metadata.insert(
-                    sequence,
-                    EditMetadata {
-                        uuid: uuid.map(String::from),
-                    },
-                );
-
-                // Track line for this file
-                index
-                    .file_to_edit_lines
-                    .entry(rel_path)
-                    .or_default()
-                    .push(sequence);
-            }
-        }
-        _ => {}
-    }
-}
-
-/// Finalize file edits list, determining added vs modified.
-fn finalize_file_edits(
-    index: &mut SessionIndex,
-    file_operations: HashMap<String, FileEditType>,
-    files_with_prior_content: HashSet<String>,
-    file_timestamps: HashMap<String, String>,
-) {
-    let mut edits: Vec<FileEdit> = file_operations
-        .into_iter()
-        .map(|(path, mut edit_type)| {
-            // If a file was written but never had prior content, it's "added"
-            if edit_type == FileEditType::Modified && !files_with_prior_content.contains(&path) {
-                edit_type = FileEditType::Added;
-            }
-            let last_edited_at = file_timestamps.get(&path).cloned();
-            FileEdit {
-                path,
-                edit_type,
-                last_edited_at,
-            }
-        })
-        .collect();
-
-    // Sort by path for consistent display
-    edits.sort_by(|a, b| a.path.cmp(&b.path));
-    index.file_edits = edits;
-}
-
-/// Convert an absolute file path to a relative path from the project root.
-fn make_relative_path(file_path: &str, project_path: &str) -> String {
-    let project = project_path.trim_end_matches('/');
-    if file_path.starts_with(project) {
-        file_path[project.len()..]
-            .trim_start_matches('/')
-            .to_string()
-    } else {
-        file_path.to_string()
+    for rel_path in tracker.record_tool_use(tool_name, input, project_path, cwd, timestamp) {
+        // Record edit metadata
+        index.edit_metadata.insert(
+            sequence,
+            EditMetadata {
+                uuid: uuid.map(String::from),
+            },
+        );
+
+        // Track line for this file (once per change, so positions line up with get_file_diffs)
+        index
+            .file_to_edit_lines
+            .entry(rel_path)
+            .or_default()
+            .push(sequence);
     }
 }
 
@@ -310,6 +209,7 @@ struct JsonEntry {
     is_meta: Option<bool>,
     message: Option<JsonMessage>,
     timestamp: Option<String>,
+    cwd: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -0,0 +1,266 @@
+//! Persistent on-disk cache for session indices.
+//!
+//! Indices are serialized to `<cache_dir>/<hash of session path>.json` so reopening a
+//! session does not rescan the whole JSONL. A cached index is reused only when the
+//! session file still starts with the bytes it was built from; anything appended since
+//! is picked up by `update_index_incremental` starting at the cached `file_size`.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::builder::build_session_index;
+use super::types::SessionIndex;
+use super::updater::{update_index_incremental, UpdateResult};
+
+/// Bump when the serialized SessionIndex layout changes.
+pub const INDEX_CACHE_VERSION: u32 = 3;
+
+/// On-disk cache file contents.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexCacheFile {
+    /// Cache format version (must equal INDEX_CACHE_VERSION)
+    version: u32,
+    /// Session file the index was built from
+    session_file: PathBuf,
+    /// Project path used to relativize edited file paths
+    project_path: String,
+    /// Hash of the first and last indexed lines (detects rewritten files)
+    prefix_fingerprint: u64,
+    /// The cached index (includes file_size and last_modified)
+    index: SessionIndex,
+}
+
+/// Load a session index from the cache and bring it up to date, or build it from scratch.
+///
+/// The cache is rewritten whenever the index had to be built or updated.
+pub fn open_session_index(
+    cache_dir: Option<&Path>,
+    session_file: &Path,
+    project_path: &str,
+) -> Result<SessionIndex, String> {
+    let cached = cache_dir.and_then(|dir| load_index_cache(dir, session_file, project_path));
+
+    let (index, changed) = match cached {
+        Some(mut index) => {
+            let result = update_index_incremental(&mut index, session_file, project_path)?;
+            (index, !matches!(result, UpdateResult::Unchanged))
+        }
+        None => (build_session_index(session_file, project_path)?, true),
+    };
+
+    if changed {
+        if let Some(dir) = cache_dir {
+            if let Err(e) = save_index_cache(dir, session_file, project_path, &index) {
+                eprintln!("[session_index] Failed to write index cache: {}", e);
+            }
+        }
+    }
+
+    Ok(index)
+}
+
+/// Load a cached index if it is still valid for the session file.
+///
+/// Returns None if the cache is missing, from another version or project, or if the
+/// session file was truncated or rewritten since the index was cached.
+pub fn load_index_cache(
+    cache_dir: &Path,
+    session_file: &Path,
+    project_path: &str,
+) -> Option<SessionIndex> {
+    let content = fs::read(cache_file_path(cache_dir, session_file)).ok()?;
+    let cache: IndexCacheFile = serde_json::from_slice(&content).ok()?;
+
+    if cache.version != INDEX_CACHE_VERSION
+        || cache.session_file != session_file
+        || cache.project_path != project_path
+    {
+        return None;
+    }
+
+    // The file must not have shrunk and must still contain the indexed lines
+    let current_size = fs::metadata(session_file).ok()?.len();
+    if current_size < cache.index.file_size {
+        return None;
+    }
+    let fingerprint = prefix_fingerprint(
+        session_file,
+        cache.index.file_size,
+        &cache.index.line_offsets,
+    )?;
+    if fingerprint != cache.prefix_fingerprint {
+        return None;
+    }
+
+    Some(cache.index)
+}
+
+/// Write an index to the cache (atomically, via a temp file and rename).
+pub fn save_index_cache(
+    cache_dir: &Path,
+    session_file: &Path,
+    project_path: &str,
+    index: &SessionIndex,
+) -> Result<(), String> {
+    let prefix_fingerprint = prefix_fingerprint(session_file, index.file_size, &index.line_offsets)
+        .ok_or_else(|| "Failed to fingerprint session file".to_string())?;
+
+    let cache = IndexCacheFile {
+        version: INDEX_CACHE_VERSION,
+        session_file: session_file.to_path_buf(),
+        project_path: project_path.to_string(),
+        prefix_fingerprint,
+        index: index.clone(),
+    };
+
+    fs::create_dir_all(cache_dir)
+        .map_err(|e| format!("Failed to create index cache dir: {}", e))?;
+
+    let content =
+        serde_json::to_vec(&cache).map_err(|e| format!("Failed to serialize index: {}", e))?;
+
+    let cache_file = cache_file_path(cache_dir, session_file);
+    let tmp_file = temp_file_path(&cache_file);
+    fs::write(&tmp_file, content).map_err(|e| format!("Failed to write index cache: {}", e))?;
+    fs::rename(&tmp_file, &cache_file).map_err(|e| format!("Failed to write index cache: {}", e))
+}
+
+/// Get the cache file path for a session file.
+pub fn cache_file_path(cache_dir: &Path, session_file: &Path) -> PathBuf {
+    let hash = fnv1a(session_file.to_string_lossy().as_bytes(), FNV_OFFSET_BASIS);
+    cache_dir.join(format!("{:016x}.json", hash))
+}
+
+/// A temp file next to `path`, unique to one write of it.
+///
+/// The same cache file can be written by several threads at once (e.g. an evicted index
+/// persisted in the background while it is reopened); each writes its own temp file and
+/// renames it into place, so the cache always holds one complete write.
+pub fn temp_file_path(path: &Path) -> PathBuf {
+    static NEXT_WRITE: AtomicU64 = AtomicU64::new(0);
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        NEXT_WRITE.fetch_add(1, Ordering::Relaxed)
+    ));
+    PathBuf::from(name)
+}
+
+/// Hash the first and last indexed lines of the session file, given the
+/// `(byte_offset, line_length)` of every line indexed from its first `file_size` bytes.
+pub fn prefix_fingerprint(
+    session_file: &Path,
+    file_size: u64,
+    line_offsets: &[(u64, usize)],
+) -> Option<u64> {
+    let mut file = File::open(session_file).ok()?;
+    let mut hash = FNV_OFFSET_BASIS;
+
+    let first = line_offsets.first();
+    let last = line_offsets.last();
+    for &(offset, length) in first.into_iter().chain(last) {
+        // The final line may not end with a newline, so its recorded length can overshoot
+        let available = file_size.saturating_sub(offset).min(length as u64);
+        let mut buffer = Vec::with_capacity(available as usize);
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        (&mut file).take(available).read_to_end(&mut buffer).ok()?;
+        hash = fnv1a(&buffer, hash);
+    }
+
+    Some(hash)
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a hash (stable across builds, unlike std's DefaultHasher).
+fn fnv1a(bytes: &[u8], seed: u64) -> u64 {
+    bytes.iter().fold(seed, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::io::Write;
+
+    const EDIT_LINE: &str = r#"{"type":"assistant","uuid":"u2","parentUuid":"u1","message":{"content":[{"type":"tool_use","name":"Edit","input":{"file_path":"/proj/src/main.rs","old_string":"a","new_string":"b"}}]}}"#;
+    const HUMAN_LINE: &str =
+        r#"{"type":"user","userType":"external","uuid":"u1","message":{"content":"fix it"}}"#;
+
+    /// Create a fixture directory containing a session file and an empty cache dir.
+    fn fixture(name: &str) -> (TempDir, PathBuf, PathBuf) {
+        let dir = TempDir::new(&format!("index-cache-{}", name));
+        let session_file = dir.join("session.jsonl");
+        fs::write(&session_file, format!("{}\n{}\n", HUMAN_LINE, EDIT_LINE)).unwrap();
+        let cache_dir = dir.join("cache");
+        (dir, session_file, cache_dir)
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let (_dir, session_file, cache_dir) = fixture("roundtrip");
+        let built = open_session_index(Some(&cache_dir), &session_file, "/proj").unwrap();
+
+        let cached = load_index_cache(&cache_dir, &session_file, "/proj").unwrap();
+        assert_eq!(cached.file_size, built.file_size);
+        assert_eq!(cached.last_modified, built.last_modified);
+        assert_eq!(cached.line_offsets, built.line_offsets);
+        assert_eq!(cached.human_message_lines, vec![0]);
+        assert_eq!(cached.line_for_uuid("u2"), Some(1));
+        assert_eq!(cached.parent_of("u2").map(String::as_str), Some("u1"));
+        assert_eq!(cached.file_to_edit_lines.get("src/main.rs"), Some(&vec![1]));
+        assert!(cached.edit_metadata.contains_key(&1));
+
+        // A different project path invalidates the cache
+        assert!(load_index_cache(&cache_dir, &session_file, "/other").is_none());
+    }
+
+    #[test]
+    fn test_cache_picks_up_appended_lines() {
+        let (_dir, session_file, cache_dir) = fixture("append");
+        open_session_index(Some(&cache_dir), &session_file, "/proj").unwrap();
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&session_file)
+            .unwrap();
+        writeln!(file, "{}", EDIT_LINE.replace("u2", "u3")).unwrap();
+
+        let index = open_session_index(Some(&cache_dir), &session_file, "/proj").unwrap();
+        assert_eq!(index.total_events(), 3);
+        assert_eq!(
+            index.file_to_edit_lines.get("src/main.rs"),
+            Some(&vec![1, 2])
+        );
+
+        // The refreshed index was written back
+        let cached = load_index_cache(&cache_dir, &session_file, "/proj").unwrap();
+        assert_eq!(cached.total_events(), 3);
+    }
+
+    #[test]
+    fn test_cache_rejected_when_file_rewritten() {
+        let (_dir, session_file, cache_dir) = fixture("rewritten");
+        open_session_index(Some(&cache_dir), &session_file, "/proj").unwrap();
+
+        // Same length, different first line
+        fs::write(
+            &session_file,
+            format!("{}\n{}\n", HUMAN_LINE.replace("fix", "fox"), EDIT_LINE),
+        )
+        .unwrap();
+        assert!(load_index_cache(&cache_dir, &session_file, "/proj").is_none());
+
+        // Truncated file
+        fs::write(&session_file, format!("{}\n", HUMAN_LINE)).unwrap();
+        assert!(load_index_cache(&cache_dir, &session_file, "/proj").is_none());
+    }
+}
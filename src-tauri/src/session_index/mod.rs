@@ -12,11 +12,22 @@
 //! - O(k) parent chain walking (for edit context)
 //! - Pre-computed line offsets for fast pagination
 //!
+//! A `ProjectEditIndex` aggregates the file edits of every session in a project, giving
+//! the edit history of a file across sessions.
+//!
+//! A `TermIndex` maps the terms of a session (or sub-agent) file to the lines they
+//! occur on, so searches only read candidate lines. It is persisted and updated
+//! incrementally like the session index.
+//!
+//! Loaded session and term indices live in an `IndexRegistry` that evicts the least
+//! recently used ones when their estimated memory exceeds the configured budget (one
+//! for session indices, one for term indices).
+//!
 //! ## Usage
 //!
 //! ```ignore
-//! // Build index for a session
-//! let index = build_session_index(&session_file, &project_path)?;
+//! // Build index for a session (reusing the on-disk cache when it is still valid)
+//! let index = open_session_index(Some(&cache_dir), &session_file, &project_path)?;
 //!
 //! // Get status for frontend
 //! let status = index.to_status();
@@ -30,13 +41,25 @@
 //! let context = get_edit_context(&index, &session_file, edit_line)?;
 //! ```
 
+mod blame;
 mod builder;
+mod cache;
+mod project;
 mod queries;
+mod registry;
+mod terms;
 mod types;
 mod updater;
 
 // Re-export public API
-pub use builder::build_session_index;
+pub use blame::{blame_file, BlameSource, FileBlame};
+pub use cache::{load_index_cache, open_session_index, save_index_cache};
+pub use project::{FileHistoryEntry, ProjectEditIndex};
 pub use queries::{get_edit_context, EditContext};
+pub use registry::{
+    persist_evicted_indices, IndexCacheStats, IndexRegistry, DEFAULT_INDEX_MEMORY_BUDGET,
+    DEFAULT_TERM_INDEX_MEMORY_BUDGET,
+};
+pub use terms::{find_fuzzy, tokenize, TermIndex, TermIndexStore};
 pub use types::{IndexStatus, SessionIndex};
 pub use updater::{update_index_incremental, UpdateResult};
@@ -0,0 +1,410 @@
+//! Project-level edit index.
+//!
+//! Aggregates the `file_to_edit_lines` of every session in a project so that the full
+//! edit history of a single file can be listed across sessions. Entries for a session are
+//! rebuilt whenever its index changes: the watcher pushes updates for watched sessions,
+//! and other sessions are reopened when their file size or mtime no longer matches.
+//!
+//! Reopening sessions can mean building their indices, so a refresh is split in three:
+//! [`ProjectEditIndex::begin_refresh`] collects the changed sessions, [`PendingRefresh::run`]
+//! indexes them without the project index borrowed (and so without its lock held), and
+//! [`ProjectEditIndex::finish_refresh`] swaps the results in.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::claude_code::{get_session_files, parse_session_event};
+
+use super::cache::open_session_index;
+use super::types::SessionIndex;
+
+/// One edit to a file, as listed in its cross-session history.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileHistoryEntry {
+    /// Session that made the edit
+    pub session_id: String,
+    /// Line (sequence number) of the edit event in the session file
+    pub sequence: u32,
+    /// Position of the edit among the file's edits in its session (matches FileDiff.sequence)
+    pub edit_index: u32,
+    /// Timestamp of the edit (ISO 8601)
+    pub timestamp: Option<String>,
+    /// Line of the human message that triggered the edit
+    pub trigger_line: Option<u32>,
+    /// Preview of the triggering human message
+    pub prompt: Option<String>,
+}
+
+/// Edit history entries of every session in a project.
+#[derive(Debug, Default)]
+pub struct ProjectEditIndex {
+    /// session_id → entries built from that session's index
+    sessions: HashMap<String, SessionEdits>,
+}
+
+/// A session's entries and the file state they were built from.
+#[derive(Debug, Clone)]
+struct SessionEdits {
+    file_size: u64,
+    last_modified: SystemTime,
+    /// file_path → edits to that file, in session order
+    files: HashMap<String, Vec<FileHistoryEntry>>,
+}
+
+impl ProjectEditIndex {
+    /// Whether a session's entries were built from an index of its current file state.
+    pub fn is_current(&self, session_id: &str, index: &SessionIndex) -> bool {
+        self.sessions.get(session_id).is_some_and(|edits| {
+            edits.file_size == index.file_size && edits.last_modified == index.last_modified
+        })
+    }
+
+    /// Rebuild a session's entries from its index.
+    /// Entries for edit lines already known are reused, so only new edit events are read.
+    pub fn update_session(
+        &mut self,
+        session_id: &str,
+        session_file: &Path,
+        index: &SessionIndex,
+    ) -> Result<(), String> {
+        let edits = SessionEdits::build(
+            session_id,
+            session_file,
+            index,
+            self.sessions.get(session_id),
+        )?;
+        self.sessions.insert(session_id.to_string(), edits);
+        Ok(())
+    }
+
+    /// Start bringing the index up to date with the project's session files: drop
+    /// sessions whose file was removed and collect the changed ones. Only stats the
+    /// session files; indexing happens in [`PendingRefresh::run`].
+    pub fn begin_refresh(&mut self, project_path: &str) -> PendingRefresh {
+        let session_files = get_session_files(project_path);
+        let present: HashSet<&str> = session_files.iter().map(|(id, _)| id.as_str()).collect();
+        self.sessions.retain(|id, _| present.contains(id.as_str()));
+
+        let sessions = session_files
+            .into_iter()
+            .filter(|(session_id, session_file)| self.is_stale(session_id, session_file))
+            .map(|(session_id, session_file)| {
+                let previous = self.sessions.get(&session_id).cloned();
+                (session_id, session_file, previous)
+            })
+            .collect();
+
+        PendingRefresh {
+            project_path: project_path.to_string(),
+            sessions,
+        }
+    }
+
+    /// Swap in refreshed sessions. Entries built meanwhile (e.g. by the watcher) from a
+    /// newer state of the session file are kept.
+    pub fn finish_refresh(&mut self, refreshed: RefreshedSessions) {
+        for (session_id, edits) in refreshed.0 {
+            let is_newer = self
+                .sessions
+                .get(&session_id)
+                .is_some_and(|current| current.file_size > edits.file_size);
+            if !is_newer {
+                self.sessions.insert(session_id, edits);
+            }
+        }
+    }
+
+    /// Every edit made to a file across all sessions, oldest first.
+    pub fn file_history(&self, file_path: &str) -> Vec<FileHistoryEntry> {
+        let mut history: Vec<FileHistoryEntry> = self
+            .sessions
+            .values()
+            .filter_map(|edits| edits.files.get(file_path))
+            .flatten()
+            .cloned()
+            .collect();
+
+        history.sort_by(|a, b| {
+            a.timestamp
+                .cmp(&b.timestamp)
+                .then_with(|| a.session_id.cmp(&b.session_id))
+                .then_with(|| a.edit_index.cmp(&b.edit_index))
+        });
+        history
+    }
+
+    /// Sessions that edited each file at or after `since` (ISO 8601), most recent first.
+    pub fn recent_editors(&self, since: &str) -> HashMap<String, Vec<String>> {
+        // file_path → (latest edit timestamp, session_id)
+        let mut latest: HashMap<&str, Vec<(&str, &str)>> = HashMap::new();
+        for (session_id, edits) in &self.sessions {
+            for (file_path, entries) in &edits.files {
+                let last_edit = entries
+                    .iter()
+                    .filter_map(|e| e.timestamp.as_deref())
+                    .filter(|t| *t >= since)
+                    .max();
+                if let Some(timestamp) = last_edit {
+                    latest
+                        .entry(file_path)
+                        .or_default()
+                        .push((timestamp, session_id));
+                }
+            }
+        }
+
+        latest
+            .into_iter()
+            .map(|(file_path, mut sessions)| {
+                sessions.sort_by(|a, b| b.cmp(a));
+                let ids = sessions.into_iter().map(|(_, id)| id.to_string()).collect();
+                (file_path.to_string(), ids)
+            })
+            .collect()
+    }
+
+    /// Whether a session's file changed since its entries were built (or has none yet).
+    fn is_stale(&self, session_id: &str, session_file: &Path) -> bool {
+        let Some(edits) = self.sessions.get(session_id) else {
+            return true;
+        };
+        match fs::metadata(session_file) {
+            Ok(metadata) => {
+                metadata.len() != edits.file_size
+                    || metadata.modified().ok() != Some(edits.last_modified)
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+/// Changed sessions of a project, waiting to be indexed.
+pub struct PendingRefresh {
+    project_path: String,
+    /// (session_id, session_file, entries built from an earlier file state)
+    sessions: Vec<(String, PathBuf, Option<SessionEdits>)>,
+}
+
+/// Entries of refreshed sessions, ready for [`ProjectEditIndex::finish_refresh`].
+pub struct RefreshedSessions(Vec<(String, SessionEdits)>);
+
+impl PendingRefresh {
+    /// Index the changed sessions. `loaded_index` returns an already loaded index for a
+    /// session (e.g. a watched one); others are opened through the disk cache.
+    pub fn run(
+        self,
+        cache_dir: Option<&Path>,
+        loaded_index: impl Fn(&str) -> Option<SessionIndex>,
+    ) -> RefreshedSessions {
+        let mut refreshed = Vec::with_capacity(self.sessions.len());
+
+        for (session_id, session_file, previous) in self.sessions {
+            let index = match loaded_index(&session_id) {
+                Some(index) => Ok(index),
+                None => open_session_index(cache_dir, &session_file, &self.project_path),
+            };
+            let result = index.and_then(|index| {
+                SessionEdits::build(&session_id, &session_file, &index, previous.as_ref())
+            });
+            match result {
+                Ok(edits) => refreshed.push((session_id, edits)),
+                Err(e) => eprintln!(
+                    "[session_index] Failed to index edits of session {}: {}",
+                    session_id, e
+                ),
+            }
+        }
+
+        RefreshedSessions(refreshed)
+    }
+}
+
+impl SessionEdits {
+    /// Build a session's entries from its index, reusing `previous` entries for edit
+    /// lines already known so only new edit events are read.
+    fn build(
+        session_id: &str,
+        session_file: &Path,
+        index: &SessionIndex,
+        previous: Option<&SessionEdits>,
+    ) -> Result<Self, String> {
+        let known: HashMap<u32, &FileHistoryEntry> = previous
+            .iter()
+            .flat_map(|edits| edits.files.values().flatten())
+            .map(|entry| (entry.sequence, entry))
+            .collect();
+
+        let mut file =
+            File::open(session_file).map_err(|e| format!("Failed to open session file: {}", e))?;
+        let mut files = HashMap::with_capacity(index.file_to_edit_lines.len());
+
+        for (file_path, edit_lines) in &index.file_to_edit_lines {
+            let mut entries = Vec::with_capacity(edit_lines.len());
+            for (edit_index, &sequence) in edit_lines.iter().enumerate() {
+                let entry = match known.get(&sequence) {
+                    Some(entry) => FileHistoryEntry {
+                        edit_index: edit_index as u32,
+                        ..(*entry).clone()
+                    },
+                    None => {
+                        let trigger_line = index.find_human_boundary(sequence);
+                        let prompt = match trigger_line {
+                            Some(line) => read_line_at(&mut file, index, line)?
+                                .and_then(|json| parse_session_event(&json, line, 0))
+                                .map(|event| event.preview),
+                            None => None,
+                        };
+                        let timestamp = read_line_at(&mut file, index, sequence)?
+                            .and_then(|json| parse_session_event(&json, sequence, 0))
+                            .and_then(|event| event.timestamp);
+                        FileHistoryEntry {
+                            session_id: session_id.to_string(),
+                            sequence,
+                            edit_index: edit_index as u32,
+                            timestamp,
+                            trigger_line,
+                            prompt,
+                        }
+                    }
+                };
+                entries.push(entry);
+            }
+            files.insert(file_path.clone(), entries);
+        }
+
+        Ok(SessionEdits {
+            file_size: index.file_size,
+            last_modified: index.last_modified,
+            files,
+        })
+    }
+}
+
+/// Read the raw JSON of an indexed line.
+fn read_line_at(
+    file: &mut File,
+    index: &SessionIndex,
+    line: u32,
+) -> Result<Option<String>, String> {
+    let Some(&(offset, _)) = index.line_offsets.get(line as usize) else {
+        return Ok(None);
+    };
+
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| format!("Failed to seek: {}", e))?;
+    let mut json = String::new();
+    BufReader::new(&*file)
+        .read_line(&mut json)
+        .map_err(|e| format!("Failed to read line: {}", e))?;
+    Ok(Some(json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::builder::build_session_index;
+    use super::super::updater::update_index_incremental;
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+    use std::io::Write;
+
+    /// A prompt followed by an Edit of `file_path`, at the given second of the minute.
+    fn edit_turn(prefix: &str, second: u32, prompt: &str, file_path: &str) -> String {
+        let user = json!({
+            "type": "user", "userType": "external", "uuid": format!("{}-u{}", prefix, second),
+            "timestamp": format!("2025-10-05T09:00:{:02}.000Z", second),
+            "message": {"content": prompt}
+        });
+        let assistant = json!({
+            "type": "assistant", "uuid": format!("{}-a{}", prefix, second),
+            "parentUuid": format!("{}-u{}", prefix, second),
+            "timestamp": format!("2025-10-05T09:00:{:02}.500Z", second),
+            "message": {"content": [{"type": "tool_use", "name": "Edit", "input": {
+                "file_path": file_path, "old_string": "a", "new_string": "b"
+            }}]}
+        });
+        format!("{}\n{}\n", user, assistant)
+    }
+
+    #[test]
+    fn test_file_history_across_sessions() {
+        let dir = TempDir::new("project-history");
+        let first = dir.join("first.jsonl");
+        let second = dir.join("second.jsonl");
+        fs::write(
+            &first,
+            edit_turn("s1", 1, "fix the parser", "/proj/src/foo.rs")
+                + &edit_turn("s1", 20, "tidy up", "/proj/src/foo.rs"),
+        )
+        .unwrap();
+        fs::write(
+            &second,
+            edit_turn("s2", 10, "add logging", "/proj/src/foo.rs")
+                + &edit_turn("s2", 11, "update docs", "/proj/README.md"),
+        )
+        .unwrap();
+
+        let mut project = ProjectEditIndex::default();
+        for (id, file) in [("s1", &first), ("s2", &second)] {
+            let index = build_session_index(file, "/proj").unwrap();
+            project.update_session(id, file, &index).unwrap();
+            assert!(project.is_current(id, &index));
+        }
+
+        let history = project.file_history("src/foo.rs");
+        let summary: Vec<(&str, u32, Option<&str>)> = history
+            .iter()
+            .map(|e| (e.session_id.as_str(), e.edit_index, e.prompt.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("s1", 0, Some("fix the parser")),
+                ("s2", 0, Some("add logging")),
+                ("s1", 1, Some("tidy up")),
+            ]
+        );
+        assert_eq!(
+            history[1].timestamp.as_deref(),
+            Some("2025-10-05T09:00:10.500Z")
+        );
+        assert_eq!(history[1].sequence, 1);
+        assert_eq!(history[1].trigger_line, Some(0));
+        assert_eq!(project.file_history("README.md").len(), 1);
+
+        let editors = project.recent_editors("2025-10-05T09:00:05.000Z");
+        assert_eq!(editors["src/foo.rs"], vec!["s1", "s2"]);
+        assert_eq!(editors["README.md"], vec!["s2"]);
+        assert!(project
+            .recent_editors("2025-10-06T00:00:00.000Z")
+            .is_empty());
+
+        // An appended edit shows up once the session's index is updated
+        let stale_index = build_session_index(&first, "/proj").unwrap();
+        let mut index = stale_index.clone();
+        let mut file = fs::OpenOptions::new().append(true).open(&first).unwrap();
+        file.write_all(edit_turn("s1", 30, "one more", "/proj/src/foo.rs").as_bytes())
+            .unwrap();
+        update_index_incremental(&mut index, &first, "/proj").unwrap();
+        assert!(!project.is_current("s1", &index));
+        project.update_session("s1", &first, &index).unwrap();
+
+        let history = project.file_history("src/foo.rs");
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[3].prompt.as_deref(), Some("one more"));
+        assert_eq!(history[3].edit_index, 2);
+
+        // A refresh that indexed an older state of the file doesn't replace newer entries
+        let pending = PendingRefresh {
+            project_path: "/proj".to_string(),
+            sessions: vec![("s1".to_string(), first.clone(), None)],
+        };
+        project.finish_refresh(pending.run(None, |_| Some(stale_index.clone())));
+        assert_eq!(project.file_history("src/foo.rs").len(), 4);
+    }
+}
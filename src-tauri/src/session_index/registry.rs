@@ -0,0 +1,475 @@
+//! Memory-bounded registry of loaded session indices.
+//!
+//! Keeps indices keyed by "project_path:session_id", plus the search term indices of
+//! files, and evicts the least recently used ones when their estimated memory exceeds
+//! the configured budget. Term indices have a budget of their own, so a search touching
+//! many files never pushes out the indices of watched sessions.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::cache::save_index_cache;
+use super::terms::{save_term_index, TermIndex};
+use super::types::{IndexStatus, SessionIndex};
+
+/// Default memory budget for loaded session indices (512 MB).
+pub const DEFAULT_INDEX_MEMORY_BUDGET: usize = 512 * 1024 * 1024;
+
+/// Default memory budget for loaded search term indices (256 MB).
+pub const DEFAULT_TERM_INDEX_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+/// A loaded index with the bookkeeping needed for eviction.
+struct RegistryEntry {
+    index: SessionIndex,
+    project_path: String,
+    session_id: String,
+    session_file: PathBuf,
+    size_bytes: usize,
+    last_access: Instant,
+}
+
+/// A loaded search term index with the bookkeeping needed for eviction.
+struct TermEntry {
+    index: Arc<TermIndex>,
+    size_bytes: usize,
+    last_access: Instant,
+}
+
+/// An index removed from the registry to stay within the memory budget, with the
+/// cache directory to write it to (None if caching is disabled).
+pub enum EvictedIndex {
+    Session {
+        index: Box<SessionIndex>,
+        project_path: String,
+        session_file: PathBuf,
+        cache_dir: Option<PathBuf>,
+    },
+    Terms {
+        index: Arc<TermIndex>,
+        file: PathBuf,
+        cache_dir: Option<PathBuf>,
+    },
+}
+
+/// Memory statistics for a single loaded index.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexEntryStats {
+    pub project_path: String,
+    pub session_id: String,
+    /// Estimated heap size of the index
+    pub size_bytes: u64,
+    /// Number of events (lines) indexed
+    pub total_events: u32,
+    /// Milliseconds since the index was last used
+    pub idle_ms: u64,
+}
+
+/// Memory statistics for the whole registry, returned to frontend.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexCacheStats {
+    /// Configured memory budget of session indices
+    pub memory_budget_bytes: u64,
+    /// Configured memory budget of search term indices
+    pub term_memory_budget_bytes: u64,
+    /// Sum of the estimated sizes of all loaded indices
+    pub total_bytes: u64,
+    /// Part of `total_bytes` taken by search term indices
+    pub term_index_bytes: u64,
+    /// Per-index statistics, most recently used first
+    pub indices: Vec<IndexEntryStats>,
+}
+
+/// Registry of loaded session and term indices with LRU eviction.
+pub struct IndexRegistry {
+    entries: HashMap<String, RegistryEntry>,
+    term_entries: HashMap<PathBuf, TermEntry>,
+    memory_budget: usize,
+    term_memory_budget: usize,
+    /// Where evicted session indices are written
+    index_cache_dir: Option<PathBuf>,
+    /// Where evicted term indices are written
+    term_cache_dir: Option<PathBuf>,
+}
+
+impl IndexRegistry {
+    pub fn new(memory_budget: usize, term_memory_budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            term_entries: HashMap::new(),
+            memory_budget,
+            term_memory_budget,
+            index_cache_dir: None,
+            term_cache_dir: None,
+        }
+    }
+
+    /// Set the cache directories evicted indices are written to.
+    pub fn with_cache_dirs(
+        mut self,
+        index_cache_dir: Option<PathBuf>,
+        term_cache_dir: Option<PathBuf>,
+    ) -> Self {
+        self.index_cache_dir = index_cache_dir;
+        self.term_cache_dir = term_cache_dir;
+        self
+    }
+
+    /// Get a clone of an index, marking it as recently used.
+    pub fn get(&mut self, key: &str) -> Option<SessionIndex> {
+        let entry = self.entries.get_mut(key)?;
+        entry.last_access = Instant::now();
+        Some(entry.index.clone())
+    }
+
+    /// Borrow a loaded index without cloning it or marking it as recently used.
+    pub fn with_index<R>(&self, key: &str, f: impl FnOnce(&SessionIndex) -> R) -> Option<R> {
+        self.entries.get(key).map(|entry| f(&entry.index))
+    }
+
+    /// Get the status of a loaded index without marking it as recently used.
+    pub fn status(&self, key: &str) -> Option<IndexStatus> {
+        self.entries.get(key).map(|entry| entry.index.to_status())
+    }
+
+    /// Insert (or replace) an index and evict others if over budget.
+    ///
+    /// The inserted index itself is never evicted, even if it alone exceeds the budget.
+    pub fn insert(
+        &mut self,
+        key: String,
+        project_path: &str,
+        session_id: &str,
+        session_file: &Path,
+        index: SessionIndex,
+    ) -> Vec<EvictedIndex> {
+        let size_bytes = index.estimated_memory_bytes();
+        self.entries.insert(
+            key.clone(),
+            RegistryEntry {
+                index,
+                project_path: project_path.to_string(),
+                session_id: session_id.to_string(),
+                session_file: session_file.to_path_buf(),
+                size_bytes,
+                last_access: Instant::now(),
+            },
+        );
+        self.evict_sessions_over_budget(Some(&key))
+    }
+
+    /// Mutate an index in place (e.g. incremental update), then re-check the budget.
+    pub fn update<R>(
+        &mut self,
+        key: &str,
+        f: impl FnOnce(&mut SessionIndex) -> R,
+    ) -> Option<(R, Vec<EvictedIndex>)> {
+        let entry = self.entries.get_mut(key)?;
+        let result = f(&mut entry.index);
+        entry.size_bytes = entry.index.estimated_memory_bytes();
+        entry.last_access = Instant::now();
+        Some((result, self.evict_sessions_over_budget(Some(key))))
+    }
+
+    /// Remove an index, returning it.
+    pub fn remove(&mut self, key: &str) -> Option<SessionIndex> {
+        self.entries.remove(key).map(|entry| entry.index)
+    }
+
+    /// Get the term index of a file, shared with its other users, marking it as recently
+    /// used.
+    pub fn get_terms(&mut self, file: &Path) -> Option<Arc<TermIndex>> {
+        let entry = self.term_entries.get_mut(file)?;
+        entry.last_access = Instant::now();
+        Some(Arc::clone(&entry.index))
+    }
+
+    /// Insert (or replace) the term index of a file and evict others if over budget.
+    ///
+    /// The inserted index itself is never evicted, even if it alone exceeds the budget.
+    pub fn insert_terms(&mut self, file: &Path, index: Arc<TermIndex>) -> Vec<EvictedIndex> {
+        let size_bytes = index.estimated_memory_bytes();
+        self.term_entries.insert(
+            file.to_path_buf(),
+            TermEntry {
+                index,
+                size_bytes,
+                last_access: Instant::now(),
+            },
+        );
+        self.evict_terms_over_budget(Some(file))
+    }
+
+    /// Remove the term index of a file, returning it.
+    pub fn remove_terms(&mut self, file: &Path) -> Option<Arc<TermIndex>> {
+        self.term_entries.remove(file).map(|entry| entry.index)
+    }
+
+    /// Change the memory budget of session indices, evicting indices if the new budget
+    /// is smaller.
+    pub fn set_memory_budget(&mut self, memory_budget: usize) -> Vec<EvictedIndex> {
+        self.memory_budget = memory_budget;
+        self.evict_sessions_over_budget(None)
+    }
+
+    /// Total estimated size of all loaded indices.
+    pub fn total_bytes(&self) -> usize {
+        self.session_index_bytes() + self.term_index_bytes()
+    }
+
+    /// Total estimated size of the loaded session indices.
+    fn session_index_bytes(&self) -> usize {
+        self.entries.values().map(|e| e.size_bytes).sum()
+    }
+
+    /// Total estimated size of the loaded term indices.
+    fn term_index_bytes(&self) -> usize {
+        self.term_entries.values().map(|e| e.size_bytes).sum()
+    }
+
+    /// Get memory statistics for all loaded indices.
+    pub fn stats(&self) -> IndexCacheStats {
+        let mut entries: Vec<&RegistryEntry> = self.entries.values().collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_access));
+
+        IndexCacheStats {
+            memory_budget_bytes: self.memory_budget as u64,
+            term_memory_budget_bytes: self.term_memory_budget as u64,
+            total_bytes: self.total_bytes() as u64,
+            term_index_bytes: self.term_index_bytes() as u64,
+            indices: entries
+                .into_iter()
+                .map(|e| IndexEntryStats {
+                    project_path: e.project_path.clone(),
+                    session_id: e.session_id.clone(),
+                    size_bytes: e.size_bytes as u64,
+                    total_events: e.index.total_events(),
+                    idle_ms: e.last_access.elapsed().as_millis() as u64,
+                })
+                .collect(),
+        }
+    }
+
+    /// Evict least recently used session indices (other than `keep`) until within budget.
+    fn evict_sessions_over_budget(&mut self, keep: Option<&str>) -> Vec<EvictedIndex> {
+        let mut evicted = Vec::new();
+
+        while self.session_index_bytes() > self.memory_budget {
+            let lru_key = self
+                .entries
+                .iter()
+                .filter(|(key, _)| Some(key.as_str()) != keep)
+                .min_by_key(|(_, entry)| entry.last_access)
+                .map(|(key, _)| key.clone());
+
+            let Some(lru_key) = lru_key else {
+                break;
+            };
+
+            if let Some(entry) = self.entries.remove(&lru_key) {
+                println!(
+                    "[session_index] Evicted index for {} ({} bytes)",
+                    entry.session_id, entry.size_bytes
+                );
+                evicted.push(EvictedIndex::Session {
+                    index: Box::new(entry.index),
+                    project_path: entry.project_path,
+                    session_file: entry.session_file,
+                    cache_dir: self.index_cache_dir.clone(),
+                });
+            }
+        }
+
+        evicted
+    }
+
+    /// Evict least recently used term indices (other than `keep`) until within budget.
+    fn evict_terms_over_budget(&mut self, keep: Option<&Path>) -> Vec<EvictedIndex> {
+        let mut evicted = Vec::new();
+
+        while self.term_index_bytes() > self.term_memory_budget {
+            let lru_file = self
+                .term_entries
+                .iter()
+                .filter(|(file, _)| Some(file.as_path()) != keep)
+                .min_by_key(|(_, entry)| entry.last_access)
+                .map(|(file, _)| file.clone());
+
+            let Some(lru_file) = lru_file else {
+                break;
+            };
+
+            if let Some(entry) = self.term_entries.remove(&lru_file) {
+                println!(
+                    "[session_index] Evicted term index for {} ({} bytes)",
+                    lru_file.display(),
+                    entry.size_bytes
+                );
+                evicted.push(EvictedIndex::Terms {
+                    index: entry.index,
+                    file: lru_file,
+                    cache_dir: self.term_cache_dir.clone(),
+                });
+            }
+        }
+
+        evicted
+    }
+}
+
+/// Write evicted indices to their disk caches in the background so they can be reloaded.
+pub fn persist_evicted_indices(evicted: Vec<EvictedIndex>) {
+    if evicted.is_empty() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        for entry in evicted {
+            let result = match &entry {
+                EvictedIndex::Session {
+                    index,
+                    project_path,
+                    session_file,
+                    cache_dir: Some(cache_dir),
+                } => save_index_cache(cache_dir, session_file, project_path, index),
+                EvictedIndex::Terms {
+                    index,
+                    file,
+                    cache_dir: Some(cache_dir),
+                } => save_term_index(cache_dir, file, index),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                eprintln!("[session_index] Failed to write index cache: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build an index whose estimated size is dominated by `lines` line offsets.
+    fn index_with_lines(lines: usize) -> SessionIndex {
+        let mut index = SessionIndex::empty();
+        index.line_offsets = vec![(0, 0); lines];
+        index
+    }
+
+    fn insert(registry: &mut IndexRegistry, id: &str, lines: usize) -> Vec<EvictedIndex> {
+        registry.insert(
+            format!("/proj:{}", id),
+            "/proj",
+            id,
+            Path::new("/proj/session.jsonl"),
+            index_with_lines(lines),
+        )
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let one = index_with_lines(1000).estimated_memory_bytes();
+        let mut registry = IndexRegistry::new(one * 2 + one / 2, DEFAULT_TERM_INDEX_MEMORY_BUDGET);
+
+        assert!(insert(&mut registry, "a", 1000).is_empty());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert!(insert(&mut registry, "b", 1000).is_empty());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+
+        // Touch "a" so "b" becomes the least recently used
+        assert!(registry.get("/proj:a").is_some());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        // Reading a status or borrowing an index does not count as a use
+        assert_eq!(registry.status("/proj:b").unwrap().total_events, 1000);
+        assert_eq!(
+            registry.with_index("/proj:b", SessionIndex::total_events),
+            Some(1000)
+        );
+
+        let evicted = insert(&mut registry, "c", 1000);
+        assert_eq!(evicted.len(), 1);
+        assert!(registry.get("/proj:a").is_some());
+        assert!(registry.get("/proj:b").is_none());
+        assert!(registry.get("/proj:c").is_some());
+        assert!(registry.total_bytes() <= one * 2 + one / 2);
+    }
+
+    #[test]
+    fn test_never_evicts_inserted_index() {
+        let mut registry = IndexRegistry::new(16, DEFAULT_TERM_INDEX_MEMORY_BUDGET);
+        assert!(insert(&mut registry, "big", 1000).is_empty());
+        assert!(registry.get("/proj:big").is_some());
+
+        // A newer index pushes out the old one but stays itself
+        let evicted = insert(&mut registry, "bigger", 2000);
+        assert_eq!(evicted.len(), 1);
+        assert!(registry.get("/proj:bigger").is_some());
+    }
+
+    #[test]
+    fn test_update_recomputes_size_and_stats() {
+        let mut registry = IndexRegistry::new(
+            DEFAULT_INDEX_MEMORY_BUDGET,
+            DEFAULT_TERM_INDEX_MEMORY_BUDGET,
+        );
+        insert(&mut registry, "a", 10);
+        let before = registry.total_bytes();
+
+        let (events, evicted) = registry
+            .update("/proj:a", |index| {
+                index.line_offsets.extend(vec![(0, 0); 1000]);
+                index.total_events()
+            })
+            .unwrap();
+        assert_eq!(events, 1010);
+        assert!(evicted.is_empty());
+        assert!(registry.total_bytes() > before);
+
+        let stats = registry.stats();
+        assert_eq!(stats.indices.len(), 1);
+        assert_eq!(stats.indices[0].session_id, "a");
+        assert_eq!(stats.indices[0].total_events, 1010);
+        assert_eq!(stats.total_bytes, registry.total_bytes() as u64);
+
+        assert!(registry.remove("/proj:a").is_some());
+        assert_eq!(registry.total_bytes(), 0);
+    }
+
+    #[test]
+    fn test_term_indices_have_their_own_budget() {
+        let one = index_with_lines(1000).estimated_memory_bytes();
+        let mut terms = TermIndex::empty();
+        terms.line_offsets = vec![(0, 0); 1000];
+        let one_terms = terms.estimated_memory_bytes();
+        let terms = Arc::new(terms);
+        let mut registry = IndexRegistry::new(one + one / 2, one_terms + one_terms / 2);
+        assert!(insert(&mut registry, "a", 1000).is_empty());
+
+        // Term indices never push out session indices
+        let first = Path::new("/proj/first.jsonl");
+        assert!(registry.insert_terms(first, Arc::clone(&terms)).is_empty());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let second = Path::new("/proj/second.jsonl");
+        let evicted = registry.insert_terms(second, terms);
+        assert!(matches!(
+            evicted.as_slice(),
+            [EvictedIndex::Terms { file, .. }] if file == first
+        ));
+        assert!(registry.get("/proj:a").is_some());
+
+        let stats = registry.stats();
+        assert_eq!(stats.indices.len(), 1);
+        assert_eq!(stats.term_index_bytes, one_terms as u64);
+        assert_eq!(stats.total_bytes, (one + one_terms) as u64);
+
+        // Nor do session indices push out term indices
+        let evicted = insert(&mut registry, "b", 1000);
+        assert!(matches!(evicted.as_slice(), [EvictedIndex::Session { .. }]));
+        assert!(registry.remove_terms(second).is_some());
+    }
+}
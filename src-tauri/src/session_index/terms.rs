@@ -0,0 +1,1034 @@
+//! Inverted term index for search.
+//!
+//! Maps every term of a JSONL file (lowercased runs of alphanumerics and `_`, taken from
+//! the searchable text of its lines) to postings of `(line, term frequency)`. Searches use
+//! it to narrow a query down to candidate lines, so only those lines are read and matched.
+//!
+//! The searchable text of a line is the raw line with the values of opaque fields (ids,
+//! timestamps, signatures) blanked out: they are unique to almost every line, so indexing
+//! them would grow the vocabulary with the file. Searches still match candidate lines
+//! against the raw line, and scan every line for terms with a digit, which may be part
+//! of an id or timestamp. A word without digits that occurs only inside an opaque value
+//! (e.g. in a base64 signature) is not found.
+//!
+//! Distinct terms form a vocabulary with a trigram index, so a fragment is looked up among
+//! the terms sharing its trigrams rather than in every term.
+//!
+//! Term indices are updated incrementally as lines are appended and persisted in a compact
+//! binary format to `<dir>/<hash of file path>.bin`. A `TermIndexStore` keeps recently used
+//! indices in the `IndexRegistry`, within a memory budget separate from session indices.
+
+use chrono::DateTime;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use super::cache::{cache_file_path, prefix_fingerprint, temp_file_path};
+use super::registry::{persist_evicted_indices, IndexRegistry};
+use super::updater::UpdateResult;
+
+/// Bump when the serialized TermIndex layout or tokenization changes.
+pub const TERM_INDEX_VERSION: u32 = 2;
+
+/// Bytes indexed between progress reports (and cancellation checks).
+const PROGRESS_INTERVAL_BYTES: u64 = 1 << 20;
+
+/// Error of an update stopped by its progress callback.
+pub const INDEXING_CANCELLED: &str = "Indexing cancelled";
+
+/// Leading bytes of a term index file.
+const TERM_INDEX_MAGIC: &[u8; 4] = b"ACTI";
+
+/// Fields whose values are opaque identifiers, timestamps or signatures, left out of the index.
+const OPAQUE_FIELDS: &[&str] = &[
+    "uuid",
+    "parentUuid",
+    "logicalParentUuid",
+    "leafUuid",
+    "sessionId",
+    "requestId",
+    "messageId",
+    "id",
+    "tool_use_id",
+    "toolUseID",
+    "parentToolUseID",
+    "sourceToolUseID",
+    "signature",
+    "timestamp",
+    "data",
+];
+
+/// Inverted index of the terms of one JSONL file.
+///
+/// Only complete (newline-terminated) lines are indexed; a line still being written is
+/// picked up by the next update.
+#[derive(Debug, Clone)]
+pub struct TermIndex {
+    /// Bytes of the file covered by the index (up to the end of the last complete line)
+    pub file_size: u64,
+    /// Modification time when index was last built/updated
+    pub last_modified: SystemTime,
+    /// (byte_offset, line_length) for each line in the file
+    pub line_offsets: Vec<(u64, usize)>,
+    /// Event timestamp of each line, in Unix seconds (None if it has none)
+    pub line_times: Vec<Option<i64>>,
+    /// Distinct terms, in order of first occurrence (a term's position is its id)
+    terms: Vec<String>,
+    /// term id → (line, occurrences in that line), in line order
+    postings: Vec<Vec<(u32, u32)>>,
+    /// term → term id
+    term_ids: HashMap<String, u32>,
+    /// Trigram (of chars) → ids of the terms containing it, ascending
+    trigrams: HashMap<[char; 3], Vec<u32>>,
+}
+
+/// Only the timestamp of a line, for recency ranking.
+#[derive(Deserialize)]
+struct LineTimestamp {
+    timestamp: Option<String>,
+}
+
+impl TermIndex {
+    /// Create an empty index (used before building).
+    pub fn empty() -> Self {
+        Self {
+            file_size: 0,
+            last_modified: SystemTime::UNIX_EPOCH,
+            line_offsets: Vec::new(),
+            line_times: Vec::new(),
+            terms: Vec::new(),
+            postings: Vec::new(),
+            term_ids: HashMap::new(),
+            trigrams: HashMap::new(),
+        }
+    }
+
+    /// Whether the file is unchanged since the last update.
+    fn is_up_to_date(&self, file: &Path) -> bool {
+        fs::metadata(file).is_ok_and(|metadata| {
+            metadata.len() == self.file_size
+                && metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH) == self.last_modified
+        })
+    }
+
+    /// Index the lines appended since the last update (rebuilding the index if the file
+    /// shrank), reporting the bytes indexed so far to `on_progress` every
+    /// `PROGRESS_INTERVAL_BYTES`. If it returns false, indexing stops after the current
+    /// line with an error; the lines indexed so far are kept, so the next update
+    /// carries on from there.
+    pub fn update_with_progress(
+        &mut self,
+        file: &Path,
+        on_progress: &mut dyn FnMut(u64) -> bool,
+    ) -> Result<UpdateResult, String> {
+        let metadata =
+            fs::metadata(file).map_err(|e| format!("Failed to read file metadata: {}", e))?;
+        let current_size = metadata.len();
+        let current_mtime = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+        if current_size == self.file_size && current_mtime == self.last_modified {
+            return Ok(UpdateResult::Unchanged);
+        }
+
+        let mut result = UpdateResult::Updated;
+        if current_size < self.file_size {
+            *self = Self::empty();
+            result = UpdateResult::Rebuilt;
+        }
+
+        let mut reader =
+            BufReader::new(File::open(file).map_err(|e| format!("Failed to open file: {}", e))?);
+        reader
+            .seek(SeekFrom::Start(self.file_size))
+            .map_err(|e| format!("Failed to seek in file: {}", e))?;
+
+        let start = self.file_size;
+        let mut byte_offset = start;
+        let mut next_report = start + PROGRESS_INTERVAL_BYTES;
+        let mut buffer = Vec::new();
+        loop {
+            if byte_offset >= next_report {
+                next_report = byte_offset + PROGRESS_INTERVAL_BYTES;
+                if !on_progress(byte_offset - start) {
+                    self.file_size = byte_offset;
+                    return Err(INDEXING_CANCELLED.to_string());
+                }
+            }
+            buffer.clear();
+            let read = reader
+                .read_until(b'\n', &mut buffer)
+                .map_err(|e| format!("Failed to read file: {}", e))?;
+            if read == 0 || buffer.last() != Some(&b'\n') {
+                break;
+            }
+
+            let line = String::from_utf8_lossy(&buffer);
+            self.add_line(&line, byte_offset, read);
+            byte_offset += read as u64;
+        }
+
+        self.file_size = byte_offset;
+        self.last_modified = current_mtime;
+        Ok(result)
+    }
+
+    /// Record the postings and timestamp of the next line.
+    fn add_line(&mut self, line: &str, byte_offset: u64, length: usize) {
+        let sequence = self.line_offsets.len() as u32;
+        self.line_offsets.push((byte_offset, length));
+
+        let timestamp = serde_json::from_str::<LineTimestamp>(line)
+            .ok()
+            .and_then(|entry| entry.timestamp)
+            .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+            .map(|t| t.timestamp());
+        self.line_times.push(timestamp);
+
+        let text = searchable_text(line).to_lowercase();
+        let mut counts: HashMap<&str, u32> = HashMap::new();
+        for term in tokenize(&text) {
+            *counts.entry(term).or_default() += 1;
+        }
+        for (term, count) in counts {
+            let id = self.term_id(term);
+            self.postings[id as usize].push((sequence, count));
+        }
+    }
+
+    /// Id of a term, adding it to the vocabulary if it is new.
+    fn term_id(&mut self, term: &str) -> u32 {
+        if let Some(&id) = self.term_ids.get(term) {
+            return id;
+        }
+        let id = self.terms.len() as u32;
+        self.terms.push(term.to_string());
+        self.postings.push(Vec::new());
+        self.term_ids.insert(term.to_string(), id);
+        self.add_trigrams(id);
+        id
+    }
+
+    /// Record the trigrams of a newly added term.
+    fn add_trigrams(&mut self, id: u32) {
+        for trigram in trigrams(&self.terms[id as usize]) {
+            let ids = self.trigrams.entry(trigram).or_default();
+            // A term repeating a trigram is listed once
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+    }
+
+    /// Number of lines indexed.
+    pub fn total_lines(&self) -> u32 {
+        self.line_offsets.len() as u32
+    }
+
+    /// Estimated heap size of the index, for the registry's memory budget.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        use std::mem::size_of;
+
+        let lines = self.line_offsets.capacity() * size_of::<(u64, usize)>()
+            + self.line_times.capacity() * size_of::<Option<i64>>();
+        // Each term is stored twice (vocabulary and id map), with its postings
+        let terms: usize = self
+            .terms
+            .iter()
+            .zip(&self.postings)
+            .map(|(term, postings)| {
+                2 * (size_of::<String>() + term.capacity())
+                    + size_of::<u32>()
+                    + size_of::<Vec<(u32, u32)>>()
+                    + postings.capacity() * size_of::<(u32, u32)>()
+            })
+            .sum();
+        let trigrams: usize = self
+            .trigrams
+            .values()
+            .map(|ids| size_of::<[char; 3]>() + size_of::<Vec<u32>>() + ids.capacity() * 4)
+            .sum();
+        size_of::<Self>() + lines + terms + trigrams
+    }
+
+    /// Ids of the indexed terms that contain `fragment`.
+    fn terms_containing(&self, fragment: &str) -> Vec<u32> {
+        let fragment_trigrams = distinct_trigrams(fragment);
+        if fragment_trigrams.is_empty() {
+            // Too short for trigrams: check every term
+            return (0..self.terms.len() as u32)
+                .filter(|&id| self.terms[id as usize].contains(fragment))
+                .collect();
+        }
+
+        // Terms containing every trigram of the fragment, verified to contain it whole
+        let mut lists = Vec::with_capacity(fragment_trigrams.len());
+        for trigram in &fragment_trigrams {
+            match self.trigrams.get(trigram) {
+                Some(ids) => lists.push(ids.as_slice()),
+                None => return Vec::new(),
+            }
+        }
+        lists.sort_by_key(|ids| ids.len());
+        let mut ids = lists[0].to_vec();
+        for list in &lists[1..] {
+            ids.retain(|id| list.binary_search(id).is_ok());
+        }
+        ids.retain(|&id| self.terms[id as usize].contains(fragment));
+        ids
+    }
+
+    /// Ids of the indexed terms that contain `pattern` within `max_edits` edits.
+    fn terms_containing_fuzzy(&self, pattern: &[char], max_edits: usize) -> Vec<u32> {
+        // Each edit breaks at most four of the pattern's trigrams (a swap touches two
+        // chars), so a term must share the rest of them
+        let text: String = pattern.iter().collect();
+        let pattern_trigrams = distinct_trigrams(&text);
+        let required = pattern_trigrams.len() as isize - 4 * max_edits as isize;
+
+        let candidates: Vec<u32> = if required > 0 {
+            let mut shared: HashMap<u32, isize> = HashMap::new();
+            for trigram in &pattern_trigrams {
+                for &id in self.trigrams.get(trigram).into_iter().flatten() {
+                    *shared.entry(id).or_default() += 1;
+                }
+            }
+            let mut ids: Vec<u32> = shared
+                .into_iter()
+                .filter(|&(_, count)| count >= required)
+                .map(|(id, _)| id)
+                .collect();
+            ids.sort_unstable();
+            ids
+        } else {
+            // Too short to filter by trigrams: skip terms too short to match
+            (0..self.terms.len() as u32)
+                .filter(|&id| self.terms[id as usize].len() + max_edits >= pattern.len())
+                .collect()
+        };
+
+        let mut chars = Vec::new();
+        candidates
+            .into_iter()
+            .filter(|&id| {
+                chars.clear();
+                chars.extend(self.terms[id as usize].chars());
+                find_fuzzy(pattern, &chars, max_edits).is_some()
+            })
+            .collect()
+    }
+
+    /// Postings of several terms merged into occurrences per line, in line order.
+    fn merged_postings(&self, ids: &[u32]) -> Vec<(u32, u32)> {
+        let mut counts: HashMap<u32, u32> = HashMap::new();
+        for &id in ids {
+            for &(line, count) in &self.postings[id as usize] {
+                *counts.entry(line).or_default() += count;
+            }
+        }
+        let mut postings: Vec<(u32, u32)> = counts.into_iter().collect();
+        postings.sort_unstable();
+        postings
+    }
+
+    /// Occurrences of `fragment` per line, in line order, counting every indexed term
+    /// that contains it. `fragment` must be lowercase.
+    pub fn fragment_postings(&self, fragment: &str) -> Vec<(u32, u32)> {
+        self.merged_postings(&self.terms_containing(fragment))
+    }
+
+    /// Like `fragment_postings`, but counting every indexed term that contains
+    /// `fragment` within `max_edits` edits (see `find_fuzzy`). `fragment` must be lowercase.
+    pub fn fuzzy_postings(&self, fragment: &str, max_edits: usize) -> Vec<(u32, u32)> {
+        let pattern: Vec<char> = fragment.chars().collect();
+        self.merged_postings(&self.terms_containing_fuzzy(&pattern, max_edits))
+    }
+}
+
+/// Trigrams of chars of a term, in order (with repeats).
+fn trigrams(term: &str) -> impl Iterator<Item = [char; 3]> + '_ {
+    let chars: Vec<char> = term.chars().collect();
+    (0..chars.len().saturating_sub(2)).map(move |i| [chars[i], chars[i + 1], chars[i + 2]])
+}
+
+/// Distinct trigrams of a text.
+fn distinct_trigrams(text: &str) -> Vec<[char; 3]> {
+    let mut result: Vec<[char; 3]> = trigrams(text).collect();
+    result.sort_unstable();
+    result.dedup();
+    result
+}
+
+/// The text of a line that the index stores: the line with the values of opaque fields
+/// blanked out with spaces. Only values without whitespace or escapes are blanked, so
+/// byte offsets are unchanged and prose stored under one of those keys (e.g. a tool
+/// input's `"id"`) is still indexed.
+pub fn searchable_text(line: &str) -> Cow<'_, str> {
+    let bytes = line.as_bytes();
+    let mut masked: Option<Vec<u8>> = None;
+
+    // Every quote found here opens a string, since strings are skipped whole
+    let mut i = 0;
+    while let Some(quote) = bytes[i..].iter().position(|&b| b == b'"') {
+        let key_start = i + quote + 1;
+        let Some(key_end) = string_end(bytes, key_start) else {
+            break;
+        };
+        i = key_end + 1;
+
+        let Some(value_start) = string_value_start(bytes, i) else {
+            continue;
+        };
+        if !OPAQUE_FIELDS.contains(&&line[key_start..key_end]) {
+            continue;
+        }
+        let Some(value_end) = string_end(bytes, value_start) else {
+            break;
+        };
+        let value = &bytes[value_start..value_end];
+        if value.iter().all(|&b| b.is_ascii_graphic() && b != b'\\') {
+            let masked = masked.get_or_insert_with(|| bytes.to_vec());
+            masked[value_start..value_end].fill(b' ');
+        }
+        i = value_end + 1;
+    }
+
+    match masked {
+        // Only ASCII bytes were replaced, so the text is still valid UTF-8
+        Some(masked) => Cow::Owned(String::from_utf8(masked).unwrap_or_default()),
+        None => Cow::Borrowed(line),
+    }
+}
+
+/// Position of the quote closing a JSON string whose contents start at `start`.
+fn string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// If a `:` and a string follow `at` (a key's closing quote + 1), where its contents start.
+fn string_value_start(bytes: &[u8], at: usize) -> Option<usize> {
+    let mut rest = bytes[at..].iter().enumerate();
+    let mut next = || rest.find(|(_, b)| !b.is_ascii_whitespace());
+    let (_, b':') = next()? else {
+        return None;
+    };
+    match next()? {
+        (offset, b'"') => Some(at + offset + 1),
+        _ => None,
+    }
+}
+
+/// Split lowercased text into the terms the index stores.
+pub fn tokenize(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|term| !term.is_empty())
+}
+
+/// Find where `pattern` occurs in `text` with the fewest edits (inserting, deleting or
+/// substituting a char, or swapping two adjacent chars), if that is at most `max_edits`.
+/// Returns the number of edits and the char range of `text` it matched.
+pub fn find_fuzzy(
+    pattern: &[char],
+    text: &[char],
+    max_edits: usize,
+) -> Option<(usize, Range<usize>)> {
+    if text.len() + max_edits < pattern.len() {
+        return None;
+    }
+
+    // Edits between pattern[..i] and the best substring of text ending before the
+    // current char (Sellers' algorithm), with where that substring starts; kept for
+    // the last two chars so swaps can be counted
+    let mut before_previous = vec![(0, 0); pattern.len() + 1];
+    let mut previous: Vec<(usize, usize)> = (0..=pattern.len()).map(|i| (i, 0)).collect();
+    let mut column = vec![(0, 0); pattern.len() + 1];
+    let mut best: Option<(usize, Range<usize>)> =
+        (pattern.len() <= max_edits).then_some((pattern.len(), 0..0));
+
+    for (j, &c) in text.iter().enumerate() {
+        column[0] = (0, j + 1);
+        for i in 1..=pattern.len() {
+            let substitute = (
+                previous[i - 1].0 + usize::from(pattern[i - 1] != c),
+                previous[i - 1].1,
+            );
+            let skip_text = (previous[i].0 + 1, previous[i].1);
+            let skip_pattern = (column[i - 1].0 + 1, column[i - 1].1);
+            column[i] = substitute.min(skip_text).min(skip_pattern);
+            if i > 1 && j > 0 && pattern[i - 2] == c && pattern[i - 1] == text[j - 1] {
+                let (edits, start) = before_previous[i - 2];
+                column[i] = column[i].min((edits + 1, start));
+            }
+        }
+
+        let (edits, start) = column[pattern.len()];
+        if edits <= max_edits && best.as_ref().is_none_or(|(fewest, _)| edits < *fewest) {
+            best = Some((edits, start..j + 1));
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut column);
+    }
+
+    best
+}
+
+/// Load a file's term index from the cache and bring it up to date, or build it from
+/// scratch, reporting progress as in `TermIndex::update_with_progress`.
+/// The cache is written when the index had to be built or rebuilt (also when that was
+/// cancelled, so it resumes from there); lines appended to a cached index are written
+/// when it leaves memory.
+pub fn open_term_index(
+    cache_dir: Option<&Path>,
+    file: &Path,
+    on_progress: &mut dyn FnMut(u64) -> bool,
+) -> Result<TermIndex, String> {
+    let cached = cache_dir.and_then(|dir| load_term_index(dir, file));
+    let is_cached = cached.is_some();
+    let mut index = cached.unwrap_or_else(TermIndex::empty);
+
+    let result = index.update_with_progress(file, on_progress);
+    let built = !is_cached || matches!(result, Ok(UpdateResult::Rebuilt));
+
+    if built && index.total_lines() > 0 {
+        if let Some(dir) = cache_dir {
+            if let Err(e) = save_term_index(dir, file, &index) {
+                eprintln!("[session_index] Failed to write term index: {}", e);
+            }
+        }
+    }
+
+    result.map(|_| index)
+}
+
+/// Path of a file's term index in the cache.
+fn term_index_path(cache_dir: &Path, file: &Path) -> PathBuf {
+    cache_file_path(cache_dir, file).with_extension("bin")
+}
+
+/// Load a cached term index if it is still valid for the file.
+fn load_term_index(cache_dir: &Path, file: &Path) -> Option<TermIndex> {
+    let content = fs::read(term_index_path(cache_dir, file)).ok()?;
+    let mut reader = Reader::new(content.strip_prefix(TERM_INDEX_MAGIC)?);
+
+    if reader.varint()? != u64::from(TERM_INDEX_VERSION)
+        || reader.bytes()? != file.to_string_lossy().as_bytes()
+    {
+        return None;
+    }
+    let fingerprint = reader.varint()?;
+    let mut index = TermIndex::empty();
+    index.file_size = reader.varint()?;
+    index.last_modified = SystemTime::UNIX_EPOCH
+        + Duration::new(reader.varint()?, u32::try_from(reader.varint()?).ok()?);
+
+    // Lines are contiguous from the start of the file
+    let total_lines = reader.varint()? as usize;
+    let mut byte_offset = 0;
+    for _ in 0..total_lines {
+        let length = reader.varint()? as usize;
+        index.line_offsets.push((byte_offset, length));
+        byte_offset += length as u64;
+    }
+    for _ in 0..total_lines {
+        index.line_times.push(match reader.varint()? {
+            0 => None,
+            n => Some(unzigzag(n - 1)),
+        });
+    }
+
+    // The file must not have shrunk and must still contain the indexed lines
+    let current_size = fs::metadata(file).ok()?.len();
+    if current_size < index.file_size
+        || prefix_fingerprint(file, index.file_size, &index.line_offsets)? != fingerprint
+    {
+        return None;
+    }
+
+    let total_terms = reader.varint()? as usize;
+    for id in 0..total_terms as u32 {
+        let term = std::str::from_utf8(reader.bytes()?).ok()?.to_string();
+        let mut postings = Vec::with_capacity(reader.varint()? as usize);
+        let mut line = 0;
+        for _ in 0..postings.capacity() {
+            line += u32::try_from(reader.varint()?).ok()?;
+            postings.push((line, u32::try_from(reader.varint()?).ok()?));
+        }
+        index.term_ids.insert(term.clone(), id);
+        index.terms.push(term);
+        index.postings.push(postings);
+        index.add_trigrams(id);
+    }
+
+    Some(index)
+}
+
+/// Write a term index to the cache (atomically, via a temp file and rename).
+pub fn save_term_index(cache_dir: &Path, file: &Path, index: &TermIndex) -> Result<(), String> {
+    let fingerprint = prefix_fingerprint(file, index.file_size, &index.line_offsets)
+        .ok_or_else(|| "Failed to fingerprint file".to_string())?;
+    let modified = index
+        .last_modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let mut content = TERM_INDEX_MAGIC.to_vec();
+    write_varint(&mut content, u64::from(TERM_INDEX_VERSION));
+    write_bytes(&mut content, file.to_string_lossy().as_bytes());
+    write_varint(&mut content, fingerprint);
+    write_varint(&mut content, index.file_size);
+    write_varint(&mut content, modified.as_secs());
+    write_varint(&mut content, u64::from(modified.subsec_nanos()));
+
+    write_varint(&mut content, index.line_offsets.len() as u64);
+    for &(_, length) in &index.line_offsets {
+        write_varint(&mut content, length as u64);
+    }
+    for time in &index.line_times {
+        write_varint(&mut content, time.map_or(0, |t| zigzag(t) + 1));
+    }
+
+    write_varint(&mut content, index.terms.len() as u64);
+    for (term, postings) in index.terms.iter().zip(&index.postings) {
+        write_bytes(&mut content, term.as_bytes());
+        write_varint(&mut content, postings.len() as u64);
+        // Lines ascend, so each is stored as the gap from the previous one
+        let mut previous = 0;
+        for &(line, count) in postings {
+            write_varint(&mut content, u64::from(line - previous));
+            write_varint(&mut content, u64::from(count));
+            previous = line;
+        }
+    }
+
+    fs::create_dir_all(cache_dir).map_err(|e| format!("Failed to create term index dir: {}", e))?;
+
+    let cache_file = term_index_path(cache_dir, file);
+    let tmp_file = temp_file_path(&cache_file);
+    fs::write(&tmp_file, content).map_err(|e| format!("Failed to write term index: {}", e))?;
+    fs::rename(&tmp_file, &cache_file).map_err(|e| format!("Failed to write term index: {}", e))
+}
+
+/// Append an unsigned LEB128 varint.
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Append a length-prefixed byte string.
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Map a signed integer to an unsigned one, keeping small magnitudes small.
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Reads the values written by `write_varint` and `write_bytes`.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.bytes.split_first()?;
+            self.bytes = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let length = usize::try_from(self.varint()?).ok()?;
+        if length > self.bytes.len() {
+            return None;
+        }
+        let (bytes, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Some(bytes)
+    }
+}
+
+/// Term indices on disk, with recently used ones kept in the index registry.
+///
+/// Loaded indices are shared: concurrent searches of a file use the same index, and
+/// loading or updating it is serialized per file, so it is built or updated only once.
+pub struct TermIndexStore {
+    /// Directory for persisted term indices (None keeps them in memory only)
+    cache_dir: Option<PathBuf>,
+    /// Registry holding the loaded indices, within its memory budget
+    registry: Arc<Mutex<IndexRegistry>>,
+    /// Locks of the files whose index is being loaded, updated or unloaded
+    file_locks: Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>,
+}
+
+impl TermIndexStore {
+    pub fn new(cache_dir: Option<PathBuf>, registry: Arc<Mutex<IndexRegistry>>) -> Self {
+        Self {
+            cache_dir,
+            registry,
+            file_locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Load (or build) a file's term index into memory, e.g. for a watched session.
+    pub fn load(&self, file: &Path) -> Result<(), String> {
+        self.open(file, &mut |_| true).map(|_| ())
+    }
+
+    /// Index the lines appended to a loaded file (no-op if it is not loaded).
+    pub fn update(&self, file: &Path) -> Result<(), String> {
+        self.with_file_lock(file, || {
+            self.refresh_loaded(file, &mut |_| true).map(|_| ())
+        })
+    }
+
+    /// Drop a loaded term index, writing it to the cache first.
+    pub fn unload(&self, file: &Path) {
+        let _ = self.with_file_lock(file, || {
+            let index = self
+                .registry
+                .lock()
+                .ok()
+                .and_then(|mut r| r.remove_terms(file));
+            if let (Some(index), Some(dir)) = (index, &self.cache_dir) {
+                if let Err(e) = save_term_index(dir, file, &index) {
+                    eprintln!("[session_index] Failed to write term index: {}", e);
+                }
+            }
+            Ok(())
+        });
+    }
+
+    /// Run `f` on the up-to-date term index of a file, from memory if it is loaded,
+    /// otherwise from the cache (building it if needed). The index stays loaded
+    /// afterwards, until the registry evicts it.
+    ///
+    /// Bringing the index up to date reports progress to `on_progress`, which can
+    /// cancel it (see `TermIndex::update_with_progress`).
+    pub fn with_index<R>(
+        &self,
+        file: &Path,
+        on_progress: &mut dyn FnMut(u64) -> bool,
+        f: impl FnOnce(&TermIndex) -> R,
+    ) -> Result<R, String> {
+        let index = self.open(file, on_progress)?;
+        Ok(f(&index))
+    }
+
+    /// Get a file's up-to-date index from memory, otherwise from the cache (building it
+    /// if needed), and keep it loaded.
+    fn open(
+        &self,
+        file: &Path,
+        on_progress: &mut dyn FnMut(u64) -> bool,
+    ) -> Result<Arc<TermIndex>, String> {
+        self.with_file_lock(file, || {
+            if let Some(index) = self.refresh_loaded(file, on_progress)? {
+                return Ok(index);
+            }
+            let index = Arc::new(open_term_index(
+                self.cache_dir.as_deref(),
+                file,
+                on_progress,
+            )?);
+            self.put_back(file, Arc::clone(&index));
+            Ok(index)
+        })
+    }
+
+    /// Bring a loaded index up to date and return it, or None if it is not loaded.
+    /// Must be called holding the file's lock.
+    ///
+    /// Searches still using the previous version keep it; if none is, the index is
+    /// updated in place rather than copied.
+    fn refresh_loaded(
+        &self,
+        file: &Path,
+        on_progress: &mut dyn FnMut(u64) -> bool,
+    ) -> Result<Option<Arc<TermIndex>>, String> {
+        let loaded = {
+            let mut registry = self.registry.lock().map_err(|e| e.to_string())?;
+            registry.get_terms(file)
+        };
+        let Some(index) = loaded else {
+            return Ok(None);
+        };
+        if index.is_up_to_date(file) {
+            return Ok(Some(index));
+        }
+
+        {
+            let mut registry = self.registry.lock().map_err(|e| e.to_string())?;
+            registry.remove_terms(file);
+        }
+        let mut index = Arc::unwrap_or_clone(index);
+        let result = index.update_with_progress(file, on_progress);
+        // Also after a cancellation: the lines indexed so far are kept
+        let index = Arc::new(index);
+        self.put_back(file, Arc::clone(&index));
+        result.map(|_| Some(index))
+    }
+
+    /// Return an index to the registry, persisting whatever it evicts.
+    fn put_back(&self, file: &Path, index: Arc<TermIndex>) {
+        if let Ok(mut registry) = self.registry.lock() {
+            let evicted = registry.insert_terms(file, index);
+            persist_evicted_indices(evicted);
+        }
+    }
+
+    /// Run `f` holding the lock of a file's index.
+    fn with_file_lock<R>(
+        &self,
+        file: &Path,
+        f: impl FnOnce() -> Result<R, String>,
+    ) -> Result<R, String> {
+        let lock = {
+            let mut locks = self.file_locks.lock().map_err(|e| e.to_string())?;
+            Arc::clone(locks.entry(file.to_path_buf()).or_default())
+        };
+        let result = match lock.lock() {
+            Ok(_guard) => f(),
+            Err(e) => Err(e.to_string()),
+        };
+
+        // Forget the lock once no one else holds it
+        let mut locks = self.file_locks.lock().map_err(|e| e.to_string())?;
+        drop(lock);
+        if locks
+            .get(file)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(file);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_index::{DEFAULT_INDEX_MEMORY_BUDGET, DEFAULT_TERM_INDEX_MEMORY_BUDGET};
+    use crate::test_support::TempDir;
+    use std::io::Write;
+    use std::sync::Barrier;
+
+    fn fixture(name: &str) -> (TempDir, PathBuf, PathBuf) {
+        let dir = TempDir::new(&format!("terms-{}", name));
+        let file = dir.join("session.jsonl");
+        let cache_dir = dir.join("search-index");
+        (dir, file, cache_dir)
+    }
+
+    #[test]
+    fn test_term_index_postings_and_updates() {
+        let (_dir, file, cache_dir) = fixture("postings");
+        fs::write(
+            &file,
+            concat!(
+                r#"{"timestamp":"2025-10-05T09:00:00Z","message":{"content":"Fix the OAuth refresh"}}"#,
+                "\n",
+                r#"{"message":{"content":"refresh refresh get_session_events"}}"#,
+                "\n",
+                r#"{"message":{"content":"partial"#,
+            ),
+        )
+        .unwrap();
+
+        let index = open_term_index(Some(&cache_dir), &file, &mut |_| true).unwrap();
+        assert_eq!(index.total_lines(), 2);
+        assert_eq!(index.line_times, vec![Some(1759654800), None]);
+        assert_eq!(index.fragment_postings("refresh"), vec![(0, 1), (1, 2)]);
+        assert_eq!(index.fragment_postings("auth"), vec![(0, 1)]);
+        assert_eq!(index.fragment_postings("session_ev"), vec![(1, 1)]);
+        // Fragments too short for trigrams are looked up in every term
+        assert_eq!(index.fragment_postings("ge"), vec![(0, 1), (1, 2)]);
+        assert!(index.fragment_postings("partial").is_empty());
+        // Timestamps are not indexed as terms
+        assert!(index.fragment_postings("2025").is_empty());
+
+        // Completing the partial line indexes it from the cached state
+        let mut handle = fs::OpenOptions::new().append(true).open(&file).unwrap();
+        writeln!(handle, r#" line"}}}}"#).unwrap();
+        let index = open_term_index(Some(&cache_dir), &file, &mut |_| true).unwrap();
+        assert_eq!(index.total_lines(), 3);
+        assert_eq!(index.fragment_postings("partial"), vec![(2, 1)]);
+        save_term_index(&cache_dir, &file, &index).unwrap();
+        let cached = load_term_index(&cache_dir, &file).unwrap();
+        assert_eq!(cached.total_lines(), 3);
+        assert_eq!(cached.line_offsets, index.line_offsets);
+        assert_eq!(cached.line_times, index.line_times);
+        assert_eq!(cached.fragment_postings("refresh"), vec![(0, 1), (1, 2)]);
+        assert_eq!(cached.fragment_postings("session_ev"), vec![(1, 1)]);
+
+        // A rewritten file is not served from the cache
+        fs::write(&file, "{\"message\":{\"content\":\"new\"}}\n").unwrap();
+        let index = open_term_index(Some(&cache_dir), &file, &mut |_| true).unwrap();
+        assert_eq!(index.total_lines(), 1);
+        assert!(index.fragment_postings("oauth").is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_build_resumes_from_the_cache() {
+        let (_dir, file, cache_dir) = fixture("cancel");
+        let line = r#"{"message":{"content":"filler text for a large session file"}}"#;
+        let lines = (PROGRESS_INTERVAL_BYTES as usize * 3 / 2) / line.len();
+        let mut content = format!("{}\n", line).repeat(lines);
+        content.push_str("{\"message\":{\"content\":\"needle\"}}\n");
+        fs::write(&file, content).unwrap();
+
+        let mut reported = Vec::new();
+        let result = open_term_index(Some(&cache_dir), &file, &mut |bytes| {
+            reported.push(bytes);
+            false
+        });
+        assert_eq!(result.unwrap_err(), INDEXING_CANCELLED);
+        assert_eq!(reported.len(), 1);
+        assert!(reported[0] >= PROGRESS_INTERVAL_BYTES);
+
+        // The lines indexed before cancelling were cached, and indexing carries on
+        let partial = load_term_index(&cache_dir, &file).unwrap();
+        assert!(partial.total_lines() > 0 && (partial.total_lines() as usize) < lines);
+        let index = open_term_index(Some(&cache_dir), &file, &mut |_| true).unwrap();
+        assert_eq!(index.total_lines() as usize, lines + 1);
+        assert_eq!(index.fragment_postings("needle"), vec![(lines as u32, 1)]);
+    }
+
+    #[test]
+    fn test_store_shares_indices_between_searches() {
+        let (_dir, file, cache_dir) = fixture("shared");
+        fs::write(&file, "{\"message\":{\"content\":\"first\"}}\n").unwrap();
+        let registry = IndexRegistry::new(
+            DEFAULT_INDEX_MEMORY_BUDGET,
+            DEFAULT_TERM_INDEX_MEMORY_BUDGET,
+        );
+        let store = TermIndexStore::new(Some(cache_dir.clone()), Arc::new(Mutex::new(registry)));
+
+        // Concurrent searches of a file build its index once and all use that one
+        let barrier = Barrier::new(8);
+        let addresses: Vec<usize> = std::thread::scope(|scope| {
+            let searches: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        store
+                            .with_index(&file, &mut |_| true, |index| {
+                                index as *const TermIndex as usize
+                            })
+                            .unwrap()
+                    })
+                })
+                .collect();
+            searches.into_iter().map(|s| s.join().unwrap()).collect()
+        });
+        assert!(addresses.windows(2).all(|pair| pair[0] == pair[1]));
+
+        // Updating the index leaves the version a running search uses untouched
+        store
+            .with_index(&file, &mut |_| true, |before| {
+                let mut handle = fs::OpenOptions::new().append(true).open(&file).unwrap();
+                writeln!(handle, r#"{{"message":{{"content":"second"}}}}"#).unwrap();
+                store.update(&file).unwrap();
+                assert_eq!(before.total_lines(), 1);
+                let after = store.with_index(&file, &mut |_| true, |index| {
+                    index.fragment_postings("second")
+                });
+                assert_eq!(after.unwrap(), vec![(1, 1)]);
+            })
+            .unwrap();
+
+        store.unload(&file);
+        assert_eq!(load_term_index(&cache_dir, &file).unwrap().total_lines(), 2);
+        assert!(store.file_locks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_searchable_text_blanks_opaque_values() {
+        let line = r#"{"uuid":"0c9e-41f2","timestamp":"2025-10-05T09:00:00Z","message":{"id":"msg_01X","content":[{"type":"thinking","thinking":"uuid: \"id\"","signature":"EqMB+/x="},{"type":"tool_use","input":{"id":"free text"}}]}}"#;
+        let text = searchable_text(line);
+        assert_eq!(text.len(), line.len());
+        assert!(!text.contains("0c9e"));
+        assert!(!text.contains("2025"));
+        assert!(!text.contains("msg_01X"));
+        assert!(!text.contains("EqMB"));
+        // Keys, string contents that look like keys, and values with spaces are kept
+        assert!(text.contains(r#""uuid":"#));
+        assert!(text.contains(r#""thinking":"uuid: \"id\"""#));
+        assert!(text.contains(r#""id":"free text""#));
+
+        let plain = "no json here";
+        assert!(matches!(searchable_text(plain), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_find_fuzzy() {
+        let find = |pattern: &str, text: &str, max_edits| {
+            let pattern: Vec<char> = pattern.chars().collect();
+            let text: Vec<char> = text.chars().collect();
+            find_fuzzy(&pattern, &text, max_edits)
+        };
+
+        assert_eq!(find("session", "get_session_events", 0), Some((0, 4..11)));
+        // A missing, an extra and a wrong char each cost one edit
+        assert_eq!(find("sesion", "get_session_events", 1), Some((1, 4..11)));
+        assert_eq!(find("sesssion", "session", 1), Some((1, 0..7)));
+        assert_eq!(
+            find("get_sesion_evnts", "get_session_events", 2),
+            Some((2, 0..18))
+        );
+        // Swapping two adjacent chars is one edit
+        assert_eq!(find("sessoin", "session", 1), Some((1, 0..7)));
+        assert_eq!(find("sesoins", "session", 1), None);
+        assert_eq!(find("oauth", "refresh", 2), None);
+        assert_eq!(find("ab", "", 1), None);
+
+        let (_dir, file, _) = fixture("fuzzy");
+        fs::write(
+            &file,
+            concat!(
+                r#"{"message":{"content":"get_session_events"}}"#,
+                "\n",
+                r#"{"message":{"content":"get_sessions"}}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        let mut index = TermIndex::empty();
+        index.update_with_progress(&file, &mut |_| true).unwrap();
+        // Long enough to narrow down by trigrams
+        assert_eq!(index.fuzzy_postings("get_sesion_events", 2), vec![(0, 1)]);
+        assert_eq!(index.fuzzy_postings("get_sessoin_evnets", 2), vec![(0, 1)]);
+        assert_eq!(index.fuzzy_postings("sesion", 1), vec![(0, 1), (1, 1)]);
+    }
+}
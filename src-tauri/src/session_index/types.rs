@@ -8,12 +8,14 @@ use std::collections::HashMap;
 use std::time::SystemTime;
 
 use crate::claude_code::FileEdit;
+use crate::shell_analyzer::KnownDirs;
 
 /// Index for a single session's JSONL file.
 ///
 /// Built once when a session is opened, updated incrementally on file changes.
 /// Provides O(1) lookups for UUIDs, file edits, and parent chain walking.
-#[derive(Debug, Clone)]
+/// Serializable so it can be persisted to the on-disk index cache.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionIndex {
     // === File State (for incremental updates) ===
     /// Size of file when index was last built/updated
@@ -43,6 +45,8 @@ pub struct SessionIndex {
     pub file_edits: Vec<FileEdit>,
     /// file_path → sequence numbers of edits to that file
     pub file_to_edit_lines: HashMap<String, Vec<u32>>,
+    /// Directories the session created or used, for resuming `mv` classification
+    pub known_dirs: KnownDirs,
 
     // === Edit Metadata (for context feature) ===
     /// Sequence number → (byte_offset, messageId) for edits
@@ -51,7 +55,7 @@ pub struct SessionIndex {
 }
 
 /// Metadata for a single file edit event.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EditMetadata {
     /// UUID of this event (for parent chain walking)
     pub uuid: Option<String>,
@@ -85,6 +89,7 @@ impl SessionIndex {
             human_message_lines: Vec::new(),
             file_edits: Vec::new(),
             file_to_edit_lines: HashMap::new(),
+            known_dirs: KnownDirs::default(),
             edit_metadata: HashMap::new(),
         }
     }
@@ -118,6 +123,62 @@ impl SessionIndex {
         }
     }
 
+    /// Estimate the heap memory held by this index, in bytes.
+    ///
+    /// Counts allocated capacity of vectors and strings plus the per-entry size of
+    /// hash maps; allocator and hash table control overhead is not included.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        use std::mem::size_of;
+
+        let string_bytes = |s: &String| size_of::<String>() + s.capacity();
+
+        let line_offsets = self.line_offsets.capacity() * size_of::<(u64, usize)>();
+        let uuid_to_line: usize = self
+            .uuid_to_line
+            .keys()
+            .map(|k| string_bytes(k) + size_of::<u32>())
+            .sum();
+        let parent_map: usize = self
+            .parent_map
+            .iter()
+            .map(|(k, v)| string_bytes(k) + string_bytes(v))
+            .sum();
+        let human_message_lines = self.human_message_lines.capacity() * size_of::<u32>();
+        let file_edits: usize = self
+            .file_edits
+            .iter()
+            .map(|e| {
+                size_of::<FileEdit>()
+                    + e.path.capacity()
+                    + e.last_edited_at.as_ref().map_or(0, String::capacity)
+            })
+            .sum();
+        let file_to_edit_lines: usize = self
+            .file_to_edit_lines
+            .iter()
+            .map(|(k, v)| string_bytes(k) + size_of::<Vec<u32>>() + v.capacity() * size_of::<u32>())
+            .sum();
+        let edit_metadata: usize = self
+            .edit_metadata
+            .values()
+            .map(|m| {
+                size_of::<u32>()
+                    + size_of::<EditMetadata>()
+                    + m.uuid.as_ref().map_or(0, String::capacity)
+            })
+            .sum();
+
+        size_of::<Self>()
+            + line_offsets
+            + uuid_to_line
+            + parent_map
+            + human_message_lines
+            + file_edits
+            + file_to_edit_lines
+            + self.known_dirs.estimated_memory_bytes()
+            + edit_metadata
+    }
+
     /// Create IndexStatus for frontend.
     pub fn to_status(&self) -> IndexStatus {
         IndexStatus {
@@ -4,12 +4,11 @@
 
 use serde::Deserialize;
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use std::path::Path;
 
-use crate::claude_code::FileEditType;
+use crate::claude_code::FileEditTracker;
 
 use super::builder::build_session_index;
 use super::types::{EditMetadata, SessionIndex};
@@ -62,10 +61,9 @@ pub fn update_index_incremental(
     let mut byte_offset = index.file_size;
     let start_sequence = index.line_offsets.len() as u32;
 
-    // Track new file edits
-    let mut new_file_operations: HashMap<String, FileEditType> = HashMap::new();
-    let mut new_files_with_prior_content: HashSet<String> = HashSet::new();
-    let mut new_file_timestamps: HashMap<String, String> = HashMap::new();
+    // Continue tracking file edits from the existing state
+    let mut tracker =
+        FileEditTracker::from_edits(&index.file_edits, std::mem::take(&mut index.known_dirs));
 
     for (rel_seq, line_result) in reader.lines().enumerate() {
         let line = match line_result {
@@ -113,10 +111,9 @@ pub fn update_index_incremental(
                                     entry.uuid.as_deref(),
                                     entry.parent_uuid.as_deref(),
                                     entry.timestamp.as_deref(),
+                                    entry.cwd.as_deref(),
                                     index,
-                                    &mut new_file_operations,
-                                    &mut new_files_with_prior_content,
-                                    &mut new_file_timestamps,
+                                    &mut tracker,
                                 );
                             }
                         }
@@ -129,12 +126,8 @@ pub fn update_index_incremental(
     }
 
     // Merge new file edits into existing
-    merge_new_file_edits(
-        index,
-        new_file_operations,
-        new_files_with_prior_content,
-        new_file_timestamps,
-    );
+    index.known_dirs = tracker.take_known_dirs();
+    index.file_edits = tracker.finish();
 
     // Update file state
     index.file_size = current_size;
@@ -185,10 +178,9 @@ fn process_tool_use_incremental(
     uuid: Option<&str>,
     _parent_uuid: Option<&str>,
     timestamp: Option<&str>,
+    cwd: Option<&str>,
     index: &mut SessionIndex,
-    new_file_operations: &mut HashMap<String, FileEditType>,
-    new_files_with_prior_content: &mut HashSet<String>,
-    new_file_timestamps: &mut HashMap<String, String>,
+    tracker: &mut FileEditTracker,
 ) {
     if item.get("type").and_then(|v| v.as_str()) != Some("tool_use") {
         return;
@@ -204,118 +196,19 @@ fn process_tool_use_incremental(
         None => return,
     };
 
-    match tool_name {
-        "Edit" => {
-            if let Some(file_path) = input.get("file_path").and_then(|v| v.as_str()) {
-                let rel_path = make_relative_path(file_path, project_path);
-
-                if let Some(old_str) = input.get("old_string").and_then(|v| v.as_str()) {
-                    if !old_str.is_empty() {
-                        new_files_with_prior_content.insert(rel_path.clone());
-                    }
-                }
-
-                new_file_operations.insert(rel_path.clone(), FileEditType::Modified);
-
-                if let Some(ts) = timestamp {
-                    new_file_timestamps.insert(rel_path.clone(), ts.to_string());
-                }
-
-                index.edit_metadata.insert(
-                    sequence,
-                    EditMetadata {
-                        uuid: uuid.map(String::from),
-                    },
-                );
-
-                index
-                    .file_to_edit_lines
-                    .entry(rel_path)
-                    .or_default()
-                    .push(sequence);
-            }
-        }
-        "Write" => {
-            if let Some(file_path) = input.get("file_path").and_then(|v| v.as_str()) {
-                let rel_path = make_relative_path(file_path, project_path);
-
-                // Check if this file already exists in the index
-                let file_exists = index.file_to_edit_lines.contains_key(&rel_path);
-                if !file_exists && !new_file_operations.contains_key(&rel_path) {
-                    new_file_operations.insert(rel_path.clone(), FileEditType::Added);
-                }
-
-                if let Some(ts) = timestamp {
-                    new_file_timestamps.insert(rel_path.clone(), ts.to_string());
-                }
-
-                index.edit_metadata.insert(
-                    sequence,
-                    EditMetadata {
-                        uuid: uuid.map(String::from),
-                    },
-                );
-
-                index
-                    .file_to_edit_lines
-                    .entry(rel_path)
-                    .or_default()
-                    .push(sequence);
-            }
-        }
-        _ => {}
-    }
-}
-
-/// Merge new file edits into the existing index.
-fn merge_new_file_edits(
-    index: &mut SessionIndex,
-    new_file_operations: HashMap<String, FileEditType>,
-    new_files_with_prior_content: HashSet<String>,
-    new_file_timestamps: HashMap<String, String>,
-) {
-    use crate::claude_code::FileEdit;
-
-    for (path, edit_type) in new_file_operations {
-        // Find existing edit for this path
-        if let Some(existing) = index.file_edits.iter_mut().find(|e| e.path == path) {
-            // Update timestamp
-            if let Some(ts) = new_file_timestamps.get(&path) {
-                existing.last_edited_at = Some(ts.clone());
-            }
-            // If it was added before and now has prior content, it's modified
-            if new_files_with_prior_content.contains(&path) {
-                existing.edit_type = FileEditType::Modified;
-            }
-        } else {
-            // New file edit
-            let mut final_type = edit_type;
-            if final_type == FileEditType::Modified && !new_files_with_prior_content.contains(&path)
-            {
-                final_type = FileEditType::Added;
-            }
-
-            index.file_edits.push(FileEdit {
-                path: path.clone(),
-                edit_type: final_type,
-                last_edited_at: new_file_timestamps.get(&path).cloned(),
-            });
-        }
-    }
-
-    // Re-sort file edits
-    index.file_edits.sort_by(|a, b| a.path.cmp(&b.path));
-}
-
-/// Convert an absolute file path to a relative path from the project root.
-fn make_relative_path(file_path: &str, project_path: &str) -> String {
-    let project = project_path.trim_end_matches('/');
-    if file_path.starts_with(project) {
-        file_path[project.len()..]
-            .trim_start_matches('/')
-            .to_string()
-    } else {
-        file_path.to_string()
+    for rel_path in tracker.record_tool_use(tool_name, input, project_path, cwd, timestamp) {
+        index.edit_metadata.insert(
+            sequence,
+            EditMetadata {
+                uuid: uuid.map(String::from),
+            },
+        );
+
+        index
+            .file_to_edit_lines
+            .entry(rel_path)
+            .or_default()
+            .push(sequence);
     }
 }
 
@@ -336,9 +229,156 @@ struct JsonEntry {
     is_meta: Option<bool>,
     message: Option<JsonMessage>,
     timestamp: Option<String>,
+    cwd: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct JsonMessage {
     content: Option<Value>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::path::PathBuf;
+
+    /// Recorded session with MultiEdit and NotebookEdit tool calls.
+    const FIXTURE: &str = include_str!("../../fixtures/sessions/multi-notebook-edits.jsonl");
+
+    fn fixture_file(name: &str, content: &str) -> (TempDir, PathBuf) {
+        let dir = TempDir::new(&format!("updater-{}", name));
+        let session_file = dir.join("session.jsonl");
+        fs::write(&session_file, content).unwrap();
+        (dir, session_file)
+    }
+
+    #[test]
+    fn test_incremental_update_matches_full_build() {
+        let lines: Vec<&str> = FIXTURE.lines().collect();
+        let head = format!("{}\n", lines[..4].join("\n"));
+        let (_dir, session_file) = fixture_file("multi-notebook", &head);
+
+        let mut index = build_session_index(&session_file, "/home/dev/notebook-app").unwrap();
+        fs::write(&session_file, FIXTURE).unwrap();
+        update_index_incremental(&mut index, &session_file, "/home/dev/notebook-app").unwrap();
+
+        let full = build_session_index(&session_file, "/home/dev/notebook-app").unwrap();
+        assert_eq!(index.file_to_edit_lines, full.file_to_edit_lines);
+        let summary = |index: &SessionIndex| {
+            index
+                .file_edits
+                .iter()
+                .map(|e| {
+                    (
+                        e.path.clone(),
+                        e.edit_type.clone(),
+                        e.last_edited_at.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summary(&index), summary(&full));
+        assert_eq!(index.human_message_lines, full.human_message_lines);
+
+        // One entry per sub-edit, in the order get_file_diffs returns them
+        assert_eq!(
+            full.file_to_edit_lines.get("src/config.py"),
+            Some(&vec![1, 1, 1])
+        );
+        assert_eq!(
+            full.file_to_edit_lines.get("analysis.ipynb"),
+            Some(&vec![3, 5, 7])
+        );
+        assert_eq!(
+            full.file_to_edit_lines.get("src/new_module.py"),
+            Some(&vec![9, 9])
+        );
+    }
+
+    #[test]
+    fn test_incremental_update_tracks_bash_ops() {
+        let fixture = include_str!("../../fixtures/sessions/bash-file-ops.jsonl");
+        let lines: Vec<&str> = fixture.lines().collect();
+        let (_dir, session_file) =
+            fixture_file("bash-file-ops", &format!("{}\n", lines[..5].join("\n")));
+
+        let mut index = build_session_index(&session_file, "/home/dev/web-app").unwrap();
+        fs::write(&session_file, fixture).unwrap();
+        update_index_incremental(&mut index, &session_file, "/home/dev/web-app").unwrap();
+
+        let full = build_session_index(&session_file, "/home/dev/web-app").unwrap();
+        let summary = |index: &SessionIndex| {
+            index
+                .file_edits
+                .iter()
+                .map(|e| {
+                    (
+                        e.path.clone(),
+                        e.edit_type.clone(),
+                        e.renamed_from.clone(),
+                        e.confidence,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summary(&index), summary(&full));
+        assert_eq!(index.file_to_edit_lines, full.file_to_edit_lines);
+
+        // Only tool edits have diffs, so only they get edit lines
+        assert_eq!(
+            full.file_to_edit_lines.get("src/old_name.ts"),
+            Some(&vec![1])
+        );
+        assert_eq!(
+            full.file_to_edit_lines.get("src/new_name.ts"),
+            Some(&vec![5])
+        );
+        assert!(!full.file_to_edit_lines.contains_key("scripts/legacy.sh"));
+    }
+
+    #[test]
+    fn test_incremental_update_remembers_session_dirs() {
+        let bash = |i: usize, command: &str| {
+            serde_json::json!({
+                "type": "assistant",
+                "uuid": format!("a{}", i),
+                "timestamp": format!("2025-10-05T09:00:{:02}.000Z", i),
+                "message": {"content": [
+                    {"type": "tool_use", "name": "Bash", "input": {"command": command}}
+                ]}
+            })
+            .to_string()
+                + "\n"
+        };
+        let head = bash(0, "mkdir out") + &bash(1, "echo draft > notes.md");
+        let (_dir, session_file) = fixture_file("known-dirs", &head);
+
+        let mut index = build_session_index(&session_file, "/proj").unwrap();
+        fs::write(&session_file, head + &bash(2, "mv notes.md out")).unwrap();
+        update_index_incremental(&mut index, &session_file, "/proj").unwrap();
+
+        // `out` was created before the update, so the file moved into it either way
+        let full = build_session_index(&session_file, "/proj").unwrap();
+        let summary = |index: &SessionIndex| {
+            index
+                .file_edits
+                .iter()
+                .map(|e| {
+                    (
+                        e.path.clone(),
+                        e.edit_type.clone(),
+                        e.renamed_from.clone(),
+                        e.last_edited_at.clone(),
+                        e.confidence,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summary(&index), summary(&full));
+        assert_eq!(index.known_dirs, full.known_dirs);
+        assert_eq!(full.file_edits.len(), 1);
+        assert_eq!(full.file_edits[0].path, "out/notes.md");
+        assert_eq!(full.file_edits[0].renamed_from.as_deref(), Some("notes.md"));
+    }
+}
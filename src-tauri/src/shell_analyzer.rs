@@ -0,0 +1,871 @@
+//! Shell command analysis.
+//!
+//! Recognises file-changing commands in Bash tool inputs (`rm`, `git rm`, `mv`, `git mv`,
+//! `unlink`, output redirections and `tee`) so deletions, renames and shell writes show up
+//! alongside Edit/Write tool edits.
+//!
+//! Parsing is best-effort: only literal arguments are understood. Words containing
+//! variables, command substitutions or globs are skipped, and `cd` is followed within a
+//! single command string.
+//!
+//! Whether `mv a b` renames `a` or moves it into directory `b` is decided from the command
+//! syntax and from [`KnownDirs`], the directories the session itself created or used,
+//! never from the current filesystem, so a historical session is classified the same way
+//! whenever it is read.
+//!
+//! `git commit` invocations are also recognised so commits made by the agent can be
+//! told apart from the user's.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+/// The kind of change a shell command made to a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellFileOpKind {
+    /// File was removed (`rm`, `git rm`, `unlink`)
+    Deleted,
+    /// File was moved here from another path (`mv`, `git mv`)
+    Renamed { from: String },
+    /// File was written through a redirection or `tee`
+    Modified,
+}
+
+/// A file change inferred from a shell command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellFileOp {
+    /// Absolute, normalized path of the affected file
+    pub path: String,
+    /// What happened to the file
+    pub kind: ShellFileOpKind,
+}
+
+/// A `git commit` invocation found in a shell command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitCommitCall {
+    /// The first `-m` / `--message` value, if given literally
+    pub message: Option<String>,
+}
+
+/// Directories a session is known to have: created with `mkdir`, entered with `cd`, or
+/// parents of files it touched.
+///
+/// Stored in the session index so an incremental update classifies `mv` destinations
+/// exactly like a full build of the same file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnownDirs(HashSet<PathBuf>);
+
+impl KnownDirs {
+    /// Record a directory and its ancestors.
+    pub fn add(&mut self, dir: &Path) {
+        for ancestor in dir.ancestors() {
+            if ancestor.as_os_str().is_empty() || !self.0.insert(ancestor.to_path_buf()) {
+                break;
+            }
+        }
+    }
+
+    /// Record the directories containing a file.
+    pub fn add_parents_of(&mut self, path: &Path) {
+        if let Some(parent) = path.parent() {
+            self.add(parent);
+        }
+    }
+
+    fn contains(&self, dir: &Path) -> bool {
+        self.0.contains(dir)
+    }
+
+    /// Estimate the heap memory held by the set, in bytes.
+    pub fn estimated_memory_bytes(&self) -> usize {
+        self.0
+            .iter()
+            .map(|dir| std::mem::size_of::<PathBuf>() + dir.capacity())
+            .sum()
+    }
+}
+
+/// Commands that run their arguments as another command.
+const COMMAND_WRAPPERS: &[&str] = &["sudo", "command", "nohup", "time", "exec"];
+
+/// Analyze a shell command line, resolving relative paths against `cwd`.
+///
+/// `known_dirs` is consulted for `mv` destinations and updated with the directories the
+/// command creates or uses, so it should be carried across the commands of a session.
+pub fn analyze_command(command: &str, cwd: &str, known_dirs: &mut KnownDirs) -> Vec<ShellFileOp> {
+    let mut ops = Vec::new();
+    let mut cwd = PathBuf::from(cwd);
+
+    for simple in split_commands(tokenize(command)) {
+        let first_op = ops.len();
+        // Stop once the working directory can no longer be followed
+        let followed = analyze_simple_command(&simple, &mut cwd, &mut ops, known_dirs);
+        for op in &ops[first_op..] {
+            known_dirs.add_parents_of(Path::new(&op.path));
+            if let ShellFileOpKind::Renamed { from } = &op.kind {
+                known_dirs.add_parents_of(Path::new(from));
+            }
+        }
+        if !followed {
+            break;
+        }
+    }
+
+    ops
+}
+
+/// Find the `git commit` invocations in a shell command line.
+pub fn find_git_commits(command: &str) -> Vec<GitCommitCall> {
+    split_commands(tokenize(command))
+        .iter()
+        .filter_map(|simple| {
+            let args = command_args(simple);
+            let (program, rest) = args.split_first()?;
+            if program.text != "git" {
+                return None;
+            }
+            let (_, subcommand, rest) = git_subcommand(rest, Path::new("/"));
+            (subcommand?.text == "commit").then(|| GitCommitCall {
+                message: commit_message(rest),
+            })
+        })
+        .collect()
+}
+
+// =============================================================================
+// Tokenizer
+// =============================================================================
+
+/// A shell word after quote removal.
+#[derive(Debug, Clone, PartialEq)]
+struct Word {
+    text: String,
+    /// False when the word contains expansions or globs we can't resolve
+    literal: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(Word),
+    /// Command boundary: `;`, `&&`, `||`, `|`, `&`, newline, `(` or `)`
+    Separator,
+    /// Output redirection: `>`, `>>`, `>|`, `&>`, `&>>` (fd prefix dropped)
+    RedirectOut,
+    /// `>&`, either fd duplication (`2>&1`) or a redirection to a file
+    RedirectDup,
+    /// Input redirection or here-string; its target is ignored
+    RedirectIn,
+}
+
+fn is_metachar(c: char) -> bool {
+    matches!(
+        c,
+        ' ' | '\t' | '\n' | ';' | '&' | '|' | '(' | ')' | '<' | '>'
+    )
+}
+
+fn tokenize(command: &str) -> Vec<Token> {
+    let chars: Vec<char> = command.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pending_heredocs: Vec<(String, bool)> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        match chars[i] {
+            ' ' | '\t' => i += 1,
+            '\n' => {
+                i += 1;
+                for (delimiter, strip_tabs) in pending_heredocs.drain(..) {
+                    i = skip_heredoc(&chars, i, &delimiter, strip_tabs);
+                }
+                tokens.push(Token::Separator);
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            ';' | '(' | ')' => {
+                tokens.push(Token::Separator);
+                i += 1;
+            }
+            '&' if next == Some('>') => {
+                i += 2;
+                if chars.get(i) == Some(&'>') {
+                    i += 1;
+                }
+                tokens.push(Token::RedirectOut);
+            }
+            '&' | '|' => {
+                i += if next == Some(chars[i]) { 2 } else { 1 };
+                tokens.push(Token::Separator);
+            }
+            '>' => {
+                i += 1;
+                match chars.get(i) {
+                    Some('>') | Some('|') => {
+                        i += 1;
+                        tokens.push(Token::RedirectOut);
+                    }
+                    Some('&') => {
+                        i += 1;
+                        tokens.push(Token::RedirectDup);
+                    }
+                    _ => tokens.push(Token::RedirectOut),
+                }
+            }
+            '<' if next == Some('<') => {
+                i += 2;
+                if chars.get(i) == Some(&'<') {
+                    i += 1;
+                    tokens.push(Token::RedirectIn);
+                    continue;
+                }
+                let strip_tabs = chars.get(i) == Some(&'-');
+                if strip_tabs {
+                    i += 1;
+                }
+                while i < chars.len() && matches!(chars[i], ' ' | '\t') {
+                    i += 1;
+                }
+                let (delimiter, end) = read_word(&chars, i);
+                i = end;
+                pending_heredocs.push((delimiter.text, strip_tabs));
+            }
+            '<' => {
+                i += 1;
+                tokens.push(Token::RedirectIn);
+            }
+            c if c.is_ascii_digit() => {
+                // An fd number directly before a redirection operator (e.g. `2>`)
+                let mut j = i;
+                while j < chars.len() && chars[j].is_ascii_digit() {
+                    j += 1;
+                }
+                if matches!(chars.get(j), Some('>') | Some('<')) {
+                    i = j;
+                } else {
+                    let (word, end) = read_word(&chars, i);
+                    tokens.push(Token::Word(word));
+                    i = end;
+                }
+            }
+            _ => {
+                let (word, end) = read_word(&chars, i);
+                tokens.push(Token::Word(word));
+                i = end;
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Read a single word starting at `start`, returning it and the index after it.
+fn read_word(chars: &[char], start: usize) -> (Word, usize) {
+    let mut text = String::new();
+    let mut literal = true;
+    let mut i = start;
+
+    while i < chars.len() && !is_metachar(chars[i]) {
+        match chars[i] {
+            '\'' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '\'' {
+                    text.push(chars[i]);
+                    i += 1;
+                }
+                i += 1;
+            }
+            '"' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    match chars[i] {
+                        '\\' if matches!(chars.get(i + 1), Some('"' | '\\' | '$' | '`')) => {
+                            text.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        '$' | '`' => {
+                            literal = false;
+                            text.push(chars[i]);
+                            i += 1;
+                        }
+                        c => {
+                            text.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+            }
+            '\\' => {
+                match chars.get(i + 1) {
+                    Some('\n') => {}
+                    Some(&c) => text.push(c),
+                    None => {}
+                }
+                i += 2;
+            }
+            '$' => {
+                literal = false;
+                i = match chars.get(i + 1) {
+                    Some('(') => skip_balanced(chars, i + 1, '(', ')'),
+                    Some('{') => skip_balanced(chars, i + 1, '{', '}'),
+                    _ => i + 1,
+                };
+            }
+            '`' => {
+                literal = false;
+                i += 1;
+                while i < chars.len() && chars[i] != '`' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            '*' | '?' | '[' => {
+                literal = false;
+                text.push(chars[i]);
+                i += 1;
+            }
+            '~' if i == start => {
+                literal = false;
+                text.push('~');
+                i += 1;
+            }
+            c => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    (Word { text, literal }, i.min(chars.len()))
+}
+
+/// Skip a balanced `open`..`close` group starting at `start`, returning the index after it.
+fn skip_balanced(chars: &[char], start: usize, open: char, close: char) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == open {
+            depth += 1;
+        } else if chars[i] == close {
+            depth -= 1;
+            if depth == 0 {
+                return i + 1;
+            }
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+/// Skip a here-document body starting at `start`, returning the index after its delimiter line.
+fn skip_heredoc(chars: &[char], start: usize, delimiter: &str, strip_tabs: bool) -> usize {
+    let mut i = start;
+    while i < chars.len() {
+        let line_end = chars[i..]
+            .iter()
+            .position(|&c| c == '\n')
+            .map_or(chars.len(), |p| i + p);
+        let line: String = chars[i..line_end].iter().collect();
+        i = line_end + 1;
+
+        let line = if strip_tabs {
+            line.trim_start_matches('\t')
+        } else {
+            line.as_str()
+        };
+        if line == delimiter {
+            break;
+        }
+    }
+    i.min(chars.len())
+}
+
+// =============================================================================
+// Command Analysis
+// =============================================================================
+
+/// A simple command: its words plus the targets of its output redirections.
+#[derive(Debug, Default)]
+struct SimpleCommand {
+    words: Vec<Word>,
+    output_targets: Vec<Word>,
+}
+
+fn split_commands(tokens: Vec<Token>) -> Vec<SimpleCommand> {
+    let mut commands = Vec::new();
+    let mut current = SimpleCommand::default();
+    let mut tokens = tokens.into_iter();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Word(word) => current.words.push(word),
+            Token::Separator => {
+                commands.push(std::mem::take(&mut current));
+            }
+            Token::RedirectOut => {
+                if let Some(Token::Word(target)) = tokens.next() {
+                    current.output_targets.push(target);
+                }
+            }
+            Token::RedirectDup => {
+                if let Some(Token::Word(target)) = tokens.next() {
+                    // `>&2` / `>&-` duplicate or close an fd; anything else is a file
+                    let is_fd =
+                        target.text == "-" || target.text.chars().all(|c| c.is_ascii_digit());
+                    if !is_fd {
+                        current.output_targets.push(target);
+                    }
+                }
+            }
+            Token::RedirectIn => {
+                tokens.next();
+            }
+        }
+    }
+    commands.push(current);
+
+    commands
+        .into_iter()
+        .filter(|c| !c.words.is_empty() || !c.output_targets.is_empty())
+        .collect()
+}
+
+/// Analyze one simple command. Returns false after a `cd` to a directory we can't resolve.
+fn analyze_simple_command(
+    command: &SimpleCommand,
+    cwd: &mut PathBuf,
+    ops: &mut Vec<ShellFileOp>,
+    known_dirs: &mut KnownDirs,
+) -> bool {
+    for target in &command.output_targets {
+        if target.literal && !target.text.starts_with("/dev/") {
+            ops.push(modified(cwd, &target.text));
+        }
+    }
+
+    let args = command_args(command);
+    let Some((program, rest)) = args.split_first() else {
+        return true;
+    };
+
+    match program.text.as_str() {
+        "cd" => match rest.first().filter(|d| d.literal && d.text != "-") {
+            Some(dir) => {
+                *cwd = resolve_path(cwd, &dir.text);
+                known_dirs.add(cwd);
+            }
+            None => return false,
+        },
+        "mkdir" => {
+            let mut args = rest.iter();
+            while let Some(arg) = args.next() {
+                if arg.text == "-m" {
+                    // Mode operand
+                    args.next();
+                } else if !arg.text.starts_with('-') && arg.literal {
+                    known_dirs.add(&resolve_path(cwd, &arg.text));
+                }
+            }
+        }
+        "rm" | "unlink" => {
+            for path in operands(rest) {
+                ops.push(deleted(cwd, &path.text));
+            }
+        }
+        "mv" => analyze_move(rest, cwd, ops, known_dirs),
+        "tee" => {
+            for path in operands(rest) {
+                if !path.text.starts_with("/dev/") {
+                    ops.push(modified(cwd, &path.text));
+                }
+            }
+        }
+        "git" => analyze_git(rest, cwd, ops, known_dirs),
+        _ => {}
+    }
+
+    true
+}
+
+/// The words of a command after variable assignments and wrappers like `sudo`.
+fn command_args(command: &SimpleCommand) -> Vec<&Word> {
+    command
+        .words
+        .iter()
+        .skip_while(|w| is_assignment(&w.text) || COMMAND_WRAPPERS.contains(&w.text.as_str()))
+        .collect()
+}
+
+/// Skip `git` global options, returning the working directory (None if `-C` can't be
+/// resolved), the subcommand and its arguments.
+fn git_subcommand<'a, 'w>(
+    args: &'a [&'w Word],
+    cwd: &Path,
+) -> (Option<PathBuf>, Option<&'w Word>, &'a [&'w Word]) {
+    let mut git_cwd = Some(cwd.to_path_buf());
+    let mut i = 0;
+
+    while i < args.len() && args[i].text.starts_with('-') {
+        match args[i].text.as_str() {
+            "-C" => {
+                git_cwd = match (git_cwd, args.get(i + 1)) {
+                    (Some(current), Some(dir)) if dir.literal => {
+                        Some(resolve_path(&current, &dir.text))
+                    }
+                    _ => None,
+                };
+                i += 2;
+            }
+            "-c" => i += 2,
+            _ => i += 1,
+        }
+    }
+
+    match args.get(i) {
+        Some(subcommand) => (git_cwd, Some(*subcommand), &args[i + 1..]),
+        None => (git_cwd, None, &[]),
+    }
+}
+
+/// Analyze `git` global options and the `rm` / `mv` subcommands.
+fn analyze_git(args: &[&Word], cwd: &Path, ops: &mut Vec<ShellFileOp>, known_dirs: &KnownDirs) {
+    let (Some(git_cwd), Some(subcommand), rest) = git_subcommand(args, cwd) else {
+        return;
+    };
+
+    match subcommand.text.as_str() {
+        "rm" => {
+            // --cached only untracks the file; it stays on disk
+            if rest.iter().any(|w| w.text == "--cached") {
+                return;
+            }
+            for path in operands(rest) {
+                ops.push(deleted(&git_cwd, &path.text));
+            }
+        }
+        "mv" => analyze_move(rest, &git_cwd, ops, known_dirs),
+        _ => {}
+    }
+}
+
+/// The first message of `git commit` arguments (`-m msg`, `-mmsg`, `-am msg`,
+/// `--message msg`, `--message=msg`). None if absent or not literal.
+fn commit_message(args: &[&Word]) -> Option<String> {
+    let literal = |word: &Word, text: &str| word.literal.then(|| text.to_string());
+    let mut i = 0;
+
+    while i < args.len() {
+        let word = args[i];
+        let text = word.text.as_str();
+        if text == "--" {
+            break;
+        }
+        if text == "--message" {
+            return args.get(i + 1).and_then(|w| literal(w, &w.text));
+        }
+        if let Some(message) = text.strip_prefix("--message=") {
+            return literal(word, message);
+        }
+        if let Some(flags) = text.strip_prefix('-').filter(|f| !f.starts_with('-')) {
+            // Stop at short options that take their own value (-F file, -C commit, ...)
+            for (pos, flag) in flags.char_indices() {
+                match flag {
+                    'm' => {
+                        let attached = &flags[pos + 1..];
+                        return if attached.is_empty() {
+                            args.get(i + 1).and_then(|w| literal(w, &w.text))
+                        } else {
+                            literal(word, attached)
+                        };
+                    }
+                    'F' | 'C' | 'c' | 't' => break,
+                    _ => {}
+                }
+            }
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Analyze `mv` / `git mv` arguments.
+///
+/// The destination is a directory when named by `-t`, when there are several sources,
+/// when it ends with `/`, or when it is one of the session's `known_dirs`.
+fn analyze_move(args: &[&Word], cwd: &Path, ops: &mut Vec<ShellFileOp>, known_dirs: &KnownDirs) {
+    let mut target_dir: Option<Word> = None;
+    let mut no_target_dir = false;
+    let mut paths: Vec<&Word> = Vec::new();
+    let mut end_of_options = false;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        if end_of_options || !arg.text.starts_with('-') || arg.text == "-" {
+            paths.push(arg);
+        } else if arg.text == "--" {
+            end_of_options = true;
+        } else if arg.text == "-t" {
+            target_dir = iter.next().map(|dir| (*dir).clone());
+        } else if let Some(dir) = arg.text.strip_prefix("--target-directory=") {
+            target_dir = Some(Word {
+                text: dir.to_string(),
+                literal: arg.literal,
+            });
+        } else if arg.text == "-T" || arg.text == "--no-target-directory" {
+            no_target_dir = true;
+        }
+    }
+
+    if let Some(dir) = target_dir {
+        return analyze_move_into(&paths, &dir, cwd, ops);
+    }
+
+    let Some((dest, sources)) = paths.split_last() else {
+        return;
+    };
+    if sources.is_empty() {
+        return;
+    }
+
+    let dest_is_dir = !no_target_dir
+        && (sources.len() > 1
+            || dest.text.ends_with('/')
+            || known_dirs.contains(&resolve_path(cwd, &dest.text)));
+
+    if dest_is_dir {
+        analyze_move_into(sources, dest, cwd, ops);
+    } else if dest.literal && sources[0].literal {
+        ops.push(renamed(cwd, &sources[0].text, &dest.text));
+    }
+}
+
+/// Record moves of each source into directory `dir`.
+fn analyze_move_into(sources: &[&Word], dir: &Word, cwd: &Path, ops: &mut Vec<ShellFileOp>) {
+    if !dir.literal {
+        return;
+    }
+    for source in sources.iter().filter(|s| s.literal) {
+        let Some(name) = Path::new(source.text.trim_end_matches('/')).file_name() else {
+            continue;
+        };
+        let dest = resolve_path(cwd, &dir.text).join(name);
+        ops.push(renamed(cwd, &source.text, &dest.to_string_lossy()));
+    }
+}
+
+/// Literal operands of a command, skipping options (`-x`, `--long`) before `--`.
+fn operands<'a>(args: &[&'a Word]) -> Vec<&'a Word> {
+    let mut result = Vec::new();
+    let mut end_of_options = false;
+    for arg in args {
+        if !end_of_options && arg.text == "--" {
+            end_of_options = true;
+        } else if !end_of_options && arg.text.starts_with('-') && arg.text.len() > 1 {
+            continue;
+        } else if arg.literal {
+            result.push(*arg);
+        }
+    }
+    result
+}
+
+/// Check whether a word is a `NAME=value` assignment.
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+/// Resolve `path` against `cwd` and normalize `.` and `..` components lexically.
+fn resolve_path(cwd: &Path, path: &str) -> PathBuf {
+    let joined = cwd.join(path);
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+fn deleted(cwd: &Path, path: &str) -> ShellFileOp {
+    ShellFileOp {
+        path: resolve_path(cwd, path).to_string_lossy().to_string(),
+        kind: ShellFileOpKind::Deleted,
+    }
+}
+
+fn modified(cwd: &Path, path: &str) -> ShellFileOp {
+    ShellFileOp {
+        path: resolve_path(cwd, path).to_string_lossy().to_string(),
+        kind: ShellFileOpKind::Modified,
+    }
+}
+
+fn renamed(cwd: &Path, from: &str, to: &str) -> ShellFileOp {
+    ShellFileOp {
+        path: resolve_path(cwd, to).to_string_lossy().to_string(),
+        kind: ShellFileOpKind::Renamed {
+            from: resolve_path(cwd, from).to_string_lossy().to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(command: &str) -> Vec<String> {
+        analyze_command(command, "/proj", &mut KnownDirs::default())
+            .into_iter()
+            .map(|op| match op.kind {
+                ShellFileOpKind::Deleted => format!("D {}", op.path),
+                ShellFileOpKind::Modified => format!("M {}", op.path),
+                ShellFileOpKind::Renamed { from } => format!("R {} -> {}", from, op.path),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_deletions() {
+        assert_eq!(
+            summary("rm -f src/a.rs ./b.rs && git rm --quiet -- -odd.txt; unlink /proj/c.txt"),
+            vec![
+                "D /proj/src/a.rs",
+                "D /proj/b.rs",
+                "D /proj/-odd.txt",
+                "D /proj/c.txt"
+            ]
+        );
+        assert!(summary("git rm --cached secrets.env").is_empty());
+        assert!(summary("rm -rf *.log $TMPDIR/out build-$(date)").is_empty());
+    }
+
+    #[test]
+    fn test_renames() {
+        assert_eq!(
+            summary("mv old.rs new.rs && git mv -f 'a b.rs' src/"),
+            vec![
+                "R /proj/old.rs -> /proj/new.rs",
+                "R /proj/a b.rs -> /proj/src/a b.rs"
+            ]
+        );
+        assert_eq!(
+            summary("mv -t dest x.rs y/z.rs"),
+            vec![
+                "R /proj/x.rs -> /proj/dest/x.rs",
+                "R /proj/y/z.rs -> /proj/dest/z.rs"
+            ]
+        );
+        assert_eq!(
+            summary("git -C sub mv one.txt ../two.txt"),
+            vec!["R /proj/sub/one.txt -> /proj/two.txt"]
+        );
+    }
+
+    #[test]
+    fn test_move_into_known_directories() {
+        let mut known_dirs = KnownDirs::default();
+        let mut summary = |command: &str| -> Vec<String> {
+            analyze_command(command, "/proj", &mut known_dirs)
+                .into_iter()
+                .map(|op| match op.kind {
+                    ShellFileOpKind::Renamed { from } => format!("R {} -> {}", from, op.path),
+                    _ => op.path,
+                })
+                .collect()
+        };
+
+        // Nothing is known about "docs" yet, so this is a rename
+        assert_eq!(summary("mv a.md docs"), vec!["R /proj/a.md -> /proj/docs"]);
+        // Directories created earlier in the session (and their parents) are move targets
+        assert_eq!(
+            summary("mkdir -p -m 755 out/logs && mv x.log out/logs"),
+            vec!["R /proj/x.log -> /proj/out/logs/x.log"]
+        );
+        assert_eq!(
+            summary("mv y.log out"),
+            vec!["R /proj/y.log -> /proj/out/y.log"]
+        );
+        // As are parents of files the session touched
+        assert_eq!(
+            summary("echo hi > lib/util/notes.txt"),
+            vec!["/proj/lib/util/notes.txt"]
+        );
+        assert_eq!(
+            summary("mv z.rs lib; mv -T w.rs lib/util"),
+            vec![
+                "R /proj/z.rs -> /proj/lib/z.rs",
+                "R /proj/w.rs -> /proj/lib/util"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redirections_and_tee() {
+        assert_eq!(
+            summary("cargo test 2>&1 | tee out.log > /dev/null; echo hi >> notes.md 2>err.txt"),
+            vec!["M /proj/out.log", "M /proj/notes.md", "M /proj/err.txt"]
+        );
+        assert_eq!(summary("cat <input.txt &>all.log"), vec!["M /proj/all.log"]);
+    }
+
+    #[test]
+    fn test_heredoc_quotes_and_comments() {
+        let command = "cat > script.sh <<'EOF'\nrm -rf /proj/src\nmv a b\nEOF\necho \"rm x\" # rm y\nrm \"quoted name.txt\"";
+        assert_eq!(
+            summary(command),
+            vec!["M /proj/script.sh", "D /proj/quoted name.txt"]
+        );
+    }
+
+    #[test]
+    fn test_git_commits() {
+        let messages = |command: &str| -> Vec<Option<String>> {
+            find_git_commits(command)
+                .into_iter()
+                .map(|call| call.message)
+                .collect()
+        };
+
+        assert_eq!(
+            messages("git add -A && git commit -am 'Fix parser' && git push"),
+            vec![Some("Fix parser".to_string())]
+        );
+        assert_eq!(
+            messages("git -C sub commit --message=\"Add x\"; git commit -mTidy"),
+            vec![Some("Add x".to_string()), Some("Tidy".to_string())]
+        );
+        assert_eq!(
+            messages("git commit -F msg.txt; git commit -m \"$(cat <<'EOF'\nBody\nEOF\n)\""),
+            vec![None, None]
+        );
+        assert!(messages("echo git commit -m x; git log --oneline").is_empty());
+    }
+
+    #[test]
+    fn test_cd_is_followed() {
+        assert_eq!(
+            summary("cd src/../lib && rm mod.rs; cd \"$HOME\" && rm x"),
+            vec!["D /proj/lib/mod.rs"]
+        );
+        assert_eq!(
+            summary("FOO=1 sudo rm ../outside.txt"),
+            vec!["D /outside.txt"]
+        );
+    }
+}
@@ -0,0 +1,426 @@
+//! Token usage and cost of sessions.
+//!
+//! Assistant lines carry the model and the token usage of the API response they belong
+//! to. A response streamed as several lines (one per content block) repeats its usage on
+//! each of them, so usage is counted once per message ID, from its last line.
+//!
+//! A session's usage includes its sub-agents, whose API calls are billed like the
+//! session's own; only the session's own responses make up its context timeline.
+//!
+//! Costs come from a `PriceTable` of per-model prices. The defaults are Anthropic's list
+//! prices; the frontend lets users edit the table and passes it with each request.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::claude_code::{
+    get_session_file_path, get_session_subagent_files, parse_session_event, wildcard_match,
+    SessionEvent, TokenUsage,
+};
+
+/// Model name Claude Code writes on messages it generates itself, without an API call.
+const SYNTHETIC_MODEL: &str = "<synthetic>";
+
+/// Prices of a model, in USD per million tokens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    /// Model IDs the price applies to; `*` matches any run of characters
+    pub model: String,
+    /// Uncached input tokens
+    pub input: f64,
+    /// Generated tokens
+    pub output: f64,
+    /// Input tokens read from the prompt cache
+    pub cache_read: f64,
+    /// Input tokens written to the prompt cache
+    pub cache_creation: f64,
+}
+
+impl ModelPrice {
+    fn new(model: &str, input: f64, output: f64, cache_read: f64, cache_creation: f64) -> Self {
+        Self {
+            model: model.to_string(),
+            input,
+            output,
+            cache_read,
+            cache_creation,
+        }
+    }
+
+    /// Cost of some usage at these prices, in USD.
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.input_tokens as f64 * self.input
+            + usage.output_tokens as f64 * self.output
+            + usage.cache_read_tokens as f64 * self.cache_read
+            + usage.cache_creation_tokens as f64 * self.cache_creation)
+            / 1_000_000.0
+    }
+}
+
+/// Per-model prices, matched in order (put specific patterns before general ones).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PriceTable {
+    pub prices: Vec<ModelPrice>,
+}
+
+impl Default for PriceTable {
+    /// Anthropic's list prices for Claude models (cache writes at the 5-minute rate).
+    fn default() -> Self {
+        Self {
+            prices: vec![
+                ModelPrice::new("claude-opus-4-5*", 5.0, 25.0, 0.5, 6.25),
+                ModelPrice::new("claude-opus-4*", 15.0, 75.0, 1.5, 18.75),
+                ModelPrice::new("claude-sonnet-4*", 3.0, 15.0, 0.3, 3.75),
+                ModelPrice::new("claude-haiku-4*", 1.0, 5.0, 0.1, 1.25),
+                ModelPrice::new("claude-3-7-sonnet*", 3.0, 15.0, 0.3, 3.75),
+                ModelPrice::new("claude-3-5-sonnet*", 3.0, 15.0, 0.3, 3.75),
+                ModelPrice::new("claude-3-5-haiku*", 0.8, 4.0, 0.08, 1.0),
+                ModelPrice::new("claude-3-opus*", 15.0, 75.0, 1.5, 18.75),
+                ModelPrice::new("claude-3-haiku*", 0.25, 1.25, 0.03, 0.3),
+            ],
+        }
+    }
+}
+
+impl PriceTable {
+    /// The first price whose pattern matches a model ID.
+    pub fn price_for(&self, model: &str) -> Option<&ModelPrice> {
+        self.prices
+            .iter()
+            .find(|price| wildcard_match(&price.model, model))
+    }
+}
+
+/// Usage of one model over a session.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelUsage {
+    pub model: String,
+    /// API responses from the model
+    pub responses: u32,
+    pub usage: TokenUsage,
+    /// Cost in USD (None if the model has no price)
+    pub cost: Option<f64>,
+}
+
+/// One API response of a session, for charting context growth.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsagePoint {
+    /// Line of the response's first event
+    pub sequence: u32,
+    pub timestamp: Option<String>,
+    pub model: String,
+    /// Prompt size of the request: the context in use before the response
+    pub context_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost of the session's own responses up to and including this one, in USD
+    pub cumulative_cost: f64,
+}
+
+/// Token usage and cost of a session, including its sub-agents.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionUsage {
+    /// Totals per model, most expensive first
+    pub models: Vec<ModelUsage>,
+    /// Totals across all models
+    pub total: TokenUsage,
+    /// Cost of the models that have a price, in USD
+    pub total_cost: f64,
+    /// Models without a price (their tokens are not in `total_cost`)
+    pub unpriced_models: Vec<String>,
+    /// Every API response of the session itself, in file order
+    pub timeline: Vec<UsagePoint>,
+    /// Sub-agents whose usage is included
+    pub subagents: u32,
+    /// API responses of those sub-agents
+    pub subagent_responses: u32,
+}
+
+/// An API response and the usage from its last line.
+struct Response {
+    sequence: u32,
+    timestamp: Option<String>,
+    model: String,
+    usage: TokenUsage,
+}
+
+/// Get the token usage and cost of a session and its sub-agents, priced with `prices`.
+pub fn get_session_usage(
+    project_path: &str,
+    session_id: &str,
+    prices: &PriceTable,
+) -> Result<SessionUsage, String> {
+    let session_file = get_session_file_path(project_path, session_id)
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+    let session = file_responses(&session_file)?;
+
+    // A sub-agent file that cannot be read is left out rather than failing the session
+    let subagents: Vec<Vec<Response>> = get_session_subagent_files(project_path, session_id)
+        .iter()
+        .filter_map(|(_, path)| file_responses(path).ok())
+        .collect();
+
+    let mut usage = summarize_usage(session, subagents.iter().flatten(), prices);
+    usage.subagents = subagents.len() as u32;
+    Ok(usage)
+}
+
+/// Read the API responses of a session or sub-agent file.
+fn file_responses(file: &Path) -> Result<Vec<Response>, String> {
+    let file = File::open(file).map_err(|e| format!("Failed to open session file: {}", e))?;
+    let mut reader = BufReader::new(file);
+
+    let mut events = Vec::new();
+    let mut buffer = Vec::new();
+    for sequence in 0.. {
+        buffer.clear();
+        let read = reader
+            .read_until(b'\n', &mut buffer)
+            .map_err(|e| format!("Failed to read session file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        // A line with invalid UTF-8 (e.g. cut-off tool output) still holds valid usage
+        let line = String::from_utf8_lossy(&buffer);
+        // Only assistant lines with usage need parsing
+        if line.contains("\"usage\"") {
+            events.extend(parse_session_event(line.trim_end(), sequence, 0));
+        }
+    }
+    Ok(collect_responses(events))
+}
+
+/// The API responses among a file's events, in file order, counting each message ID once.
+fn collect_responses(events: impl IntoIterator<Item = SessionEvent>) -> Vec<Response> {
+    let mut responses: Vec<Response> = Vec::new();
+    let mut response_for_message: HashMap<String, usize> = HashMap::new();
+    for event in events {
+        let Some(usage) = event.usage else {
+            continue;
+        };
+        let model = event.model.unwrap_or_else(|| "unknown".to_string());
+        if model == SYNTHETIC_MODEL {
+            continue;
+        }
+        if let Some(&at) = event
+            .message_id
+            .as_ref()
+            .and_then(|id| response_for_message.get(id))
+        {
+            responses[at].usage = usage;
+            continue;
+        }
+        if let Some(id) = event.message_id {
+            response_for_message.insert(id, responses.len());
+        }
+        responses.push(Response {
+            sequence: event.sequence,
+            timestamp: event.timestamp,
+            model,
+            usage,
+        });
+    }
+    responses
+}
+
+/// Aggregate the usage of a session's responses and its sub-agents' per model, and the
+/// session's own over time.
+fn summarize_usage<'a>(
+    session: Vec<Response>,
+    subagents: impl Iterator<Item = &'a Response>,
+    prices: &PriceTable,
+) -> SessionUsage {
+    let mut summary = SessionUsage::default();
+    let mut models: HashMap<String, ModelUsage> = HashMap::new();
+    // Add a response to the per-model and overall totals
+    let mut add = |summary: &mut SessionUsage, response: &Response| {
+        let price = prices.price_for(&response.model);
+        let model = models
+            .entry(response.model.clone())
+            .or_insert_with(|| ModelUsage {
+                model: response.model.clone(),
+                responses: 0,
+                usage: TokenUsage::default(),
+                cost: price.map(|_| 0.0),
+            });
+        model.responses += 1;
+        model.usage.add(&response.usage);
+        if let (Some(price), Some(cost)) = (price, model.cost.as_mut()) {
+            let response_cost = price.cost(&response.usage);
+            *cost += response_cost;
+            summary.total_cost += response_cost;
+        }
+        summary.total.add(&response.usage);
+    };
+
+    // The session's own responses come first, so the timeline's costs are its own
+    for response in session {
+        add(&mut summary, &response);
+        summary.timeline.push(UsagePoint {
+            sequence: response.sequence,
+            timestamp: response.timestamp,
+            model: response.model,
+            context_tokens: response.usage.context_tokens(),
+            output_tokens: response.usage.output_tokens,
+            cumulative_cost: summary.total_cost,
+        });
+    }
+    for response in subagents {
+        add(&mut summary, response);
+        summary.subagent_responses += 1;
+    }
+
+    summary.models = models.into_values().collect();
+    summary.models.sort_by(|a, b| {
+        b.cost
+            .unwrap_or(0.0)
+            .total_cmp(&a.cost.unwrap_or(0.0))
+            .then_with(|| a.model.cmp(&b.model))
+    });
+    summary.unpriced_models = summary
+        .models
+        .iter()
+        .filter(|m| m.cost.is_none())
+        .map(|m| m.model.clone())
+        .collect();
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn assistant(id: &str, model: &str, input: u64, cache_read: u64, output: u64) -> String {
+        format!(
+            r#"{{"type":"assistant","timestamp":"2025-10-05T09:00:00Z","message":{{"id":"{}","model":"{}","content":[],"usage":{{"input_tokens":{},"cache_creation_input_tokens":100,"cache_read_input_tokens":{},"output_tokens":{}}}}}}}"#,
+            id, model, input, cache_read, output
+        )
+    }
+
+    #[test]
+    fn test_session_usage() {
+        let lines = [
+            r#"{"type":"user","message":{"role":"user","content":"Fix the bug"}}"#.to_string(),
+            // One streamed response: thinking, then text with the final output count
+            assistant("msg_1", "claude-sonnet-4-5-20250929", 10, 1000, 5),
+            assistant("msg_1", "claude-sonnet-4-5-20250929", 10, 1000, 50),
+            assistant("msg_2", "claude-sonnet-4-5-20250929", 20, 3000, 200),
+            assistant("msg_3", "claude-opus-4-1-20250805", 1_000_000, 0, 0),
+            assistant("msg_4", "gpt-5", 7, 0, 3),
+            assistant("msg_5", SYNTHETIC_MODEL, 0, 0, 0),
+        ];
+        let event = parse_session_event(&lines[2], 2, 0).unwrap();
+        assert_eq!(event.model.as_deref(), Some("claude-sonnet-4-5-20250929"));
+        assert_eq!(event.message_id.as_deref(), Some("msg_1"));
+        assert_eq!(
+            event.usage,
+            Some(TokenUsage {
+                input_tokens: 10,
+                output_tokens: 50,
+                cache_read_tokens: 1000,
+                cache_creation_tokens: 100,
+            })
+        );
+        assert!(parse_session_event(&lines[0], 0, 0)
+            .unwrap()
+            .usage
+            .is_none());
+
+        let events = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| parse_session_event(line, i as u32, 0));
+        let summary = summarize_usage(
+            collect_responses(events),
+            std::iter::empty(),
+            &PriceTable::default(),
+        );
+
+        let models: Vec<(&str, u32)> = summary
+            .models
+            .iter()
+            .map(|m| (m.model.as_str(), m.responses))
+            .collect();
+        assert_eq!(
+            models,
+            vec![
+                ("claude-opus-4-1-20250805", 1),
+                ("claude-sonnet-4-5-20250929", 2),
+                ("gpt-5", 1),
+            ]
+        );
+        assert_eq!(summary.unpriced_models, vec!["gpt-5"]);
+        let sonnet = &summary.models[1].usage;
+        assert_eq!((sonnet.input_tokens, sonnet.output_tokens), (30, 250));
+
+        // 1M opus input tokens plus the cache writes, at opus 4.1 prices
+        let opus_cost = summary.models[0].cost.unwrap();
+        assert!((opus_cost - (15.0 + 100.0 * 18.75 / 1e6)).abs() < 1e-9);
+        let sonnet_cost = (30.0 * 3.0 + 250.0 * 15.0 + 4000.0 * 0.3 + 200.0 * 3.75) / 1e6;
+        assert!((summary.total_cost - opus_cost - sonnet_cost).abs() < 1e-9);
+
+        let context: Vec<(u32, u64)> = summary
+            .timeline
+            .iter()
+            .map(|p| (p.sequence, p.context_tokens))
+            .collect();
+        assert_eq!(
+            context,
+            vec![(1, 1110), (3, 3120), (4, 1_000_100), (5, 107)]
+        );
+        assert_eq!(summary.timeline[3].cumulative_cost, summary.total_cost);
+
+        // User prices override the defaults
+        let prices = PriceTable {
+            prices: vec![ModelPrice::new("gpt-*", 1.0, 1.0, 0.0, 0.0)],
+        };
+        let events = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| parse_session_event(line, i as u32, 0));
+        let summary = summarize_usage(collect_responses(events), std::iter::empty(), &prices);
+        assert_eq!(summary.unpriced_models.len(), 2);
+        assert!((summary.total_cost - 10.0 / 1e6).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_file_responses_and_subagent_totals() {
+        let dir = TempDir::new("usage");
+        let session_file = dir.join("session.jsonl");
+        let agent_file = dir.join("agent-a1.jsonl");
+
+        // A line with invalid UTF-8 does not hide the lines after it
+        let mut content = assistant("msg_1", "claude-sonnet-4-5-20250929", 10, 0, 5).into_bytes();
+        content
+            .extend_from_slice(b"\n{\"type\":\"user\",\"message\":{\"content\":\"\xff\xfe\"}}\n");
+        content.extend(assistant("msg_2", "claude-sonnet-4-5-20250929", 20, 0, 5).into_bytes());
+        content.push(b'\n');
+        std::fs::write(&session_file, content).unwrap();
+        std::fs::write(
+            &agent_file,
+            assistant("msg_3", "claude-haiku-4-5-20251001", 30, 0, 5) + "\n",
+        )
+        .unwrap();
+
+        let session = file_responses(&session_file).unwrap();
+        let sequences: Vec<u32> = session.iter().map(|r| r.sequence).collect();
+        assert_eq!(sequences, vec![0, 2]);
+
+        let subagent = file_responses(&agent_file).unwrap();
+        let summary = summarize_usage(session, subagent.iter(), &PriceTable::default());
+        assert_eq!(summary.total.input_tokens, 60);
+        assert_eq!(summary.subagent_responses, 1);
+        assert_eq!(summary.models.len(), 2);
+        // Only the session's own responses are on its timeline
+        assert_eq!(summary.timeline.len(), 2);
+        assert!(summary.timeline[1].cumulative_cost < summary.total_cost);
+    }
+}
@@ -6,12 +6,16 @@
 use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebouncedEventKind};
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Duration;
 use tauri::{AppHandle, Emitter};
 
+use crate::claude_code::{get_session_file_path, get_subagent_file_path};
 use crate::session_index::{
-    build_session_index, update_index_incremental, IndexStatus, SessionIndex, UpdateResult,
+    load_index_cache, open_session_index, persist_evicted_indices, save_index_cache,
+    update_index_incremental, FileHistoryEntry, IndexCacheStats, IndexRegistry, IndexStatus,
+    ProjectEditIndex, SessionIndex, TermIndexStore, UpdateResult, DEFAULT_INDEX_MEMORY_BUDGET,
+    DEFAULT_TERM_INDEX_MEMORY_BUDGET,
 };
 
 /// Event payload sent to the frontend when a session file changes.
@@ -43,9 +47,17 @@ pub struct IndexReadyPayload {
 pub struct WatcherState {
     /// Map of "project_path:session_id" -> watcher handle (for cleanup)
     watchers: Mutex<HashMap<String, WatcherHandle>>,
-    /// Map of "project_path:session_id" -> session index (for fast lookups)
+    /// Registry of "project_path:session_id" -> session index (for fast lookups), and of
+    /// the search term indices, each bounded by a memory budget with LRU eviction.
     /// Wrapped in Arc so it can be shared with background indexing threads
-    indices: Arc<Mutex<HashMap<String, SessionIndex>>>,
+    indices: Arc<Mutex<IndexRegistry>>,
+    /// Directory for the persistent index cache (None disables caching)
+    index_cache_dir: Option<PathBuf>,
+    /// Map of project_path -> edits of all its sessions, built on first file history request
+    /// and kept in sync with watched sessions
+    project_edits: Arc<Mutex<HashMap<String, ProjectEditIndex>>>,
+    /// Term indices for search, loaded into `indices` for watched and recently searched files
+    term_indices: Arc<TermIndexStore>,
 }
 
 struct WatcherHandle {
@@ -54,54 +66,180 @@ struct WatcherHandle {
 }
 
 impl WatcherState {
-    pub fn new() -> Self {
+    pub fn new(index_cache_dir: Option<PathBuf>, search_index_dir: Option<PathBuf>) -> Self {
+        let indices = Arc::new(Mutex::new(
+            IndexRegistry::new(
+                DEFAULT_INDEX_MEMORY_BUDGET,
+                DEFAULT_TERM_INDEX_MEMORY_BUDGET,
+            )
+            .with_cache_dirs(index_cache_dir.clone(), search_index_dir.clone()),
+        ));
         Self {
             watchers: Mutex::new(HashMap::new()),
-            indices: Arc::new(Mutex::new(HashMap::new())),
+            indices: Arc::clone(&indices),
+            index_cache_dir,
+            project_edits: Arc::new(Mutex::new(HashMap::new())),
+            term_indices: Arc::new(TermIndexStore::new(search_index_dir, indices)),
         }
     }
 
+    /// Term indices used by searches.
+    pub fn term_indices(&self) -> &TermIndexStore {
+        &self.term_indices
+    }
+
+    /// Get a clone of the term indices Arc for sharing with background searches.
+    pub fn term_indices_arc(&self) -> Arc<TermIndexStore> {
+        Arc::clone(&self.term_indices)
+    }
+
     /// Get a clone of the indices Arc for sharing with background threads.
-    fn indices_arc(&self) -> Arc<Mutex<HashMap<String, SessionIndex>>> {
+    fn indices_arc(&self) -> Arc<Mutex<IndexRegistry>> {
         Arc::clone(&self.indices)
     }
 
     /// Get the index for a session, if it exists.
+    /// Indices evicted while their session is still watched are reloaded from the disk cache.
     pub fn get_index(&self, project_path: &str, session_id: &str) -> Option<SessionIndex> {
         let key = format!("{}:{}", project_path, session_id);
-        let indices = self.indices.lock().ok()?;
-        indices.get(&key).cloned()
+        {
+            let mut indices = self.indices.lock().ok()?;
+            if let Some(index) = indices.get(&key) {
+                return Some(index);
+            }
+        }
+        self.reload_evicted_index(&key, project_path, session_id)
+    }
+
+    /// Get the index for a session, building (or loading from the disk cache) one that isn't loaded.
+    /// Indices opened this way are not registered, so sessions that aren't watched stay unloaded.
+    pub fn get_or_open_index(
+        &self,
+        project_path: &str,
+        session_id: &str,
+    ) -> Result<SessionIndex, String> {
+        if let Some(index) = self.get_index(project_path, session_id) {
+            return Ok(index);
+        }
+        let session_file = get_session_file_path(project_path, session_id)
+            .ok_or_else(|| format!("Session file not found for {}", session_id))?;
+        open_session_index(self.index_cache_dir.as_deref(), &session_file, project_path)
+    }
+
+    /// Get every edit any session of a project made to a file, oldest first.
+    pub fn get_file_history(
+        &self,
+        project_path: &str,
+        file_path: &str,
+    ) -> Result<Vec<FileHistoryEntry>, String> {
+        let projects = self.refresh_project_edits(project_path)?;
+        Ok(projects[project_path].file_history(file_path))
+    }
+
+    /// Get the sessions of a project that edited each file at or after `since` (ISO 8601).
+    pub fn get_recent_file_editors(
+        &self,
+        project_path: &str,
+        since: &str,
+    ) -> Result<HashMap<String, Vec<String>>, String> {
+        let projects = self.refresh_project_edits(project_path)?;
+        Ok(projects[project_path].recent_editors(since))
+    }
+
+    /// Bring a project's edit index up to date and return the locked project indices.
+    ///
+    /// Changed sessions are indexed with the lock released, so the watcher can keep
+    /// updating live sessions while a large project is indexed for the first time.
+    fn refresh_project_edits(
+        &self,
+        project_path: &str,
+    ) -> Result<MutexGuard<'_, HashMap<String, ProjectEditIndex>>, String> {
+        let pending = {
+            let mut projects = self.project_edits.lock().map_err(|e| e.to_string())?;
+            projects
+                .entry(project_path.to_string())
+                .or_default()
+                .begin_refresh(project_path)
+        };
+
+        let refreshed = pending.run(self.index_cache_dir.as_deref(), |session_id| {
+            self.get_index(project_path, session_id)
+        });
+
+        let mut projects = self.project_edits.lock().map_err(|e| e.to_string())?;
+        projects
+            .entry(project_path.to_string())
+            .or_default()
+            .finish_refresh(refreshed);
+        Ok(projects)
     }
 
     /// Get the index status for a session.
+    /// Indices evicted while their session is still watched are reloaded from the disk cache,
+    /// since nothing else would rebuild them and the status would stay "building".
     pub fn get_index_status(&self, project_path: &str, session_id: &str) -> IndexStatus {
         let key = format!("{}:{}", project_path, session_id);
-        let indices = match self.indices.lock() {
-            Ok(i) => i,
+        let status = match self.indices.lock() {
+            Ok(indices) => indices.status(&key),
             Err(_) => return IndexStatus::error("Failed to lock indices"),
         };
 
-        match indices.get(&key) {
-            Some(index) => index.to_status(),
-            None => IndexStatus::building(),
+        match status {
+            Some(status) => status,
+            None => match self.reload_evicted_index(&key, project_path, session_id) {
+                Some(index) => index.to_status(),
+                None => IndexStatus::building(),
+            },
         }
     }
-}
 
-/// Get the session file path for watching.
-fn get_session_file_path(project_path: &str, session_id: &str) -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    let encoded_name = project_path.replace('/', "-");
-    let session_file = home
-        .join(".claude")
-        .join("projects")
-        .join(&encoded_name)
-        .join(format!("{}.jsonl", session_id));
-
-    if session_file.exists() {
-        Some(session_file)
-    } else {
-        None
+    /// Get memory statistics for all loaded indices.
+    pub fn get_index_cache_stats(&self) -> Result<IndexCacheStats, String> {
+        let indices = self.indices.lock().map_err(|e| e.to_string())?;
+        Ok(indices.stats())
+    }
+
+    /// Set the memory budget for loaded session indices, evicting indices if necessary.
+    pub fn set_index_memory_budget(&self, budget_bytes: usize) -> Result<(), String> {
+        let evicted = {
+            let mut indices = self.indices.lock().map_err(|e| e.to_string())?;
+            indices.set_memory_budget(budget_bytes)
+        };
+        persist_evicted_indices(evicted);
+        Ok(())
+    }
+
+    /// Reload an index for a watched session from the disk cache.
+    /// Returns None if the session is not watched or has no valid cache (e.g. still building).
+    fn reload_evicted_index(
+        &self,
+        key: &str,
+        project_path: &str,
+        session_id: &str,
+    ) -> Option<SessionIndex> {
+        let is_watched = self.watchers.lock().ok()?.contains_key(key);
+        if !is_watched {
+            return None;
+        }
+
+        let cache_dir = self.index_cache_dir.as_deref()?;
+        let session_file = get_session_file_path(project_path, session_id)?;
+        let mut index = load_index_cache(cache_dir, &session_file, project_path)?;
+        update_index_incremental(&mut index, &session_file, project_path).ok()?;
+
+        let evicted = {
+            let mut indices = self.indices.lock().ok()?;
+            indices.insert(
+                key.to_string(),
+                project_path,
+                session_id,
+                &session_file,
+                index.clone(),
+            )
+        };
+        persist_evicted_indices(evicted);
+
+        Some(index)
     }
 }
 
@@ -132,6 +270,8 @@ pub fn watch_session(
     let watcher_session_id = session_id.clone();
     let watcher_session_file = session_file.clone();
     let watcher_indices = state.indices_arc();
+    let watcher_project_edits = Arc::clone(&state.project_edits);
+    let watcher_term_indices = Arc::clone(&state.term_indices);
     let watcher_key = key.clone();
 
     // Create debounced watcher with 500ms debounce
@@ -143,31 +283,63 @@ pub fn watch_session(
                     if event.kind == DebouncedEventKind::Any {
                         // Update the index incrementally
                         if let Ok(mut indices) = watcher_indices.lock() {
-                            if let Some(index) = indices.get_mut(&watcher_key) {
-                                match update_index_incremental(
+                            let update = indices.update(&watcher_key, |index| {
+                                update_index_incremental(
                                     index,
                                     &watcher_session_file,
                                     &watcher_project_path,
-                                ) {
-                                    Ok(UpdateResult::Updated) => {
+                                )
+                                .map(|result| (result, index.total_events()))
+                            });
+                            if let Some((result, evicted)) = update {
+                                match result {
+                                    Ok((UpdateResult::Updated, total_events)) => {
                                         println!(
                                             "[session_index] Incremental update: now {} events",
-                                            index.total_events()
+                                            total_events
                                         );
                                     }
-                                    Ok(UpdateResult::Rebuilt) => {
+                                    Ok((UpdateResult::Rebuilt, total_events)) => {
                                         println!(
                                             "[session_index] Index rebuilt: {} events",
-                                            index.total_events()
+                                            total_events
                                         );
                                     }
-                                    Ok(UpdateResult::Unchanged) => {
+                                    Ok((UpdateResult::Unchanged, _)) => {
                                         // No logging for unchanged
                                     }
                                     Err(e) => {
                                         eprintln!("[session_index] Incremental update failed: {}", e);
                                     }
                                 }
+                                persist_evicted_indices(evicted);
+                            }
+                        }
+
+                        // Keep the search term index in step with the session index
+                        if let Err(e) = watcher_term_indices.update(&watcher_session_file) {
+                            eprintln!("[session_index] Term index update failed: {}", e);
+                        }
+
+                        // Keep the project's edit history in sync (lock order: project edits, then indices)
+                        if let Ok(mut projects) = watcher_project_edits.lock() {
+                            if let Some(project) = projects.get_mut(&watcher_project_path) {
+                                // Borrow the index instead of cloning it
+                                let result = watcher_indices.lock().ok().and_then(|indices| {
+                                    indices.with_index(&watcher_key, |index| {
+                                        if project.is_current(&watcher_session_id, index) {
+                                            return Ok(());
+                                        }
+                                        project.update_session(
+                                            &watcher_session_id,
+                                            &watcher_session_file,
+                                            index,
+                                        )
+                                    })
+                                });
+                                if let Some(Err(e)) = result {
+                                    eprintln!("[session_index] Project edit update failed: {}", e);
+                                }
                             }
                         }
 
@@ -206,19 +378,25 @@ pub fn watch_session(
 
     // Clone data for the background indexing thread
     let indices = state.indices_arc();
+    let index_cache_dir = state.index_cache_dir.clone();
+    let index_term_indices = Arc::clone(&state.term_indices);
     let index_app_handle = app_handle;
     let index_project_path = project_path;
     let index_session_id = session_id;
     let index_session_file = session_file;
     let index_key = key;
 
-    // Spawn background thread to build the index
+    // Spawn background thread to load (from cache) or build the index
     std::thread::spawn(move || {
-        let status = match build_session_index(&index_session_file, &index_project_path) {
+        let status = match open_session_index(
+            index_cache_dir.as_deref(),
+            &index_session_file,
+            &index_project_path,
+        ) {
             Ok(index) => {
                 // Log index stats for verification
                 println!(
-                    "[session_index] Built index for {}: {} events, {} file edits, {} files edited",
+                    "[session_index] Loaded index for {}: {} events, {} file edits, {} files edited",
                     index_session_id,
                     index.total_events(),
                     index.file_edits.len(),
@@ -227,9 +405,16 @@ pub fn watch_session(
 
                 let status = index.to_status();
 
-                // Store the index
+                // Store the index (may evict least recently used indices)
                 if let Ok(mut indices) = indices.lock() {
-                    indices.insert(index_key, index);
+                    let evicted = indices.insert(
+                        index_key,
+                        &index_project_path,
+                        &index_session_id,
+                        &index_session_file,
+                        index,
+                    );
+                    persist_evicted_indices(evicted);
                 }
 
                 status
@@ -240,6 +425,11 @@ pub fn watch_session(
             }
         };
 
+        // Load the search term index so searches of this session skip the disk cache
+        if let Err(e) = index_term_indices.load(&index_session_file) {
+            eprintln!("[session_index] Failed to load term index: {}", e);
+        }
+
         // Emit index-ready event to frontend
         let _ = index_app_handle.emit(
             "index-ready",
@@ -269,31 +459,29 @@ pub fn unwatch_session(
     }
 
     // Remove the index
-    {
+    let index = {
         let mut indices = state.indices.lock().map_err(|e| e.to_string())?;
-        indices.remove(&key);
+        indices.remove(&key)
+    };
+
+    // Persist incremental updates made while watching so the next open starts from here
+    if let Some(session_file) = get_session_file_path(project_path, session_id) {
+        let cache_dir = state.index_cache_dir.clone();
+        let term_indices = Arc::clone(&state.term_indices);
+        let project_path = project_path.to_string();
+        std::thread::spawn(move || {
+            if let (Some(index), Some(cache_dir)) = (index, cache_dir) {
+                if let Err(e) = save_index_cache(&cache_dir, &session_file, &project_path, &index) {
+                    eprintln!("[session_index] Failed to write index cache: {}", e);
+                }
+            }
+            term_indices.unload(&session_file);
+        });
     }
 
     Ok(())
 }
 
-/// Get the sub-agent file path for watching.
-fn get_subagent_file_path(project_path: &str, agent_id: &str) -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    let encoded_name = project_path.replace('/', "-");
-    let agent_file = home
-        .join(".claude")
-        .join("projects")
-        .join(&encoded_name)
-        .join(format!("agent-{}.jsonl", agent_id));
-
-    if agent_file.exists() {
-        Some(agent_file)
-    } else {
-        None
-    }
-}
-
 /// Start watching a sub-agent file for changes.
 pub fn watch_subagent(
     app_handle: AppHandle,
@@ -0,0 +1,109 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type {
+  FileSearchResult,
+  SearchOptions,
+  SearchProgress,
+  SearchProgressEvent,
+  SearchResultEvent,
+  SearchScope,
+} from "./types";
+
+interface SearchJobHandlers {
+  /** New matches in one file, streamed in batches */
+  onResult?: (result: FileSearchResult) => void;
+  /** Progress while the search runs, including the final event */
+  onProgress?: (progress: SearchProgress) => void;
+}
+
+export interface SearchJob {
+  /** Resolves with the final progress once the search is done; never settles once cancel() is called */
+  done: Promise<SearchProgress>;
+  /** Stop the search; no more handlers are called */
+  cancel: () => void;
+}
+
+/**
+ * Run a search as a background job with start_search, forwarding its
+ * "search-result" and "search-progress" events to the handlers.
+ */
+export function startSearchJob(
+  scope: SearchScope,
+  query: string,
+  options: SearchOptions | undefined,
+  maxResults: number | undefined,
+  { onResult, onProgress }: SearchJobHandlers = {}
+): SearchJob {
+  let searchId: number | null = null;
+  let stopped = false;
+  // Events that arrive before start_search returns the id
+  const pending: (SearchResultEvent | SearchProgressEvent)[] = [];
+  const unlisteners: (() => void)[] = [];
+
+  let resolve: (progress: SearchProgress) => void;
+  let reject: (err: unknown) => void;
+  const done = new Promise<SearchProgress>((res, rej) => {
+    resolve = res;
+    reject = rej;
+  });
+
+  const stop = () => {
+    stopped = true;
+    unlisteners.forEach((unlisten) => unlisten());
+    unlisteners.length = 0;
+  };
+
+  const dispatch = (event: SearchResultEvent | SearchProgressEvent) => {
+    if (stopped || event.searchId !== searchId) return;
+    if ("matches" in event) {
+      onResult?.(event);
+    } else {
+      onProgress?.(event);
+      if (event.done) {
+        stop();
+        resolve(event);
+      }
+    }
+  };
+
+  const handle = (event: SearchResultEvent | SearchProgressEvent) => {
+    if (searchId === null) {
+      pending.push(event);
+    } else {
+      dispatch(event);
+    }
+  };
+
+  async function run() {
+    // Listen before starting the search so no early event is missed
+    unlisteners.push(
+      await listen<SearchResultEvent>("search-result", (event) => handle(event.payload)),
+      await listen<SearchProgressEvent>("search-progress", (event) => handle(event.payload))
+    );
+    if (stopped) {
+      stop();
+      return;
+    }
+    searchId = await invoke<number>("start_search", { scope, query, options, maxResults });
+    if (stopped) {
+      invoke("cancel_search", { searchId });
+      return;
+    }
+    pending.splice(0).forEach(dispatch);
+  }
+
+  run().catch((err) => {
+    if (stopped) return;
+    stop();
+    reject(err instanceof Error ? err : new Error(String(err)));
+  });
+
+  return {
+    done,
+    cancel: () => {
+      if (stopped) return;
+      stop();
+      if (searchId !== null) invoke("cancel_search", { searchId });
+    },
+  };
+}
@@ -18,6 +18,8 @@ export interface Session {
   version: string | null;
   /** Git branch at time of session */
   gitBranch: string | null;
+  /** First human prompt of the session (truncated) */
+  firstMessage: string | null;
   /** Session start timestamp (ISO 8601) */
   startedAt: string | null;
   /** Last activity timestamp (ISO 8601) */
@@ -70,7 +72,10 @@ export const terminalDisplayNames: Record<TerminalType, string> = {
 };
 
 // File edit types - matches Rust structs in claude_code.rs
-export type FileEditType = "added" | "modified" | "deleted";
+export type FileEditType = "added" | "modified" | "deleted" | "renamed";
+
+/** "low" when the latest change was inferred from a Bash command */
+export type EditConfidence = "high" | "low";
 
 export interface FileEdit {
   /** Relative path from project root */
@@ -79,6 +84,10 @@ export interface FileEdit {
   editType: FileEditType;
   /** Timestamp of the last edit to this file (ISO 8601) */
   lastEditedAt: string | null;
+  /** Original relative path (renamed only) */
+  renamedFrom: string | null;
+  /** How reliably the edit was detected */
+  confidence: EditConfidence;
 }
 
 export interface FileDiff {
@@ -90,17 +99,308 @@ export interface FileDiff {
   sequence: number;
   /** Timestamp of the change (ISO 8601) */
   timestamp: string | null;
+  /** Whether the change replaces the whole file (Write) */
+  isWrite: boolean;
+  /** Whether every occurrence of oldString was replaced */
+  replaceAll: boolean;
+  /** Notebook cell ID (NotebookEdit only) */
+  cellId: string | null;
+  /** Notebook edit mode (NotebookEdit only) */
+  editMode: "replace" | "insert" | "delete" | null;
+}
+
+/** Revision a file is compared against (matches Rust GitRevision in git.rs) */
+export type GitRevision =
+  | { kind: "head" }
+  | { kind: "index" }
+  | { kind: "branch"; name: string }
+  | { kind: "commit"; id: string }
+  | { kind: "mergeBase" }
+  | { kind: "sessionStart"; gitBranch: string | null; startedAt: string };
+
+/** What a git file diff can show (matches Rust GitDiffKind) */
+export type GitDiffKind = "text" | "large" | "binary" | "image";
+
+export type TextEncoding = "utf8" | "utf8Bom" | "utf16Le" | "utf16Be" | "latin1";
+
+export interface ImageInfo {
+  /** Image format: "png", "jpeg", "gif", "bmp" or "webp" */
+  format: string;
+  width: number;
+  height: number;
+}
+
+/** What a piece of content turned out to be (matches Rust ContentInfo in file_content.rs) */
+export interface ContentInfo {
+  /** Size of the full content in bytes */
+  sizeBytes: number;
+  /** Encoding of text content (null for binary content) */
+  encoding: TextEncoding | null;
+  /** Whether the decoded text was cut off at the size cap */
+  truncated: boolean;
+  /** Format and dimensions for recognised image formats */
+  image: ImageInfo | null;
 }
 
 export interface GitFileDiff {
-  /** Content of the file at HEAD (original) */
+  /** What the diff can show; the content fields are empty for binary and image diffs */
+  kind: GitDiffKind;
+  /** Content of the file at the compared revision (original), decoded to UTF-8 */
   original: string;
-  /** Current content of the file in working directory */
+  /** Current content of the file in working directory, decoded to UTF-8 */
   current: string;
-  /** Whether the file exists at HEAD */
+  /** Whether the file exists at the compared revision */
+  existsAtHead: boolean;
+  /** Whether the file exists in working directory */
+  existsInWorkdir: boolean;
+  /** Size, encoding and image details of the original content */
+  originalInfo: ContentInfo;
+  /** Size, encoding and image details of the current content */
+  currentInfo: ContentInfo;
+}
+
+export interface GitStructuredDiff {
+  /** What the diff can show; there are no hunks for binary and image diffs */
+  kind: GitDiffKind;
+  /** Hunks computed from the (possibly truncated) text of both sides */
+  diff: StructuredDiff;
+  /** Path the original content was read from when git detects the file as renamed */
+  renamedFrom: string | null;
+  /** Whether the file (or its rename source) exists at the compared revision */
   existsAtHead: boolean;
   /** Whether the file exists in working directory */
   existsInWorkdir: boolean;
+  /** Size, encoding and image details of the original content */
+  originalInfo: ContentInfo;
+  /** Size, encoding and image details of the current content */
+  currentInfo: ContentInfo;
+}
+
+// Structured diff types - matches Rust structs in diff_engine.rs
+export interface StructuredDiffOptions {
+  /** Unchanged lines shown around each change (default 3) */
+  contextLines?: number;
+  /** Ignore whitespace when comparing lines */
+  ignoreWhitespace?: boolean;
+  /** Index of the first hunk to return */
+  hunkOffset?: number;
+  /** Maximum number of hunks to return (all when omitted) */
+  hunkLimit?: number | null;
+}
+
+export type DiffLineKind = "context" | "added" | "removed";
+
+/** A changed range within a line, in UTF-16 code units (string indices) */
+export interface WordSpan {
+  start: number;
+  end: number;
+}
+
+export interface DiffLine {
+  kind: DiffLineKind;
+  /** Line number in the old content (null for added lines) */
+  oldLine: number | null;
+  /** Line number in the new content (null for removed lines) */
+  newLine: number | null;
+  /** Line content without its line ending */
+  content: string;
+  /** Changed words within an added or removed line */
+  highlights: WordSpan[];
+}
+
+export interface DiffHunk {
+  /** First line of the hunk in the old content (1-based) */
+  oldStart: number;
+  /** Number of old content lines in the hunk */
+  oldLines: number;
+  /** First line of the hunk in the new content (1-based) */
+  newStart: number;
+  /** Number of new content lines in the hunk */
+  newLines: number;
+  lines: DiffLine[];
+}
+
+export interface StructuredDiff {
+  /** Hunks in the requested page */
+  hunks: DiffHunk[];
+  /** Index of the first returned hunk */
+  hunkOffset: number;
+  /** Number of hunks in the whole diff */
+  totalHunks: number;
+  /** Whether there are more hunks after this page */
+  hasMore: boolean;
+  /** Lines added across the whole diff */
+  additions: number;
+  /** Lines removed across the whole diff */
+  deletions: number;
+}
+
+// Repository status types - matches Rust structs in repo_status.rs
+export type FileChangeKind =
+  | "added"
+  | "modified"
+  | "deleted"
+  | "renamed"
+  | "typechange"
+  | "untracked";
+
+export interface StatusFile {
+  /** Path relative to the repository root */
+  path: string;
+  /** Previous path (renames only) */
+  oldPath: string | null;
+  /** Kind of change */
+  change: FileChangeKind;
+  /** Lines added (0 for binary files) */
+  additions: number;
+  /** Lines deleted (0 for binary files) */
+  deletions: number;
+  /** Whether git considers the file binary */
+  binary: boolean;
+  /** Sessions that edited the file recently, most recent first */
+  editedBySessions: string[];
+}
+
+export interface WorktreeInfo {
+  /** Worktree name */
+  name: string;
+  /** Absolute path of the worktree */
+  path: string;
+  /** Branch checked out in the worktree (null if detached or missing) */
+  branch: string | null;
+  /** Whether the worktree is locked */
+  locked: boolean;
+  /** Whether the worktree's directory no longer exists */
+  missing: boolean;
+}
+
+export interface RepoStatus {
+  /** Absolute path of the repository's working directory */
+  workdir: string;
+  /** Current branch (null when HEAD is detached or unborn) */
+  branch: string | null;
+  /** Abbreviated id of the HEAD commit (null before the first commit) */
+  headCommit: string | null;
+  /** Upstream branch of the current branch, e.g. "origin/main" */
+  upstream: string | null;
+  /** Commits on the branch that aren't on its upstream */
+  ahead: number;
+  /** Commits on the upstream that aren't on the branch */
+  behind: number;
+  /** Changes staged in the index */
+  staged: StatusFile[];
+  /** Changes in the working directory that aren't staged */
+  unstaged: StatusFile[];
+  /** Files not tracked by git */
+  untracked: StatusFile[];
+  /** Number of stash entries */
+  stashCount: number;
+  /** Linked worktrees (the main working directory isn't included) */
+  worktrees: WorktreeInfo[];
+}
+
+// Commit correlation types - matches Rust structs in git_correlation.rs
+export type FileCommitStatus =
+  | "committed"
+  | "partiallyCommitted"
+  | "uncommitted"
+  | "overwritten";
+
+export interface FileCommitCorrelation {
+  /** Path relative to the project root */
+  filePath: string;
+  /** Whether the session's edits were committed */
+  status: FileCommitStatus;
+  /** Commits that added lines written by the session, oldest first */
+  commits: string[];
+  /** Number of lines written by the session that are committed */
+  committedLines: number;
+  /** Number of distinct lines the session's edits left in the file */
+  sessionLines: number;
+}
+
+export interface CorrelatedCommit {
+  /** Full commit hash */
+  id: string;
+  /** First line of the commit message */
+  summary: string;
+  /** Author name */
+  author: string;
+  /** Commit time (ISO 8601) */
+  timestamp: string;
+  /** Whether the agent made the commit through a Bash `git commit` call */
+  byAgent: boolean;
+  /** Session files the commit added lines to (relative to the project root) */
+  files: string[];
+}
+
+export interface SessionCommitCorrelation {
+  /** One entry per edited file, sorted by path */
+  files: FileCommitCorrelation[];
+  /** Relevant commits since the session started, oldest first */
+  commits: CorrelatedCommit[];
+}
+
+// Patch export types - matches Rust structs in patch.rs
+export interface PatchFilter {
+  /** Only include these relative paths (an entry ending in "/" includes a directory) */
+  files?: string[] | null;
+  /** Only include edits made at or after this time (ISO 8601) */
+  after?: string | null;
+  /** Only include edits made at or before this time (ISO 8601) */
+  before?: string | null;
+}
+
+export interface PatchExport {
+  /** Where the patch was written */
+  outputPath: string;
+  /** Relative paths of the files in the patch */
+  files: string[];
+  /** Files that were skipped or may not apply cleanly */
+  warnings: string[];
+  /** Size of the patch in bytes */
+  sizeBytes: number;
+}
+
+// File reconstruction types - matches Rust structs in reconstruction.rs
+export type ReconstructionBase = "gitHead" | "firstWrite" | "empty";
+
+export type StepStatus = "applied" | "drift" | "skipped";
+
+export interface FileSnapshot {
+  /** Sequence number of the diff this snapshot follows (matches FileDiff.sequence) */
+  sequence: number;
+  /** Timestamp of the edit (ISO 8601) */
+  timestamp: string | null;
+  /** How the step applied */
+  status: StepStatus;
+  /** Number of replacements made (0 for drift, Write and skipped steps) */
+  replacements: number;
+  /** Full file content after this step */
+  content: string;
+}
+
+export interface FileReconstruction {
+  /** Where the replay started from */
+  base: ReconstructionBase;
+  /** Content before the first step */
+  baseContent: string;
+  /** One snapshot per diff, in order */
+  steps: FileSnapshot[];
+  /** Number of steps flagged as drift */
+  driftCount: number;
+  /** Whether the final snapshot equals the file on disk (null if the file is missing) */
+  matchesCurrent: boolean | null;
+}
+
+export interface EditStructuredDiff {
+  /** Sequence number of the diff (matches FileDiff.sequence) */
+  sequence: number;
+  /** Timestamp of the edit (ISO 8601) */
+  timestamp: string | null;
+  /** Whether line numbers refer to the whole file (false when the step couldn't be replayed) */
+  anchored: boolean;
+  diff: StructuredDiff;
 }
 
 // Session Event Log types
@@ -113,6 +413,18 @@ export interface CompactMetadata {
   preTokens: number;
 }
 
+/** Tokens billed for one API response */
+export interface TokenUsage {
+  /** Uncached input tokens */
+  inputTokens: number;
+  /** Generated tokens */
+  outputTokens: number;
+  /** Input tokens read from the prompt cache */
+  cacheReadTokens: number;
+  /** Input tokens written to the prompt cache */
+  cacheCreationTokens: number;
+}
+
 /** A single event in the session log */
 export interface SessionEvent {
   /** Sequence number (line number in file, 0-indexed) */
@@ -157,6 +469,12 @@ export interface SessionEvent {
   isToolResult: boolean;
   /** Whether this is a meta/context injection (isMeta: true) */
   isMeta: boolean;
+  /** Model that generated an assistant message */
+  model: string | null;
+  /** API message ID (shared by the lines of one streamed response, which all repeat its usage) */
+  messageId: string | null;
+  /** Tokens billed for the response (assistant messages) */
+  usage: TokenUsage | null;
 }
 
 /** Paginated response for session events */
@@ -171,6 +489,70 @@ export interface SessionEventsResponse {
   hasMore: boolean;
 }
 
+// =============================================================================
+// Token Usage Types
+// =============================================================================
+
+/** Prices of a model, in USD per million tokens */
+export interface ModelPrice {
+  /** Model IDs the price applies to; `*` matches any run of characters */
+  model: string;
+  /** Uncached input tokens */
+  input: number;
+  /** Generated tokens */
+  output: number;
+  /** Input tokens read from the prompt cache */
+  cacheRead: number;
+  /** Input tokens written to the prompt cache */
+  cacheCreation: number;
+}
+
+/** Per-model prices, matched in order (specific patterns before general ones) */
+export interface PriceTable {
+  prices: ModelPrice[];
+}
+
+/** Usage of one model over a session */
+export interface ModelUsage {
+  model: string;
+  /** API responses from the model */
+  responses: number;
+  usage: TokenUsage;
+  /** Cost in USD (null if the model has no price) */
+  cost: number | null;
+}
+
+/** One API response of a session, for charting context growth */
+export interface UsagePoint {
+  /** Line of the response's first event */
+  sequence: number;
+  timestamp: string | null;
+  model: string;
+  /** Prompt size of the request: the context in use before the response */
+  contextTokens: number;
+  outputTokens: number;
+  /** Cost of the session's own responses up to and including this one, in USD */
+  cumulativeCost: number;
+}
+
+/** Token usage and cost of a session, including its sub-agents */
+export interface SessionUsage {
+  /** Totals per model, most expensive first */
+  models: ModelUsage[];
+  /** Totals across all models */
+  total: TokenUsage;
+  /** Cost of the models that have a price, in USD */
+  totalCost: number;
+  /** Models without a price (their tokens are not in totalCost) */
+  unpricedModels: string[];
+  /** Every API response of the session itself, in file order */
+  timeline: UsagePoint[];
+  /** Sub-agents whose usage is included */
+  subagents: number;
+  /** API responses of those sub-agents */
+  subagentResponses: number;
+}
+
 // =============================================================================
 // Policy Evaluation / CupcakeSpan Types
 // =============================================================================
@@ -418,6 +800,72 @@ export interface EditContext {
   editLine: number;
 }
 
+/** One edit to a file in its cross-session history (matches Rust FileHistoryEntry) */
+export interface FileHistoryEntry {
+  /** Session that made the edit */
+  sessionId: string;
+  /** Line (sequence number) of the edit event in the session file */
+  sequence: number;
+  /** Position of the edit among the file's edits in its session (matches FileDiff.sequence) */
+  editIndex: number;
+  /** Timestamp of the edit (ISO 8601) */
+  timestamp: string | null;
+  /** Line of the human message that triggered the edit */
+  triggerLine: number | null;
+  /** Preview of the triggering human message */
+  prompt: string | null;
+}
+
+/** The session edit a working-tree line is attributed to (matches Rust LineBlame) */
+export interface LineBlame {
+  /** Session that made the edit */
+  sessionId: string;
+  /** Line (sequence number) of the edit event in the session file */
+  sequence: number;
+  /** Position of the edit among the file's edits (matches FileDiff.sequence) */
+  editIndex: number;
+  /** Line of the human message that triggered the edit */
+  triggerLine: number | null;
+  /** Timestamp of the edit (ISO 8601) */
+  timestamp: string | null;
+  /** High for an exact block match, low for a single-line match */
+  confidence: EditConfidence;
+}
+
+/** Blame for every line of a file (matches Rust FileBlame) */
+export interface FileBlame {
+  /** One entry per line of the current content (null = not written by these sessions) */
+  lines: (LineBlame | null)[];
+  /** Number of attributed lines */
+  attributedCount: number;
+}
+
+/** Memory statistics for a single loaded index (matches Rust IndexEntryStats) */
+export interface IndexEntryStats {
+  projectPath: string;
+  sessionId: string;
+  /** Estimated heap size of the index */
+  sizeBytes: number;
+  /** Number of events (lines) indexed */
+  totalEvents: number;
+  /** Milliseconds since the index was last used */
+  idleMs: number;
+}
+
+/** Memory statistics for all loaded indices (returned by get_index_cache_stats) */
+export interface IndexCacheStats {
+  /** Configured memory budget of session indices */
+  memoryBudgetBytes: number;
+  /** Configured memory budget of search term indices */
+  termMemoryBudgetBytes: number;
+  /** Sum of the estimated sizes of all loaded indices */
+  totalBytes: number;
+  /** Part of totalBytes taken by search term indices */
+  termIndexBytes: number;
+  /** Per-index statistics, most recently used first */
+  indices: IndexEntryStats[];
+}
+
 // =============================================================================
 // Search Types
 // =============================================================================
@@ -428,8 +876,38 @@ export interface SearchMatch {
   sequence: number;
   /** Byte offset in file for loading full JSON */
   byteOffset: number;
+  /** Content block the snippet was taken from */
+  block: MatchBlock;
   /** Snippet of text showing match context */
   snippet: string;
+  /** Matched ranges within the snippet */
+  highlights: MatchSpan[];
+  /** Relevance from term frequency, recency and similarity (higher is more relevant) */
+  score: number;
+  /** How closely the line matches the fuzzy terms of the query, from 0 to 1 (1 when exact) */
+  similarity: number;
+}
+
+/** Content block of an event that a match was found in */
+export type MatchBlock =
+  | { kind: "text" }
+  | { kind: "thinking" }
+  | { kind: "toolInput"; tool: string; field: string }
+  | { kind: "toolResult" }
+  | { kind: "raw" };
+
+/** A matched range of a snippet, in UTF-16 code units (string indices) */
+export interface MatchSpan {
+  start: number;
+  end: number;
+}
+
+/** How a query is matched */
+export interface SearchOptions {
+  /** Match terms, phrases and regexes case-sensitively */
+  caseSensitive?: boolean;
+  /** Match plain terms allowing typos (phrases, prefixes and regexes stay exact) */
+  fuzzy?: boolean;
 }
 
 /** Search response from backend */
@@ -441,3 +919,57 @@ export interface SearchResponse {
   /** Whether search was truncated (hit max_results limit) */
   truncated: boolean;
 }
+
+/** Matches from one session or sub-agent file of a search */
+export interface FileSearchResult {
+  projectPath: string;
+  /** Session ID (for sub-agents, the parent session when known) */
+  sessionId: string | null;
+  /** Sub-agent ID, for sub-agent files */
+  agentId: string | null;
+  matches: SearchMatch[];
+}
+
+/**
+ * Where a background search looks: one session, one sub-agent, or every session
+ * and sub-agent of a project (of all projects when projectPath is null)
+ */
+export type SearchScope =
+  | { kind: "session"; projectPath: string; sessionId: string }
+  | { kind: "subagent"; projectPath: string; agentId: string }
+  | { kind: "projects"; projectPath: string | null };
+
+/** Progress of a background search */
+export interface SearchProgress {
+  /** Files searched so far */
+  filesSearched: number;
+  /** Files the search covers */
+  totalFiles: number;
+  /** Lines searched so far, across all files */
+  linesSearched: number;
+  /** Bytes of files indexed so far, for files whose search index had to be built or updated */
+  bytesIndexed: number;
+  /** Matches found so far, up to the limit on results */
+  matchesFound: number;
+  /**
+   * Once the limit on results is reached, the lowest score still kept: streamed
+   * matches scoring below it have been displaced by better ones
+   */
+  minScore: number | null;
+  /** Whether the search has finished (this is its last progress event) */
+  done: boolean;
+  /** Whether the search was stopped by cancel_search */
+  cancelled: boolean;
+  /** Whether search was truncated (hit max_results limit) */
+  truncated: boolean;
+}
+
+/** Payload of the "search-result" event: new matches of a background search in one file */
+export interface SearchResultEvent extends FileSearchResult {
+  searchId: number;
+}
+
+/** Payload of the "search-progress" event */
+export interface SearchProgressEvent extends SearchProgress {
+  searchId: number;
+}
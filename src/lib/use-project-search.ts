@@ -0,0 +1,81 @@
+import { useState, useEffect } from "react";
+import { startSearchJob } from "./search-jobs";
+import type { FileSearchResult, SearchProgress } from "./types";
+
+interface UseProjectSearchResult {
+  /** Files with matches, most relevant first, updated as batches stream in */
+  results: FileSearchResult[];
+  /** Whether the search is still running */
+  searching: boolean;
+  /** Latest progress of the search */
+  progress: SearchProgress | null;
+  error: string | null;
+}
+
+const fileKey = (result: FileSearchResult) =>
+  `${result.projectPath}/${result.sessionId}/${result.agentId}`;
+
+const bestScore = (result: FileSearchResult) =>
+  Math.max(...result.matches.map((m) => m.score));
+
+// Add a batch of matches to its file's result, keeping files and matches ranked by score
+function mergeBatch(results: FileSearchResult[], batch: FileSearchResult): FileSearchResult[] {
+  const key = fileKey(batch);
+  const existing = results.find((r) => fileKey(r) === key);
+  const merged: FileSearchResult = existing
+    ? { ...existing, matches: [...existing.matches, ...batch.matches] }
+    : batch;
+  merged.matches = [...merged.matches].sort((a, b) => b.score - a.score);
+  return [...results.filter((r) => fileKey(r) !== key), merged].sort(
+    (a, b) => bestScore(b) - bestScore(a)
+  );
+}
+
+// Drop matches displaced by better ones, and files left without matches
+function dropBelow(results: FileSearchResult[], minScore: number | null): FileSearchResult[] {
+  if (minScore === null) return results;
+  return results
+    .map((r) => ({ ...r, matches: r.matches.filter((m) => m.score >= minScore) }))
+    .filter((r) => r.matches.length > 0);
+}
+
+/**
+ * Search the sessions and sub-agents of a project, or of every project when
+ * projectPath is null. The search runs as a background job whose matches stream
+ * in while it runs; changing the query cancels it.
+ */
+export function useProjectSearch(
+  query: string,
+  projectPath: string | null = null
+): UseProjectSearchResult {
+  const [results, setResults] = useState<FileSearchResult[]>([]);
+  const [searching, setSearching] = useState(false);
+  const [progress, setProgress] = useState<SearchProgress | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    setResults([]);
+    setProgress(null);
+    setError(null);
+    if (!query.trim()) {
+      setSearching(false);
+      return;
+    }
+
+    setSearching(true);
+    const job = startSearchJob({ kind: "projects", projectPath }, query, undefined, undefined, {
+      onResult: (batch) => setResults((prev) => mergeBatch(prev, batch)),
+      onProgress: (progress) => {
+        setProgress(progress);
+        setResults((prev) => dropBelow(prev, progress.minScore));
+      },
+    });
+    job.done
+      .catch((err) => setError(err instanceof Error ? err.message : String(err)))
+      .finally(() => setSearching(false));
+
+    return () => job.cancel();
+  }, [query, projectPath]);
+
+  return { results, searching, progress, error };
+}
@@ -20,9 +20,10 @@ import {
   TooltipTrigger,
 } from "@/components/ui/tooltip";
 import { cn } from "@/lib/utils";
-import type { GitFileDiff } from "@/lib/types";
+import type { ContentInfo, GitFileDiff } from "@/lib/types";
 import {
   buildFileTree,
+  formatBytes,
   formatTimestamp,
   getFileEditIcon,
   getLanguageFromPath,
@@ -30,6 +31,7 @@ import {
 } from "../utils";
 import { TreeNodeItem } from "./tree-node";
 import { EditContextView } from "./edit-context";
+import { HunkView } from "./hunk-view";
 import type { EditViewerProps, DiffViewMode, FileListMode, DiffContentMode } from "../types";
 
 export function EditViewer({
@@ -419,32 +421,45 @@ export function EditViewer({
                       <span className="text-muted-foreground font-normal">
                         {!gitDiff.existsAtHead && "(new file)"}
                         {!gitDiff.existsInWorkdir && "(deleted)"}
+                        {gitDiff.kind === "large" && "(truncated preview)"}
                       </span>
                     </div>
-                    <DiffEditor
-                      height="100%"
-                      language={getLanguageFromPath(selectedFile)}
-                      original={gitDiff.original}
-                      modified={gitDiff.current}
-                      theme={monacoTheme}
-                      options={{
-                        readOnly: true,
-                        renderSideBySide: diffViewMode === "split",
-                        minimap: { enabled: false },
-                        scrollBeyondLastLine: false,
-                        fontSize: 12,
-                        lineNumbers: "on",
-                        folding: true,
-                        wordWrap: "on",
-                        diffWordWrap: "on",
-                      }}
-                      loading={
-                        <div className="flex items-center justify-center h-24 text-muted-foreground">
-                          <IconLoader2 className="size-4 animate-spin mr-2" />
-                          Loading editor...
-                        </div>
-                      }
-                    />
+                    {gitDiff.kind === "binary" || gitDiff.kind === "image" ? (
+                      <div className="p-8 text-center text-sm text-muted-foreground space-y-1">
+                        <p>{gitDiff.kind === "image" ? "Image file" : "Binary file"} - no text diff</p>
+                        <p>
+                          {describeContent(gitDiff.originalInfo, gitDiff.existsAtHead)} →{" "}
+                          {describeContent(gitDiff.currentInfo, gitDiff.existsInWorkdir)}
+                        </p>
+                      </div>
+                    ) : gitDiff.kind === "large" ? (
+                      <HunkView projectPath={projectPath} filePath={selectedFile} />
+                    ) : (
+                      <DiffEditor
+                        height="100%"
+                        language={getLanguageFromPath(selectedFile)}
+                        original={gitDiff.original}
+                        modified={gitDiff.current}
+                        theme={monacoTheme}
+                        options={{
+                          readOnly: true,
+                          renderSideBySide: diffViewMode === "split",
+                          minimap: { enabled: false },
+                          scrollBeyondLastLine: false,
+                          fontSize: 12,
+                          lineNumbers: "on",
+                          folding: true,
+                          wordWrap: "on",
+                          diffWordWrap: "on",
+                        }}
+                        loading={
+                          <div className="flex items-center justify-center h-24 text-muted-foreground">
+                            <IconLoader2 className="size-4 animate-spin mr-2" />
+                            Loading editor...
+                          </div>
+                        }
+                      />
+                    )}
                   </div>
                 ) : (
                   <div className="border border-dashed border-border rounded-lg p-8 text-center text-muted-foreground">
@@ -547,3 +562,10 @@ export function EditViewer({
     </PanelGroup>
   );
 }
+
+/** Short description of one side of a binary or image diff. */
+function describeContent(info: ContentInfo, exists: boolean): string {
+  if (!exists) return "none";
+  const size = formatBytes(info.sizeBytes);
+  return info.image ? `${info.image.width}×${info.image.height} ${info.image.format}, ${size}` : size;
+}
@@ -44,8 +44,13 @@ export function EventLogViewer({
   onSelectSubagent,
   searchQuery,
   onSearchChange,
+  caseSensitive,
+  onCaseSensitiveChange,
+  fuzzy,
+  onFuzzyChange,
   searchLoading,
   searchResults,
+  searchError,
   snippetMap,
   isSearchMode,
   searchEventsLoading,
@@ -368,9 +373,8 @@ export function EventLogViewer({
       highlightedIndices,
       flashingByteOffsets,
       snippetMap,
-      searchQuery,
     }),
-    [events, summaryMap, handleSelectMainEvent, handleSelectSubagent, selectedSubagentId, highlightedIndices, flashingByteOffsets, snippetMap, searchQuery]
+    [events, summaryMap, handleSelectMainEvent, handleSelectSubagent, selectedSubagentId, highlightedIndices, flashingByteOffsets, snippetMap]
   );
 
   const subagentRowProps = useMemo(
@@ -399,15 +403,40 @@ export function EventLogViewer({
                 type="text"
                 value={searchQuery}
                 onChange={(e) => onSearchChange(e.target.value)}
-                placeholder="Search (AND, OR)"
+                placeholder='Search (AND, OR, NOT, "phrase", /regex/)'
+                title={searchError ?? undefined}
                 className={cn(
-                  "pl-7 pr-2 py-1 rounded text-[0.65rem] bg-muted/50 border border-transparent",
+                  "pl-7 pr-12 py-1 rounded text-[0.65rem] bg-muted/50 border border-transparent",
                   "focus:border-primary focus:outline-none focus:ring-1 focus:ring-primary/20",
-                  "placeholder:text-muted-foreground/60 w-36 sm:w-44"
+                  "placeholder:text-muted-foreground/60 w-36 sm:w-44",
+                  searchError && "border-destructive focus:border-destructive focus:ring-destructive/20"
                 )}
               />
-              {searchLoading && (
+              {searchLoading ? (
                 <IconLoader2 className="absolute right-2 top-1/2 -translate-y-1/2 size-3 animate-spin text-muted-foreground" />
+              ) : (
+                <div className="absolute right-1 top-1/2 -translate-y-1/2 flex items-center gap-0.5">
+                  <button
+                    onClick={() => onFuzzyChange(!fuzzy)}
+                    title="Tolerate typos"
+                    className={cn(
+                      "px-1 rounded text-[0.6rem] font-medium transition-colors",
+                      fuzzy ? "bg-primary/20 text-primary" : "text-muted-foreground hover:text-foreground"
+                    )}
+                  >
+                    ~
+                  </button>
+                  <button
+                    onClick={() => onCaseSensitiveChange(!caseSensitive)}
+                    title="Match case"
+                    className={cn(
+                      "px-1 rounded text-[0.6rem] font-medium transition-colors",
+                      caseSensitive ? "bg-primary/20 text-primary" : "text-muted-foreground hover:text-foreground"
+                    )}
+                  >
+                    Aa
+                  </button>
+                </div>
               )}
             </div>
             {searchResults && (
@@ -1,38 +1,41 @@
 import { cn } from "@/lib/utils";
 import { formatEventTime, getEventBadgeClass, getEventDisplayLabel } from "../utils";
+import type { MatchBlock, MatchSpan } from "@/lib/types";
 import type { EventRowProps } from "../types";
 
-// Parse search query into terms (same logic as Rust backend)
-function parseSearchTerms(query: string): string[] {
-  if (!query.trim()) return [];
-  return query
-    .split(/\s+/)
-    .filter((word) => word !== "AND" && word !== "OR")
-    .map((word) => word.toLowerCase());
-}
-
-// Highlight matching terms in text
-function highlightTerms(text: string, terms: string[]): React.ReactNode {
-  if (terms.length === 0) return text;
-
-  // Build regex to match any term (case-insensitive)
-  const pattern = terms.map((t) => t.replace(/[.*+?^${}()|[\]\\]/g, "\\$&")).join("|");
-  const regex = new RegExp(`(${pattern})`, "gi");
-
-  const parts = text.split(regex);
-  if (parts.length === 1) return text;
+// Highlight the matched spans (UTF-16 offsets from the backend) in a snippet
+function highlightSpans(text: string, spans: MatchSpan[]): React.ReactNode {
+  if (spans.length === 0) return text;
 
-  return parts.map((part, i) => {
-    const isMatch = terms.some((t) => part.toLowerCase() === t);
-    if (isMatch) {
-      return (
-        <mark key={i} className="bg-yellow-300 dark:bg-yellow-600 text-foreground rounded-sm px-0.5">
-          {part}
-        </mark>
-      );
-    }
-    return part;
+  const parts: React.ReactNode[] = [];
+  let at = 0;
+  spans.forEach((span, i) => {
+    if (span.start > at) parts.push(text.slice(at, span.start));
+    parts.push(
+      <mark key={i} className="bg-yellow-300 dark:bg-yellow-600 text-foreground rounded-sm px-0.5">
+        {text.slice(span.start, span.end)}
+      </mark>
+    );
+    at = span.end;
   });
+  if (at < text.length) parts.push(text.slice(at));
+  return parts;
+}
+
+// Label for the block a match came from (none for plain message text)
+function matchBlockLabel(block: MatchBlock): string | null {
+  switch (block.kind) {
+    case "thinking":
+      return "thinking";
+    case "toolInput":
+      return `${block.tool}.${block.field}`;
+    case "toolResult":
+      return "result";
+    case "raw":
+      return "json";
+    default:
+      return null;
+  }
 }
 
 export function EventRowComponent({
@@ -46,7 +49,6 @@ export function EventRowComponent({
   highlightedIndices,
   flashingByteOffsets,
   snippetMap,
-  searchQuery,
 }: EventRowProps) {
   const event = events[index];
   const isCompaction = event.subtype === "compact_boundary";
@@ -54,7 +56,6 @@ export function EventRowComponent({
   const linkedSummary = event.logicalParentUuid ? summaryMap.get(event.logicalParentUuid) : null;
   const isHighlighted = highlightedIndices?.has(index) ?? false;
   const isFlashing = flashingByteOffsets?.has(event.byteOffset) ?? false;
-  const searchTerms = searchQuery ? parseSearchTerms(searchQuery) : [];
 
   // Highlight wrapper - adds a visible boundary box around highlighted rows
   const HighlightWrapper = ({ children }: { children: React.ReactNode }) => {
@@ -190,13 +191,25 @@ export function EventRowComponent({
             {/* Preview text (or snippet when searching, with highlighted terms) */}
             <span className="flex-1 min-w-0 text-xs truncate text-muted-foreground">
               {(() => {
-                const snippet = snippetMap?.get(event.sequence);
-                const text = snippet ?? event.preview ?? event.summary ?? "";
-                // Highlight search terms in snippets
-                if (snippet && searchTerms.length > 0) {
-                  return highlightTerms(text, searchTerms);
+                const match = snippetMap?.get(event.sequence);
+                // Highlight matched spans in snippets
+                if (match) {
+                  const label = matchBlockLabel(match.block);
+                  return (
+                    <>
+                      {label && (
+                        <span className="mr-1.5 px-1 rounded bg-muted font-mono text-[10px]">{label}</span>
+                      )}
+                      {match.similarity < 1 && (
+                        <span className="mr-1.5 font-mono text-[10px]" title="Similarity to the query">
+                          ~{Math.round(match.similarity * 100)}%
+                        </span>
+                      )}
+                      {highlightSpans(match.snippet, match.highlights)}
+                    </>
+                  );
                 }
-                return text;
+                return event.preview ?? event.summary ?? "";
               })()}
             </span>
           </div>
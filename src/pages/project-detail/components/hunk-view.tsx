@@ -0,0 +1,132 @@
+import { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { IconLoader2 } from "@tabler/icons-react";
+import { cn } from "@/lib/utils";
+import type { DiffHunk, DiffLine, GitStructuredDiff } from "@/lib/types";
+
+/** Hunks requested per page */
+const HUNK_PAGE_SIZE = 50;
+
+interface HunkViewProps {
+  projectPath: string;
+  filePath: string;
+}
+
+/**
+ * Renders the server-computed git diff of a file hunk by hunk.
+ * Used for files too large for the editor's diff; more hunks load on demand.
+ */
+export function HunkView({ projectPath, filePath }: HunkViewProps) {
+  const [hunks, setHunks] = useState<DiffHunk[]>([]);
+  const [result, setResult] = useState<GitStructuredDiff | null>(null);
+  const [loading, setLoading] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  async function loadHunks(hunkOffset: number) {
+    setLoading(true);
+    setError(null);
+    try {
+      const page = await invoke<GitStructuredDiff>("get_git_structured_diff", {
+        projectPath,
+        filePath,
+        options: { hunkOffset, hunkLimit: HUNK_PAGE_SIZE },
+      });
+      setResult(page);
+      setHunks((prev) => (hunkOffset === 0 ? page.diff.hunks : [...prev, ...page.diff.hunks]));
+    } catch (err) {
+      console.error("Failed to load structured diff:", err);
+      setError(err instanceof Error ? err.message : String(err));
+    } finally {
+      setLoading(false);
+    }
+  }
+
+  useEffect(() => {
+    setHunks([]);
+    setResult(null);
+    loadHunks(0);
+  }, [projectPath, filePath]);
+
+  if (error) {
+    return <p className="p-4 text-sm text-destructive">{error}</p>;
+  }
+
+  return (
+    <div className="flex-1 overflow-auto font-mono text-xs">
+      {result && (
+        <div className="px-3 py-1 text-muted-foreground border-b border-border">
+          {result.renamedFrom && <span className="mr-2">renamed from {result.renamedFrom}</span>}
+          <span className="text-green-600 dark:text-green-400">+{result.diff.additions}</span>{" "}
+          <span className="text-red-600 dark:text-red-400">-{result.diff.deletions}</span>{" "}
+          in {result.diff.totalHunks} hunks
+        </div>
+      )}
+      {hunks.map((hunk) => (
+        <div key={`${hunk.oldStart}-${hunk.newStart}`} className="border-b border-border">
+          <div className="bg-muted/50 px-3 py-0.5 text-muted-foreground">
+            @@ -{hunk.oldStart},{hunk.oldLines} +{hunk.newStart},{hunk.newLines} @@
+          </div>
+          {hunk.lines.map((line, i) => (
+            <HunkLine key={i} line={line} />
+          ))}
+        </div>
+      ))}
+      {loading ? (
+        <div className="flex items-center justify-center h-12 text-muted-foreground">
+          <IconLoader2 className="size-4 animate-spin mr-2" />
+          Loading hunks...
+        </div>
+      ) : (
+        result?.diff.hasMore && (
+          <button
+            onClick={() => loadHunks(hunks.length)}
+            className="w-full py-2 text-muted-foreground hover:text-foreground hover:bg-muted/50"
+          >
+            Show more ({result.diff.totalHunks - hunks.length} hunks remaining)
+          </button>
+        )
+      )}
+    </div>
+  );
+}
+
+function HunkLine({ line }: { line: DiffLine }) {
+  const marker = line.kind === "added" ? "+" : line.kind === "removed" ? "-" : " ";
+  const highlight =
+    line.kind === "added" ? "bg-green-500/30" : "bg-red-500/30";
+
+  // Split the content into plain and highlighted segments
+  const segments: { text: string; changed: boolean }[] = [];
+  let at = 0;
+  for (const span of line.highlights) {
+    if (span.start > at) segments.push({ text: line.content.slice(at, span.start), changed: false });
+    segments.push({ text: line.content.slice(span.start, span.end), changed: true });
+    at = span.end;
+  }
+  if (at < line.content.length) segments.push({ text: line.content.slice(at), changed: false });
+
+  return (
+    <div
+      className={cn(
+        "flex whitespace-pre",
+        line.kind === "added" && "bg-green-500/10",
+        line.kind === "removed" && "bg-red-500/10"
+      )}
+    >
+      <span className="w-12 shrink-0 text-right pr-2 text-muted-foreground select-none">
+        {line.oldLine ?? ""}
+      </span>
+      <span className="w-12 shrink-0 text-right pr-2 text-muted-foreground select-none">
+        {line.newLine ?? ""}
+      </span>
+      <span className="w-4 shrink-0 select-none">{marker}</span>
+      <span>
+        {segments.map((segment, i) => (
+          <span key={i} className={cn(segment.changed && highlight)}>
+            {segment.text}
+          </span>
+        ))}
+      </span>
+    </div>
+  );
+}
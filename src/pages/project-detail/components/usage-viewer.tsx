@@ -0,0 +1,275 @@
+import { useEffect, useRef, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import * as d3 from "d3";
+import { IconLoader2 } from "@tabler/icons-react";
+import { loadPriceTable } from "@/pages/settings";
+import type { SessionUsage, TokenUsage, UsagePoint } from "@/lib/types";
+
+interface UsageViewerProps {
+  projectPath: string;
+  sessionId: string;
+}
+
+// CSS variable references for theming
+const CSS_VARS = {
+  TEXT_COLOR: "var(--color-foreground)",
+  MUTED_TEXT_COLOR: "var(--color-muted-foreground)",
+  BORDER_COLOR: "var(--color-border)",
+  PRIMARY_COLOR: "var(--color-primary)",
+};
+
+const CHART_HEIGHT = 220;
+
+function formatTokens(tokens: number): string {
+  return d3.format(".3~s")(tokens).replace("G", "B");
+}
+
+function formatCost(cost: number): string {
+  return `$${cost.toFixed(cost < 1 ? 4 : 2)}`;
+}
+
+function totalTokens(usage: TokenUsage): number {
+  return usage.inputTokens + usage.outputTokens + usage.cacheReadTokens + usage.cacheCreationTokens;
+}
+
+/** Line chart of the context size of each API response */
+function ContextChart({ timeline }: { timeline: UsagePoint[] }) {
+  const containerRef = useRef<HTMLDivElement>(null);
+  const svgRef = useRef<SVGSVGElement>(null);
+  const [width, setWidth] = useState(0);
+
+  // Handle resize
+  useEffect(() => {
+    if (!containerRef.current) return;
+    const resizeObserver = new ResizeObserver((entries) => {
+      requestAnimationFrame(() => {
+        if (entries[0]) setWidth(entries[0].contentRect.width);
+      });
+    });
+    resizeObserver.observe(containerRef.current);
+    return () => resizeObserver.disconnect();
+  }, []);
+
+  // D3 Rendering
+  useEffect(() => {
+    if (!svgRef.current || timeline.length === 0 || width === 0) return;
+
+    const svg = d3.select(svgRef.current);
+    svg.selectAll("*").remove();
+    svg
+      .attr("width", width)
+      .attr("height", CHART_HEIGHT)
+      .style("font-family", "ui-monospace, monospace")
+      .style("display", "block");
+
+    const margin = { top: 12, right: 16, bottom: 24, left: 48 };
+    const xScale = d3
+      .scaleLinear()
+      .domain([0, Math.max(1, timeline.length - 1)])
+      .range([margin.left, width - margin.right]);
+    const yScale = d3
+      .scaleLinear()
+      .domain([0, d3.max(timeline, (d) => d.contextTokens) ?? 1])
+      .nice()
+      .range([CHART_HEIGHT - margin.bottom, margin.top]);
+
+    const yAxis = svg
+      .append("g")
+      .attr("transform", `translate(${margin.left}, 0)`)
+      .call(
+        d3
+          .axisLeft(yScale)
+          .ticks(5)
+          .tickFormat((d) => formatTokens(d.valueOf()))
+          .tickSize(-(width - margin.left - margin.right))
+      );
+    yAxis.select(".domain").remove();
+    yAxis.selectAll(".tick line").attr("stroke", CSS_VARS.BORDER_COLOR).attr("stroke-dasharray", "2,2");
+    yAxis.selectAll(".tick text").attr("fill", CSS_VARS.MUTED_TEXT_COLOR).style("font-size", "10px");
+
+    const xAxis = svg
+      .append("g")
+      .attr("transform", `translate(0, ${CHART_HEIGHT - margin.bottom})`)
+      .call(
+        d3
+          .axisBottom(xScale)
+          .ticks(Math.min(timeline.length, Math.floor(width / 80)))
+          .tickFormat((d) => String(d.valueOf() + 1))
+      );
+    xAxis.select(".domain").attr("stroke", CSS_VARS.BORDER_COLOR);
+    xAxis.selectAll(".tick line").attr("stroke", CSS_VARS.BORDER_COLOR);
+    xAxis.selectAll(".tick text").attr("fill", CSS_VARS.MUTED_TEXT_COLOR).style("font-size", "10px");
+
+    const area = d3
+      .area<UsagePoint>()
+      .x((_, i) => xScale(i))
+      .y0(yScale(0))
+      .y1((d) => yScale(d.contextTokens));
+    const line = d3
+      .line<UsagePoint>()
+      .x((_, i) => xScale(i))
+      .y((d) => yScale(d.contextTokens));
+
+    svg
+      .append("path")
+      .datum(timeline)
+      .attr("d", area)
+      .attr("fill", CSS_VARS.PRIMARY_COLOR)
+      .attr("fill-opacity", 0.1);
+    svg
+      .append("path")
+      .datum(timeline)
+      .attr("d", line)
+      .attr("fill", "none")
+      .attr("stroke", CSS_VARS.PRIMARY_COLOR)
+      .attr("stroke-width", 1.5);
+
+    // Native tooltips per response
+    svg
+      .append("g")
+      .selectAll("circle")
+      .data(timeline)
+      .enter()
+      .append("circle")
+      .attr("cx", (_, i) => xScale(i))
+      .attr("cy", (d) => yScale(d.contextTokens))
+      .attr("r", timeline.length > 200 ? 0 : 2)
+      .attr("fill", CSS_VARS.PRIMARY_COLOR)
+      .append("title")
+      .text(
+        (d, i) =>
+          `#${i + 1} ${d.model}\n${d.contextTokens.toLocaleString()} context tokens\n` +
+          `${d.outputTokens.toLocaleString()} output tokens\n${formatCost(d.cumulativeCost)} so far`
+      );
+  }, [timeline, width]);
+
+  return (
+    <div ref={containerRef} className="w-full">
+      <svg ref={svgRef} />
+    </div>
+  );
+}
+
+export function UsageViewer({ projectPath, sessionId }: UsageViewerProps) {
+  const [usage, setUsage] = useState<SessionUsage | null>(null);
+  const [loading, setLoading] = useState(true);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    let cancelled = false;
+    setLoading(true);
+    setError(null);
+    invoke<SessionUsage>("get_session_usage", {
+      projectPath,
+      sessionId,
+      prices: loadPriceTable(),
+    })
+      .then((result) => {
+        if (!cancelled) setUsage(result);
+      })
+      .catch((err) => {
+        if (!cancelled) setError(err instanceof Error ? err.message : String(err));
+      })
+      .finally(() => {
+        if (!cancelled) setLoading(false);
+      });
+    return () => {
+      cancelled = true;
+    };
+  }, [projectPath, sessionId]);
+
+  if (loading) {
+    return (
+      <div className="h-full flex items-center justify-center text-muted-foreground">
+        <IconLoader2 className="size-5 animate-spin" />
+      </div>
+    );
+  }
+  if (error || !usage) {
+    return (
+      <div className="h-full flex items-center justify-center text-sm text-destructive">
+        {error ?? "No usage data"}
+      </div>
+    );
+  }
+  if (usage.timeline.length === 0) {
+    return (
+      <div className="h-full flex items-center justify-center text-sm text-muted-foreground">
+        No API responses in this session
+      </div>
+    );
+  }
+
+  const stats = [
+    { label: "Cost", value: formatCost(usage.totalCost) },
+    {
+      label: "Responses",
+      value: (usage.timeline.length + usage.subagentResponses).toLocaleString(),
+    },
+    { label: "Input", value: formatTokens(usage.total.inputTokens) },
+    { label: "Output", value: formatTokens(usage.total.outputTokens) },
+    { label: "Cache read", value: formatTokens(usage.total.cacheReadTokens) },
+    { label: "Cache write", value: formatTokens(usage.total.cacheCreationTokens) },
+  ];
+
+  return (
+    <div className="h-full overflow-auto p-4 space-y-6">
+      {/* Totals */}
+      <div className="grid grid-cols-3 sm:grid-cols-6 gap-3">
+        {stats.map((stat) => (
+          <div key={stat.label} className="rounded-lg border border-border px-3 py-2">
+            <div className="text-[0.65rem] text-muted-foreground">{stat.label}</div>
+            <div className="text-sm font-medium font-mono">{stat.value}</div>
+          </div>
+        ))}
+      </div>
+      {usage.subagents > 0 && (
+        <p className="text-xs text-muted-foreground">
+          Includes {usage.subagentResponses.toLocaleString()} responses from{" "}
+          {usage.subagents.toLocaleString()} sub-agent{usage.subagents === 1 ? "" : "s"}; the chart
+          shows the session's own context.
+        </p>
+      )}
+      {usage.unpricedModels.length > 0 && (
+        <p className="text-xs text-muted-foreground">
+          No price for {usage.unpricedModels.join(", ")}; add one in Settings → Pricing to include
+          it in the cost.
+        </p>
+      )}
+
+      {/* Context growth */}
+      <div>
+        <h3 className="text-xs font-medium mb-2">Context size per response</h3>
+        <ContextChart timeline={usage.timeline} />
+      </div>
+
+      {/* Per-model totals */}
+      <div className="border border-border rounded-lg overflow-hidden">
+        <div className="grid grid-cols-[1fr_repeat(6,5.5rem)] gap-2 px-3 py-2 bg-muted/50 border-b border-border text-xs font-medium text-muted-foreground">
+          <div>Model</div>
+          <div className="text-right">Responses</div>
+          <div className="text-right">Input</div>
+          <div className="text-right">Output</div>
+          <div className="text-right">Cache read</div>
+          <div className="text-right">Cache write</div>
+          <div className="text-right">Cost</div>
+        </div>
+        {usage.models.map((model) => (
+          <div
+            key={model.model}
+            className="grid grid-cols-[1fr_repeat(6,5.5rem)] gap-2 px-3 py-2 border-b border-border/50 last:border-b-0 text-xs font-mono"
+            title={`${totalTokens(model.usage).toLocaleString()} tokens`}
+          >
+            <div className="truncate">{model.model}</div>
+            <div className="text-right">{model.responses.toLocaleString()}</div>
+            <div className="text-right">{formatTokens(model.usage.inputTokens)}</div>
+            <div className="text-right">{formatTokens(model.usage.outputTokens)}</div>
+            <div className="text-right">{formatTokens(model.usage.cacheReadTokens)}</div>
+            <div className="text-right">{formatTokens(model.usage.cacheCreationTokens)}</div>
+            <div className="text-right">{model.cost === null ? "—" : formatCost(model.cost)}</div>
+          </div>
+        ))}
+      </div>
+    </div>
+  );
+}
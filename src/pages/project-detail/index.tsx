@@ -14,11 +14,13 @@ import {
 import { Button } from "@/components/ui/button";
 import { cn } from "@/lib/utils";
 import { useSessionIndex } from "@/lib/use-session-index";
-import type { Session, ActiveSessionsResult, FileEdit, FileDiff, SessionEvent, SessionEventsResponse, SearchResponse } from "@/lib/types";
+import { startSearchJob, type SearchJob } from "@/lib/search-jobs";
+import type { Session, ActiveSessionsResult, FileEdit, FileDiff, SessionEvent, SessionEventsResponse, SearchMatch, SearchResponse } from "@/lib/types";
 import { formatRelativeTime, truncateUuid } from "./utils";
 import { EditViewer } from "./components/edit-viewer";
 import { EventLogViewer } from "./components/event-log-viewer";
 import { PolicyViewer } from "./components/policy-viewer";
+import { UsageViewer } from "./components/usage-viewer";
 import type { ProjectDetailPageProps, TabId, EventFilterMode } from "./types";
 
 export function ProjectDetailPage({ projectPath }: ProjectDetailPageProps) {
@@ -52,8 +54,11 @@ export function ProjectDetailPage({ projectPath }: ProjectDetailPageProps) {
 
   // Search state
   const [searchQuery, setSearchQuery] = useState("");
+  const [caseSensitive, setCaseSensitive] = useState(false);
+  const [fuzzy, setFuzzy] = useState(false);
   const [searchResults, setSearchResults] = useState<SearchResponse | null>(null);
   const [searchLoading, setSearchLoading] = useState(false);
+  const [searchError, setSearchError] = useState<string | null>(null);
   const [searchEvents, setSearchEvents] = useState<SessionEvent[]>([]);
   const [searchEventsLoading, setSearchEventsLoading] = useState(false);
 
@@ -243,26 +248,45 @@ export function ProjectDetailPage({ projectPath }: ProjectDetailPageProps) {
     if (!searchQuery.trim() || !selectedSessionId) {
       setSearchResults(null);
       setSearchEvents([]);
+      setSearchError(null);
+      // A cancelled search never finishes, so it does not clear these itself
+      setSearchLoading(false);
+      setSearchEventsLoading(false);
       return;
     }
 
+    let job: SearchJob | null = null;
     const timer = setTimeout(async () => {
       setSearchLoading(true);
       setSearchEventsLoading(true);
       try {
-        // Step 1: Get search matches
-        const response = await invoke<SearchResponse>("search_session_events", {
-          projectPath,
-          sessionId: selectedSessionId,
-          query: searchQuery,
-          maxResults: 1000, // Cap for full event loading
-        });
+        // Step 1: Get search matches from a background job (cancelled if the query changes)
+        const matches: SearchMatch[] = [];
+        const searchJob = startSearchJob(
+          { kind: "session", projectPath, sessionId: selectedSessionId },
+          searchQuery,
+          { caseSensitive, fuzzy },
+          1000, // Cap for full event loading
+          { onResult: (result) => matches.push(...result.matches) }
+        );
+        job = searchJob;
+        const progress = await searchJob.done;
+        const minScore = progress.minScore;
+        const response: SearchResponse = {
+          // Matches displaced by better ones are below the final minimum score
+          matches: minScore === null ? matches : matches.filter((m) => m.score >= minScore),
+          totalSearched: progress.linesSearched,
+          truncated: progress.truncated,
+        };
         setSearchResults(response);
+        setSearchError(null);
 
         // Step 2: Fetch full events for matches
         if (response.matches.length > 0) {
-          // Sort by sequence descending (newest first) for consistent UX
-          const sortedMatches = [...response.matches].sort((a, b) => b.sequence - a.sequence);
+          // Most relevant first, newest first among equals
+          const sortedMatches = [...response.matches].sort(
+            (a, b) => b.score - a.score || b.sequence - a.sequence
+          );
           const offsets: [number, number][] = sortedMatches.map(m => [m.sequence, m.byteOffset]);
 
           const fullEvents = await invoke<SessionEvent[]>("get_events_by_offsets", {
@@ -276,6 +300,7 @@ export function ProjectDetailPage({ projectPath }: ProjectDetailPageProps) {
         }
       } catch (err) {
         console.error("Search failed:", err);
+        setSearchError(err instanceof Error ? err.message : String(err));
         setSearchResults(null);
         setSearchEvents([]);
       } finally {
@@ -284,8 +309,11 @@ export function ProjectDetailPage({ projectPath }: ProjectDetailPageProps) {
       }
     }, 300);
 
-    return () => clearTimeout(timer);
-  }, [projectPath, selectedSessionId, searchQuery]);
+    return () => {
+      clearTimeout(timer);
+      job?.cancel();
+    };
+  }, [projectPath, selectedSessionId, searchQuery, caseSensitive, fuzzy]);
 
   // Filter or highlight events based on current filter, mode, and search
   const { filteredEvents, highlightedIndices, isSearchMode } = useMemo(() => {
@@ -364,9 +392,9 @@ export function ProjectDetailPage({ projectPath }: ProjectDetailPageProps) {
   // Build snippet lookup map from search results
   const snippetMap = useMemo(() => {
     if (!searchResults) return undefined;
-    const map = new Map<number, string>();
+    const map = new Map<number, SearchMatch>();
     for (const match of searchResults.matches) {
-      map.set(match.sequence, match.snippet);
+      map.set(match.sequence, match);
     }
     return map;
   }, [searchResults]);
@@ -479,6 +507,17 @@ export function ProjectDetailPage({ projectPath }: ProjectDetailPageProps) {
           >
             Edits
           </button>
+          <button
+            onClick={() => setActiveTab("usage")}
+            className={cn(
+              "py-2 text-xs font-medium border-b-2 transition-colors",
+              activeTab === "usage"
+                ? "border-primary text-foreground"
+                : "border-transparent text-muted-foreground hover:text-foreground"
+            )}
+          >
+            Usage
+          </button>
           <button
             onClick={() => setActiveTab("policies")}
             className={cn(
@@ -529,8 +568,13 @@ export function ProjectDetailPage({ projectPath }: ProjectDetailPageProps) {
             onSelectSubagent={setSelectedSubagentId}
             searchQuery={searchQuery}
             onSearchChange={setSearchQuery}
+            caseSensitive={caseSensitive}
+            onCaseSensitiveChange={setCaseSensitive}
+            fuzzy={fuzzy}
+            onFuzzyChange={setFuzzy}
             searchLoading={searchLoading}
             searchResults={searchResults}
+            searchError={searchError}
             snippetMap={snippetMap}
             isSearchMode={isSearchMode}
             searchEventsLoading={searchEventsLoading}
@@ -546,6 +590,8 @@ export function ProjectDetailPage({ projectPath }: ProjectDetailPageProps) {
             diffs={diffs}
             diffsLoading={diffsLoading}
           />
+        ) : activeTab === "usage" ? (
+          <UsageViewer projectPath={projectPath} sessionId={selectedSessionId ?? ""} />
         ) : (
           <PolicyViewer projectPath={projectPath} />
         )}
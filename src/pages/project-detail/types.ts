@@ -1,6 +1,6 @@
-import type { FileEdit, FileDiff, FileEditType, SessionEvent, SearchResponse } from "@/lib/types";
+import type { FileEdit, FileDiff, FileEditType, SessionEvent, SearchMatch, SearchResponse } from "@/lib/types";
 
-export type TabId = "events" | "edits" | "policies";
+export type TabId = "events" | "edits" | "usage" | "policies";
 export type DiffViewMode = "split" | "unified";
 export type FileListMode = "tree" | "log";
 export type DiffContentMode = "edits" | "full";
@@ -59,9 +59,15 @@ export interface EventLogViewerProps {
   // Search props
   searchQuery: string;
   onSearchChange: (query: string) => void;
+  caseSensitive: boolean;
+  onCaseSensitiveChange: (caseSensitive: boolean) => void;
+  fuzzy: boolean;
+  onFuzzyChange: (fuzzy: boolean) => void;
   searchLoading: boolean;
   searchResults: SearchResponse | null;
-  snippetMap?: Map<number, string>;
+  /** Why the query couldn't be parsed */
+  searchError?: string | null;
+  snippetMap?: Map<number, SearchMatch>;
   isSearchMode?: boolean;
   searchEventsLoading?: boolean;
 }
@@ -83,8 +89,7 @@ export interface EventRowBaseProps {
   selectedSubagentId: string | null;
   highlightedIndices?: Set<number>;
   flashingByteOffsets?: Set<number>;
-  snippetMap?: Map<number, string>;
-  searchQuery?: string;
+  snippetMap?: Map<number, SearchMatch>;
 }
 
 // Full props received by the component (includes react-window injected props)
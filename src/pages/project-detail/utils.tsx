@@ -2,6 +2,7 @@ import {
   IconPlus,
   IconMinus,
   IconPlusMinus,
+  IconArrowRight,
 } from "@tabler/icons-react";
 import { useTheme } from "@/components/theme-provider";
 import type { FileEdit, FileEditType, SessionEvent } from "@/lib/types";
@@ -129,6 +130,12 @@ export function formatTimestamp(isoString: string): string {
   });
 }
 
+export function formatBytes(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
 export function getFileEditIcon(editType: FileEditType) {
   switch (editType) {
     case "added":
@@ -137,6 +144,8 @@ export function getFileEditIcon(editType: FileEditType) {
       return <IconPlusMinus className="size-3.5 shrink-0 text-yellow-500" />;
     case "deleted":
       return <IconMinus className="size-3.5 shrink-0 text-red-500" />;
+    case "renamed":
+      return <IconArrowRight className="size-3.5 shrink-0 text-blue-500" />;
   }
 }
 
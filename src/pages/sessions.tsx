@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { Badge } from "@/components/ui/badge";
 import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
 import { useTheme } from "@/components/theme-provider";
 import {
   IconArrowRight,
@@ -9,6 +10,7 @@ import {
   IconFolderOpen,
   IconTerminal2,
   IconSquareRoundedPlus,
+  IconSearch,
 } from "@tabler/icons-react";
 import {
   Tooltip,
@@ -26,8 +28,10 @@ import {
 import { invoke } from "@tauri-apps/api/core";
 import { useProjects } from "@/lib/use-projects";
 import { useActiveSessions } from "@/lib/use-active-sessions";
-import type { AgentType, TerminalType } from "@/lib/types";
+import { useProjectSearch } from "@/lib/use-project-search";
+import type { AgentType, FileSearchResult, Project, SearchProgress, TerminalType } from "@/lib/types";
 import { TERMINAL_STORAGE_KEY } from "@/pages/settings";
+import { formatBytes } from "@/pages/project-detail/utils";
 import { terminalDisplayNames } from "@/lib/types";
 
 const INITIAL_DISPLAY_COUNT = 8;
@@ -71,6 +75,79 @@ function useResolvedTheme(): "light" | "dark" {
   return theme;
 }
 
+interface ProjectSearchResultsProps {
+  results: FileSearchResult[];
+  projects: Project[];
+  searching: boolean;
+  progress: SearchProgress | null;
+  error: string | null;
+  onSelectProject: (projectPath: string) => void;
+}
+
+// Matches across all projects, grouped by session and sub-agent
+function ProjectSearchResults({
+  results,
+  projects,
+  searching,
+  progress,
+  error,
+  onSelectProject,
+}: ProjectSearchResultsProps) {
+  if (error) {
+    return <p className="text-sm text-destructive py-4">{error}</p>;
+  }
+  if (results.length === 0) {
+    return (
+      <div className="flex items-center justify-center py-12 text-sm text-muted-foreground">
+        {searching ? (
+          <IconLoader2 className="size-5 animate-spin" />
+        ) : (
+          "No matches found"
+        )}
+      </div>
+    );
+  }
+
+  const projectNames = new Map(projects.map((p) => [p.projectPath, p.projectName]));
+
+  return (
+    <div className="border border-border rounded-lg overflow-hidden divide-y divide-border">
+      {results.map((result) => (
+        <button
+          key={`${result.projectPath}/${result.sessionId}/${result.agentId}`}
+          onClick={() => onSelectProject(result.projectPath)}
+          className="w-full text-left px-4 py-3 hover:bg-muted/30 transition-colors"
+        >
+          <div className="flex items-center gap-2 text-xs text-muted-foreground">
+            <span className="text-sm font-medium text-foreground truncate">
+              {projectNames.get(result.projectPath) ?? result.projectPath}
+            </span>
+            <span className="font-mono truncate">
+              {result.agentId ? `agent ${result.agentId}` : result.sessionId}
+            </span>
+            <Badge variant="secondary" className="font-mono text-xs ml-auto">
+              {result.matches.length}
+            </Badge>
+          </div>
+          {result.matches.slice(0, 3).map((match) => (
+            <div key={match.sequence} className="text-xs text-muted-foreground truncate mt-1">
+              {match.snippet}
+            </div>
+          ))}
+        </button>
+      ))}
+      {(searching || progress?.truncated) && (
+        <div className="px-4 py-2 text-xs text-muted-foreground">
+          {searching
+            ? `Searching... ${progress?.filesSearched ?? 0}/${progress?.totalFiles ?? "?"} files` +
+              (progress?.bytesIndexed ? `, indexed ${formatBytes(progress.bytesIndexed)}` : "")
+            : "Result limit reached; refine the query to see more"}
+        </div>
+      )}
+    </div>
+  );
+}
+
 interface SessionsPageProps {
   onSelectProject: (projectPath: string) => void;
 }
@@ -80,6 +157,9 @@ export function SessionsPage({ onSelectProject }: SessionsPageProps) {
   const { projects, loading, error } = useProjects();
   const { supported: activeSessionsSupported, isActive } = useActiveSessions();
   const [showAll, setShowAll] = useState(false);
+  const [searchInput, setSearchInput] = useState("");
+  const [searchQuery, setSearchQuery] = useState("");
+  const search = useProjectSearch(searchQuery);
   const [selectedTerminal, setSelectedTerminal] = useState<TerminalType | null>(
     null
   );
@@ -98,6 +178,12 @@ export function SessionsPage({ onSelectProject }: SessionsPageProps) {
     });
   }, []);
 
+  // Debounce the search input
+  useEffect(() => {
+    const timer = setTimeout(() => setSearchQuery(searchInput), 300);
+    return () => clearTimeout(timer);
+  }, [searchInput]);
+
   const launchClaude = async (
     projectPath: string,
     continueSession: boolean,
@@ -144,7 +230,22 @@ export function SessionsPage({ onSelectProject }: SessionsPageProps) {
     <div className="h-full flex flex-col">
       <div className="flex-1 overflow-auto">
         <div className="max-w-5xl mx-auto p-6">
-          {projects.length > 0 ? (
+          <div className="relative mb-4">
+            <IconSearch className="absolute left-2.5 top-1/2 -translate-y-1/2 size-4 text-muted-foreground" />
+            <Input
+              value={searchInput}
+              onChange={(e) => setSearchInput(e.target.value)}
+              placeholder="Search all sessions..."
+              className="pl-8"
+            />
+          </div>
+          {searchQuery.trim() ? (
+            <ProjectSearchResults
+              {...search}
+              projects={projects}
+              onSelectProject={onSelectProject}
+            />
+          ) : projects.length > 0 ? (
             <div className="border border-border rounded-lg overflow-hidden">
               {/* Table Header */}
               <div className="grid grid-cols-[1fr_72px_80px_100px_68px] gap-4 px-4 py-2.5 bg-muted/50 border-b border-border text-xs font-medium text-muted-foreground">
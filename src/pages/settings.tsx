@@ -1,6 +1,7 @@
 import { useState, useEffect } from "react";
 import { cn } from "@/lib/utils";
 import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
 import {
   Card,
   CardContent,
@@ -18,14 +19,30 @@ import {
   IconDeviceDesktop,
   IconCheck,
   IconTerminal2,
+  IconCoin,
+  IconPlus,
+  IconTrash,
 } from "@tabler/icons-react";
 import { invoke } from "@tauri-apps/api/core";
-import type { TerminalType } from "@/lib/types";
+import type { ModelPrice, PriceTable, TerminalType } from "@/lib/types";
 import { terminalDisplayNames } from "@/lib/types";
 
-type SettingsSection = "appearance" | "terminal" | "about";
+type SettingsSection = "appearance" | "terminal" | "pricing" | "about";
 
 export const TERMINAL_STORAGE_KEY = "agent-console:default-terminal";
+/** Edited model prices (a PriceTable as JSON); unset means the default prices */
+export const PRICE_TABLE_STORAGE_KEY = "agent-console:price-table";
+
+/** The user's price table, or null to use the default prices */
+export function loadPriceTable(): PriceTable | null {
+  const saved = localStorage.getItem(PRICE_TABLE_STORAGE_KEY);
+  if (!saved) return null;
+  try {
+    return JSON.parse(saved) as PriceTable;
+  } catch {
+    return null;
+  }
+}
 
 interface SettingsPageProps {
   onBack: () => void;
@@ -46,6 +63,11 @@ const navItems: {
     label: "Terminal",
     icon: <IconTerminal2 className="size-4" />,
   },
+  {
+    id: "pricing",
+    label: "Pricing",
+    icon: <IconCoin className="size-4" />,
+  },
   { id: "about", label: "About", icon: <IconInfoCircle className="size-4" /> },
 ];
 
@@ -92,6 +114,7 @@ export function SettingsPage({ onBack }: SettingsPageProps) {
         <div className="max-w-2xl">
           {activeSection === "appearance" && <AppearanceSection />}
           {activeSection === "terminal" && <TerminalSection />}
+          {activeSection === "pricing" && <PricingSection />}
           {activeSection === "about" && <AboutSection />}
         </div>
       </main>
@@ -275,6 +298,128 @@ function TerminalSection() {
   );
 }
 
+const PRICE_FIELDS: { key: Exclude<keyof ModelPrice, "model">; label: string }[] = [
+  { key: "input", label: "Input" },
+  { key: "output", label: "Output" },
+  { key: "cacheRead", label: "Cache read" },
+  { key: "cacheCreation", label: "Cache write" },
+];
+
+function PricingSection() {
+  const [prices, setPrices] = useState<ModelPrice[]>([]);
+  const [customized, setCustomized] = useState(false);
+
+  useEffect(() => {
+    const saved = loadPriceTable();
+    if (saved) {
+      setPrices(saved.prices);
+      setCustomized(true);
+    } else {
+      invoke<PriceTable>("get_default_price_table").then((table) => setPrices(table.prices));
+    }
+  }, []);
+
+  const save = (next: ModelPrice[]) => {
+    setPrices(next);
+    setCustomized(true);
+    localStorage.setItem(PRICE_TABLE_STORAGE_KEY, JSON.stringify({ prices: next }));
+  };
+
+  const updatePrice = (index: number, change: Partial<ModelPrice>) => {
+    save(prices.map((price, i) => (i === index ? { ...price, ...change } : price)));
+  };
+
+  const resetToDefaults = async () => {
+    localStorage.removeItem(PRICE_TABLE_STORAGE_KEY);
+    const table = await invoke<PriceTable>("get_default_price_table");
+    setPrices(table.prices);
+    setCustomized(false);
+  };
+
+  return (
+    <div className="space-y-6">
+      <div>
+        <h2 className="text-lg font-semibold">Pricing</h2>
+        <p className="text-sm text-muted-foreground">
+          Model prices used to estimate the cost of sessions.
+        </p>
+      </div>
+
+      <Card>
+        <CardHeader>
+          <CardTitle>Model Prices</CardTitle>
+          <CardDescription>
+            USD per million tokens. Models are matched against the patterns in
+            order (<code>*</code> matches anything), so put specific patterns
+            first.
+          </CardDescription>
+        </CardHeader>
+        <CardContent className="space-y-2">
+          <div className="grid grid-cols-[1fr_repeat(4,4.5rem)_1.75rem] gap-2 text-xs text-muted-foreground">
+            <span>Model</span>
+            {PRICE_FIELDS.map((field) => (
+              <span key={field.key}>{field.label}</span>
+            ))}
+            <span />
+          </div>
+          {prices.map((price, index) => (
+            <div
+              key={index}
+              className="grid grid-cols-[1fr_repeat(4,4.5rem)_1.75rem] gap-2 items-center"
+            >
+              <Input
+                value={price.model}
+                onChange={(e) => updatePrice(index, { model: e.target.value })}
+                className="font-mono"
+              />
+              {PRICE_FIELDS.map((field) => (
+                <Input
+                  key={field.key}
+                  type="number"
+                  min={0}
+                  step="any"
+                  value={price[field.key]}
+                  onChange={(e) =>
+                    updatePrice(index, { [field.key]: Number(e.target.value) || 0 })
+                  }
+                />
+              ))}
+              <Button
+                variant="ghost"
+                size="icon-sm"
+                onClick={() => save(prices.filter((_, i) => i !== index))}
+                title="Remove"
+              >
+                <IconTrash className="size-3.5" />
+              </Button>
+            </div>
+          ))}
+          <div className="flex items-center gap-2 pt-2">
+            <Button
+              variant="outline"
+              size="sm"
+              onClick={() =>
+                save([
+                  ...prices,
+                  { model: "", input: 0, output: 0, cacheRead: 0, cacheCreation: 0 },
+                ])
+              }
+            >
+              <IconPlus className="size-3.5" />
+              Add model
+            </Button>
+            {customized && (
+              <Button variant="ghost" size="sm" onClick={resetToDefaults}>
+                Reset to defaults
+              </Button>
+            )}
+          </div>
+        </CardContent>
+      </Card>
+    </div>
+  );
+}
+
 function AboutSection() {
   return (
     <div className="space-y-6">